@@ -1,17 +1,43 @@
-use crate::{Cp, Deactivate, Exclude, Focus, Include, Mv, New, Rm};
-use anyhow::{bail, Context as _};
-use cargo_metadata::Metadata;
+//! The command line interface of `cargo member`.
+//!
+//! Parse [`Cargo`] with [`StructOpt`], then [`run`] it with a [`Context`].
+
+// The doc comments of the options are the help messages, where `[cargo]` is not a link.
+#![allow(rustdoc::broken_intra_doc_links)]
+
+use crate::{
+    Action, ActivateAll, Bump, BumpLevel, Canonicalize, Check, CheckVersionSync, Cp, Deactivate,
+    DependentScan, Exclude, Focus, Gc, Include, Init, List, ListOnly, MemberPosition, MergeInto,
+    MetadataExt as _, Mv, New, Plan, RegisterIn, RestoreFocus, Rm, Rollback, Sort, SortKey, Status,
+    Timings, VendorMember, WriteColorExt as _, FOCUS_SNAPSHOT_FILE_NAME,
+};
+use anyhow::{anyhow, bail, ensure, Context as _};
+use cargo_metadata::{Metadata, Version};
 use easy_ext::ext;
 use env_logger::fmt::WriteStyle;
+use itertools::Itertools as _;
+use log::debug;
+use regex::Regex;
 use serde::Deserialize;
 use std::{
     env,
-    io::Write as _,
+    ffi::OsString,
+    io::{self, BufRead as _, Read as _, Write as _},
+    iter,
     path::{Path, PathBuf},
     process::{self, Stdio},
     str,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Instant,
+};
+use structopt::{
+    clap::{AppSettings, Shell},
+    StructOpt,
 };
-use structopt::{clap::AppSettings, StructOpt};
 use strum::{EnumString, EnumVariantNames, IntoStaticStr, VariantNames as _};
 use termcolor::{BufferedStandardStream, ColorSpec, WriteColor};
 
@@ -29,6 +55,10 @@ pub enum Cargo {
 
 #[derive(StructOpt, Debug)]
 pub enum CargoMember {
+    /// Create a workspace
+    #[structopt(author)]
+    Init(CargoMemberInit),
+
     /// Add a package to `workspace.members`
     #[structopt(author, visible_alias("i"))]
     Include(CargoMemberInclude),
@@ -45,6 +75,10 @@ pub enum CargoMember {
     #[structopt(author, visible_alias("f"))]
     Focus(CargoMemberFocus),
 
+    /// Add every package under the workspace root back to `workspace.members`, undoing `focus`
+    #[structopt(author)]
+    ActivateAll(CargoMemberActivateAll),
+
     /// Create a new workspace member with `cargo new`
     #[structopt(author, visible_alias("n"))]
     New(CargoMemberNew),
@@ -60,38 +94,230 @@ pub enum CargoMember {
     /// Move a workspace member
     #[structopt(author, visible_alias("m"))]
     Mv(CargoMemberMv),
+
+    /// Run `include`, `exclude`, `mv` and so on from a file, stopping at the first failure and
+    /// undoing the changes of the batch
+    #[structopt(author)]
+    Batch(CargoMemberBatch),
+
+    /// Check that every member has the same `package.version`
+    #[structopt(author)]
+    CheckVersionSync(CargoMemberCheckVersionSync),
+
+    /// Print the path to the root manifest of the workspace
+    #[structopt(author)]
+    WhichWorkspace(CargoMemberWhichWorkspace),
+
+    /// Open the directory of a member in `$VISUAL` or `$EDITOR`
+    #[structopt(author)]
+    Open(CargoMemberOpen),
+
+    /// Run a command in the directory of each member
+    #[structopt(author)]
+    Exec(CargoMemberExec),
+
+    /// Normalize the paths in `workspace.{members, exclude}` (e.g. `./a/` to `a`)
+    #[structopt(author)]
+    Canonicalize(CargoMemberCanonicalize),
+
+    /// Sort `workspace.{members, exclude}` alphabetically, removing duplicates
+    #[structopt(author)]
+    Sort(CargoMemberSort),
+
+    /// Bump `package.version` of the members
+    #[structopt(author)]
+    Bump(CargoMemberBump),
+
+    /// Summarize unregistered packages, dead member entries, version drift and dangling path
+    /// dependencies
+    #[structopt(author)]
+    Status(CargoMemberStatus),
+
+    /// Check that the membership of the workspace is consistent, for CI
+    #[structopt(author)]
+    Check(CargoMemberCheck),
+
+    /// List the packages under the workspace root with their status
+    #[structopt(author)]
+    List(CargoMemberList),
+
+    /// Replace the fields a member inherits from the workspace with their values, so that the
+    /// member no longer depends on the workspace
+    #[structopt(author)]
+    VendorMember(CargoMemberVendorMember),
+
+    /// Remove the build artifacts of packages that are no longer in the workspace from the
+    /// target directory
+    #[structopt(author)]
+    Gc(CargoMemberGc),
+
+    /// Generate shell completion scripts
+    #[structopt(author)]
+    Completions(CargoMemberCompletions),
 }
 
 impl CargoMember {
+    /// `--color`, overridden by `--no-color`. `auto` is treated as `never` if `NO_COLOR` is set.
     pub fn color(&self) -> self::ColorChoice {
-        match *self {
-            Self::Include(CargoMemberInclude { color, .. })
-            | Self::Exclude(CargoMemberExclude { color, .. })
-            | Self::Deactivate(CargoMemberDeactivate { color, .. })
-            | Self::Focus(CargoMemberFocus { color, .. })
-            | Self::New(CargoMemberNew { color, .. })
-            | Self::Cp(CargoMemberCp { color, .. })
-            | Self::Rm(CargoMemberRm { color, .. })
-            | Self::Mv(CargoMemberMv { color, .. }) => color,
+        let (color, no_color) = match *self {
+            Self::Init(CargoMemberInit {
+                color, no_color, ..
+            })
+            | Self::Include(CargoMemberInclude {
+                color, no_color, ..
+            })
+            | Self::Exclude(CargoMemberExclude {
+                color, no_color, ..
+            })
+            | Self::Deactivate(CargoMemberDeactivate {
+                color, no_color, ..
+            })
+            | Self::Focus(CargoMemberFocus {
+                color, no_color, ..
+            })
+            | Self::ActivateAll(CargoMemberActivateAll {
+                color, no_color, ..
+            })
+            | Self::New(CargoMemberNew {
+                color, no_color, ..
+            })
+            | Self::Cp(CargoMemberCp {
+                color, no_color, ..
+            })
+            | Self::Rm(CargoMemberRm {
+                color, no_color, ..
+            })
+            | Self::Mv(CargoMemberMv {
+                color, no_color, ..
+            })
+            | Self::Batch(CargoMemberBatch {
+                color, no_color, ..
+            })
+            | Self::CheckVersionSync(CargoMemberCheckVersionSync {
+                color, no_color, ..
+            })
+            | Self::WhichWorkspace(CargoMemberWhichWorkspace {
+                color, no_color, ..
+            })
+            | Self::Open(CargoMemberOpen {
+                color, no_color, ..
+            })
+            | Self::Exec(CargoMemberExec {
+                color, no_color, ..
+            })
+            | Self::Canonicalize(CargoMemberCanonicalize {
+                color, no_color, ..
+            })
+            | Self::Sort(CargoMemberSort {
+                color, no_color, ..
+            })
+            | Self::Bump(CargoMemberBump {
+                color, no_color, ..
+            })
+            | Self::Status(CargoMemberStatus {
+                color, no_color, ..
+            })
+            | Self::Check(CargoMemberCheck {
+                color, no_color, ..
+            })
+            | Self::List(CargoMemberList {
+                color, no_color, ..
+            })
+            | Self::VendorMember(CargoMemberVendorMember {
+                color, no_color, ..
+            })
+            | Self::Gc(CargoMemberGc {
+                color, no_color, ..
+            })
+            | Self::Completions(CargoMemberCompletions {
+                color, no_color, ..
+            }) => (color, no_color),
+        };
+        if no_color {
+            self::ColorChoice::Never
+        } else {
+            color.respect_no_color_env()
         }
     }
 }
 
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberInit {
+    /// Entries of `workspace.members` to start with. Globs (e.g. `crates/*`) are kept as-is
+    #[structopt(long, value_name("PATH"), number_of_values(1))]
+    pub members: Vec<String>,
+
+    /// Also add the packages found under the directory that `--members` does not cover
+    #[structopt(long)]
+    pub include_existing: bool,
+
+    /// Set `workspace.resolver`
+    #[structopt(long, value_name("VERSION"))]
+    pub resolver: Option<String>,
+
+    /// Add an empty `[workspace.package]`
+    #[structopt(long)]
+    pub workspace_package: bool,
+
+    /// Add an empty `[workspace.dependencies]`
+    #[structopt(long)]
+    pub workspace_dependencies: bool,
+
+    /// Replace `[workspace]` if `Cargo.toml` already has one
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Print the manifest to stdout instead of writing it
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Directory to create the workspace in
+    #[structopt(default_value("."))]
+    pub path: PathBuf,
+}
+
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberInclude {
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Print the workspace root found for the CWD
+    #[structopt(short, long)]
+    pub verbose: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -104,7 +330,109 @@ pub struct CargoMemberInclude {
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Paths to include
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Print what the command does and which file it edits before doing it
+    #[structopt(long)]
+    pub explain: bool,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Where to insert the entries in `workspace.members`: `first`, `last`, `before:<PATH>` or
+    /// `after:<PATH>`, PATH being an entry of `workspace.members`
+    #[structopt(long, value_name("POS"), default_value("last"))]
+    pub position: MemberPosition,
+
+    /// Merge the members of paths that are workspace roots themselves, removing their `[workspace]`
+    #[structopt(long)]
+    pub flatten_nested: bool,
+
+    /// Add the packages under the paths instead of the paths themselves, skipping the ones already
+    /// in `workspace.members` or `workspace.exclude`. `target` directories and other workspaces
+    /// are not searched
+    #[structopt(short, long, conflicts_with_all(&["from-stdin-json", "from-file"]))]
+    pub recursive: bool,
+
+    /// Also add the paths to `workspace.default-members`
+    #[structopt(long)]
+    pub default: bool,
+
+    /// Set `package.publish = false` in the manifests of the packages that are not members yet,
+    /// unless `package.publish` is present. The workspace root manifest is not affected
+    #[structopt(long)]
+    pub private: bool,
+
+    /// Make the packages that are not members yet inherit the fields defined in
+    /// `workspace.package`. Fields set to different values are left alone
+    #[structopt(long)]
+    pub inherit_package: bool,
+
+    /// Run `cargo check` for the added packages after updating `Cargo.lock`. If it fails, the
+    /// modified files are restored
+    #[structopt(long, conflicts_with("from-stdin-json"))]
+    pub verify: bool,
+
+    /// Write `# <TEXT>` at the end of the lines of the entries added to `workspace.members`
+    #[structopt(
+        long,
+        value_name("TEXT"),
+        conflicts_with_all(&["members-per-line", "from-stdin-json"])
+    )]
+    pub comment: Option<String>,
+
+    /// Resolve the paths and globs relative to the workspace root instead of the CWD
+    #[structopt(long)]
+    pub relative_to_root: bool,
+
+    /// Read a JSON array of `{"path": .., "package": ..}` from stdin and include them all at once.
+    /// The result of each entry is printed to stdout as JSON
+    #[structopt(long, conflicts_with_all(&["stdout", "paths", "default"]))]
+    pub from_stdin_json: bool,
+
+    /// Read the paths from this file, one per line. `#` starts a comment
+    #[structopt(long, value_name("PATH"), conflicts_with_all(&["paths", "from-stdin-json"]))]
+    pub from_file: Option<PathBuf>,
+
+    /// What the paths in `--from-file` are relative to: the directory of the file, or the
+    /// workspace root
+    #[structopt(
+        long,
+        value_name("BASE"),
+        possible_values(self::FromFileBase::VARIANTS),
+        default_value("file")
+    )]
+    pub from_file_base: self::FromFileBase,
+
+    /// Apply the valid entries of `--from-file` even if some are invalid, then fail
+    #[structopt(long, requires("from-file"))]
+    pub keep_going: bool,
+
+    /// Paths to include. Globs (e.g. `crates/*`) are matched like the paths and expanded to the
+    /// matching packages, or to any matching directories with `--force`
     pub paths: Vec<PathBuf>,
 }
 
@@ -114,19 +442,32 @@ pub struct CargoMemberExclude {
     #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
     pub package: Vec<String>,
 
+    /// Resolve the paths and globs relative to the workspace root instead of the CWD
+    #[structopt(long)]
+    pub relative_to_root: bool,
+
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -135,7 +476,93 @@ pub struct CargoMemberExclude {
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Paths to exclude
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Print what the command does and which file it edits before doing it
+    #[structopt(long)]
+    pub explain: bool,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Skip package specs that do not match any member
+    #[structopt(long)]
+    pub ignore_missing: bool,
+
+    /// Fail if the packages are dependencies of packages that remain in the workspace
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Proceed even if `--strict` is enabled and the packages are still depended on
+    #[structopt(long, requires("strict"))]
+    pub force: bool,
+
+    /// Also select the members whose name (or path, see `--match-field`) matches the regex
+    #[structopt(long, value_name("REGEX"), allow_hyphen_values(true))]
+    pub matching: Option<Regex>,
+
+    /// Deselect the members whose name (or path, see `--match-field`) matches the regex
+    #[structopt(long, value_name("REGEX"), allow_hyphen_values(true))]
+    pub exclude_matching: Option<Regex>,
+
+    /// What `--matching` and `--exclude-matching` match against. Paths are relative to the
+    /// workspace root and separated by `/`
+    #[structopt(
+        long,
+        value_name("FIELD"),
+        possible_values(self::MatchField::VARIANTS),
+        default_value("name")
+    )]
+    pub match_field: self::MatchField,
+
+    /// Read the paths from this file, one per line. `#` starts a comment
+    #[structopt(long, value_name("PATH"), conflicts_with_all(&["paths"]))]
+    pub from_file: Option<PathBuf>,
+
+    /// What the paths in `--from-file` are relative to: the directory of the file, or the
+    /// workspace root
+    #[structopt(
+        long,
+        value_name("BASE"),
+        possible_values(self::FromFileBase::VARIANTS),
+        default_value("file")
+    )]
+    pub from_file_base: self::FromFileBase,
+
+    /// Expand each directory to every member found beneath it
+    #[structopt(long)]
+    pub recursive: bool,
+
+    /// Apply the valid entries of `--from-file` and skip the directories without members with
+    /// `--recursive`, then fail
+    #[structopt(long)]
+    pub keep_going: bool,
+
+    /// Paths to exclude. Globs (e.g. `crates/*`) are matched like the paths and expanded to the
+    /// matching packages
     pub paths: Vec<PathBuf>,
 }
 
@@ -149,15 +576,24 @@ pub struct CargoMemberDeactivate {
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -166,39 +602,231 @@ pub struct CargoMemberDeactivate {
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Paths to exclude
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Print what the command does and which file it edits before doing it
+    #[structopt(long)]
+    pub explain: bool,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Skip package specs that do not match any member
+    #[structopt(long)]
+    pub ignore_missing: bool,
+
+    /// Fail if the packages are dependencies of packages that remain in the workspace
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Proceed even if `--strict` is enabled and the packages are still depended on
+    #[structopt(long, requires("strict"))]
+    pub force: bool,
+
+    /// Expand each directory to every member found beneath it
+    #[structopt(long)]
+    pub recursive: bool,
+
+    /// With `--recursive`, skip the directories without members, then fail
+    #[structopt(long, requires("recursive"))]
+    pub keep_going: bool,
+
+    /// Paths to deactivate. Globs (e.g. `crates/*`) are matched relative to the CWD and expanded
+    /// to the matching packages
     pub paths: Vec<PathBuf>,
 }
 
 #[derive(StructOpt, Debug)]
 pub struct CargoMemberFocus {
+    /// [cargo] Package to focus. An alternative to PATH
+    #[structopt(short, long, value_name("SPEC"), conflicts_with("path"))]
+    pub package: Option<String>,
+
+    /// Reinstate `workspace.{members, exclude}` as they were before the first focus, from the
+    /// snapshot saved at the workspace root, and remove the snapshot
+    #[structopt(
+        long,
+        conflicts_with_all(&[
+            "package",
+            "path",
+            "exclude",
+            "force",
+            "allow-broken",
+            "with-dependents",
+        ])
+    )]
+    pub restore: bool,
+
     /// Add existing packages to `workspace.exclude`
     #[structopt(long)]
     pub exclude: bool,
 
+    /// Allow the workspace root and paths that are not members
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Deactivate or exclude the members the package depends on too, even though it may then fail
+    /// to build. Without this, they are kept active
+    #[structopt(long)]
+    pub allow_broken: bool,
+
+    /// Also keep active the members that transitively depend on the package. Unless
+    /// `--allow-broken`, both sets are kept, but not the other dependencies of the dependents
+    #[structopt(long)]
+    pub with_dependents: bool,
+
     /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
 
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Print what the command does and which file it edits before doing it
+    #[structopt(long)]
+    pub explain: bool,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Output format. `json` prints the plan to stdout and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
     /// [cargo] Path to Cargo.toml
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
 
     /// Path to focus
-    pub path: PathBuf,
+    #[structopt(required_unless_one(&["package", "restore"]))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberActivateAll {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Print the workspace root found for the CWD
+    #[structopt(short, long)]
+    pub verbose: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Actually rewrite `workspace.{members, exclude}`
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Dry run. Prints the resulting `workspace.members`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest to stdout instead of writing it. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.members` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
 }
 
 #[derive(StructOpt, Debug)]
@@ -207,6 +835,10 @@ pub struct CargoMemberNew {
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo-new] Registry to use
     #[structopt(long, value_name("REGISTRY"))]
     pub registry: Option<String>,
@@ -219,6 +851,11 @@ pub struct CargoMemberNew {
     )]
     pub vcs: Option<String>,
 
+    /// [cargo-new] Use a binary template. With `--lib`, also adds `src/main.rs` calling `run` in
+    /// `src/lib.rs`
+    #[structopt(long)]
+    pub bin: bool,
+
     /// [cargo-new] Use a library template
     #[structopt(long)]
     pub lib: bool,
@@ -227,15 +864,49 @@ pub struct CargoMemberNew {
     #[structopt(long, value_name("NAME"))]
     pub name: Option<String>,
 
+    /// Add `lints.workspace = true` to the package. Enabled by default if `workspace.lints` exists
+    #[structopt(long)]
+    pub inherit_lints: bool,
+
+    /// Do not add `lints.workspace = true` to the package
+    #[structopt(long, conflicts_with("inherit-lints"))]
+    pub no_inherit_lints: bool,
+
+    /// Also add the package to `workspace.default-members`
+    #[structopt(long)]
+    pub default: bool,
+
+    /// Set `package.publish = false` in the manifest of the new package. The workspace root
+    /// manifest is not affected
+    #[structopt(long)]
+    pub private: bool,
+
+    /// Make the new package inherit the fields defined in `workspace.package`
+    #[structopt(long)]
+    pub inherit_package: bool,
+
+    /// Set `workspace.resolver`. Without this, warns if an edition 2024 package is added to a workspace with a lower resolver
+    #[structopt(long, value_name("VERSION"), possible_values(&["1", "2", "3"]))]
+    pub set_resolver: Option<String>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Print the workspace root found for the CWD and stream the output of `--smoke-test`
+    #[structopt(short, long)]
+    pub verbose: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -244,6 +915,53 @@ pub struct CargoMemberNew {
     #[structopt(long)]
     pub dry_run: bool,
 
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Where to insert the entries in `workspace.members`: `first`, `last`, `before:<PATH>` or
+    /// `after:<PATH>`, PATH being an entry of `workspace.members`
+    #[structopt(long, value_name("POS"), default_value("last"))]
+    pub position: MemberPosition,
+
+    /// Set `package.<KEY>` of the resulting package. VALUE is parsed as a TOML fragment, so
+    /// strings must be quoted (e.g. `rust-version='"1.70"'`)
+    #[structopt(long, value_name("KEY=VALUE"), number_of_values(1))]
+    pub set_field: Vec<String>,
+
+    /// Run `cargo build -p <name>` for the new package afterwards and fail if it does not build
+    #[structopt(long)]
+    pub smoke_test: bool,
+
+    /// With `--smoke-test`, restore `Cargo.toml` and `Cargo.lock` of the workspace and remove the
+    /// new package if it does not build
+    #[structopt(long, requires("smoke-test"))]
+    pub rollback_on_failure: bool,
+
     /// [cargo-new] Path
     pub path: PathBuf,
 }
@@ -254,31 +972,186 @@ pub struct CargoMemberCp {
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
-    /// [cargo] Run without accessing the network
+    /// Shorthand for `--color never`
     #[structopt(long)]
-    pub offline: bool,
+    pub no_color: bool,
 
-    /// Dry run. Also enables `--frozen` and `--locked`
+    /// Stream the output of `--smoke-test`
+    #[structopt(short, long)]
+    pub verbose: bool,
+
+    /// Print how long each phase took at the end
+    #[structopt(long)]
+    pub time: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
     #[structopt(long)]
     pub dry_run: bool,
 
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Replace `path` of each path dependency with `version`, the version of the package it points
+    /// to
+    #[structopt(long)]
+    pub strip_path_deps_to_versions: bool,
+
+    /// With `--strip-path-deps-to-versions`, keep `path` alongside `version`
+    #[structopt(long, requires("strip-path-deps-to-versions"))]
+    pub keep_path: bool,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Set `package.<KEY>` of the resulting package. VALUE is parsed as a TOML fragment, so
+    /// strings must be quoted (e.g. `rust-version='"1.70"'`)
+    #[structopt(long, value_name("KEY=VALUE"), number_of_values(1))]
+    pub set_field: Vec<String>,
+
     /// Do not modify the `package.name`
     #[structopt(long)]
     pub no_rename: bool,
 
-    /// Package ID specification
-    pub src: String,
+    /// Also rename the source files of the `lib` and `bin` targets named after the package (e.g.
+    /// `src/bin/<name>.rs`). `src/main.rs` and `src/lib.rs` are left alone
+    #[structopt(long, conflicts_with("no-rename"))]
+    pub rename_paths: bool,
+
+    /// If the name derived from DST is taken by another member, append this suffix (and `-2`,
+    /// `-3`, ... if that is taken too)
+    #[structopt(long, value_name("S"), conflicts_with("no-rename"))]
+    pub name_suffix: Option<String>,
+
+    /// Set `package.version` of the copy
+    #[structopt(long, value_name("SEMVER"), conflicts_with("bump"))]
+    pub set_version: Option<Version>,
+
+    /// Bump `package.version` of the copy from the version of the source package
+    #[structopt(long, value_name("LEVEL"), possible_values(&["major", "minor", "patch"]))]
+    pub bump: Option<BumpLevel>,
+
+    /// Report the `#[path = ".."]` attributes in the package's `.rs` files that point outside the
+    /// package
+    #[structopt(long)]
+    pub scan_path_attrs: bool,
+
+    /// With `--scan-path-attrs`, rewrite the reported attributes so that they still resolve from
+    /// the destination
+    #[structopt(long, requires("scan-path-attrs"))]
+    pub fix_path_attrs: bool,
+
+    /// Replace the fields inherited from the workspace (e.g. `version.workspace = true`) with
+    /// their values if the copy does not belong to the same workspace. Without this, such a copy
+    /// is an error unless the destination workspace defines the fields
+    #[structopt(long)]
+    pub inline_inherited: bool,
+
+    /// Run `cargo build -p <name>` for the copied package afterwards and fail if it does not build
+    #[structopt(long)]
+    pub smoke_test: bool,
+
+    /// With `--smoke-test`, restore `Cargo.toml` and `Cargo.lock` of the workspace and remove the
+    /// new package if it does not build
+    #[structopt(long, requires("smoke-test"))]
+    pub rollback_on_failure: bool,
+
+    /// Proceed even if the workspace ends up with two packages with the same name
+    #[structopt(long)]
+    pub allow_duplicate_names: bool,
+
+    /// Copy only `Cargo.toml` and `src/`
+    #[structopt(long)]
+    pub minimal: bool,
+
+    /// Skip paths matching the glob (relative to the source package). Applied after `--minimal`
+    #[structopt(long, value_name("GLOB"), number_of_values(1))]
+    pub exclude: Vec<String>,
+
+    /// Skip `build.target-dir` of the cargo configuration if it is inside the source package
+    #[structopt(long)]
+    pub respect_cargo_config: bool,
+
+    /// Directories (relative to the source package) to skip as build artifacts
+    #[structopt(
+        long,
+        value_name("DIRS"),
+        use_delimiter(true),
+        default_value("target"),
+        conflicts_with("copy-all")
+    )]
+    pub artifact_dirs: Vec<String>,
+
+    /// Do not skip anything: artifact directories, VCS directories and ignored files
+    #[structopt(long)]
+    pub copy_all: bool,
+
+    /// Set `workspace.resolver`. Without this, warns if an edition 2024 package is added to a workspace with a lower resolver
+    #[structopt(long, value_name("VERSION"), possible_values(&["1", "2", "3"]))]
+    pub set_resolver: Option<String>,
+
+    /// Copy even if the destination is an existing package or member
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Workspace to add the copy to
+    #[structopt(
+        long,
+        value_name("WHERE"),
+        possible_values(RegisterIn::VARIANTS),
+        default_value("dst")
+    )]
+    pub register_in: RegisterIn,
+
+    /// Point the dependencies of this member on the source package to the copy
+    #[structopt(long, value_name("SPEC"))]
+    pub replace_in: Option<String>,
+
+    /// Package ID specification(s). With more than one, each is copied into `<dst>/`
+    #[structopt(required(true), min_values(1))]
+    pub src: Vec<String>,
 
     /// Directory
+    #[structopt(required(true))]
     pub dst: PathBuf,
 }
 
@@ -292,15 +1165,24 @@ pub struct CargoMemberRm {
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -313,7 +1195,66 @@ pub struct CargoMemberRm {
     #[structopt(long)]
     pub dry_run: bool,
 
-    /// Paths to exclude
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Skip package specs that do not match any member and paths that do not exist
+    #[structopt(long)]
+    pub ignore_missing: bool,
+
+    /// Also remove the entries of `workspace.dependencies` pointing to the removed packages
+    #[structopt(long)]
+    pub prune_deps: bool,
+
+    /// Also select the members whose name (or path, see `--match-field`) matches the regex
+    #[structopt(long, value_name("REGEX"), allow_hyphen_values(true))]
+    pub matching: Option<Regex>,
+
+    /// Deselect the members whose name (or path, see `--match-field`) matches the regex
+    #[structopt(long, value_name("REGEX"), allow_hyphen_values(true))]
+    pub exclude_matching: Option<Regex>,
+
+    /// What `--matching` and `--exclude-matching` match against. Paths are relative to the
+    /// workspace root and separated by `/`
+    #[structopt(
+        long,
+        value_name("FIELD"),
+        possible_values(self::MatchField::VARIANTS),
+        default_value("name")
+    )]
+    pub match_field: self::MatchField,
+
+    /// Expand each directory to every member found beneath it
+    #[structopt(long)]
+    pub recursive: bool,
+
+    /// With `--recursive`, skip the directories without members, then fail
+    #[structopt(long, requires("recursive"))]
+    pub keep_going: bool,
+
+    /// Paths to remove. Globs (e.g. `crates/*`) are matched relative to the CWD and expanded to
+    /// the matching packages, or to any matching directories with `--force`
     pub paths: Vec<PathBuf>,
 }
 
@@ -323,15 +1264,32 @@ pub struct CargoMemberMv {
     #[structopt(long, value_name("PATH"))]
     pub manifest_path: Option<PathBuf>,
 
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
     /// [cargo] Coloring
     #[structopt(
         long,
         value_name("WHEN"),
         possible_values(self::ColorChoice::VARIANTS),
-        default_value("auto")
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
     )]
     pub color: self::ColorChoice,
 
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Stream the output of `--smoke-test`
+    #[structopt(short, long)]
+    pub verbose: bool,
+
+    /// Print how long each phase took at the end
+    #[structopt(long)]
+    pub time: bool,
+
     /// [cargo] Run without accessing the network
     #[structopt(long)]
     pub offline: bool,
@@ -340,311 +1298,3093 @@ pub struct CargoMemberMv {
     #[structopt(long)]
     pub dry_run: bool,
 
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Output format. `json` prints a JSON object per file the command would change to stdout
+    /// instead of changing it, and requires `--dry-run`
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"), conflicts_with("merge-into"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Lay out the modified `workspace.{members, exclude}` with N entries per line, `0` being the
+    /// same as `1`. The array is put on one line if every entry fits. Defaults to keeping the
+    /// layout
+    #[structopt(long, value_name("N"))]
+    pub members_per_line: Option<usize>,
+
+    /// Write the added entries of `workspace.{members, exclude}` as `./<path>`
+    #[structopt(long)]
+    pub prefix_dot_slash: bool,
+
+    /// Set `package.<KEY>` of the resulting package. VALUE is parsed as a TOML fragment, so
+    /// strings must be quoted (e.g. `rust-version='"1.70"'`)
+    #[structopt(long, value_name("KEY=VALUE"), number_of_values(1))]
+    pub set_field: Vec<String>,
+
     /// Do not modify the `package.name`
     #[structopt(long)]
     pub no_rename: bool,
 
-    /// Package ID specification
-    pub src: String,
+    /// Also rename the source files of the `lib` and `bin` targets named after the package (e.g.
+    /// `src/bin/<name>.rs`). `src/main.rs` and `src/lib.rs` are left alone
+    #[structopt(long, conflicts_with("no-rename"), conflicts_with("merge-into"))]
+    pub rename_paths: bool,
+
+    /// Proceed even if the workspace ends up with two packages with the same name
+    #[structopt(long)]
+    pub allow_duplicate_names: bool,
+
+    /// Rewrite the relative links in the package's Markdown files that point to the other
+    /// members, so that they still resolve after the move
+    #[structopt(long, conflicts_with("merge-into"))]
+    pub update_readme_links: bool,
+
+    /// Report the `#[path = ".."]` attributes in the package's `.rs` files that point outside the
+    /// package
+    #[structopt(long, conflicts_with("merge-into"))]
+    pub scan_path_attrs: bool,
+
+    /// With `--scan-path-attrs`, rewrite the reported attributes so that they still resolve from
+    /// the destination
+    #[structopt(long, requires("scan-path-attrs"))]
+    pub fix_path_attrs: bool,
+
+    /// Replace the fields inherited from the workspace with their values if the destination is
+    /// outside of it. Without this, such a move is an error unless the destination workspace
+    /// defines the fields
+    #[structopt(long, conflicts_with("merge-into"))]
+    pub inline_inherited: bool,
+
+    /// Root `Cargo.toml` of the workspace to move the package to, instead of the one that
+    /// contains DST. The fields inherited from the source workspace are inlined
+    #[structopt(long, value_name("PATH"), conflicts_with("merge-into"))]
+    pub into_manifest: Option<PathBuf>,
+
+    /// Run `cargo build -p <name>` for the moved package afterwards and fail if it does not build
+    #[structopt(long, conflicts_with("merge-into"))]
+    pub smoke_test: bool,
+
+    /// Merge the package into this member, moving `src/` to `src/<name>/`, and remove it.
+    /// Dependency cycles this introduces are reported, except ones through `dev-dependencies`
+    #[structopt(long, value_name("SPEC"), conflicts_with("no-rename"))]
+    pub merge_into: Option<String>,
+
+    /// With `--merge-into`, merge even if there are conflicting files or dependencies
+    #[structopt(long, requires("merge-into"))]
+    pub force: bool,
+
+    /// How to find the members whose path dependencies point to the moved package. `all` reads
+    /// every member's manifest, `resolve-graph` uses the dependency graph from `cargo metadata`
+    #[structopt(
+        long,
+        value_name("HOW"),
+        possible_values(DependentScan::VARIANTS),
+        default_value("resolve-graph")
+    )]
+    pub dependent_scan: DependentScan,
+
+    /// Do not rewrite the path dependencies of the other packages that point to the moved
+    /// package. They are reported instead
+    #[structopt(long)]
+    pub no_update_deps: bool,
+
+    /// Package ID specification(s). With more than one, each is moved into `<dst>/`
+    #[structopt(required_unless("merge-into"), min_values(1))]
+    pub src: Vec<String>,
 
     /// Directory
-    pub dst: PathBuf,
+    #[structopt(required_unless("merge-into"))]
+    pub dst: Option<PathBuf>,
 }
 
-/// Coloring.
-#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, Debug)]
-#[strum(serialize_all = "kebab-case")]
-pub enum ColorChoice {
-    Auto,
-    Always,
-    Never,
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberCheckVersionSync {
+    /// Expected version. Defaults to the most common one among the members
+    #[structopt(long, value_name("SEMVER"))]
+    pub expected: Option<Version>,
+
+    /// Set `package.version` of the out-of-sync members to the expected one
+    #[structopt(long)]
+    pub fix: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
 }
 
-impl From<self::ColorChoice> for WriteStyle {
-    fn from(choice: self::ColorChoice) -> Self {
-        match choice {
-            self::ColorChoice::Auto => Self::Auto,
-            self::ColorChoice::Always => Self::Always,
-            self::ColorChoice::Never => Self::Never,
-        }
-    }
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberCanonicalize {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest to stdout instead of writing it. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
 }
 
-#[derive(Debug)]
-pub struct Context<W> {
-    cwd: PathBuf,
-    stderr: W,
-    stderr_redirection: Stdio,
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberSort {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Exit with a non-zero code without writing the manifest if the arrays are not sorted
+    #[structopt(long, conflicts_with_all(&["dry-run", "stdout", "report-file"]))]
+    pub check: bool,
+
+    /// Dry run
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest to stdout instead of writing it. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberBatch {
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Run every step with `--dry-run` and print the changes of the whole batch to stdout as JSON
+    /// lines. `cargo metadata` does not see the changes of the previous steps
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// JSON array of the steps, or `-` for stdin. Each step is `{"command": "<CMD>", "args":
+    /// [..]}`, CMD being `include`, `exclude`, `deactivate`, `focus`, `activate-all`, `new`, `cp`,
+    /// `rm`, `mv` or `sort`, and args being its arguments. Relative paths are resolved against the
+    /// CWD
+    pub file: PathBuf,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberStatus {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Print the details of each category
+    #[structopt(short, long)]
+    pub verbose: bool,
+
+    /// Exit with a non-zero code if any category is non-empty
+    #[structopt(long)]
+    pub check: bool,
+
+    /// Output format. `json` prints each finding to stdout as a JSON object with `category`,
+    /// `path`, `message` and `severity`, followed by a summary object
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
 }
 
-impl<W> Context<W> {
-    pub fn new(stderr: W) -> anyhow::Result<Self> {
-        let cwd = env::current_dir().with_context(|| "failed to get CWD")?;
-        let stderr_redirection = Stdio::inherit();
-        Ok(Self {
-            cwd,
-            stderr,
-            stderr_redirection,
-        })
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberCheck {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Also require `Cargo.lock` to be up to date
+    #[structopt(long)]
+    pub locked: bool,
+
+    /// Also require `workspace.{members, exclude}` to be sorted
+    #[structopt(long)]
+    pub require_sorted: bool,
+
+    /// With `--require-sorted`, what to compare the entries by. `name` compares the package names
+    #[structopt(
+        long,
+        value_name("KEY"),
+        possible_values(SortKey::VARIANTS),
+        default_value("path")
+    )]
+    pub sort_key: SortKey,
+
+    /// Print the violations to stdout as JSON
+    #[structopt(long)]
+    pub json: bool,
+
+    /// Output format. `json` prints each finding to stdout as a JSON object with `category`,
+    /// `path`, `message` and `severity`, followed by a summary object
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberBump {
+    /// [cargo] Package(s) to bump. Defaults to all the members
+    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub package: Vec<String>,
+
+    /// Package(s) not to bump
+    #[structopt(long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub exclude: Vec<String>,
+
+    /// Bump `workspace.package.version`. Members that inherit it are skipped regardless of this
+    #[structopt(long)]
+    pub workspace_package: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest(s) to stdout instead of writing them. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// `major`, `minor`, `patch`, or the new version
+    #[structopt(value_name("LEVEL|SEMVER"))]
+    pub level: BumpLevel,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberList {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Add the number of the other members each member depends on. This resolves the dependencies
+    #[structopt(long)]
+    pub with_deps_count: bool,
+
+    /// Add the lines of Rust code of each member, roughly counted as the lines of the `.rs` files
+    /// under `src/`
+    #[structopt(long)]
+    pub with_loc: bool,
+
+    /// List only the members, the excluded packages or the default members
+    #[structopt(long, value_name("KIND"), possible_values(ListOnly::VARIANTS))]
+    pub only: Option<ListOnly>,
+
+    /// Print only the path of each member, one per line, with nothing else. With `--only`, print
+    /// the packages it selects instead
+    #[structopt(long, conflicts_with_all(&["with-deps-count", "with-loc"]))]
+    pub print_members_only: bool,
+
+    /// With `--print-members-only`, print the package names instead of the paths
+    #[structopt(long, requires("print-members-only"))]
+    pub names: bool,
+
+    /// Output format. `json` prints each package as a JSON object
+    #[structopt(
+        long,
+        value_name("FMT"),
+        possible_values(self::MessageFormat::VARIANTS),
+        default_value("human")
+    )]
+    pub message_format: self::MessageFormat,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberVendorMember {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Dry run. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+
+    /// Print the modified manifest to stdout instead of writing it. Implies `--dry-run`
+    #[structopt(long)]
+    pub stdout: bool,
+
+    /// Append a JSON line describing each change made to the file system to this file
+    #[structopt(long, value_name("PATH"))]
+    pub report_file: Option<PathBuf>,
+
+    /// Replace `path` of each path dependency with `version`, the version of the package it points
+    /// to
+    #[structopt(long)]
+    pub strip_path_deps_to_versions: bool,
+
+    /// With `--strip-path-deps-to-versions`, keep `path` alongside `version`
+    #[structopt(long, requires("strip-path-deps-to-versions"))]
+    pub keep_path: bool,
+
+    /// Package ID specification
+    #[structopt(value_name("SPEC"))]
+    pub spec: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberGc {
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Actually remove the artifacts
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Dry run. Lists the artifacts to remove. Also enables `--frozen` and `--locked`
+    #[structopt(long)]
+    pub dry_run: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberWhichWorkspace {
+    /// Output in JSON
+    #[structopt(long)]
+    pub json: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberOpen {
+    /// Open `Cargo.toml` of the member instead of the directory
+    #[structopt(long)]
+    pub manifest: bool,
+
+    /// Print the path instead of launching the editor
+    #[structopt(long)]
+    pub print: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Package spec of the member
+    #[structopt(value_name("SPEC"))]
+    pub spec: String,
+}
+
+#[derive(StructOpt, Debug)]
+#[structopt(setting(AppSettings::TrailingVarArg))]
+pub struct CargoMemberExec {
+    /// [cargo] Package(s) to run the command in. Defaults to every member
+    #[structopt(short, long, value_name("SPEC"), min_values(1), number_of_values(1))]
+    pub package: Vec<String>,
+
+    /// Run the command in up to N members at once
+    #[structopt(short, long, value_name("N"), default_value("1"))]
+    pub jobs: usize,
+
+    /// Keep running the command in the remaining members after it fails in one
+    #[structopt(long)]
+    pub keep_going: bool,
+
+    /// Stream the output of each member as it is produced, prefixing each line with the package
+    /// name, instead of printing it all at once when the command finishes
+    #[structopt(long)]
+    pub no_buffer: bool,
+
+    /// [cargo] Path to Cargo.toml
+    #[structopt(long, value_name("PATH"))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Operate on the workspace whose root is this directory, regardless of the CWD
+    #[structopt(long, value_name("DIR"), conflicts_with("manifest-path"))]
+    pub workspace_root: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// [cargo] Run without accessing the network
+    #[structopt(long)]
+    pub offline: bool,
+
+    /// Command to run in the directory of each member
+    #[structopt(required(true), min_values(1), allow_hyphen_values(true))]
+    pub command: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct CargoMemberCompletions {
+    /// Write the scripts for every supported shell. Requires `--out-dir`
+    #[structopt(long, requires("out-dir"), conflicts_with("shell"))]
+    pub all: bool,
+
+    /// Write the script(s) into this directory instead of stdout
+    #[structopt(long, value_name("DIR"))]
+    pub out_dir: Option<PathBuf>,
+
+    /// [cargo] Coloring
+    #[structopt(
+        long,
+        value_name("WHEN"),
+        possible_values(self::ColorChoice::VARIANTS),
+        default_value("auto"),
+        env("CARGO_TERM_COLOR")
+    )]
+    pub color: self::ColorChoice,
+
+    /// Shorthand for `--color never`
+    #[structopt(long)]
+    pub no_color: bool,
+
+    /// Shell to generate the script for
+    #[structopt(possible_values(&Shell::variants()), required_unless("all"))]
+    pub shell: Option<Shell>,
+}
+
+/// Coloring.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// What `--matching` and `--exclude-matching` match against.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum MatchField {
+    Name,
+    Path,
+}
+
+/// What the paths in `--from-file` are relative to.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum FromFileBase {
+    File,
+    Root,
+}
+
+/// Output format.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+impl self::ColorChoice {
+    /// Turns `auto` into `never` if `NO_COLOR` is set to a non-empty value.
+    /// An explicit `always` (from `--color` or `CARGO_TERM_COLOR`) wins over it.
+    fn respect_no_color_env(self) -> Self {
+        match self {
+            Self::Auto if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => Self::Never,
+            color => color,
+        }
+    }
+}
+
+impl From<self::ColorChoice> for WriteStyle {
+    fn from(choice: self::ColorChoice) -> Self {
+        match choice {
+            self::ColorChoice::Auto => Self::Auto,
+            self::ColorChoice::Always => Self::Always,
+            self::ColorChoice::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Context<W> {
+    cwd: PathBuf,
+    stderr: W,
+    stderr_redirection: Stdio,
+}
+
+impl<W> Context<W> {
+    pub fn new(stderr: W) -> anyhow::Result<Self> {
+        let cwd = env::current_dir().with_context(|| "failed to get CWD")?;
+        let stderr_redirection = Stdio::inherit();
+        Ok(Self {
+            cwd,
+            stderr,
+            stderr_redirection,
+        })
+    }
+
+    /// Sets the directory that relative paths and the workspace are resolved against. Defaults
+    /// to the CWD of the process.
+    pub fn cwd(self, cwd: impl Into<PathBuf>) -> Self {
+        Self {
+            cwd: cwd.into(),
+            ..self
+        }
+    }
+}
+
+pub fn init_logger(color: self::ColorChoice) {
+    env_logger::Builder::from_default_env()
+        .write_style(color.into())
+        .init();
+}
+
+pub fn stderr(color: self::ColorChoice) -> BufferedStandardStream {
+    BufferedStandardStream::stderr(match color {
+        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
+        self::ColorChoice::Always => termcolor::ColorChoice::Always,
+        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
+    })
+}
+
+pub fn exit_with_error(error: anyhow::Error, color: self::ColorChoice) -> ! {
+    let mut stderr = BufferedStandardStream::stderr(match color {
+        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
+        self::ColorChoice::Always => termcolor::ColorChoice::Always,
+        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
+    });
+
+    let _ = stderr.set_color(
+        ColorSpec::new()
+            .set_fg(Some(termcolor::Color::Red))
+            .set_bold(true)
+            .set_reset(false),
+    );
+    let _ = stderr.write_all(b"error: ");
+    let _ = stderr.reset();
+    let _ = writeln!(stderr, "{}", error);
+
+    for error in error.chain().skip(1) {
+        let _ = writeln!(stderr, "\nCaused by:\n  {}", error);
+    }
+
+    let _ = stderr.flush();
+    process::exit(101);
+}
+
+pub fn run(opt: CargoMember, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let (message_format, dry_run, stdout) = match &opt {
+        CargoMember::Include(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::Exclude(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::Deactivate(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::ActivateAll(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::New(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::Cp(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::Rm(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        CargoMember::Mv(opt) => (opt.message_format, opt.dry_run, opt.stdout),
+        _ => return dispatch(opt, ctx, &mut Apply::Each),
+    };
+    if message_format == self::MessageFormat::Human {
+        return dispatch(opt, ctx, &mut Apply::Each);
+    }
+
+    // `--message-format` has a default value, which clap counts as present.
+    ensure!(dry_run, "`--message-format json` requires `--dry-run`");
+    ensure!(
+        !stdout,
+        "`--message-format json` cannot be used with `--stdout`"
+    );
+    let mut apply = Apply::Collect(vec![]);
+    dispatch(opt, ctx, &mut apply)?;
+    let mut stdout = io::stdout().lock();
+    for action in apply.planned() {
+        writeln!(stdout, "{}", action.to_json())?;
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+/// How the plans of the commands are performed.
+enum Apply {
+    /// Each plan is applied.
+    Each,
+    /// Each plan is applied, keeping what undoes it.
+    Undoably(Vec<Rollback>),
+    /// Nothing is performed. The actions are collected, each plan being made on top of the
+    /// earlier ones.
+    Collect(Vec<Action>),
+}
+
+impl Apply {
+    /// The actions the next plan is made on top of.
+    fn planned(&self) -> Vec<Action> {
+        match self {
+            Self::Collect(actions) => actions.clone(),
+            Self::Each | Self::Undoably(_) => vec![],
+        }
+    }
+
+    fn apply(&mut self, plan: Plan<impl WriteColor>) -> anyhow::Result<()> {
+        match self {
+            Self::Each => plan.apply(),
+            Self::Undoably(rollbacks) => {
+                let (result, rollback) = plan.apply_undoable();
+                rollbacks.push(rollback);
+                result
+            }
+            Self::Collect(actions) => {
+                // The plan starts with the collected actions.
+                *actions = plan.into_actions()?.0;
+                Ok(())
+            }
+        }
+    }
+}
+
+fn dispatch(
+    opt: CargoMember,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    match opt {
+        CargoMember::Init(opt) => init(opt, ctx),
+        CargoMember::Include(opt) => include(opt, ctx, apply),
+        CargoMember::Exclude(opt) => exclude(opt, ctx, apply),
+        CargoMember::Deactivate(opt) => deactivate(opt, ctx, apply),
+        CargoMember::Focus(opt) => focus(opt, ctx, apply),
+        CargoMember::ActivateAll(opt) => activate_all(opt, ctx, apply),
+        CargoMember::New(opt) => new(opt, ctx, apply),
+        CargoMember::Cp(opt) => cp(opt, ctx, apply),
+        CargoMember::Rm(opt) => rm(opt, ctx, apply),
+        CargoMember::Mv(opt) => mv(opt, ctx, apply),
+        CargoMember::Batch(opt) => batch(opt, ctx),
+        CargoMember::CheckVersionSync(opt) => check_version_sync(opt, ctx),
+        CargoMember::WhichWorkspace(opt) => which_workspace(opt, ctx),
+        CargoMember::Open(opt) => open(opt, ctx),
+        CargoMember::Exec(opt) => exec(opt, ctx),
+        CargoMember::Canonicalize(opt) => canonicalize(opt, ctx),
+        CargoMember::Sort(opt) => sort(opt, ctx, apply),
+        CargoMember::Bump(opt) => bump(opt, ctx),
+        CargoMember::VendorMember(opt) => vendor_member(opt, ctx),
+        CargoMember::Gc(opt) => gc(opt, ctx),
+        CargoMember::Status(opt) => status(opt, ctx),
+        CargoMember::Check(opt) => check(opt, ctx),
+        CargoMember::List(opt) => list(opt, ctx),
+        CargoMember::Completions(opt) => completions(opt, ctx),
+    }
+}
+
+fn init(opt: CargoMemberInit, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberInit {
+        members,
+        include_existing,
+        resolver,
+        workspace_package,
+        workspace_dependencies,
+        force,
+        dry_run,
+        path,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    Init::new(&crate::normalize_path(&cwd.join(path)))
+        .members(members)
+        .include_existing(include_existing)
+        .resolver(resolver)
+        .workspace_package(workspace_package)
+        .workspace_dependencies(workspace_dependencies)
+        .force(force)
+        .dry_run(dry_run)
+        .stderr(stderr)
+        .exec()
+}
+
+fn include(
+    opt: CargoMemberInclude,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberInclude {
+        manifest_path,
+        workspace_root,
+        verbose,
+        offline,
+        force,
+        dry_run,
+        stdout,
+        report_file,
+        flatten_nested,
+        recursive,
+        default,
+        private,
+        inherit_package,
+        verify,
+        comment,
+        relative_to_root,
+        from_stdin_json,
+        from_file,
+        from_file_base,
+        keep_going,
+        paths,
+        members_per_line,
+        prefix_dot_slash,
+        position,
+        explain,
+        ..
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stderr,
+        stderr_redirection,
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+    if verbose {
+        note_workspace_root(&possibly_empty_workspace_root, &mut stderr)?;
+    }
+
+    if explain {
+        self::explain(
+            &mut stderr,
+            format!(
+                "`include` adds the package(s) to `workspace.members` and removes them from \
+                 `workspace.exclude` in `{}`, then updates `Cargo.lock`",
+                possibly_empty_workspace_root.join("Cargo.toml").display(),
+            ),
+        )?;
+    }
+    let base = if relative_to_root {
+        &possibly_empty_workspace_root
+    } else {
+        &cwd
+    };
+
+    if from_stdin_json {
+        return include_from_stdin_json(
+            &possibly_empty_workspace_root,
+            base,
+            force,
+            offline,
+            dry_run,
+            flatten_nested,
+            stderr,
+            apply,
+        );
+    }
+
+    let (paths, member_list) = match &from_file {
+        Some(from_file) => {
+            let member_list = MemberList::read(
+                &cwd.join(from_file),
+                from_file_base,
+                &possibly_empty_workspace_root,
+                force,
+                |path| validate_package_dir(path, None, force),
+                keep_going,
+                &mut stderr,
+            )?;
+            (member_list.paths.clone(), Some(member_list))
+        }
+        None => (resolve_paths(paths, base, force || recursive)?, None),
+    };
+
+    let plan = Include::new(&possibly_empty_workspace_root, paths)
+        .force(force)
+        .offline(offline)
+        .members_per_line(members_per_line)
+        .prefix_dot_slash(prefix_dot_slash)
+        .position(position)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .flatten_nested(flatten_nested)
+        .recursive(recursive)
+        .default_members(default)
+        .private(private)
+        .inherit_package(inherit_package)
+        .verify(verify)
+        .verify_stderr_redirection(stderr_redirection)
+        .comment(comment.as_ref())
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)?;
+
+    member_list.map_or(Ok(()), MemberList::finish)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn include_from_stdin_json(
+    possibly_empty_workspace_root: &Path,
+    base: &Path,
+    force: bool,
+    offline: bool,
+    dry_run: bool,
+    flatten_nested: bool,
+    stderr: impl WriteColor,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let entries = serde_json::from_reader::<_, Vec<Entry>>(io::stdin())
+        .with_context(|| "failed to parse the JSON from stdin")?;
+
+    let entries = entries
+        .into_iter()
+        .map(|Entry { path, package }| {
+            let path = base.join(path.trim_leading_dots());
+            let error = validate_package_dir(&path, package.as_deref(), force).err();
+            (path, package, error)
+        })
+        .collect::<Vec<_>>();
+
+    let num_errors = entries.iter().filter(|(_, _, e)| e.is_some()).count();
+    let result = if num_errors > 0 {
+        Err(anyhow!(
+            "{} of {} entries are invalid. nothing is included",
+            num_errors,
+            entries.len(),
+        ))
+    } else {
+        let paths = entries.iter().map(|(path, _, _)| path);
+        Include::new(possibly_empty_workspace_root, paths)
+            .force(force)
+            .offline(offline)
+            .dry_run(dry_run)
+            .flatten_nested(flatten_nested)
+            .on_top_of(apply.planned())
+            .stderr(stderr)
+            .plan()
+            .and_then(|plan| apply.apply(plan))
+    };
+
+    let json = entries
+        .iter()
+        .map(|(path, package, error)| {
+            serde_json::json!({
+                "path": path,
+                "package": package,
+                "result": match (error, &result) {
+                    (Some(_), _) => "invalid",
+                    (None, Ok(())) => "included",
+                    (None, Err(_)) => "skipped",
+                },
+                "error": error.as_ref().map(|e| format!("{:#}", e)),
+            })
+        })
+        .collect::<Vec<_>>();
+    writeln!(io::stdout(), "{}", serde_json::Value::from(json))?;
+    return result;
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Entry {
+        path: PathBuf,
+        package: Option<String>,
+    }
+}
+
+/// Prints the contract of the command for `--explain`.
+fn explain(mut stderr: impl WriteColor, explanation: String) -> io::Result<()> {
+    stderr.status_with_color("Explain", explanation, termcolor::Color::Cyan)
+}
+
+/// Checks that `path` is a package (named `package` if specified) that can be included.
+fn validate_package_dir(path: &Path, package: Option<&str>, force: bool) -> anyhow::Result<()> {
+    let manifest_path = path.join("Cargo.toml");
+    if !manifest_path.exists() {
+        if force && package.is_none() {
+            return Ok(());
+        }
+        bail!("`{}` does not exist", manifest_path.display());
+    }
+    if let Some(package) = package {
+        let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+        let name = cargo_toml["package"]["name"].as_str();
+        if name != Some(package) {
+            bail!(
+                "expected `{}`, found {} at `{}`",
+                package,
+                name.map_or_else(|| "no package".to_owned(), |n| format!("`{}`", n)),
+                manifest_path.display(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Paths read from a `--from-file` list.
+struct MemberList {
+    file: PathBuf,
+    paths: Vec<PathBuf>,
+    num_entries: usize,
+    num_invalid: usize,
+}
+
+impl MemberList {
+    /// Reads `file`, resolving each entry (which may be a glob) and checking it with `validate`.
+    /// Globs match only packages unless `any_dir`.
+    ///
+    /// Each invalid entry is reported. Unless `keep_going` is enabled, an error is returned if
+    /// there is any.
+    fn read(
+        file: &Path,
+        base: self::FromFileBase,
+        workspace_root: &Path,
+        any_dir: bool,
+        validate: impl Fn(&Path) -> anyhow::Result<()>,
+        keep_going: bool,
+        mut stderr: impl WriteColor,
+    ) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read `{}`", file.display()))?;
+        let base = match base {
+            self::FromFileBase::File => file.parent().unwrap_or(workspace_root),
+            self::FromFileBase::Root => workspace_root,
+        };
+
+        let (mut paths, mut num_entries, mut num_invalid) = (vec![], 0, 0);
+        for (i, line) in content.lines().enumerate() {
+            let entry = line.split('#').next().unwrap_or_default().trim();
+            if entry.is_empty() {
+                continue;
+            }
+            num_entries += 1;
+            let resolved = resolve_paths(vec![entry.into()], base, any_dir).and_then(|resolved| {
+                let resolved = resolved
+                    .iter()
+                    .map(|p| crate::normalize_path(p))
+                    .collect::<Vec<_>>();
+                resolved.iter().try_for_each(|p| validate(p))?;
+                Ok(resolved)
+            });
+            match resolved {
+                Ok(resolved) => paths.extend(resolved),
+                Err(err) => {
+                    num_invalid += 1;
+                    stderr.warn(format!("{}:{}: {:#}", file.display(), i + 1, err))?;
+                }
+            }
+        }
+
+        if num_invalid > 0 && !keep_going {
+            bail!(
+                "{} of {} entries in `{}` are invalid. nothing is applied. enable `--keep-going` \
+                 to apply the valid ones",
+                num_invalid,
+                num_entries,
+                file.display(),
+            );
+        }
+        Ok(Self {
+            file: file.to_owned(),
+            paths,
+            num_entries,
+            num_invalid,
+        })
+    }
+
+    /// Fails if any entry has been skipped.
+    fn finish(self) -> anyhow::Result<()> {
+        if self.num_invalid > 0 {
+            bail!(
+                "{} of {} entries in `{}` were skipped",
+                self.num_invalid,
+                self.num_entries,
+                self.file.display(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn exclude(
+    opt: CargoMemberExclude,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberExclude {
+        package,
+        relative_to_root,
+        manifest_path,
+        workspace_root,
+        offline,
+        dry_run,
+        stdout,
+        report_file,
+        ignore_missing,
+        strict,
+        force,
+        matching,
+        exclude_matching,
+        match_field,
+        from_file,
+        from_file_base,
+        recursive,
+        keep_going,
+        paths,
+        members_per_line,
+        prefix_dot_slash,
+        explain,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    ensure!(
+        !keep_going || from_file.is_some() || recursive,
+        "`--keep-going` requires `--from-file` or `--recursive`",
+    );
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+
+    if explain {
+        self::explain(
+            &mut stderr,
+            format!(
+                "`exclude` moves the package(s) from `workspace.members` to `workspace.exclude` \
+                 in `{}`, so that cargo ignores them while they stay under the workspace root",
+                metadata.workspace_root.join("Cargo.toml"),
+            ),
+        )?;
+    }
+    let paths = resolve_paths(
+        paths,
+        if relative_to_root {
+            metadata.workspace_root.as_std_path()
+        } else {
+            &cwd
+        },
+        false,
+    )?;
+    let (paths, member_list) = match &from_file {
+        Some(from_file) => {
+            let member_list = MemberList::read(
+                &cwd.join(from_file),
+                from_file_base,
+                metadata.workspace_root.as_std_path(),
+                false,
+                |path| {
+                    ensure!(
+                        ignore_missing || path.exists(),
+                        "`{}` does not exist",
+                        path.display(),
+                    );
+                    Ok(())
+                },
+                keep_going,
+                &mut stderr,
+            )?;
+            (member_list.paths.clone(), Some(member_list))
+        }
+        None => (paths, None),
+    };
+    let (paths, num_skipped) = if recursive {
+        expand_recursive(&metadata, paths, keep_going, &mut stderr)?
+    } else {
+        (paths, 0)
+    };
+    let (paths, package) = select_matching(
+        &metadata,
+        paths,
+        package,
+        matching.as_ref(),
+        exclude_matching.as_ref(),
+        match_field,
+    );
+
+    let plan = Exclude::from_metadata(&metadata, paths, package)
+        .members_per_line(members_per_line)
+        .prefix_dot_slash(prefix_dot_slash)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .ignore_missing(ignore_missing)
+        .strict(strict)
+        .force(force)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)?;
+
+    finish_recursive(num_skipped)?;
+    member_list.map_or(Ok(()), MemberList::finish)
+}
+
+fn deactivate(
+    opt: CargoMemberDeactivate,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberDeactivate {
+        package,
+        manifest_path,
+        workspace_root,
+        offline,
+        dry_run,
+        stdout,
+        report_file,
+        ignore_missing,
+        strict,
+        force,
+        recursive,
+        keep_going,
+        paths,
+        members_per_line,
+        explain,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+    let paths = resolve_paths(paths, &cwd, false)?;
+    let (paths, num_skipped) = if recursive {
+        expand_recursive(&metadata, paths, keep_going, &mut stderr)?
+    } else {
+        (paths, 0)
+    };
+
+    if explain {
+        self::explain(
+            &mut stderr,
+            format!(
+                "`deactivate` removes the package(s) from both `workspace.members` and \
+                 `workspace.exclude` in `{}`, leaving their directories as they are",
+                metadata.workspace_root.join("Cargo.toml"),
+            ),
+        )?;
+    }
+
+    let plan = Deactivate::from_metadata(&metadata, paths, package)
+        .members_per_line(members_per_line)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .ignore_missing(ignore_missing)
+        .strict(strict)
+        .force(force)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)?;
+
+    finish_recursive(num_skipped)
+}
+
+fn focus(
+    opt: CargoMemberFocus,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberFocus {
+        package,
+        restore,
+        exclude,
+        force,
+        with_dependents,
+        allow_broken,
+        dry_run,
+        stdout,
+        report_file,
+        message_format,
+        manifest_path,
+        workspace_root,
+        offline,
+        path,
+        members_per_line,
+        prefix_dot_slash,
+        explain,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    // `--message-format` has a default value, which clap counts as present.
+    if message_format == self::MessageFormat::Json {
+        ensure!(dry_run, "`--message-format json` requires `--dry-run`");
+        ensure!(
+            !stdout,
+            "`--message-format json` cannot be used with `--stdout`"
+        );
+    }
+
+    if restore {
+        ensure!(
+            message_format != self::MessageFormat::Json,
+            "`--message-format json` cannot be used with `--restore`",
+        );
+        let workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+        if explain {
+            self::explain(
+                &mut stderr,
+                format!(
+                    "`focus --restore` reinstates `workspace.members` and `workspace.exclude` in \
+                     `{}` from `{}`, then removes it",
+                    workspace_root.join("Cargo.toml").display(),
+                    workspace_root.join(FOCUS_SNAPSHOT_FILE_NAME).display(),
+                ),
+            )?;
+        }
+        let plan = RestoreFocus::new(&workspace_root)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .offline(offline)
+            .on_top_of(apply.planned())
+            .stderr(stderr)
+            .plan()?
+            .report_file(report_file.map(|p| cwd.join(p)));
+        return apply.apply(plan);
+    }
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+    let workspace_root = metadata.workspace_root.clone().into_std_path_buf();
+    let path = match package {
+        Some(spec) => metadata
+            .query_for_member(Some(&spec))?
+            .manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+            .to_owned()
+            .into_std_path_buf(),
+        None => cwd.join(
+            path.expect("required unless `--package`")
+                .trim_leading_dots(),
+        ),
+    };
+
+    if explain {
+        self::explain(
+            &mut stderr,
+            format!(
+                "`focus` includes `{}` and {} every other member, editing `workspace.members` and \
+                 `workspace.exclude` in `{}`",
+                path.display(),
+                if exclude { "excludes" } else { "deactivates" },
+                workspace_root.join("Cargo.toml").display(),
+            ),
+        )?;
+    }
+
+    let plan = Focus::from_metadata(&metadata, &path)
+        .with_dependents(with_dependents)
+        .allow_broken(allow_broken)
+        .members_per_line(members_per_line)
+        .prefix_dot_slash(prefix_dot_slash)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .offline(offline)
+        .exclude(exclude)
+        .force(force)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?;
+
+    if message_format == self::MessageFormat::Json {
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let before = Action::read_toml_planned(&apply.planned(), &manifest_path)?;
+        let after = match plan.actions().iter().rev().find_map(|action| match action {
+            Action::WriteFile { path, after, .. } if *path == manifest_path => Some(after),
+            _ => None,
+        }) {
+            Some(after) => crate::fs::parse_toml_edit(after, &manifest_path)?,
+            None => before.clone(),
+        };
+        let entries = |cargo_toml: &toml_edit::Document, field: &str| {
+            cargo_toml["workspace"][field]
+                .as_array()
+                .into_iter()
+                .flat_map(|a| a.iter())
+                .flat_map(|v| v.as_str())
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        };
+        let (members_before, members_after) =
+            (entries(&before, "members"), entries(&after, "members"));
+        let (exclude_before, exclude_after) =
+            (entries(&before, "exclude"), entries(&after, "exclude"));
+
+        let json = serde_json::json!({
+            "focus": crate::relative_path(&workspace_root, &path),
+            "deactivate": members_before
+                .iter()
+                .filter(|m| !members_after.contains(m))
+                .collect::<Vec<_>>(),
+            "exclude": exclude_after
+                .iter()
+                .filter(|e| !exclude_before.contains(e))
+                .collect::<Vec<_>>(),
+            "default_members": entries(&after, "default-members"),
+        });
+        writeln!(io::stdout(), "{}", json)?;
+    }
+
+    apply.apply(plan.report_file(report_file.map(|p| cwd.join(p))))
+}
+
+fn activate_all(
+    opt: CargoMemberActivateAll,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberActivateAll {
+        manifest_path,
+        workspace_root,
+        verbose,
+        offline,
+        force,
+        dry_run,
+        stdout,
+        report_file,
+        members_per_line,
+        prefix_dot_slash,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    let workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+    if verbose {
+        note_workspace_root(&workspace_root, &mut stderr)?;
+    }
+
+    let plan = ActivateAll::new(&workspace_root)
+        .force(force)
+        .members_per_line(members_per_line)
+        .prefix_dot_slash(prefix_dot_slash)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .offline(offline)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)
+}
+
+fn new(
+    opt: CargoMemberNew,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberNew {
+        manifest_path,
+        workspace_root,
+        registry,
+        vcs,
+        bin,
+        lib,
+        name,
+        inherit_lints,
+        no_inherit_lints,
+        default,
+        private,
+        inherit_package,
+        set_resolver,
+        verbose,
+        offline,
+        dry_run,
+        stdout,
+        report_file,
+        path,
+        members_per_line,
+        prefix_dot_slash,
+        position,
+        set_field,
+        smoke_test,
+        rollback_on_failure,
+        ..
+    } = opt;
+
+    let Context {
+        cwd,
+        mut stderr,
+        stderr_redirection,
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    let set_fields = parse_set_fields(&set_field)?;
+
+    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+    if verbose {
+        note_workspace_root(&possibly_empty_workspace_root, &mut stderr)?;
+    }
+    let path = crate::normalize_path(&cwd.join(path.trim_leading_dots()));
+
+    let plan = New::new(&possibly_empty_workspace_root, &path)
+        .cargo_new_registry(registry)
+        .cargo_new_vcs(vcs)
+        .cargo_new_bin(bin)
+        .cargo_new_lib(lib)
+        .cargo_new_name(name)
+        .cargo_new_stderr_redirection(stderr_redirection)
+        .inherit_lints(match (inherit_lints, no_inherit_lints) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        })
+        .set_resolver(set_resolver)
+        .default_members(default)
+        .private(private)
+        .inherit_package(inherit_package)
+        .offline(offline)
+        .members_per_line(members_per_line)
+        .prefix_dot_slash(prefix_dot_slash)
+        .position(position)
+        .set_fields(set_fields.iter().copied())
+        .smoke_test(smoke_test)
+        .rollback_on_failure(rollback_on_failure)
+        .verbose(verbose)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)
+}
+
+fn cp(opt: CargoMemberCp, ctx: Context<impl WriteColor>, apply: &mut Apply) -> anyhow::Result<()> {
+    let CargoMemberCp {
+        manifest_path,
+        workspace_root,
+        verbose,
+        time,
+        offline,
+        dry_run,
+        stdout,
+        report_file,
+        no_rename,
+        rename_paths,
+        name_suffix,
+        set_version,
+        bump,
+        scan_path_attrs,
+        fix_path_attrs,
+        inline_inherited,
+        smoke_test,
+        rollback_on_failure,
+        allow_duplicate_names,
+        minimal,
+        exclude,
+        respect_cargo_config,
+        artifact_dirs,
+        copy_all,
+        set_resolver,
+        force,
+        register_in,
+        replace_in,
+        src,
+        dst,
+        members_per_line,
+        prefix_dot_slash,
+        set_field,
+        strip_path_deps_to_versions,
+        keep_path,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    let set_fields = parse_set_fields(&set_field)?;
+
+    let timings = if time {
+        Timings::new()
+    } else {
+        Timings::default()
+    };
+
+    let start = Instant::now();
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+    timings.add("metadata fetch", start);
+    let dst = cwd.join(dst.trim_leading_dots());
+    ensure_dir_for_multiple_srcs(&src, &dst)?;
+    let replace_in = replace_in
+        .map(|spec| -> anyhow::Result<_> {
+            Ok(metadata
+                .query_for_member(Some(&spec))?
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                .to_owned()
+                .into_std_path_buf())
+        })
+        .transpose()?;
+
+    for src in &src {
+        let plan = Cp::from_metadata(&metadata, src, &dst)
+            .members_per_line(members_per_line)
+            .prefix_dot_slash(prefix_dot_slash)
+            .set_fields(set_fields.iter().copied())
+            .strip_path_deps_to_versions(strip_path_deps_to_versions)
+            .keep_path(keep_path)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .no_rename(no_rename)
+            .rename_paths(rename_paths)
+            .name_suffix(name_suffix.as_ref())
+            .version(
+                set_version
+                    .clone()
+                    .map(BumpLevel::Version)
+                    .or_else(|| bump.clone()),
+            )
+            .scan_path_attrs(scan_path_attrs)
+            .fix_path_attrs(fix_path_attrs)
+            .inline_inherited(inline_inherited)
+            .smoke_test(smoke_test)
+            .rollback_on_failure(rollback_on_failure)
+            .offline(offline)
+            .verbose(verbose)
+            .timings(timings.clone())
+            .allow_duplicate_names(allow_duplicate_names)
+            .minimal(minimal)
+            .exclude(&exclude)
+            .respect_cargo_config(respect_cargo_config)
+            .artifact_dirs(&artifact_dirs)
+            .copy_all(copy_all)
+            .set_resolver(set_resolver.as_ref())
+            .force(force)
+            .register_in(register_in)
+            .replace_in(replace_in.as_deref())
+            .on_top_of(apply.planned())
+            .stderr(&mut stderr)
+            .plan()?
+            .report_file(report_file.as_ref().map(|p| cwd.join(p)));
+        apply.apply(plan)?;
+    }
+    timings.report(stderr).map_err(Into::into)
+}
+
+fn rm(opt: CargoMemberRm, ctx: Context<impl WriteColor>, apply: &mut Apply) -> anyhow::Result<()> {
+    let CargoMemberRm {
+        package,
+        manifest_path,
+        workspace_root,
+        offline,
+        force,
+        dry_run,
+        stdout,
+        report_file,
+        ignore_missing,
+        prune_deps,
+        matching,
+        exclude_matching,
+        match_field,
+        recursive,
+        keep_going,
+        paths,
+        members_per_line,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+    let paths = resolve_paths(paths, &cwd, force)?;
+    let (paths, num_skipped) = if recursive {
+        expand_recursive(&metadata, paths, keep_going, &mut stderr)?
+    } else {
+        (paths, 0)
+    };
+    let (paths, package) = select_matching(
+        &metadata,
+        paths,
+        package,
+        matching.as_ref(),
+        exclude_matching.as_ref(),
+        match_field,
+    );
+
+    let plan = Rm::from_metadata(&metadata, paths, package)
+        .force(force)
+        .members_per_line(members_per_line)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .ignore_missing(ignore_missing)
+        .prune_deps(prune_deps)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)?;
+
+    finish_recursive(num_skipped)
+}
+
+fn mv(opt: CargoMemberMv, ctx: Context<impl WriteColor>, apply: &mut Apply) -> anyhow::Result<()> {
+    let CargoMemberMv {
+        manifest_path,
+        workspace_root,
+        verbose,
+        time,
+        offline,
+        dry_run,
+        stdout,
+        report_file,
+        no_rename,
+        rename_paths,
+        allow_duplicate_names,
+        update_readme_links,
+        scan_path_attrs,
+        fix_path_attrs,
+        inline_inherited,
+        into_manifest,
+        smoke_test,
+        merge_into,
+        force,
+        dependent_scan,
+        no_update_deps,
+        src,
+        dst,
+        members_per_line,
+        prefix_dot_slash,
+        set_field,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    let set_fields = parse_set_fields(&set_field)?;
+
+    let timings = if time {
+        Timings::new()
+    } else {
+        Timings::default()
+    };
+
+    let start = Instant::now();
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
+    timings.add("metadata fetch", start);
+    if let Some(merge_into) = merge_into {
+        // clap assigns a lone positional argument to `<dst>`.
+        let src = match (&*src, dst) {
+            ([src], None) => src.clone(),
+            ([], Some(src)) => src.to_string_lossy().into_owned(),
+            _ => bail!("`--merge-into` takes only one package"),
+        };
+        let merge_into = MergeInto::from_metadata(&metadata, &src, &merge_into)
+            .force(force)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .on_top_of(apply.planned())
+            .stderr(&mut stderr);
+        let names = merge_into.names();
+        let plan = merge_into
+            .plan()?
+            .report_file(report_file.as_ref().map(|p| cwd.join(p)));
+        apply
+            .apply(plan)
+            .map_err(|err| crate::dependency_cycle_context(err, names))?;
+        return timings.report(stderr).map_err(Into::into);
+    }
+
+    let dst = cwd.join(
+        dst.expect("required unless `--merge-into`")
+            .trim_leading_dots(),
+    );
+    ensure_dir_for_multiple_srcs(&src, &dst)?;
+    let into_manifest = into_manifest.map(|p| cwd.join(p.trim_leading_dots()));
+
+    for src in &src {
+        let plan = Mv::from_metadata(&metadata, src, &dst)
+            .members_per_line(members_per_line)
+            .prefix_dot_slash(prefix_dot_slash)
+            .set_fields(set_fields.iter().copied())
+            .dependent_scan(dependent_scan)
+            .update_deps(!no_update_deps)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .no_rename(no_rename)
+            .rename_paths(rename_paths)
+            .allow_duplicate_names(allow_duplicate_names)
+            .update_readme_links(update_readme_links)
+            .scan_path_attrs(scan_path_attrs)
+            .fix_path_attrs(fix_path_attrs)
+            .inline_inherited(inline_inherited)
+            .into_manifest(into_manifest.as_deref())
+            .smoke_test(smoke_test)
+            .offline(offline)
+            .verbose(verbose)
+            .timings(timings.clone())
+            .on_top_of(apply.planned())
+            .stderr(&mut stderr)
+            .plan()?
+            .report_file(report_file.as_ref().map(|p| cwd.join(p)));
+        apply.apply(plan)?;
     }
+    timings.report(stderr).map_err(Into::into)
 }
 
-pub fn init_logger(color: self::ColorChoice) {
-    env_logger::Builder::from_default_env()
-        .write_style(color.into())
-        .init();
-}
+fn batch(opt: CargoMemberBatch, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberBatch { dry_run, file, .. } = opt;
 
-pub fn stderr(color: self::ColorChoice) -> BufferedStandardStream {
-    BufferedStandardStream::stderr(match color {
-        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
-        self::ColorChoice::Always => termcolor::ColorChoice::Always,
-        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
-    })
-}
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
 
-pub fn exit_with_error(error: anyhow::Error, color: self::ColorChoice) -> ! {
-    let mut stderr = BufferedStandardStream::stderr(match color {
-        self::ColorChoice::Auto if atty::is(atty::Stream::Stderr) => termcolor::ColorChoice::Auto,
-        self::ColorChoice::Always => termcolor::ColorChoice::Always,
-        self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
-    });
+    let (json, source) = if file == Path::new("-") {
+        let mut json = String::new();
+        io::stdin()
+            .read_to_string(&mut json)
+            .with_context(|| "failed to read stdin")?;
+        (json, "stdin".to_owned())
+    } else {
+        let file = cwd.join(file);
+        let json = std::fs::read_to_string(&file)
+            .with_context(|| format!("failed to read `{}`", file.display()))?;
+        (json, format!("`{}`", file.display()))
+    };
+    let steps = serde_json::from_str::<Vec<Step>>(&json)
+        .with_context(|| format!("failed to parse {}", source))?;
 
-    let _ = stderr.set_color(
-        ColorSpec::new()
-            .set_fg(Some(termcolor::Color::Red))
-            .set_bold(true)
-            .set_reset(false),
-    );
-    let _ = stderr.write_all(b"error: ");
-    let _ = stderr.reset();
-    let _ = writeln!(stderr, "{}", error);
+    // Every step is checked before any of them runs.
+    let num_steps = steps.len();
+    let steps = steps
+        .into_iter()
+        .enumerate()
+        .map(|(i, Step { command, args })| {
+            let description = format!(
+                "step {} of {} (`cargo member {}{}`)",
+                i + 1,
+                num_steps,
+                command,
+                args.iter().format_with("", |arg, f| f(&format_args!(
+                    " {}",
+                    shell_escape::escape(arg.as_str().into()),
+                ))),
+            );
+            let mut opt = CargoMember::from_iter_safe(
+                iter::once("cargo-member")
+                    .chain(iter::once(&*command))
+                    .chain(args.iter().map(|a| &**a)),
+            )
+            .map_err(|err| {
+                let message = err.message.lines().next().unwrap_or_default();
+                anyhow!("{}", message.trim_start_matches("error: "))
+            })
+            .with_context(|| format!("invalid {}", description))?;
+            match batch_options(&mut opt) {
+                Some((_, self::MessageFormat::Json)) => bail!(
+                    "`--message-format json` cannot be used in a batch: {}",
+                    description,
+                ),
+                Some((step_dry_run, self::MessageFormat::Human)) => *step_dry_run |= dry_run,
+                None => bail!("`{}` cannot be used in a batch: {}", command, description),
+            }
+            Ok((description, opt))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
 
-    for error in error.chain().skip(1) {
-        let _ = writeln!(stderr, "\nCaused by:\n  {}", error);
+    // Each step is planned on top of the earlier ones on dry run, and is undone with them on
+    // failure otherwise.
+    let mut apply = if dry_run {
+        Apply::Collect(vec![])
+    } else {
+        Apply::Undoably(vec![])
+    };
+    for (i, (description, opt)) in steps.into_iter().enumerate() {
+        stderr.status("Running", &description)?;
+        let ctx = Context {
+            cwd: cwd.clone(),
+            stderr: &mut stderr as &mut dyn WriteColor,
+            stderr_redirection: Stdio::inherit(),
+        };
+        if let Err(err) = dispatch(opt, ctx, &mut apply) {
+            if let Apply::Undoably(rollbacks) = &mut apply {
+                for rollback in rollbacks.iter_mut().rev() {
+                    rollback.restore(&mut stderr)?;
+                }
+            }
+            return Err(err.context(format!(
+                "{} failed. the remaining {} step(s) were not run and the changes of the batch \
+                 were undone",
+                description,
+                num_steps - i - 1,
+            )));
+        }
+    }
+    if !dry_run {
+        return Ok(());
     }
+    let mut stdout = io::stdout().lock();
+    for action in apply.planned() {
+        writeln!(stdout, "{}", action.to_json())?;
+    }
+    stdout.flush().map_err(Into::into)
+}
 
-    let _ = stderr.flush();
-    process::exit(101);
+/// A step of `cargo member batch`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
 }
 
-pub fn run(opt: CargoMember, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+/// `--dry-run` and `--message-format` of the commands `batch` can run.
+fn batch_options(opt: &mut CargoMember) -> Option<(&mut bool, self::MessageFormat)> {
     match opt {
-        CargoMember::Include(opt) => include(opt, ctx),
-        CargoMember::Exclude(opt) => exclude(opt, ctx),
-        CargoMember::Deactivate(opt) => deactivate(opt, ctx),
-        CargoMember::Focus(opt) => focus(opt, ctx),
-        CargoMember::New(opt) => new(opt, ctx),
-        CargoMember::Cp(opt) => cp(opt, ctx),
-        CargoMember::Rm(opt) => rm(opt, ctx),
-        CargoMember::Mv(opt) => mv(opt, ctx),
+        CargoMember::Include(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Exclude(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Deactivate(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Focus(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::ActivateAll(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::New(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Cp(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Rm(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Mv(opt) => Some((&mut opt.dry_run, opt.message_format)),
+        CargoMember::Sort(opt) => Some((&mut opt.dry_run, self::MessageFormat::Human)),
+        _ => None,
     }
 }
 
-fn include(opt: CargoMemberInclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberInclude {
+fn check_version_sync(
+    opt: CargoMemberCheckVersionSync,
+    ctx: Context<impl WriteColor>,
+) -> anyhow::Result<()> {
+    let CargoMemberCheckVersionSync {
+        expected,
+        fix,
         manifest_path,
+        workspace_root,
         offline,
-        force,
         dry_run,
-        paths,
+        stdout,
         ..
     } = opt;
 
-    let Context { cwd, stderr, .. } = ctx;
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
 
-    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
 
-    Include::new(&possibly_empty_workspace_root, paths)
-        .force(force)
-        .offline(offline)
+    let check_version_sync = match crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    ) {
+        Ok(metadata) => CheckVersionSync::from_metadata(&metadata),
+        // Without `--fix` nothing is modified, so the manifests are good enough.
+        Err(err) if !fix => {
+            warn_metadata_failure(&mut stderr, &err)?;
+            let workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+            CheckVersionSync::from_manifests(&workspace_root)
+        }
+        Err(err) => return Err(err),
+    };
+
+    check_version_sync
+        .expected(expected)
+        .fix(fix)
         .dry_run(dry_run)
+        .stdout(stdout)
         .stderr(stderr)
         .exec()
 }
 
-fn exclude(opt: CargoMemberExclude, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberExclude {
-        package,
+fn status(opt: CargoMemberStatus, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberStatus {
         manifest_path,
-        offline,
-        dry_run,
-        paths,
+        workspace_root,
+        verbose,
+        check,
+        message_format,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let metadata =
-        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    let (possibly_empty_workspace_root, manifest_file_name) =
+        find_root_manifest_with_file_name(workspace_root, manifest_path, &cwd)?;
 
-    Exclude::from_metadata(&metadata, paths, package)
-        .dry_run(dry_run)
+    Status::new(&possibly_empty_workspace_root)
+        .manifest_file_name(manifest_file_name)
+        .verbose(verbose)
+        .check(check)
+        .json_lines(message_format == self::MessageFormat::Json)
         .stderr(stderr)
         .exec()
 }
 
-fn deactivate(opt: CargoMemberDeactivate, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberDeactivate {
-        package,
+fn check(opt: CargoMemberCheck, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberCheck {
         manifest_path,
+        workspace_root,
         offline,
-        dry_run,
-        paths,
+        locked,
+        require_sorted,
+        sort_key,
+        json,
+        message_format,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let metadata =
-        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
+    ensure!(
+        !(json && message_format == self::MessageFormat::Json),
+        "`--json` cannot be used with `--message-format json`",
+    );
 
-    Deactivate::from_metadata(&metadata, paths, package)
-        .dry_run(dry_run)
+    let (possibly_empty_workspace_root, manifest_file_name) =
+        find_root_manifest_with_file_name(workspace_root, manifest_path, &cwd)?;
+
+    Check::new(&possibly_empty_workspace_root)
+        .manifest_file_name(manifest_file_name)
+        .require_sorted(require_sorted)
+        .sort_key(sort_key)
+        .locked(locked)
+        .offline(offline)
+        .json(json)
+        .json_lines(message_format == self::MessageFormat::Json)
         .stderr(stderr)
         .exec()
 }
 
-fn focus(opt: CargoMemberFocus, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberFocus {
+fn bump(opt: CargoMemberBump, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberBump {
+        package,
         exclude,
-        dry_run,
+        workspace_package,
         manifest_path,
+        workspace_root,
         offline,
-        path,
+        dry_run,
+        stdout,
+        report_file,
+        level,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let Metadata { workspace_root, .. } =
-        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let path = cwd.join(path.trim_leading_dots());
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
 
-    Focus::new(&workspace_root.into_std_path_buf(), &path)
+    Bump::from_metadata(&metadata, level, package, exclude)
+        .workspace_package(workspace_package)
         .dry_run(dry_run)
-        .offline(offline)
-        .exclude(exclude)
+        .stdout(stdout)
         .stderr(stderr)
-        .exec()
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)))
+        .apply()
 }
 
-fn new(opt: CargoMemberNew, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberNew {
+fn list(opt: CargoMemberList, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberList {
         manifest_path,
-        registry,
-        vcs,
-        lib,
-        name,
+        workspace_root,
         offline,
-        dry_run,
-        path,
+        with_deps_count,
+        with_loc,
+        only,
+        print_members_only,
+        names,
+        message_format,
+        color,
+        no_color,
         ..
     } = opt;
 
-    let Context {
-        cwd,
-        stderr,
-        stderr_redirection,
-    } = ctx;
+    let Context { cwd, stderr, .. } = ctx;
 
-    let possibly_empty_workspace_root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
-    let path = cwd.join(path.trim_leading_dots());
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+    // `--message-format` has a default value, which clap counts as present.
+    ensure!(
+        !(print_members_only && message_format == self::MessageFormat::Json),
+        "`--print-members-only` cannot be used with `--message-format json`",
+    );
 
-    New::new(&possibly_empty_workspace_root, &path)
-        .cargo_new_registry(registry)
-        .cargo_new_vcs(vcs)
-        .cargo_new_lib(lib)
-        .cargo_new_name(name)
-        .cargo_new_stderr_redirection(stderr_redirection)
-        .offline(offline)
-        .dry_run(dry_run)
+    // The resolve graph costs resolving the dependencies. `--locked` keeps `Cargo.lock` as it is.
+    let metadata = if with_deps_count {
+        crate::cargo_metadata(manifest_path.as_deref(), false, true, offline, &cwd)?
+    } else {
+        crate::cargo_metadata_no_deps(manifest_path.as_deref(), offline, &cwd)?
+    };
+
+    List::from_metadata(&metadata)
+        .with_deps_count(with_deps_count)
+        .with_loc(with_loc)
+        .only(only)
+        .print_members_only(print_members_only)
+        .names(names)
+        .json_lines(message_format == self::MessageFormat::Json)
+        .color(match color {
+            _ if no_color => termcolor::ColorChoice::Never,
+            self::ColorChoice::Auto if atty::is(atty::Stream::Stdout) => {
+                termcolor::ColorChoice::Auto
+            }
+            self::ColorChoice::Always => termcolor::ColorChoice::Always,
+            self::ColorChoice::Auto | self::ColorChoice::Never => termcolor::ColorChoice::Never,
+        })
         .stderr(stderr)
         .exec()
 }
 
-fn cp(opt: CargoMemberCp, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberCp {
+fn vendor_member(
+    opt: CargoMemberVendorMember,
+    ctx: Context<impl WriteColor>,
+) -> anyhow::Result<()> {
+    let CargoMemberVendorMember {
         manifest_path,
+        workspace_root,
         offline,
         dry_run,
-        no_rename,
-        src,
-        dst,
+        stdout,
+        report_file,
+        spec,
+        strip_path_deps_to_versions,
+        keep_path,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let metadata =
-        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let dst = cwd.join(dst.trim_leading_dots());
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(
+        manifest_path.as_deref(),
+        dry_run || stdout,
+        dry_run || stdout,
+        offline,
+        &cwd,
+    )?;
 
-    Cp::from_metadata(&metadata, &src, &dst)
+    VendorMember::from_metadata(&metadata, &spec)
+        .strip_path_deps_to_versions(strip_path_deps_to_versions)
+        .keep_path(keep_path)
         .dry_run(dry_run)
-        .no_rename(no_rename)
+        .stdout(stdout)
         .stderr(stderr)
-        .exec()
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)))
+        .apply()
 }
 
-fn rm(opt: CargoMemberRm, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberRm {
-        package,
+fn gc(opt: CargoMemberGc, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberGc {
         manifest_path,
+        workspace_root,
         offline,
         force,
         dry_run,
-        paths,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
     let metadata =
         crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let paths = paths.into_iter().map(|p| cwd.join(p.trim_leading_dots()));
 
-    Rm::from_metadata(&metadata, paths, package)
+    Gc::from_metadata(&metadata)
         .force(force)
         .dry_run(dry_run)
         .stderr(stderr)
         .exec()
 }
 
-fn mv(opt: CargoMemberMv, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
-    let CargoMemberMv {
+fn canonicalize(opt: CargoMemberCanonicalize, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberCanonicalize {
         manifest_path,
-        offline,
+        workspace_root,
         dry_run,
-        no_rename,
-        src,
-        dst,
+        stdout,
+        report_file,
         ..
     } = opt;
 
     let Context { cwd, stderr, .. } = ctx;
 
-    let metadata =
-        crate::cargo_metadata(manifest_path.as_deref(), dry_run, dry_run, offline, &cwd)?;
-    let dst = cwd.join(dst.trim_leading_dots());
+    let (possibly_empty_workspace_root, manifest_file_name) =
+        find_root_manifest_with_file_name(workspace_root, manifest_path, &cwd)?;
 
-    Mv::from_metadata(&metadata, &src, &dst)
+    Canonicalize::new(&possibly_empty_workspace_root)
+        .manifest_file_name(manifest_file_name)
         .dry_run(dry_run)
-        .no_rename(no_rename)
+        .stdout(stdout)
         .stderr(stderr)
-        .exec()
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)))
+        .apply()
+}
+
+fn sort(
+    opt: CargoMemberSort,
+    ctx: Context<impl WriteColor>,
+    apply: &mut Apply,
+) -> anyhow::Result<()> {
+    let CargoMemberSort {
+        manifest_path,
+        workspace_root,
+        check,
+        dry_run,
+        stdout,
+        report_file,
+        ..
+    } = opt;
+
+    let Context { cwd, stderr, .. } = ctx;
+
+    let (possibly_empty_workspace_root, manifest_file_name) =
+        find_root_manifest_with_file_name(workspace_root, manifest_path, &cwd)?;
+
+    let plan = Sort::new(&possibly_empty_workspace_root)
+        .manifest_file_name(manifest_file_name)
+        .check(check)
+        .dry_run(dry_run)
+        .stdout(stdout)
+        .on_top_of(apply.planned())
+        .stderr(stderr)
+        .plan()?
+        .report_file(report_file.map(|p| cwd.join(p)));
+    apply.apply(plan)
+}
+
+fn completions(opt: CargoMemberCompletions, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    const BIN_NAME: &str = "cargo-member";
+
+    let CargoMemberCompletions {
+        all,
+        out_dir,
+        shell,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let shells = if all {
+        vec![
+            Shell::Bash,
+            Shell::Zsh,
+            Shell::Fish,
+            Shell::PowerShell,
+            Shell::Elvish,
+        ]
+    } else {
+        shell.into_iter().collect()
+    };
+
+    for shell in shells {
+        let mut script = vec![];
+        Cargo::clap().gen_completions_to(BIN_NAME, shell, &mut script);
+
+        let out_dir = match &out_dir {
+            Some(out_dir) => cwd.join(out_dir),
+            None => {
+                io::stdout().write_all(&script)?;
+                continue;
+            }
+        };
+        // Same as the names `clap::App::gen_completions` uses.
+        let file_name = match shell {
+            Shell::Bash => format!("{}.bash", BIN_NAME),
+            Shell::Zsh => format!("_{}", BIN_NAME),
+            Shell::Fish => format!("{}.fish", BIN_NAME),
+            Shell::PowerShell => format!("_{}.ps1", BIN_NAME),
+            Shell::Elvish => format!("{}.elv", BIN_NAME),
+        };
+        let path = out_dir.join(file_name);
+        stderr.status("Writing", path.display())?;
+        crate::fs::create_dir_all(&out_dir, false)?;
+        crate::fs::write(&path, String::from_utf8(script)?, false)?;
+    }
+    Ok(())
+}
+
+fn which_workspace(
+    opt: CargoMemberWhichWorkspace,
+    ctx: Context<impl WriteColor>,
+) -> anyhow::Result<()> {
+    let CargoMemberWhichWorkspace {
+        json,
+        manifest_path,
+        workspace_root,
+        offline,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let (root_manifest_path, root_package) =
+        match crate::cargo_metadata_no_deps(manifest_path.as_deref(), offline, &cwd) {
+            Ok(metadata) => {
+                let root_manifest_path = metadata.workspace_root.join("Cargo.toml");
+                let root_package = metadata
+                    .packages
+                    .iter()
+                    .find(|p| p.manifest_path == root_manifest_path)
+                    .map(|p| p.name.clone());
+                (root_manifest_path.into_std_path_buf(), root_package)
+            }
+            Err(err) => {
+                let root = find_root_manifest(manifest_path.as_deref(), &cwd)?;
+                if root.join("Cargo.toml").exists() {
+                    warn_metadata_failure(&mut stderr, &err)?;
+                } else {
+                    debug!("{}", err);
+                }
+                (root.join("Cargo.toml"), None)
+            }
+        };
+
+    let mut stdout = io::stdout();
+    if json {
+        let json = serde_json::json!({
+            "manifest_path": root_manifest_path,
+            "root_package": root_package,
+        });
+        writeln!(stdout, "{}", json)?;
+    } else if let Some(root_package) = root_package {
+        writeln!(
+            stdout,
+            "{} ({})",
+            root_manifest_path.display(),
+            root_package
+        )?;
+    } else {
+        writeln!(stdout, "{}", root_manifest_path.display())?;
+    }
+    stdout.flush().map_err(Into::into)
+}
+
+/// Surfaces the error of `cargo metadata` as a warning for the read-only commands that can go on
+/// by reading the manifests directly.
+fn warn_metadata_failure(mut stderr: impl WriteColor, err: &anyhow::Error) -> io::Result<()> {
+    let err = err.to_string();
+    stderr.warn(format!(
+        "`cargo metadata` failed. reading the manifests directly instead\n{}",
+        err.trim_start_matches("error: "),
+    ))
+}
+
+/// Splits each `KEY=VALUE` of `--set-field` at the first `=`.
+fn parse_set_fields(set_field: &[String]) -> anyhow::Result<Vec<(&str, &str)>> {
+    set_field
+        .iter()
+        .map(|s| {
+            let (key, value) = s
+                .split_once('=')
+                .with_context(|| format!("expected `KEY=VALUE` for `--set-field`: {:?}", s))?;
+            Ok((key.trim(), value.trim()))
+        })
+        .collect()
+}
+
+fn open(opt: CargoMemberOpen, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberOpen {
+        manifest,
+        print,
+        manifest_path,
+        workspace_root,
+        offline,
+        spec,
+        ..
+    } = opt;
+
+    let Context { cwd, .. } = ctx;
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+    let member = metadata.query_for_member(Some(&spec))?;
+    let member_manifest_path = member.manifest_path.clone().into_std_path_buf();
+    let path = if manifest {
+        &*member_manifest_path
+    } else {
+        member_manifest_path
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+    };
+
+    if print {
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{}", path.display())?;
+        return stdout.flush().map_err(Into::into);
+    }
+
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .flat_map(env::var)
+        .find(|v| !v.trim().is_empty())
+        .with_context(|| {
+            "neither `$VISUAL` nor `$EDITOR` is set. use `--print` to print the path instead"
+        })?;
+    let mut editor_args = editor.split_whitespace();
+    let program = editor_args.next().expect("should not be empty");
+
+    let status = process::Command::new(program)
+        .args(editor_args)
+        .arg(path)
+        .current_dir(&cwd)
+        .status()
+        .with_context(|| format!("failed to execute `{}`", editor))?;
+    ensure!(status.success(), "`{}` failed ({})", editor, status);
+    Ok(())
+}
+
+fn exec(opt: CargoMemberExec, ctx: Context<impl WriteColor>) -> anyhow::Result<()> {
+    let CargoMemberExec {
+        package,
+        jobs,
+        keep_going,
+        no_buffer,
+        manifest_path,
+        workspace_root,
+        offline,
+        command,
+        ..
+    } = opt;
+
+    let Context {
+        cwd, mut stderr, ..
+    } = ctx;
+
+    ensure!(jobs > 0, "`--jobs` must be greater than 0");
+
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, &cwd)?;
+
+    let metadata = crate::cargo_metadata(manifest_path.as_deref(), false, false, offline, &cwd)?;
+    let mut members = if package.is_empty() {
+        metadata
+            .packages
+            .iter()
+            .filter(|p| metadata.workspace_members.contains(&p.id))
+            .collect::<Vec<_>>()
+    } else {
+        package
+            .iter()
+            .map(|spec| metadata.query_for_member(Some(spec)))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    members.sort_by(|p1, p2| p1.manifest_path.cmp(&p2.manifest_path));
+    members.dedup_by(|p1, p2| p1.id == p2.id);
+
+    let command_line = command
+        .iter()
+        .map(|s| shell_escape::escape(s.into()))
+        .join(" ");
+    let (program, args) = command.split_first().expect("`command` is required");
+
+    // Each worker takes the next member until every member has been taken or, unless
+    // `--keep-going`, something has failed. The results are reported from this thread.
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let (mut num_failed, mut num_finished) = (0, 0);
+    thread::scope(|scope| -> anyhow::Result<()> {
+        for _ in 0..jobs.min(members.len()) {
+            let (tx, next, stopped, members) = (tx.clone(), &next, &stopped, &members);
+            scope.spawn(move || {
+                while !stopped.load(Ordering::SeqCst) {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    let member = match members.get(i) {
+                        Some(member) => member,
+                        None => break,
+                    };
+                    let dir = member
+                        .manifest_path
+                        .parent()
+                        .expect(r#"`manifest_path` should end with "Cargo.toml""#);
+                    let result =
+                        run_in_member(&member.name, dir.as_std_path(), program, args, no_buffer);
+                    if tx.send((*member, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (member, result) in rx {
+            num_finished += 1;
+            let failure = match result {
+                Ok(output) => {
+                    stderr.write_all(&output.stderr)?;
+                    stderr.flush()?;
+                    let mut stdout = io::stdout().lock();
+                    stdout.write_all(&output.stdout)?;
+                    stdout.flush()?;
+                    (!output.status.success()).then(|| output.status.to_string())
+                }
+                Err(err) => Some(err.to_string()),
+            };
+            match failure {
+                None => stderr.status(
+                    "Finished",
+                    format!("`{}` in `{}`", command_line, member.name),
+                )?,
+                Some(failure) => {
+                    num_failed += 1;
+                    if !keep_going {
+                        stopped.store(true, Ordering::SeqCst);
+                    }
+                    stderr.status_with_color(
+                        "Failed",
+                        format!("`{}` in `{}` ({})", command_line, member.name, failure),
+                        termcolor::Color::Red,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    let num_skipped = members.len() - num_finished;
+    if num_skipped > 0 {
+        stderr.note(format!(
+            "skipped {} member(s). enable `--keep-going` to run the command in every member",
+            num_skipped,
+        ))?;
+    }
+    ensure!(
+        num_failed == 0,
+        "`{}` failed in {} of {} member(s)",
+        command_line,
+        num_failed,
+        num_finished,
+    );
+    Ok(())
+}
+
+/// Runs `program` with `args` in `dir` for `exec`.
+///
+/// With `no_buffer`, the combined output is streamed to stdout line by line, each line prefixed
+/// with `name`, and the returned output is empty. Otherwise the output is captured.
+fn run_in_member(
+    name: &str,
+    dir: &Path,
+    program: &str,
+    args: &[String],
+    no_buffer: bool,
+) -> io::Result<process::Output> {
+    let expression = duct::cmd(program, args).dir(dir).unchecked();
+    if !no_buffer {
+        return expression.stdout_capture().stderr_capture().run();
+    }
+    let reader = expression.stderr_to_stdout().reader()?;
+    let mut lines = io::BufReader::new(&reader);
+    let mut line = vec![];
+    while lines.read_until(b'\n', &mut line)? > 0 {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "[{}] ", name)?;
+        stdout.write_all(&line)?;
+        if !line.ends_with(b"\n") {
+            stdout.write_all(b"\n")?;
+        }
+        stdout.flush()?;
+        line.clear();
+    }
+    let output = reader.try_wait()?.expect("should have exited at EOF");
+    Ok(process::Output {
+        status: output.status,
+        stdout: vec![],
+        stderr: vec![],
+    })
+}
+
+/// Joins `paths` to `base`, expanding globs (e.g. `crates/*`) relative to `base` as well to the
+/// matching directories that contain a `Cargo.toml`, or to any matching directories if `any_dir`.
+fn resolve_paths(paths: Vec<PathBuf>, base: &Path, any_dir: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut acc = vec![];
+    for path in paths {
+        let path = path.trim_leading_dots();
+        let glob = match path.to_str() {
+            Some(s) if s.contains(&['*', '?', '['][..]) => s,
+            _ => {
+                acc.push(crate::normalize_path(&base.join(path)));
+                continue;
+            }
+        };
+        // `glob` matches nothing with a trailing slash (e.g. `vendor/**/`).
+        let pattern = glob.trim_end_matches('/');
+        let pattern = if path.is_absolute() {
+            pattern.to_owned()
+        } else {
+            let base = base
+                .to_str()
+                .with_context(|| format!("{:?} is not valid UTF-8 path", base))?;
+            format!("{}/{}", glob::Pattern::escape(base), pattern)
+        };
+        let matches = glob::glob(&pattern)
+            .with_context(|| format!("invalid glob: {:?}", pattern))?
+            .filter_map(|entry| {
+                entry
+                    .map(|p| {
+                        Some(crate::normalize_path(&p))
+                            .filter(|p| p.is_dir() && (any_dir || p.join("Cargo.toml").exists()))
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if matches.is_empty() {
+            bail!(
+                "{:?} did not match any {}",
+                glob,
+                if any_dir { "directory" } else { "package" },
+            );
+        }
+        acc.extend(matches);
+    }
+    Ok(acc)
+}
+
+/// Fails unless `dst` is an existing directory when more than one package is copied or moved.
+fn ensure_dir_for_multiple_srcs(srcs: &[String], dst: &Path) -> anyhow::Result<()> {
+    if srcs.len() > 1 && !dst.is_dir() {
+        bail!(
+            "`{}` is not a directory. it must exist when multiple packages are given",
+            dst.display(),
+        );
+    }
+    Ok(())
+}
+
+/// Returns `<workspace_root>/Cargo.toml` if `--workspace-root` is given, or `manifest_path`.
+///
+/// Fails if `manifest_path` is not named `Cargo.toml`. Only the commands that go through
+/// `find_root_manifest_with_file_name` accept such a manifest.
+fn workspace_manifest_path(
+    workspace_root: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
+    cwd: &Path,
+) -> anyhow::Result<Option<PathBuf>> {
+    let workspace_root = match workspace_root {
+        Some(workspace_root) => cwd.join(workspace_root.trim_leading_dots()),
+        None => {
+            if let Some(manifest_path) = manifest_path
+                .as_ref()
+                .filter(|p| p.file_name().is_some_and(|n| n != "Cargo.toml"))
+            {
+                bail!(
+                    "`--manifest-path` must point to a `Cargo.toml` for this command, not `{}`. \
+                     only `status`, `check`, `canonicalize` and `sort` accept a manifest with \
+                     another name",
+                    manifest_path.display(),
+                );
+            }
+            return Ok(manifest_path);
+        }
+    };
+    let manifest_path = workspace_root.join("Cargo.toml");
+    if !manifest_path.exists() {
+        bail!("`{}` does not exist", manifest_path.display());
+    }
+    if !crate::is_workspace_root(&workspace_root)? {
+        bail!("`{}` is not a workspace manifest", manifest_path.display());
+    }
+    Ok(Some(manifest_path))
+}
+
+/// Replaces each of `paths` with the members found beneath it, for `--recursive`.
+///
+/// A path without any member is an error unless `keep_going` is enabled, in which case it is
+/// skipped. Returns the number of the skipped paths.
+fn expand_recursive(
+    metadata: &Metadata,
+    paths: Vec<PathBuf>,
+    keep_going: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<(Vec<PathBuf>, usize)> {
+    let workspace_root = metadata.workspace_root.as_std_path();
+    let members = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .flat_map(|p| p.manifest_path.parent())
+        .map(|d| d.as_std_path())
+        .collect::<Vec<_>>();
+
+    let (mut expanded, mut num_skipped) = (vec![], 0);
+    for path in &paths {
+        let path = crate::normalize_path(path);
+        // The root package is selected only by naming the workspace root itself.
+        let found = members
+            .iter()
+            .filter(|d| d.starts_with(&path) && (**d != workspace_root || path == workspace_root))
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            let message = format!("no members found under `{}`", path.display());
+            if !keep_going {
+                bail!("{}", message);
+            }
+            stderr.warn(message)?;
+            num_skipped += 1;
+            continue;
+        }
+        stderr.note(format!(
+            "`{}` expands to {} member(s)",
+            path.display(),
+            found.len(),
+        ))?;
+        for dir in found {
+            if !expanded.iter().any(|p: &PathBuf| p == dir) {
+                expanded.push(dir.to_path_buf());
+            }
+        }
+    }
+    Ok((expanded, num_skipped))
+}
+
+/// Fails if `--recursive --keep-going` has skipped any path.
+fn finish_recursive(num_skipped: usize) -> anyhow::Result<()> {
+    if num_skipped > 0 {
+        bail!("{} path(s) without members were skipped", num_skipped);
+    }
+    Ok(())
+}
+
+/// Adds the members matching `matching` to `paths`, then drops the members matching
+/// `exclude_matching` from `paths` and `package`.
+fn select_matching(
+    metadata: &Metadata,
+    mut paths: Vec<PathBuf>,
+    mut package: Vec<String>,
+    matching: Option<&Regex>,
+    exclude_matching: Option<&Regex>,
+    match_field: self::MatchField,
+) -> (Vec<PathBuf>, Vec<String>) {
+    let members = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .map(|p| {
+            let dir = p
+                .manifest_path
+                .parent()
+                .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                .as_std_path()
+                .to_owned();
+            let field = match match_field {
+                self::MatchField::Name => p.name.clone(),
+                self::MatchField::Path => dir
+                    .strip_prefix(&metadata.workspace_root)
+                    .unwrap_or(&dir)
+                    .iter()
+                    .map(|s| s.to_string_lossy())
+                    .join("/"),
+            };
+            (p, dir, field)
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(matching) = matching {
+        for (_, dir, field) in &members {
+            if matching.is_match(field) && !paths.contains(dir) {
+                paths.push(dir.clone());
+            }
+        }
+    }
+
+    if let Some(exclude_matching) = exclude_matching {
+        let excluded = members
+            .iter()
+            .filter(|(_, _, field)| exclude_matching.is_match(field))
+            .collect::<Vec<_>>();
+        paths.retain(|p| !excluded.iter().any(|(_, dir, _)| dir == p));
+        package.retain(|spec| match metadata.query_for_member(Some(spec)) {
+            Ok(member) => !excluded.iter().any(|(p, ..)| p.id == member.id),
+            Err(_) => true,
+        });
+    }
+
+    (paths, package)
+}
+
+/// Prints the workspace root chosen for `--verbose`.
+fn note_workspace_root(root: &Path, mut stderr: impl WriteColor) -> io::Result<()> {
+    stderr.note(format!(
+        "using the workspace at `{}`",
+        root.join("Cargo.toml").display(),
+    ))
+}
+
+/// Like `workspace_manifest_path` followed by `find_root_manifest`, but also accepts a
+/// `--manifest-path` that is not named `Cargo.toml`, which `cargo` itself rejects. Returns the
+/// directory and the file name of the manifest.
+fn find_root_manifest_with_file_name(
+    workspace_root: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
+    cwd: &Path,
+) -> anyhow::Result<(PathBuf, OsString)> {
+    if let Some(manifest_path) = &manifest_path {
+        if let Some(file_name) = manifest_path.file_name().filter(|&n| n != "Cargo.toml") {
+            let manifest_path = cwd.join(manifest_path);
+            if !manifest_path.is_file() {
+                bail!("`{}` does not exist", manifest_path.display());
+            }
+            let root = manifest_path.parent().expect("should have a file name");
+            return Ok((root.to_owned(), file_name.to_owned()));
+        }
+    }
+    let manifest_path = workspace_manifest_path(workspace_root, manifest_path, cwd)?;
+    Ok((
+        find_root_manifest(manifest_path.as_deref(), cwd)?,
+        "Cargo.toml".into(),
+    ))
 }
 
 fn find_root_manifest(manifest_path: Option<&Path>, cwd: &Path) -> anyhow::Result<PathBuf> {
-    let mut path = cargo_locate_project(manifest_path, cwd)?;
-    path.pop();
-    return Ok(path);
+    // `cargo locate-project` returns the nearest manifest, which may be a member of a workspace
+    // further up.
+    let path = cargo_locate_project(manifest_path, cwd)?;
+    return crate::find_workspace_root(&path);
 
     fn cargo_locate_project(manifest_path: Option<&Path>, cwd: &Path) -> anyhow::Result<PathBuf> {
-        let program = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
+        let program = crate::cargo_exe();
 
         let mut args = vec!["locate-project".as_ref()];
         if let Some(manifest_path) = manifest_path {
@@ -1,27 +1,55 @@
 use anyhow::Context as _;
 use log::debug;
-use serde::de::DeserializeOwned;
-use std::path::Path;
+use std::{
+    borrow::Cow,
+    io::{self, Write as _},
+    path::Path,
+};
 
-pub(crate) fn read_toml<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> anyhow::Result<T> {
-    let path = path.as_ref();
-    let toml = toml::from_str(&read_to_string(path)?)
-        .with_context(|| format!("failed to parse the TOML file at {}", path.display()))?;
-    debug!("Read the TOML file at {}", path.display());
-    Ok(toml)
+/// Renames `src` to `dst`, or copies and removes it if they are on different file systems.
+pub(crate) fn move_dir(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    fn copy_dir(src: &Path, dst: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(extended_length(dst))?;
+        for entry in std::fs::read_dir(extended_length(src))? {
+            let entry = entry?;
+            let dst = dst.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                copy_dir(&entry.path(), &dst)?;
+            } else {
+                std::fs::copy(entry.path(), extended_length(&dst))?;
+            }
+        }
+        Ok(())
+    }
+
+    std::fs::rename(extended_length(src), extended_length(dst))
+        .or_else(|_| {
+            copy_dir(src, dst)?;
+            remove_dir_all::remove_dir_all(extended_length(src))
+        })
+        .with_context(|| format!("failed to move `{}` to `{}`", src.display(), dst.display()))
 }
 
 pub(crate) fn read_toml_edit(path: impl AsRef<Path>) -> anyhow::Result<toml_edit::Document> {
     let path = path.as_ref();
-    let edit = read_to_string(path)?
-        .parse()
-        .with_context(|| format!("failed to parse the TOML file at {}", path.display()))?;
+    let edit = parse_toml_edit(&read_to_string(path)?, path)?;
     debug!("Read the TOML file at {}", path.display());
     Ok(edit)
 }
 
-fn read_to_string(path: &Path) -> anyhow::Result<String> {
-    std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
+pub(crate) fn parse_toml_edit(toml: &str, path: &Path) -> anyhow::Result<toml_edit::Document> {
+    toml.parse()
+        .with_context(|| format!("failed to parse the TOML file at {}", path.display()))
+}
+
+pub(crate) fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(extended_length(path))
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+pub(crate) fn read_to_string(path: &Path) -> anyhow::Result<String> {
+    std::fs::read_to_string(extended_length(path))
+        .with_context(|| format!("failed to read {}", path.display()))
 }
 
 pub(crate) fn write(
@@ -31,14 +59,41 @@ pub(crate) fn write(
 ) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
-        std::fs::write(path, contents)
+        // Write to a temporary file and rename it so that `path` is never left half-written.
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp = path.with_file_name(format!("{}.cargo-member.tmp", file_name));
+        std::fs::write(extended_length(&tmp), contents)
+            .and_then(|()| {
+                if let Ok(metadata) = std::fs::metadata(extended_length(path)) {
+                    std::fs::set_permissions(extended_length(&tmp), metadata.permissions())?;
+                }
+                std::fs::rename(extended_length(&tmp), extended_length(path))
+            })
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(extended_length(&tmp));
+            })
             .with_context(|| format!("failed to write {}", path.display()))?;
     }
-    debug!(
-        "{}Wrote {}",
-        if dry_run { "[dry-run] " } else { "" },
-        path.display(),
-    );
+    debug!("{}Wrote {}", log_prefix(dry_run), path.display());
+    Ok(())
+}
+
+pub(crate) fn write_or_print(
+    path: impl AsRef<Path>,
+    contents: impl AsRef<[u8]>,
+    dry_run: bool,
+    stdout: bool,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !stdout {
+        return write(path, contents, dry_run);
+    }
+    let mut stdout = io::stdout();
+    stdout
+        .write_all(contents.as_ref())
+        .and_then(|()| stdout.flush())
+        .with_context(|| "failed to write to stdout")?;
+    debug!("Printed {} to stdout", path.display());
     Ok(())
 }
 
@@ -49,13 +104,13 @@ pub(crate) fn copy(
 ) -> anyhow::Result<()> {
     let (src, dst) = (src.as_ref(), dst.as_ref());
     if !dry_run {
-        std::fs::copy(src, dst).with_context(|| {
+        std::fs::copy(extended_length(src), extended_length(dst)).with_context(|| {
             format!("failed to copy `{}` to `{}`", src.display(), dst.display())
         })?;
     }
     debug!(
         "{}Copied {} to {}",
-        if dry_run { "[dry-run] " } else { "" },
+        log_prefix(dry_run),
         src.display(),
         dst.display(),
     );
@@ -65,27 +120,58 @@ pub(crate) fn copy(
 pub(crate) fn create_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
-        std::fs::create_dir_all(path)
+        std::fs::create_dir_all(extended_length(path))
             .with_context(|| format!("failed to create `{}`", path.display()))?;
     }
-    debug!(
-        "{}Created {}",
-        if dry_run { "[dry-run] " } else { "" },
-        path.display(),
-    );
+    debug!("{}Created {}", log_prefix(dry_run), path.display());
     Ok(())
 }
 
 pub(crate) fn remove_dir_all(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
     let path = path.as_ref();
     if !dry_run {
-        remove_dir_all::remove_dir_all(path)
+        remove_dir_all::remove_dir_all(extended_length(path))
             .with_context(|| format!("failed to remove `{}`", path.display()))?;
     }
-    debug!(
-        "{}Removed {}",
-        if dry_run { "[dry-run] " } else { "" },
-        path.display(),
-    );
+    debug!("{}Removed {}", log_prefix(dry_run), path.display());
+    Ok(())
+}
+
+pub(crate) fn remove_file(path: impl AsRef<Path>, dry_run: bool) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    if !dry_run {
+        std::fs::remove_file(extended_length(path))
+            .with_context(|| format!("failed to remove `{}`", path.display()))?;
+    }
+    debug!("{}Removed {}", log_prefix(dry_run), path.display());
     Ok(())
 }
+
+fn log_prefix(dry_run: bool) -> &'static str {
+    if dry_run {
+        "[dry-run] "
+    } else {
+        ""
+    }
+}
+
+/// Adds the `\\?\` prefix to absolute paths on Windows so that they are not limited to
+/// `MAX_PATH`.
+#[cfg(windows)]
+fn extended_length(path: &Path) -> Cow<'_, Path> {
+    use std::path::{Component, PathBuf, Prefix};
+
+    match path.components().next() {
+        Some(Component::Prefix(prefix)) if matches!(prefix.kind(), Prefix::Disk(_)) => {
+            // the prefix disables the normalization of `/`
+            let path = path.components().collect::<PathBuf>();
+            Cow::Owned(format!(r"\\?\{}", path.display()).into())
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+
+#[cfg(not(windows))]
+fn extended_length(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
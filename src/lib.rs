@@ -1,28 +1,65 @@
+//! Manages the members of a Cargo workspace.
+//!
+//! Each command of `cargo member` is a builder here: [`Include`], [`Exclude`], [`Deactivate`],
+//! [`Focus`], [`ActivateAll`], [`New`], [`Cp`], [`Rm`], [`Mv`] and so on. Construct one with
+//! `new` or `from_metadata`, set the options, and call `exec`. Most builders also have `plan`,
+//! which returns the changes as a [`Plan`] without performing them.
+//!
+//! The constructors and the setters never fail. Errors in their arguments (e.g. a relative path
+//! or a package that the metadata does not contain) are returned from `exec` and `plan`, which
+//! also fail on I/O errors, invalid manifests and failures of `cargo`.
+//!
+//! ```no_run
+//! use std::{env, io};
+//! use termcolor::NoColor;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let cwd = env::current_dir()?;
+//! let metadata = cargo_member::cargo_metadata(None, false, false, false, &cwd)?;
+//!
+//! // Exclude the package in `./b` and the member named `c`.
+//! cargo_member::Exclude::from_metadata(&metadata, &[cwd.join("b")], &["c"])
+//!     .dry_run(true)
+//!     .stderr(NoColor::new(io::stderr()))
+//!     .exec()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`cli::run`] runs a parsed command line with any [`WriteColor`] as stderr.
+
 #![warn(rust_2018_idioms)]
 
 use std::{
+    cell::RefCell,
+    collections::BTreeMap,
     env,
     ffi::{OsStr, OsString},
     fmt::{self, Debug, Display},
-    io::{self, Sink},
+    io::{self, Sink, Write as _},
+    iter, mem,
     ops::Deref,
-    path::{Path, PathBuf},
-    process::{Command, Stdio},
-    slice, str, vec,
+    path::{Component, Path, PathBuf},
+    process::{self, Command, Stdio},
+    rc::Rc,
+    slice,
+    str::{self, FromStr},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    vec,
 };
 
 use anyhow::{anyhow, bail, Context as _, ensure};
-use cargo_metadata::{Metadata, MetadataCommand, Package, Resolve};
+use cargo_metadata::{Metadata, MetadataCommand, Package, PackageId, Resolve, Version};
 use easy_ext::ext;
-use ignore::{Walk, WalkBuilder};
+use ignore::{overrides::OverrideBuilder, Walk, WalkBuilder};
 use itertools::Itertools as _;
 use log::debug;
-use serde::Deserialize;
-use termcolor::{ColorSpec, NoColor, WriteColor};
+use serde::{Deserialize, Serialize};
+use strum::{EnumString, EnumVariantNames, IntoStaticStr};
+use termcolor::{BufferedStandardStream, ColorSpec, NoColor, WriteColor};
 use toml_edit::Value;
 use url::Url;
 
-#[doc(hidden)]
 pub mod cli;
 mod fs;
 
@@ -32,7 +69,20 @@ pub struct Include<W> {
     paths: anyhow::Result<Vec<PathBuf>>,
     force: bool,
     dry_run: bool,
+    stdout: bool,
+    flatten_nested: bool,
+    recursive: bool,
+    verify: bool,
+    verify_stderr_redirection: Stdio,
     offline: bool,
+    default_members: bool,
+    members_per_line: Option<usize>,
+    private: bool,
+    prefix_dot_slash: bool,
+    inherit_package: bool,
+    comment: Option<String>,
+    position: MemberPosition,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -47,6 +97,19 @@ impl Include<NoColor<Sink>> {
             force: false,
             dry_run: false,
             offline: false,
+            stdout: false,
+            flatten_nested: false,
+            default_members: false,
+            members_per_line: None,
+            private: false,
+            prefix_dot_slash: false,
+            inherit_package: false,
+            comment: None,
+            position: MemberPosition::Last,
+            recursive: false,
+            verify: false,
+            verify_stderr_redirection: Stdio::null(),
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -65,6 +128,109 @@ impl<W: WriteColor> Include<W> {
         Self { offline, ..self }
     }
 
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Merges the members of included paths that are workspace roots themselves into this
+    /// workspace, and removes their `[workspace]` tables.
+    pub fn flatten_nested(self, flatten_nested: bool) -> Self {
+        Self {
+            flatten_nested,
+            ..self
+        }
+    }
+
+    /// Also adds the paths to `workspace.default-members`, creating it if absent.
+    pub fn default_members(self, default_members: bool) -> Self {
+        Self {
+            default_members,
+            ..self
+        }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Sets `package.publish = false` in the manifest of each included package that is not a
+    /// member yet, unless `package.publish` is already present. This edits the packages' own
+    /// manifests, not the workspace root.
+    pub fn private(self, private: bool) -> Self {
+        Self { private, ..self }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Makes each included package that is not a member yet inherit the fields defined in
+    /// `workspace.package` with `{key}.workspace = true`. Fields the package sets to a different
+    /// value are left alone.
+    pub fn inherit_package(self, inherit_package: bool) -> Self {
+        Self {
+            inherit_package,
+            ..self
+        }
+    }
+
+    /// Writes `# {comment}` at the end of the lines of the entries added to `workspace.members`.
+    /// Cannot be combined with `members_per_line`.
+    pub fn comment<S: AsRef<str>>(self, comment: Option<S>) -> Self {
+        let comment = comment.map(|s| s.as_ref().to_owned());
+        Self { comment, ..self }
+    }
+
+    /// Where to insert the entries added to `workspace.members`. Defaults to
+    /// [`MemberPosition::Last`]. Several paths keep their order.
+    pub fn position(self, position: MemberPosition) -> Self {
+        Self { position, ..self }
+    }
+
+    /// Includes the packages under the paths instead of the paths themselves, skipping the ones
+    /// already in `workspace.members` or under `workspace.exclude`. `target` directories and other
+    /// workspaces are not searched.
+    pub fn recursive(self, recursive: bool) -> Self {
+        Self { recursive, ..self }
+    }
+
+    /// After writing the manifest, runs `cargo check` for the included packages. If the lockfile cannot
+    /// be updated or the check fails, the modified files are restored and the error is returned.
+    pub fn verify(self, verify: bool) -> Self {
+        Self { verify, ..self }
+    }
+
+    /// Where the output of `cargo check` of [`Include::verify`] goes. If piped, it is added to the
+    /// error.
+    pub fn verify_stderr_redirection(self, verify_stderr_redirection: Stdio) -> Self {
+        Self {
+            verify_stderr_redirection,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Include<W2> {
         Include {
             possibly_empty_workspace_root: self.possibly_empty_workspace_root,
@@ -73,10 +239,28 @@ impl<W: WriteColor> Include<W> {
             dry_run: self.dry_run,
             offline: self.offline,
             stderr,
+            stdout: self.stdout,
+            flatten_nested: self.flatten_nested,
+            default_members: self.default_members,
+            members_per_line: self.members_per_line,
+            private: self.private,
+            prefix_dot_slash: self.prefix_dot_slash,
+            inherit_package: self.inherit_package,
+            comment: self.comment,
+            position: self.position,
+            recursive: self.recursive,
+            verify: self.verify,
+            verify_stderr_redirection: self.verify_stderr_redirection,
+            on_top_of: self.on_top_of,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
         let Self {
             possibly_empty_workspace_root,
             paths,
@@ -84,11 +268,49 @@ impl<W: WriteColor> Include<W> {
             dry_run,
             offline,
             mut stderr,
+            stdout,
+            flatten_nested,
+            default_members,
+            members_per_line,
+            private,
+            prefix_dot_slash,
+            inherit_package,
+            comment,
+            mut position,
+            recursive,
+            verify,
+            verify_stderr_redirection,
+            on_top_of,
         } = self;
 
-        let (possibly_empty_workspace_root, paths) = (possibly_empty_workspace_root?, paths?);
+        let dry_run = dry_run || stdout;
+
+        let (possibly_empty_workspace_root, mut paths) = (possibly_empty_workspace_root?, paths?);
+        if recursive {
+            paths = find_packages_under(&possibly_empty_workspace_root, &paths, &mut stderr)?;
+        }
+        ensure!(
+            comment.is_none() || members_per_line.is_none(),
+            "`comment` cannot be combined with `members_per_line`",
+        );
+        ensure!(
+            !comment.iter().any(|c| c.contains(['\n', '\r'])),
+            "the comment must be a single line",
+        );
+
+        let current_members = if private || inherit_package {
+            let root_cargo_toml = Action::read_toml_planned(
+                &on_top_of,
+                &possibly_empty_workspace_root.join("Cargo.toml"),
+            )?;
+            expand_members(&possibly_empty_workspace_root, &root_cargo_toml)?.0
+        } else {
+            vec![]
+        };
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
+        let mut actions = on_top_of.clone();
+        let mut included = vec![];
+        for path in &paths {
             if !(force || path.join("Cargo.toml").exists()) {
                 return Err(
                     anyhow!("`{}` does not exist", path.join("Cargo.toml").display()).context(
@@ -99,41 +321,208 @@ impl<W: WriteColor> Include<W> {
                     ),
                 );
             }
-            modify_members(
+            if private && path.join("Cargo.toml").exists() && !current_members.contains(path) {
+                let manifest_path = path.join("Cargo.toml");
+                let mut cargo_toml = crate::fs::parse_toml_edit(
+                    &Action::read_planned(&actions, &manifest_path)?,
+                    &manifest_path,
+                )?;
+                if mark_unpublishable(&mut cargo_toml, &manifest_path, &mut stderr)? {
+                    Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+                }
+            }
+            if inherit_package
+                && path.join("Cargo.toml").exists()
+                && !current_members.contains(path)
+            {
+                let manifest_path = path.join("Cargo.toml");
+                let mut cargo_toml = crate::fs::parse_toml_edit(
+                    &Action::read_planned(&actions, &manifest_path)?,
+                    &manifest_path,
+                )?;
+                let root_manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
+                let root_cargo_toml = crate::fs::parse_toml_edit(
+                    &Action::read_planned(&actions, &root_manifest_path)?,
+                    &root_manifest_path,
+                )?;
+                if inherit_workspace_package(
+                    &mut cargo_toml,
+                    &root_cargo_toml,
+                    &manifest_path,
+                    &mut stderr,
+                )? {
+                    Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+                }
+            }
+            if *path == possibly_empty_workspace_root || !is_workspace_root(path)? {
+                plan_members(
+                    &possibly_empty_workspace_root,
+                    &[path],
+                    &[],
+                    &[],
+                    &[path],
+                    MemberLayout {
+                        per_line: members_per_line,
+                        prefix_dot_slash,
+                        comment: comment.as_deref(),
+                        position: Some(&position),
+                    },
+                    &mut actions,
+                    &mut stderr,
+                )?;
+                position = position.following(&possibly_empty_workspace_root, &[path])?;
+                included.push(path.clone());
+                continue;
+            }
+            if !flatten_nested {
+                bail!(
+                    "`{}` is the root of another workspace. exclude it from this workspace, or \
+                     enable `--flatten-nested` to merge its members into this workspace",
+                    path.display(),
+                );
+            }
+            let (members, exclude, is_package) =
+                flatten_nested_workspace(path, &mut actions, &mut stderr)?;
+            let members = iter::once(&**path)
+                .filter(|_| is_package)
+                .chain(members.iter().map(|p| &**p))
+                .collect::<Vec<_>>();
+            let exclude = exclude.iter().map(|p| &**p).collect::<Vec<_>>();
+            let unexclude = iter::once(&**path)
+                .chain(members.iter().copied())
+                .unique()
+                .collect::<Vec<_>>();
+            plan_members(
                 &possibly_empty_workspace_root,
-                &[path],
-                &[],
+                &members,
+                &exclude,
                 &[],
-                &[path],
-                dry_run,
+                &unexclude,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash,
+                    comment: comment.as_deref(),
+                    position: Some(&position),
+                },
+                &mut actions,
                 &mut stderr,
-            )
-                .map(|p| acc | p)
-        })?;
-
-        if !modified {
-            stderr.warn("`workspace` unchanged")?;
-        }
-
-        if dry_run {
-            stderr.warn("not modifying the manifest due to dry run")?;
-        } else if paths.iter().all(|p| p.exists()) {
-            stderr.status(
-                "Updating",
-                possibly_empty_workspace_root.join("Cargo.lock").display(),
             )?;
+            position = position.following(&possibly_empty_workspace_root, &members)?;
+            included.extend(members.iter().map(|&p| p.to_owned()));
+        }
 
-            cargo_metadata(
-                Some(&possibly_empty_workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                offline,
+        if default_members {
+            let paths = paths.iter().map(Deref::deref).collect::<Vec<_>>();
+            plan_default_members(
                 &possibly_empty_workspace_root,
+                &paths,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
+                &mut stderr,
             )?;
-        } else if !force {
+        }
+
+        if dry_run && actions != on_top_of && position != MemberPosition::Last {
+            let manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
+            note_planned_members(&actions, &manifest_path, &mut stderr)?;
+        }
+
+        if actions == on_top_of || dry_run && !stdout {
+            stderr.warn("`workspace` unchanged")?;
+        }
+
+        let update_lockfile = if paths.iter().all(|p| p.exists()) {
+            Some(possibly_empty_workspace_root)
+        } else if !force && !dry_run {
             bail!("some packages do not exist");
+        } else {
+            None
+        };
+
+        let verify = if verify && !dry_run {
+            let mut packages = vec![];
+            for path in included {
+                let manifest_path = path.join("Cargo.toml");
+                if !manifest_path.exists() {
+                    continue;
+                }
+                let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+                if let Some(name) = cargo_toml["package"]["name"].as_str() {
+                    packages.push(name.to_owned());
+                }
+            }
+            Some(Verify {
+                packages,
+                stderr_redirection: verify_stderr_redirection,
+            })
+        } else {
+            None
+        };
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: update_lockfile.into_iter().collect(),
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// Where [`Include`] inserts the entries it adds to `workspace.members`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MemberPosition {
+    First,
+    Last,
+    /// Right before this entry of `workspace.members`, which is resolved relative to the
+    /// workspace root.
+    Before(String),
+    /// Right after this entry of `workspace.members`, which is resolved relative to the
+    /// workspace root.
+    After(String),
+}
+
+impl MemberPosition {
+    /// Returns the position right after the last of `added`, so that the next entries follow
+    /// them. `Before` and `Last` are already such.
+    fn following(self, workspace_root: &Path, added: &[&Path]) -> anyhow::Result<Self> {
+        match (self, added.last()) {
+            (Self::First, Some(last)) | (Self::After(_), Some(last)) => {
+                Ok(Self::After(member_entry(workspace_root, last)?))
+            }
+            (position, _) => Ok(position),
+        }
+    }
+}
+
+impl FromStr for MemberPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            s => match s.split_once(':') {
+                Some(("before", entry)) if !entry.is_empty() => Ok(Self::Before(entry.to_owned())),
+                Some(("after", entry)) if !entry.is_empty() => Ok(Self::After(entry.to_owned())),
+                _ => bail!(
+                    "expected `first`, `last`, `before:<PATH>` or `after:<PATH>`, got {:?}",
+                    s,
+                ),
+            },
         }
-        Ok(())
     }
 }
 
@@ -141,7 +530,16 @@ impl<W: WriteColor> Include<W> {
 pub struct Exclude<W> {
     workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
+    unresolved_specs: Vec<anyhow::Error>,
+    workspace_dependencies: Vec<(String, PathBuf, Vec<PathBuf>)>,
     dry_run: bool,
+    stdout: bool,
+    ignore_missing: bool,
+    strict: bool,
+    force: bool,
+    members_per_line: Option<usize>,
+    prefix_dot_slash: bool,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -154,6 +552,15 @@ impl Exclude<NoColor<Sink>> {
             workspace_root: ensure_absolute(workspace_root),
             paths: paths.into_iter().map(ensure_absolute).collect(),
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs: vec![],
+            workspace_dependencies: vec![],
+            strict: false,
+            force: false,
+            members_per_line: None,
+            prefix_dot_slash: false,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -168,22 +575,24 @@ impl Exclude<NoColor<Sink>> {
         paths: Ps,
         specs: Ss,
     ) -> Self {
+        let (member_dirs, unresolved_specs) = resolve_specs(metadata, specs);
         Self {
             workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
             paths: paths
                 .into_iter()
                 .map(ensure_absolute)
-                .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
-                    Ok(member
-                        .manifest_path
-                        .clone().into_std_path_buf()
-                        .parent()
-                        .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                        .to_owned())
-                }))
+                .chain(member_dirs.into_iter().map(Ok))
                 .collect(),
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs,
+            workspace_dependencies: workspace_dependencies(metadata),
+            strict: false,
+            force: false,
+            members_per_line: None,
+            prefix_dot_slash: false,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -194,56 +603,155 @@ impl<W: WriteColor> Exclude<W> {
         Self { dry_run, ..self }
     }
 
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Skips package specs that do not match any member instead of failing.
+    pub fn ignore_missing(self, ignore_missing: bool) -> Self {
+        Self {
+            ignore_missing,
+            ..self
+        }
+    }
+
+    /// Fails instead of warning when the packages are dependencies of members that remain active.
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+
+    /// Proceeds even if `strict` is enabled and the packages are still depended on.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Exclude<W2> {
         Exclude {
             workspace_root: self.workspace_root,
             paths: self.paths,
             dry_run: self.dry_run,
             stderr,
+            stdout: self.stdout,
+            ignore_missing: self.ignore_missing,
+            unresolved_specs: self.unresolved_specs,
+            workspace_dependencies: self.workspace_dependencies,
+            strict: self.strict,
+            force: self.force,
+            members_per_line: self.members_per_line,
+            prefix_dot_slash: self.prefix_dot_slash,
+            on_top_of: self.on_top_of,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
         let Self {
             mut stderr,
             workspace_root,
             paths,
             dry_run,
+            stdout,
+            ignore_missing,
+            unresolved_specs,
+            workspace_dependencies,
+            strict,
+            force,
+            members_per_line,
+            prefix_dot_slash,
+            on_top_of,
         } = self;
 
+        let dry_run = dry_run || stdout;
+
         let (workspace_root, paths) = (workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            modify_members(
+        for err in unresolved_specs {
+            if !ignore_missing {
+                return Err(err);
+            }
+            stderr.note(format!("skipping: {}", err))?;
+        }
+
+        warn_dependents(&workspace_dependencies, &paths, strict, force, &mut stderr)?;
+
+        let mut actions = on_top_of.clone();
+        for path in &paths {
+            plan_members(
                 &workspace_root,
                 &[],
                 &[path],
                 &[path],
                 &[],
-                dry_run,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
                 &mut stderr,
-            )
-                .map(|p| acc | p)
-        })?;
+            )?;
+        }
 
-        if !modified {
+        if actions == on_top_of || dry_run && !stdout {
             stderr.warn("`workspace` unchanged")?;
         }
 
-        if dry_run {
-            stderr.warn("not modifying the manifest due to dry run")?;
-        } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
-            stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
+        let cargo_toml = Action::read_planned(&actions, &workspace_root.join("Cargo.toml"))?;
+        let update_lockfile = if is_empty_workspace(&cargo_toml)? {
+            None
+        } else {
+            Some(workspace_root)
+        };
 
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                false,
-                &workspace_root,
-            )?;
-        }
-        Ok(())
+        Ok(Plan {
+            actions,
+            update_lockfiles: update_lockfile.into_iter().collect(),
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
     }
 }
 
@@ -251,7 +759,15 @@ impl<W: WriteColor> Exclude<W> {
 pub struct Deactivate<W> {
     workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
+    unresolved_specs: Vec<anyhow::Error>,
+    workspace_dependencies: Vec<(String, PathBuf, Vec<PathBuf>)>,
     dry_run: bool,
+    stdout: bool,
+    ignore_missing: bool,
+    strict: bool,
+    force: bool,
+    members_per_line: Option<usize>,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -264,6 +780,14 @@ impl Deactivate<NoColor<Sink>> {
             workspace_root: ensure_absolute(workspace_root),
             paths: paths.into_iter().map(ensure_absolute).collect(),
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs: vec![],
+            workspace_dependencies: vec![],
+            strict: false,
+            force: false,
+            members_per_line: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -278,22 +802,23 @@ impl Deactivate<NoColor<Sink>> {
         paths: Ps,
         specs: Ss,
     ) -> Self {
+        let (member_dirs, unresolved_specs) = resolve_specs(metadata, specs);
         Self {
             workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
             paths: paths
                 .into_iter()
                 .map(ensure_absolute)
-                .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
-                    Ok(member
-                        .manifest_path
-                        .clone().into_std_path_buf()
-                        .parent()
-                        .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                        .to_owned())
-                }))
+                .chain(member_dirs.into_iter().map(Ok))
                 .collect(),
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs,
+            workspace_dependencies: workspace_dependencies(metadata),
+            strict: false,
+            force: false,
+            members_per_line: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -304,56 +829,144 @@ impl<W: WriteColor> Deactivate<W> {
         Self { dry_run, ..self }
     }
 
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Skips package specs that do not match any member instead of failing.
+    pub fn ignore_missing(self, ignore_missing: bool) -> Self {
+        Self {
+            ignore_missing,
+            ..self
+        }
+    }
+
+    /// Fails instead of warning when the packages are dependencies of members that remain active.
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
+
+    /// Proceeds even if `strict` is enabled and the packages are still depended on.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Deactivate<W2> {
         Deactivate {
             workspace_root: self.workspace_root,
             paths: self.paths,
             dry_run: self.dry_run,
             stderr,
+            stdout: self.stdout,
+            ignore_missing: self.ignore_missing,
+            unresolved_specs: self.unresolved_specs,
+            workspace_dependencies: self.workspace_dependencies,
+            strict: self.strict,
+            force: self.force,
+            members_per_line: self.members_per_line,
+            on_top_of: self.on_top_of,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
         let Self {
             mut stderr,
             workspace_root,
             paths,
             dry_run,
+            stdout,
+            ignore_missing,
+            unresolved_specs,
+            workspace_dependencies,
+            strict,
+            force,
+            members_per_line,
+            on_top_of,
         } = self;
 
+        let dry_run = dry_run || stdout;
+
         let (workspace_root, paths) = (workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            modify_members(
+        for err in unresolved_specs {
+            if !ignore_missing {
+                return Err(err);
+            }
+            stderr.note(format!("skipping: {}", err))?;
+        }
+
+        warn_dependents(&workspace_dependencies, &paths, strict, force, &mut stderr)?;
+
+        let mut actions = on_top_of.clone();
+        for path in &paths {
+            plan_members(
                 &workspace_root,
                 &[],
                 &[],
                 &[path],
                 &[path],
-                dry_run,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash: false,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
                 &mut stderr,
-            )
-                .map(|p| acc | p)
-        })?;
+            )?;
+        }
 
-        if !modified {
+        if actions == on_top_of || dry_run && !stdout {
             stderr.warn("`workspace` unchanged")?;
         }
 
-        if dry_run {
-            stderr.warn("not modifying the manifest due to dry run")?;
-        } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
-            stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
+        let cargo_toml = Action::read_planned(&actions, &workspace_root.join("Cargo.toml"))?;
+        let update_lockfile = if is_empty_workspace(&cargo_toml)? {
+            None
+        } else {
+            Some(workspace_root)
+        };
 
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                false,
-                &workspace_root,
-            )?;
-        }
-        Ok(())
+        Ok(Plan {
+            actions,
+            update_lockfiles: update_lockfile.into_iter().collect(),
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
     }
 }
 
@@ -362,8 +975,18 @@ pub struct Focus<W> {
     workspace_root: anyhow::Result<PathBuf>,
     path: anyhow::Result<PathBuf>,
     dry_run: bool,
+    stdout: bool,
     offline: bool,
     exclude: bool,
+    force: bool,
+    members_per_line: Option<usize>,
+    prefix_dot_slash: bool,
+    with_deps: bool,
+    with_dependents: bool,
+    allow_broken: bool,
+    resolved_dependencies: Option<Vec<PathBuf>>,
+    resolved_dependents: Option<Vec<PathBuf>>,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -375,9 +998,32 @@ impl Focus<NoColor<Sink>> {
             dry_run: false,
             offline: false,
             exclude: false,
+            force: false,
+            stdout: false,
+            members_per_line: None,
+            prefix_dot_slash: false,
+            with_deps: false,
+            with_dependents: false,
+            resolved_dependencies: None,
+            resolved_dependents: None,
+            allow_broken: false,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
+
+    /// Reads the dependencies and the dependents of the package at `path` from the resolve graph
+    /// of `metadata`, for [`with_deps`](Focus::with_deps) and
+    /// [`with_dependents`](Focus::with_dependents).
+    pub fn from_metadata(metadata: &Metadata, path: &Path) -> Self {
+        let workspace_root = metadata.workspace_root.clone().into_std_path_buf();
+        let dir = normalize_path(path);
+        Self {
+            resolved_dependencies: resolved_member_closure(metadata, &dir, false),
+            resolved_dependents: resolved_member_closure(metadata, &dir, true),
+            ..Self::new(&workspace_root, path)
+        }
+    }
 }
 
 impl<W: WriteColor> Focus<W> {
@@ -393,36 +1039,217 @@ impl<W: WriteColor> Focus<W> {
         Self { exclude, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Focus<W2> {
-        Focus {
-            workspace_root: self.workspace_root,
-            path: self.path,
-            dry_run: self.dry_run,
-            offline: self.offline,
-            exclude: self.exclude,
-            stderr,
-        }
+    /// Allows focusing on the workspace root or on a path that is not a member of the workspace.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
     }
 
-    pub fn exec(self) -> anyhow::Result<()> {
-        let Self {
-            workspace_root,
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Also keeps active the members the package transitively depends on, including through
+    /// `dev-dependencies` and `build-dependencies`. Requires [`Focus::from_metadata`].
+    ///
+    /// This is already the default with [`Focus::from_metadata`] unless
+    /// [`allow_broken`](Self::allow_broken) is enabled, so this only makes the plan fail when the
+    /// resolve graph is not available, instead of deactivating the dependencies.
+    pub fn with_deps(self, with_deps: bool) -> Self {
+        Self { with_deps, ..self }
+    }
+
+    /// Also keeps active the members that transitively depend on the package, so that their
+    /// tests can be run. Requires [`Focus::from_metadata`].
+    ///
+    /// Combined with [`with_deps`](Self::with_deps), both sets are kept. The dependencies of the
+    /// dependents are not kept unless the package depends on them too.
+    pub fn with_dependents(self, with_dependents: bool) -> Self {
+        Self {
+            with_dependents,
+            ..self
+        }
+    }
+
+    /// Deactivates or excludes the members the package transitively depends on too, even though
+    /// the package may then fail to build. Without this, they are kept active whenever the
+    /// resolve graph is available, as with [`with_deps`](Self::with_deps).
+    pub fn allow_broken(self, allow_broken: bool) -> Self {
+        Self {
+            allow_broken,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Focus<W2> {
+        Focus {
+            workspace_root: self.workspace_root,
+            path: self.path,
+            dry_run: self.dry_run,
+            offline: self.offline,
+            exclude: self.exclude,
+            force: self.force,
+            stderr,
+            stdout: self.stdout,
+            members_per_line: self.members_per_line,
+            prefix_dot_slash: self.prefix_dot_slash,
+            with_deps: self.with_deps,
+            with_dependents: self.with_dependents,
+            resolved_dependencies: self.resolved_dependencies,
+            resolved_dependents: self.resolved_dependents,
+            allow_broken: self.allow_broken,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them. `workspace.members` and `workspace.exclude`
+    /// are edited in a single write of the root manifest.
+    ///
+    /// Their previous values are saved to [`FOCUS_SNAPSHOT_FILE_NAME`] for [`RestoreFocus`],
+    /// unless the snapshot of an earlier focus is still there.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            workspace_root,
             path,
             dry_run,
             offline,
             exclude,
+            force,
             mut stderr,
+            stdout,
+            members_per_line,
+            prefix_dot_slash,
+            with_deps,
+            with_dependents,
+            resolved_dependencies,
+            resolved_dependents,
+            allow_broken,
+            on_top_of,
         } = self;
 
+        let dry_run = dry_run || stdout;
+
         let (workspace_root, path) = (workspace_root?, path?);
 
+        if !force {
+            ensure!(
+                path != workspace_root,
+                "`{}` is the workspace root. enable `--force` to focus",
+                path.display(),
+            );
+            if !path.join("Cargo.toml").exists() {
+                return Err(
+                    anyhow!("`{}` does not exist", path.join("Cargo.toml").display()).context(
+                        format!(
+                            "`{}` does not seem to be a package. enable `--force` to focus",
+                            path.display(),
+                        ),
+                    ),
+                );
+            }
+            ensure!(
+                focusable_members(&workspace_root)?.contains(&path),
+                "`{}` is not a member of the workspace at `{}`. enable `--force` to focus",
+                path.display(),
+                workspace_root.display(),
+            );
+        }
+
+        ensure!(
+            !(with_deps && allow_broken),
+            "`with_deps` cannot be used with `allow_broken`",
+        );
+        // Deactivating a dependency of the package may break building it.
+        let with_deps = with_deps || !allow_broken && resolved_dependencies.is_some();
+
+        let mut kept = vec![path.clone()];
+        for (enabled, resolved, name, relation) in [
+            (
+                with_deps,
+                &resolved_dependencies,
+                "with_deps",
+                "a dependency of",
+            ),
+            (
+                with_dependents,
+                &resolved_dependents,
+                "with_dependents",
+                "a dependent of",
+            ),
+        ] {
+            if !enabled {
+                continue;
+            }
+            let resolved = resolved.as_ref().with_context(|| {
+                format!(
+                    "`{}` requires the resolve graph. use `Focus::from_metadata` with the \
+                     dependencies resolved",
+                    name,
+                )
+            })?;
+            for dir in resolved {
+                // The root package cannot be deactivated anyway.
+                if !kept.contains(dir) && *dir != workspace_root {
+                    stderr.status(
+                        "Keeping",
+                        format!(
+                            "`{}` active as {} `{}`",
+                            dir.display(),
+                            relation,
+                            path.display(),
+                        ),
+                    )?;
+                    kept.push(dir.clone());
+                }
+            }
+        }
+        // With `force`, `path` may be the workspace root, which is a member without being listed.
+        let kept = kept
+            .iter()
+            .map(Deref::deref)
+            .filter(|&dir| dir != workspace_root)
+            .collect::<Vec<_>>();
+
         let mut targets = vec![];
         for entry in Walk::new(&workspace_root) {
             match entry {
                 Ok(entry) => {
                     if entry.path().ends_with("Cargo.toml") {
                         let dir = entry.path().parent().expect("should not empty");
-                        if ![&*workspace_root, &*path].contains(&dir) {
+                        if dir != workspace_root && !kept.contains(&dir) {
                             targets.push(dir.to_owned());
                         }
                     }
@@ -432,30 +1259,435 @@ impl<W: WriteColor> Focus<W> {
         }
         let targets = targets.iter().map(Deref::deref).collect::<Vec<_>>();
 
-        modify_members(
+        let mut actions = on_top_of.clone();
+        plan_members(
             &workspace_root,
-            &[&path],
+            &kept,
             if exclude { &targets } else { &[] },
             &targets,
-            &[&path],
-            dry_run,
+            &kept,
+            MemberLayout {
+                per_line: members_per_line,
+                prefix_dot_slash,
+                comment: None,
+                position: None,
+            },
+            &mut actions,
             &mut stderr,
         )?;
 
-        if dry_run {
-            stderr.warn("not modifying `workspace` due to dry run")?;
-        } else {
-            stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
+        let manifest_path = workspace_root.join("Cargo.toml");
+        toml::from_str::<toml::Value>(&Action::read_planned(&actions, &manifest_path)?)
+            .with_context(|| {
+                format!(
+                    "the edited `{}` would be invalid. not modifying it",
+                    manifest_path.display(),
+                )
+            })?;
+
+        let snapshot_path = workspace_root.join(FOCUS_SNAPSHOT_FILE_NAME);
+        if actions != on_top_of {
+            if snapshot_path.exists() {
+                stderr.note(format!(
+                    "keeping the snapshot of the previous focus in `{}`",
+                    snapshot_path.display(),
+                ))?;
+            } else {
+                let cargo_toml = Action::read_toml_planned(&on_top_of, &manifest_path)?;
+                let array = |field: &str| {
+                    cargo_toml["workspace"][field]
+                        .as_value()
+                        .map(|v| v.to_string().trim().to_owned())
+                };
+                let snapshot = FocusSnapshot {
+                    members: array("members"),
+                    exclude: array("exclude"),
+                };
+                stderr.status(
+                    "Saving",
+                    format!(
+                        "`workspace.{{members, exclude}}` to `{}`",
+                        snapshot_path.display(),
+                    ),
+                )?;
+                // Written first so that the manifest is never focused without a snapshot.
+                actions.insert(
+                    0,
+                    Action::WriteFile {
+                        path: snapshot_path,
+                        before: None,
+                        after: serde_json::to_string_pretty(&snapshot)? + "\n",
+                    },
+                );
+            }
+        }
 
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                offline,
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![workspace_root],
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// The file [`Focus`] saves the previous `workspace.{members, exclude}` to, under the workspace
+/// root.
+pub const FOCUS_SNAPSHOT_FILE_NAME: &str = ".cargo-member-focus.json";
+
+/// `workspace.members` and `workspace.exclude` as TOML, `None` being absent.
+#[derive(Debug, Serialize, Deserialize)]
+struct FocusSnapshot {
+    members: Option<String>,
+    exclude: Option<String>,
+}
+
+/// Returns the directories of the members, including the ones deactivated or excluded by the
+/// focus saved in [`FOCUS_SNAPSHOT_FILE_NAME`] if any.
+fn focusable_members(workspace_root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let mut members = expand_members(workspace_root, &root_cargo_toml)?.0;
+    let snapshot_path = workspace_root.join(FOCUS_SNAPSHOT_FILE_NAME);
+    if snapshot_path.exists() {
+        let FocusSnapshot { members: m, exclude } =
+            serde_json::from_str(&crate::fs::read_to_string(&snapshot_path)?)
+                .with_context(|| format!("failed to parse `{}`", snapshot_path.display()))?;
+        let mut workspace = "[workspace]\n".to_owned();
+        for (field, array) in [("members", m), ("exclude", exclude)] {
+            if let Some(array) = array {
+                workspace += &format!("{} = {}\n", field, array);
+            }
+        }
+        let snapshot = crate::fs::parse_toml_edit(&workspace, &snapshot_path)?;
+        for dir in expand_members(workspace_root, &snapshot)?.0 {
+            if !members.contains(&dir) {
+                members.push(dir);
+            }
+        }
+    }
+    Ok(members)
+}
+
+/// Reinstates the `workspace.{members, exclude}` saved by [`Focus`], and removes the snapshot.
+#[derive(Debug)]
+pub struct RestoreFocus<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    stdout: bool,
+    offline: bool,
+    on_top_of: Vec<Action>,
+    stderr: W,
+}
+
+impl RestoreFocus<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            dry_run: false,
+            stdout: false,
+            offline: false,
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> RestoreFocus<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifest to stdout instead of writing it. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> RestoreFocus<W2> {
+        RestoreFocus {
+            workspace_root: self.workspace_root,
+            dry_run: self.dry_run,
+            stdout: self.stdout,
+            offline: self.offline,
+            stderr,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them. It is an error if there is no snapshot.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            workspace_root,
+            dry_run,
+            stdout,
+            offline,
+            mut stderr,
+            on_top_of,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let workspace_root = workspace_root?;
+        let snapshot_path = workspace_root.join(FOCUS_SNAPSHOT_FILE_NAME);
+        ensure!(
+            snapshot_path.exists(),
+            "`{}` does not exist. there is no focus to restore",
+            snapshot_path.display(),
+        );
+        let FocusSnapshot { members, exclude } =
+            serde_json::from_str(&Action::read_planned(&on_top_of, &snapshot_path)?)
+                .with_context(|| format!("failed to parse `{}`", snapshot_path.display()))?;
+
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let mut cargo_toml = Action::read_toml_planned(&on_top_of, &manifest_path)?;
+        for (field, array) in [("members", members), ("exclude", exclude)] {
+            stderr.status(
+                "Restoring",
+                format!("`workspace.{}` in `{}`", field, manifest_path.display()),
+            )?;
+            match array {
+                Some(array) => {
+                    let value = crate::fs::parse_toml_edit(
+                        &format!("value = {}\n", array),
+                        &snapshot_path,
+                    )?;
+                    cargo_toml["workspace"][field] = value["value"].clone();
+                }
+                None => {
+                    if let Some(workspace) = cargo_toml["workspace"].as_table_mut() {
+                        workspace.remove(field);
+                    }
+                }
+            }
+        }
+
+        let mut actions = on_top_of;
+        Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+        stderr.status_with_color("Removing", snapshot_path.display(), termcolor::Color::Red)?;
+        actions.push(Action::RemoveFile {
+            path: snapshot_path,
+        });
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![workspace_root],
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct ActivateAll<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    force: bool,
+    dry_run: bool,
+    stdout: bool,
+    offline: bool,
+    members_per_line: Option<usize>,
+    prefix_dot_slash: bool,
+    on_top_of: Vec<Action>,
+    stderr: W,
+}
+
+impl ActivateAll<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            force: false,
+            dry_run: false,
+            stdout: false,
+            offline: false,
+            members_per_line: None,
+            prefix_dot_slash: false,
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> ActivateAll<W> {
+    /// Rewrites the manifest. Without this, planning any change is an error unless `dry_run` is
+    /// enabled.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifest to stdout instead of writing it. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Writes the entries added to `workspace.members` as `./{path}`. This only affects how the
+    /// entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> ActivateAll<W2> {
+        ActivateAll {
+            workspace_root: self.workspace_root,
+            force: self.force,
+            dry_run: self.dry_run,
+            stdout: self.stdout,
+            offline: self.offline,
+            members_per_line: self.members_per_line,
+            prefix_dot_slash: self.prefix_dot_slash,
+            stderr,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    ///
+    /// The entries of `workspace.exclude` that point to packages are removed, and every package
+    /// under the workspace root that is not in another workspace nor under the remaining entries
+    /// of `workspace.exclude` is added to `workspace.members`.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            workspace_root,
+            force,
+            dry_run,
+            stdout,
+            offline,
+            members_per_line,
+            prefix_dot_slash,
+            mut stderr,
+            on_top_of,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let workspace_root = workspace_root?;
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let cargo_toml = Action::read_toml_planned(&on_top_of, &manifest_path)?;
+
+        let (members, _) = expand_members(&workspace_root, &cargo_toml)?;
+        let (unexclude, excluded) = workspace_entries(&cargo_toml, "exclude")
+            .iter()
+            .map(|e| normalize_path(&workspace_root.join(e)))
+            .partition::<Vec<_>, _>(|dir| dir.join("Cargo.toml").exists());
+        let mut activate = find_orphans(&workspace_root, &members, &excluded, &mut stderr)?;
+        activate.sort();
+
+        let activate = activate.iter().map(Deref::deref).collect::<Vec<_>>();
+        let unexclude = unexclude.iter().map(Deref::deref).collect::<Vec<_>>();
+
+        let mut actions = on_top_of.clone();
+        if !(activate.is_empty() && unexclude.is_empty()) {
+            plan_members(
                 &workspace_root,
+                &activate,
+                &[],
+                &[],
+                &unexclude,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
+                &mut stderr,
             )?;
         }
-        Ok(())
+
+        if actions == on_top_of {
+            stderr.warn("`workspace` unchanged")?;
+        } else if dry_run {
+            note_planned_members(&actions, &manifest_path, &mut stderr)?;
+        } else if !force {
+            bail!(
+                "this rewrites `workspace.{{members, exclude}}` of `{}`. enable `--force` to \
+                 proceed, or `--dry-run` to see the result",
+                manifest_path.display(),
+            );
+        }
+
+        let update_lockfile = Some(workspace_root).filter(|_| actions != on_top_of);
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: update_lockfile.into_iter().collect(),
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
     }
 }
 
@@ -466,14 +1698,30 @@ pub struct New<W> {
     cargo_new_registry: Option<String>,
     cargo_new_vcs: Option<String>,
     cargo_new_lib: bool,
+    cargo_new_bin: bool,
     cargo_new_name: Option<String>,
     cargo_new_stderr_redirection: Stdio,
+    inherit_lints: Option<bool>,
     offline: bool,
     dry_run: bool,
+    stdout: bool,
+    set_resolver: Option<String>,
+    default_members: bool,
+    members_per_line: Option<usize>,
+    private: bool,
+    prefix_dot_slash: bool,
+    set_fields: Vec<(String, String)>,
+    inherit_package: bool,
+    position: MemberPosition,
+    smoke_test: bool,
+    rollback_on_failure: bool,
+    verbose: bool,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
 impl New<NoColor<Sink>> {
+    #[allow(clippy::self_named_constructors)]
     pub fn new(possibly_empty_workspace_root: &Path, path: &Path) -> Self {
         Self {
             possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
@@ -483,8 +1731,23 @@ impl New<NoColor<Sink>> {
             cargo_new_lib: false,
             cargo_new_name: None,
             cargo_new_stderr_redirection: Stdio::null(),
+            inherit_lints: None,
             offline: false,
             dry_run: false,
+            stdout: false,
+            cargo_new_bin: false,
+            set_resolver: None,
+            default_members: false,
+            members_per_line: None,
+            private: false,
+            prefix_dot_slash: false,
+            set_fields: vec![],
+            inherit_package: false,
+            position: MemberPosition::Last,
+            smoke_test: false,
+            rollback_on_failure: false,
+            verbose: false,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -514,6 +1777,20 @@ impl<W: WriteColor> New<W> {
         }
     }
 
+    /// Uses a binary template. Combined with `cargo_new_lib`, creates a library and a
+    /// `src/main.rs` that calls `run` in the library:
+    ///
+    /// ```text
+    /// src/lib.rs  # `pub fn run()` prepended to the `cargo new --lib` template
+    /// src/main.rs # `fn main() { <crate>::run(); }`
+    /// ```
+    pub fn cargo_new_bin(self, cargo_new_bin: bool) -> Self {
+        Self {
+            cargo_new_bin,
+            ..self
+        }
+    }
+
     pub fn cargo_new_name<S: AsRef<str>>(self, cargo_new_name: Option<S>) -> Self {
         let cargo_new_name = cargo_new_name.map(|s| s.as_ref().to_owned());
         Self {
@@ -529,14 +1806,134 @@ impl<W: WriteColor> New<W> {
         }
     }
 
-    pub fn offline(self, offline: bool) -> Self {
-        Self { offline, ..self }
-    }
+    /// Whether to add `lints.workspace = true` to the new package.
+    ///
+    /// `None` adds it only if the workspace defines `workspace.lints`. Unless `Some(false)`, a
+    /// missing `workspace.lints` is noted.
+    pub fn inherit_lints(self, inherit_lints: Option<bool>) -> Self {
+        Self {
+            inherit_lints,
+            ..self
+        }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
 
     pub fn dry_run(self, dry_run: bool) -> Self {
         Self { dry_run, ..self }
     }
 
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Sets `workspace.resolver` in the workspace manifest.
+    ///
+    /// Without this, a warning is emitted when an edition 2024 package is added to a workspace
+    /// whose resolver is lower than `"3"`.
+    pub fn set_resolver<S: AsRef<str>>(self, set_resolver: Option<S>) -> Self {
+        let set_resolver = set_resolver.map(|s| s.as_ref().to_owned());
+        Self {
+            set_resolver,
+            ..self
+        }
+    }
+
+    /// Also adds the package to `workspace.default-members`, creating it if absent.
+    pub fn default_members(self, default_members: bool) -> Self {
+        Self {
+            default_members,
+            ..self
+        }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Sets `package.publish = false` in the manifest of the new package. This edits the
+    /// package's own manifest, not the workspace root.
+    pub fn private(self, private: bool) -> Self {
+        Self { private, ..self }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Sets `package.{key}` of the new package to each value, parsed as a TOML fragment. Strings must be
+    /// quoted: `("rust-version", "\"1.70\"")`, `("authors", "[\"Me\"]")`.
+    pub fn set_fields<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<str>>(
+        self,
+        set_fields: I,
+    ) -> Self {
+        let set_fields = set_fields
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+            .collect();
+        Self { set_fields, ..self }
+    }
+
+    /// Makes the new package inherit the fields defined in `workspace.package` with
+    /// `{key}.workspace = true`. `cargo new` usually does this by itself if it recognizes the
+    /// workspace.
+    pub fn inherit_package(self, inherit_package: bool) -> Self {
+        Self {
+            inherit_package,
+            ..self
+        }
+    }
+
+    /// Where to insert the entry added to `workspace.members`. Defaults to
+    /// [`MemberPosition::Last`].
+    pub fn position(self, position: MemberPosition) -> Self {
+        Self { position, ..self }
+    }
+
+    /// Runs `cargo build -p <name>` for the new package afterwards and fails if it does not
+    /// build. Skipped with `dry_run`.
+    pub fn smoke_test(self, smoke_test: bool) -> Self {
+        Self { smoke_test, ..self }
+    }
+
+    /// With `smoke_test`, restores `Cargo.toml` and `Cargo.lock` of the workspace and removes the
+    /// new package if it does not build.
+    pub fn rollback_on_failure(self, rollback_on_failure: bool) -> Self {
+        Self {
+            rollback_on_failure,
+            ..self
+        }
+    }
+
+    /// Streams the output of the smoke test instead of printing it only if the build fails.
+    pub fn verbose(self, verbose: bool) -> Self {
+        Self { verbose, ..self }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> New<W2> {
         New {
             possibly_empty_workspace_root: self.possibly_empty_workspace_root,
@@ -546,13 +1943,36 @@ impl<W: WriteColor> New<W> {
             cargo_new_lib: self.cargo_new_lib,
             cargo_new_name: self.cargo_new_name,
             cargo_new_stderr_redirection: self.cargo_new_stderr_redirection,
+            inherit_lints: self.inherit_lints,
             offline: self.offline,
             dry_run: self.dry_run,
             stderr,
+            stdout: self.stdout,
+            cargo_new_bin: self.cargo_new_bin,
+            set_resolver: self.set_resolver,
+            default_members: self.default_members,
+            members_per_line: self.members_per_line,
+            private: self.private,
+            prefix_dot_slash: self.prefix_dot_slash,
+            set_fields: self.set_fields,
+            inherit_package: self.inherit_package,
+            position: self.position,
+            smoke_test: self.smoke_test,
+            rollback_on_failure: self.rollback_on_failure,
+            verbose: self.verbose,
+            on_top_of: self.on_top_of,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Returns the changes `exec` would make without performing them.
+    ///
+    /// Without `dry_run`, `cargo new` is run in a temporary directory and the plan copies the
+    /// generated files from it. The directory is removed when the plan is dropped.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
         let Self {
             possibly_empty_workspace_root,
             path,
@@ -561,32 +1981,94 @@ impl<W: WriteColor> New<W> {
             cargo_new_lib,
             cargo_new_name,
             cargo_new_stderr_redirection,
+            inherit_lints,
             offline,
             dry_run,
             mut stderr,
+            stdout,
+            cargo_new_bin,
+            set_resolver,
+            default_members,
+            members_per_line,
+            private,
+            prefix_dot_slash,
+            set_fields,
+            inherit_package,
+            position,
+            smoke_test,
+            rollback_on_failure,
+            verbose,
+            on_top_of,
         } = self;
 
+        let dry_run = dry_run || stdout;
+
         let (possibly_empty_workspace_root, path) = (possibly_empty_workspace_root?, path?);
+        let set_fields = parse_package_fields(&set_fields)?;
 
-        Include::new(&possibly_empty_workspace_root, &[&path])
-            .force(true)
-            .dry_run(dry_run)
-            .stderr(&mut stderr)
-            .exec()?;
+        if cfg!(windows) {
+            ensure_not_reserved_on_windows(path.file_name().unwrap_or_default())?;
+            if let Some(cargo_new_name) = &cargo_new_name {
+                ensure_not_reserved_on_windows(cargo_new_name.as_ref())?;
+            }
+        }
+
+        let mut staging = None;
+        let (mut actions, mut update_lockfiles) =
+            Include::new(&possibly_empty_workspace_root, [&path])
+                .force(true)
+                .default_members(default_members)
+                .members_per_line(members_per_line)
+                .prefix_dot_slash(prefix_dot_slash)
+                .position(position)
+                .dry_run(dry_run)
+                .stdout(stdout)
+                .on_top_of(on_top_of)
+                .stderr(&mut stderr)
+                .plan()?
+                .into_actions()?;
 
         if dry_run {
-            stderr.warn("not creating a new package due to dry run")?;
+            if let Some(set_resolver) = &set_resolver {
+                set_workspace_resolver(
+                    &possibly_empty_workspace_root,
+                    set_resolver,
+                    &mut actions,
+                    &mut stderr,
+                )?;
+            }
         } else {
-            let cargo_exe = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
+            ensure!(
+                !path.exists(),
+                "destination `{}` already exists",
+                path.display(),
+            );
+
+            let staging = staging.insert(StagingDir::new("cargo-member-new")?);
+            let staged = stage_workspace(&staging.0, &possibly_empty_workspace_root, &path)?;
+
+            // `cargo new` does not initialize a repository inside an existing one.
+            let in_repository = path
+                .ancestors()
+                .skip(1)
+                .any(|d| d.join(".git").exists() || d.join(".hg").exists());
+
+            let cargo_exe = cargo_exe();
 
             let args = Args::new()
                 .arg("new")
                 .option(cargo_new_registry.as_ref(), "--registry")
-                .option(cargo_new_vcs.as_ref(), "--vcs")
+                .option(
+                    cargo_new_vcs
+                        .as_deref()
+                        .or(Some("none").filter(|_| in_repository)),
+                    "--vcs",
+                )
+                .flag(cargo_new_bin && !cargo_new_lib, "--bin")
                 .flag(cargo_new_lib, "--lib")
                 .option(cargo_new_name.as_ref(), "--name")
                 .flag(offline, "--offline")
-                .arg(&path);
+                .arg(&staged);
 
             let output = Command::new(&cargo_exe)
                 .args(&args)
@@ -611,17 +2093,658 @@ impl<W: WriteColor> New<W> {
                 );
             }
 
-            stderr.status(
-                "Updating",
-                possibly_empty_workspace_root.join("Cargo.lock").display(),
+            for entry in WalkBuilder::new(&staged)
+                .standard_filters(false)
+                .hidden(false)
+                .build()
+            {
+                let entry = entry?;
+                let dst = path.join(entry.path().strip_prefix(&staged)?);
+                if entry.path().is_dir() {
+                    actions.push(Action::CreateDir { path: dst });
+                } else {
+                    actions.push(Action::CopyFile {
+                        src: entry.path().to_owned(),
+                        dst,
+                    });
+                }
+            }
+
+            if cargo_new_lib && cargo_new_bin {
+                add_thin_main(&path, &mut actions, &mut stderr)?;
+            }
+
+            let manifest_path = path.join("Cargo.toml");
+            let cargo_toml = Action::read_toml_planned(&actions, &manifest_path)?;
+            check_resolver(
+                &possibly_empty_workspace_root,
+                &path,
+                &cargo_toml,
+                set_resolver.as_deref(),
+                &mut actions,
+                &mut stderr,
             )?;
 
-            cargo_metadata(None, false, false, offline, &possibly_empty_workspace_root)?;
+            if private {
+                let mut cargo_toml = Action::read_toml_planned(&actions, &manifest_path)?;
+                if mark_unpublishable(&mut cargo_toml, &manifest_path, &mut stderr)? {
+                    Action::plan_write(
+                        &mut actions,
+                        manifest_path.clone(),
+                        cargo_toml.to_string(),
+                    )?;
+                }
+            }
+
+            if !set_fields.is_empty() {
+                let mut cargo_toml = Action::read_toml_planned(&actions, &manifest_path)?;
+                set_package_fields(&mut cargo_toml, &set_fields, &manifest_path, &mut stderr)?;
+                Action::plan_write(&mut actions, manifest_path.clone(), cargo_toml.to_string())?;
+            }
+
+            let root_manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
+            if inherit_package {
+                let mut cargo_toml = Action::read_toml_planned(&actions, &manifest_path)?;
+                let root_cargo_toml = Action::read_toml_planned(&actions, &root_manifest_path)?;
+                if inherit_workspace_package(
+                    &mut cargo_toml,
+                    &root_cargo_toml,
+                    &manifest_path,
+                    &mut stderr,
+                )? {
+                    Action::plan_write(
+                        &mut actions,
+                        manifest_path.clone(),
+                        cargo_toml.to_string(),
+                    )?;
+                }
+            }
+
+            if inherit_lints != Some(false) {
+                let root_cargo_toml = Action::read_toml_planned(&actions, &root_manifest_path)?;
+                if root_cargo_toml["workspace"]["lints"].is_none() {
+                    stderr.note(format!(
+                        "`workspace.lints` is not defined in `{}`",
+                        root_manifest_path.display(),
+                    ))?;
+                } else {
+                    inherit_workspace_lints(&manifest_path, &mut actions, &mut stderr)?;
+                }
+            }
+
+            update_lockfiles.push(possibly_empty_workspace_root.clone());
+        }
+
+        Ok(Plan {
+            actions,
+            update_lockfiles,
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: Some(SmokeTest {
+                dir: path,
+                workspace_root: Some(possibly_empty_workspace_root),
+                verbose,
+                rollback: rollback_on_failure,
+            })
+            .filter(|_| smoke_test),
+            staging,
+            dry_run_warning: Some("not creating a new package due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// Writes a manifest to `staging` with `[workspace]` of `workspace_root` and `path` as its only
+/// member, so that `cargo new` sees the workspace without modifying it. Returns the counterpart
+/// of `path` in `staging`, which is outside of any workspace if `path` is not under
+/// `workspace_root`.
+fn stage_workspace(staging: &Path, workspace_root: &Path, path: &Path) -> anyhow::Result<PathBuf> {
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let (rel, mut workspace) = match (
+        path.strip_prefix(workspace_root),
+        root_cargo_toml.as_table().get("workspace"),
+    ) {
+        (Ok(rel), Some(workspace)) => (rel, workspace.clone()),
+        _ => return Ok(staging.join(path.file_name().expect("should be absolute"))),
+    };
+    if let Some(workspace) = workspace.as_table_like_mut() {
+        for key in &["members", "exclude", "default-members"] {
+            workspace.remove(key);
+        }
+    }
+    let member = rel.iter().map(|c| c.to_string_lossy()).join("/");
+    workspace["members"] = toml_edit::value(iter::once(member).collect::<toml_edit::Array>());
+
+    let mut cargo_toml = toml_edit::Document::new();
+    cargo_toml["workspace"] = workspace;
+    let manifest_path = staging.join("workspace").join("Cargo.toml");
+    std::fs::create_dir_all(staging.join("workspace"))
+        .and_then(|()| std::fs::write(&manifest_path, cargo_toml.to_string()))
+        .with_context(|| format!("failed to write {}", manifest_path.display()))?;
+    Ok(staging.join("workspace").join(rel))
+}
+
+
+/// Runs `cargo build -p <name>` for the package at `dir`.
+fn smoke_test(
+    dir: &Path,
+    offline: bool,
+    verbose: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+    let name = cargo_toml["package"]["name"]
+        .as_str()
+        .with_context(|| format!("missing `package.name` in `{}`", manifest_path.display()))?;
+
+    stderr.status("Building", format!("`{}` as a smoke test", name))?;
+
+    let cargo_exe = cargo_exe();
+
+    let args = Args::new()
+        .arg("build")
+        .option(Some(&manifest_path), "--manifest-path")
+        .option(Some(name), "-p")
+        .flag(offline, "--offline");
+
+    let output = Command::new(&cargo_exe)
+        .args(&args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(if verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::null()
+        })
+        .stderr(if verbose {
+            Stdio::inherit()
+        } else {
+            Stdio::piped()
+        })
+        .output()
+        .with_context(|| format!("failed to execute `{}`", cargo_exe.to_string_lossy()))?;
+
+    if output.status.success() {
+        return stderr
+            .status("Finished", format!("the smoke test of `{}`", name))
+            .map_err(Into::into);
+    }
+
+    stderr.write_all(&output.stderr)?;
+    bail!(
+        "`{}{}` failed ({})",
+        shell_escape::escape(cargo_exe.to_string_lossy()),
+        args.0.iter().format_with("", |s, f| f(&format_args!(
+            " {}",
+            shell_escape::escape(s.to_string_lossy()),
+        ))),
+        output.status,
+    );
+}
+
+/// Explains the error of applying the plan of [`MergeInto`] if `Cargo.lock` could not be updated
+/// because of a dependency cycle.
+///
+/// Dependents of `src` now depend on `dst`, which may depend on them. Cargo rejects such cycles
+/// unless they go through `dev-dependencies`.
+fn dependency_cycle_context(err: anyhow::Error, names: Option<(String, String)>) -> anyhow::Error {
+    match names {
+        Some((src_name, dst_name)) if err.to_string().contains("cyclic package dependency") => err
+            .context(format!(
+                "merging `{}` into `{}` introduced a dependency cycle",
+                src_name, dst_name,
+            )),
+        _ => err,
+    }
+}
+
+/// What [`Plan::apply`] restores if an action fails, or if the verification or the smoke test
+/// fails. `cargo member batch` keeps the one of each step to undo the earlier steps when a later
+/// one fails.
+#[derive(Debug, Default)]
+pub(crate) struct Rollback {
+    /// The files the actions write, copy over or remove and the lockfiles, and their contents if
+    /// they existed.
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+    /// The outermost directories the actions create.
+    created: Vec<PathBuf>,
+    /// Whether the directories are moved to `backup` instead of being removed. See
+    /// [`Plan::apply_undoable`].
+    keep_removed_dirs: bool,
+    /// The removed directories and where they are kept.
+    removed: Vec<(PathBuf, PathBuf)>,
+    backup: Option<StagingDir>,
+}
+
+impl Rollback {
+    fn save_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        if self.files.iter().any(|(p, _)| p == path) {
+            return Ok(());
+        }
+        let contents = if path.exists() {
+            Some(crate::fs::read(path)?)
+        } else {
+            None
+        };
+        self.files.push((path.to_owned(), contents));
+        Ok(())
+    }
+
+    /// Moves the directory at `path` to the backup instead of removing it.
+    fn remove_dir(&mut self, path: &Path) -> anyhow::Result<()> {
+        let backup = match &mut self.backup {
+            Some(backup) => backup,
+            None => self.backup.insert(StagingDir::new("cargo-member-backup")?),
+        };
+        let kept = backup.0.join(self.removed.len().to_string());
+        crate::fs::move_dir(path, &kept)?;
+        self.removed.push((path.to_owned(), kept));
+        Ok(())
+    }
+
+    /// Puts back the files that have changed, removes the created directories and moves the
+    /// removed ones back. Nothing is left to undo afterwards.
+    pub(crate) fn restore(&mut self, mut stderr: impl WriteColor) -> anyhow::Result<()> {
+        for (path, contents) in mem::take(&mut self.files) {
+            let current = if path.exists() {
+                Some(crate::fs::read(&path)?)
+            } else {
+                None
+            };
+            if current == contents {
+                continue;
+            }
+            match contents {
+                Some(contents) => {
+                    stderr.status_with_color("Restoring", path.display(), termcolor::Color::Red)?;
+                    crate::fs::write(&path, contents, false)?;
+                }
+                None => {
+                    stderr.status_with_color("Removing", path.display(), termcolor::Color::Red)?;
+                    crate::fs::remove_file(&path, false)?;
+                }
+            }
+        }
+        for created in mem::take(&mut self.created).iter().filter(|d| d.exists()) {
+            stderr.status_with_color(
+                "Removing",
+                format!("directory `{}`", created.display()),
+                termcolor::Color::Red,
+            )?;
+            crate::fs::remove_dir_all(created, false)?;
+        }
+        for (path, kept) in mem::take(&mut self.removed).into_iter().rev() {
+            stderr.status_with_color(
+                "Restoring",
+                format!("directory `{}`", path.display()),
+                termcolor::Color::Red,
+            )?;
+            crate::fs::move_dir(&kept, &path)?;
         }
         Ok(())
     }
 }
 
+/// Adds `src/main.rs` that calls `run` in `src/lib.rs` to the library package at `dir`.
+fn add_thin_main(
+    dir: &Path,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let cargo_toml = Action::read_toml_planned(actions, &dir.join("Cargo.toml"))?;
+    let crate_name = cargo_toml["package"]["name"]
+        .as_str()
+        .with_context(|| format!("`package.name` is not found in `{}`", dir.display()))?
+        .replace('-', "_");
+
+    let lib_rs = dir.join("src").join("lib.rs");
+    let main_rs = dir.join("src").join("main.rs");
+    let lib = Action::read_planned(actions, &lib_rs)?;
+    Action::plan_write(
+        actions,
+        lib_rs,
+        format!(
+            "pub fn run() {{\n    println!(\"Hello, world!\");\n}}\n{}{}",
+            if lib.is_empty() { "" } else { "\n" },
+            lib,
+        ),
+    )?;
+    stderr.status("Creating", format!("`{}`", main_rs.display()))?;
+    Action::plan_write(
+        actions,
+        main_rs,
+        format!("fn main() {{\n    {}::run();\n}}\n", crate_name),
+    )
+}
+
+fn inherit_workspace_lints(
+    manifest_path: &Path,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let mut cargo_toml = Action::read_toml_planned(actions, manifest_path)?;
+    if cargo_toml["lints"]["workspace"].as_bool() == Some(true) {
+        return Ok(());
+    }
+    if !cargo_toml["lints"].is_none() {
+        return stderr
+            .warn(format!(
+                "`lints` is already defined in `{}`. not adding `lints.workspace = true`",
+                manifest_path.display(),
+            ))
+            .map_err(Into::into);
+    }
+    cargo_toml["lints"] = toml_edit::table();
+    cargo_toml["lints"]["workspace"] = toml_edit::value(true);
+    stderr.status(
+        "Adding",
+        format!("`lints.workspace = true` to `{}`", manifest_path.display()),
+    )?;
+    Action::plan_write(actions, manifest_path.to_owned(), cargo_toml.to_string())
+}
+
+/// Parses the values of `(key, value)`s as TOML fragments, checking the types of the well-known
+/// `package` fields.
+fn parse_package_fields(
+    set_fields: &[(String, String)],
+) -> anyhow::Result<Vec<(String, toml_edit::Item)>> {
+    const STRING_FIELDS: &[&str] = &[
+        "version",
+        "edition",
+        "rust-version",
+        "description",
+        "documentation",
+        "homepage",
+        "repository",
+        "readme",
+        "license",
+        "license-file",
+        "links",
+        "default-run",
+        "resolver",
+    ];
+    const ARRAY_FIELDS: &[&str] = &["authors", "keywords", "categories", "include", "exclude"];
+
+    set_fields
+        .iter()
+        .map(|(key, value)| {
+            ensure!(
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                "invalid key: {:?}",
+                key,
+            );
+            ensure!(
+                !["name", "workspace"].contains(&&**key),
+                "`package.{}` cannot be set directly",
+                key,
+            );
+            let mut doc = format!("value = {}", value)
+                .parse::<toml_edit::Document>()
+                .map_err(|e| anyhow!("{}", e))
+                .with_context(|| {
+                    format!(
+                        "invalid value for `package.{}`: {:?}. note that values are parsed as \
+                         TOML (strings must be quoted)",
+                        key, value,
+                    )
+                })?;
+            let item = doc.as_table_mut().remove("value").expect("should exist");
+            ensure!(
+                doc.as_table().is_empty(),
+                "invalid value for `package.{}`: {:?}. expected a single value",
+                key,
+                value,
+            );
+            let inherited = item.as_inline_table().is_some();
+            if STRING_FIELDS.contains(&&**key) && !(item.is_str() || inherited) {
+                bail!(
+                    "`package.{}` must be a string. note that values are parsed as TOML: \
+                     `{}='\"{}\"'`",
+                    key,
+                    key,
+                    value,
+                );
+            }
+            if ARRAY_FIELDS.contains(&&**key) && !(item.is_array() || inherited) {
+                bail!("`package.{}` must be an array", key);
+            }
+            Ok((key.clone(), item))
+        })
+        .collect()
+}
+
+/// Sets `package.{key}` for each of `fields`.
+fn set_package_fields(
+    cargo_toml: &mut toml_edit::Document,
+    fields: &[(String, toml_edit::Item)],
+    manifest_path: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for (key, item) in fields {
+        let value = item.as_value().expect("should be a value");
+        stderr.status(
+            "Setting",
+            format!(
+                "`package.{} = {}` in `{}`",
+                key,
+                value.to_string().trim(),
+                manifest_path.display(),
+            ),
+        )?;
+        cargo_toml["package"][key] = item.clone();
+    }
+    Ok(())
+}
+
+/// Replaces each `package.{key}` with `{key}.workspace = true` if `workspace.package.{key}` is
+/// defined in `root_cargo_toml` and `package.{key}` is absent or has the same value. Returns
+/// whether `cargo_toml` is modified.
+fn inherit_workspace_package(
+    cargo_toml: &mut toml_edit::Document,
+    root_cargo_toml: &toml_edit::Document,
+    manifest_path: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<bool> {
+    /// The fields that can be inherited from `workspace.package`.
+    const INHERITABLE: &[&str] = &[
+        "authors",
+        "categories",
+        "description",
+        "documentation",
+        "edition",
+        "exclude",
+        "homepage",
+        "include",
+        "keywords",
+        "license",
+        "license-file",
+        "publish",
+        "readme",
+        "repository",
+        "rust-version",
+        "version",
+    ];
+
+    let workspace_package = match root_cargo_toml["workspace"]["package"].as_table_like() {
+        Some(workspace_package) if !cargo_toml["package"].is_none() => workspace_package,
+        _ => return Ok(false),
+    };
+    let as_toml = |item: &toml_edit::Item| {
+        item.as_value()
+            .and_then(|v| toml::from_str::<toml::Value>(&format!("v = {}", v)).ok())
+    };
+
+    let mut modified = false;
+    for (key, value) in workspace_package.iter() {
+        if !INHERITABLE.contains(&key) {
+            continue;
+        }
+        let current = &cargo_toml["package"][key];
+        if current["workspace"].as_bool() == Some(true) {
+            continue;
+        }
+        if !current.is_none() && as_toml(current) != as_toml(value) {
+            stderr.note(format!(
+                "keeping `package.{}` in `{}`: it differs from `workspace.package.{}`",
+                key,
+                manifest_path.display(),
+                key,
+            ))?;
+            continue;
+        }
+        let mut inherited = toml_edit::Table::new();
+        inherited.set_dotted(true);
+        inherited["workspace"] = toml_edit::value(true);
+        cargo_toml["package"][key] = toml_edit::Item::Table(inherited);
+        // The key keeps the decor of `key = ..`, which would put a space before the dot.
+        if let Some(decor) = cargo_toml["package"]
+            .as_table_mut()
+            .and_then(|t| t.key_decor_mut(key))
+        {
+            let prefix = decor.prefix().unwrap_or("").to_owned();
+            *decor = toml_edit::Decor::new(prefix, "");
+        }
+        stderr.status(
+            "Inheriting",
+            format!(
+                "`package.{}` from the workspace in `{}`",
+                key,
+                manifest_path.display(),
+            ),
+        )?;
+        modified = true;
+    }
+    Ok(modified)
+}
+
+/// Sets `package.publish = false` unless `package.publish` is present. Returns whether
+/// `cargo_toml` is modified.
+fn mark_unpublishable(
+    cargo_toml: &mut toml_edit::Document,
+    manifest_path: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<bool> {
+    if cargo_toml["package"].is_none() || !cargo_toml["package"]["publish"].is_none() {
+        return Ok(false);
+    }
+    cargo_toml["package"]["publish"] = toml_edit::value(false);
+    stderr.status(
+        "Adding",
+        format!("`package.publish = false` to `{}`", manifest_path.display()),
+    )?;
+    Ok(true)
+}
+
+/// Directories skipped by [`Cp`] by default.
+pub const DEFAULT_ARTIFACT_DIRS: &[&str] = &["target"];
+
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkBuilder::new(dir)
+        .standard_filters(false)
+        .build()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Warns if `package` uses edition 2024 while the workspace resolver is lower than `"3"`, or sets
+/// `workspace.resolver` to `set_resolver`.
+///
+/// Edition 2024 implies `resolver = "3"` only for a root package. A virtual workspace, or one whose
+/// root package uses an older edition, keeps resolving with the older resolver for every member.
+fn check_resolver(
+    workspace_root: &Path,
+    package: &Path,
+    cargo_toml: &toml_edit::Document,
+    set_resolver: Option<&str>,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    if let Some(set_resolver) = set_resolver {
+        return set_workspace_resolver(workspace_root, set_resolver, actions, stderr);
+    }
+
+    let root_manifest_path = workspace_root.join("Cargo.toml");
+    let root_cargo_toml = Action::read_toml_planned(actions, &root_manifest_path)?;
+
+    let edition = if cargo_toml["package"]["edition"]["workspace"].as_bool() == Some(true) {
+        root_cargo_toml["workspace"]["package"]["edition"].as_str()
+    } else {
+        cargo_toml["package"]["edition"].as_str()
+    };
+    if edition != Some("2024") {
+        return Ok(());
+    }
+
+    let resolver = match (
+        root_cargo_toml["workspace"]["resolver"].as_str(),
+        root_cargo_toml["package"]["edition"].as_str(),
+    ) {
+        (Some(resolver), _) => resolver,
+        (None, Some("2024")) => "3",
+        (None, Some("2021")) => "2",
+        (None, _) => "1",
+    };
+    if resolver < "3" {
+        stderr.warn(format!(
+            "`{}` uses edition 2024 but the workspace at `{}` uses resolver {:?}. edition 2024 \
+             implies `resolver = \"3\"` only for a root package; members of a virtual workspace \
+             follow `workspace.resolver`. enable `--set-resolver 3` to set it",
+            package.display(),
+            workspace_root.display(),
+            resolver,
+        ))?;
+    }
+    Ok(())
+}
+
+fn set_workspace_resolver(
+    workspace_root: &Path,
+    resolver: &str,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+    let old = cargo_toml["workspace"]["resolver"]
+        .as_str()
+        .map(ToOwned::to_owned);
+    match old {
+        Some(old) if old == resolver => return Ok(()),
+        Some(old) => stderr.status(
+            "Changing",
+            format!(
+                "`workspace.resolver` in `{}` from {:?} to {:?}",
+                manifest_path.display(),
+                old,
+                resolver,
+            ),
+        )?,
+        None => stderr.status(
+            "Adding",
+            format!(
+                "`workspace.resolver = {:?}` to `{}`",
+                resolver,
+                manifest_path.display(),
+            ),
+        )?,
+    }
+    cargo_toml["workspace"]["resolver"] = toml_edit::value(resolver);
+    Action::plan_write(actions, manifest_path, cargo_toml.to_string())
+}
+
 #[derive(Default, Debug)]
 struct Args(Vec<OsString>);
 
@@ -685,8 +2808,38 @@ impl IntoIterator for Args {
 pub struct Cp<W> {
     src: anyhow::Result<PathBuf>,
     dst: anyhow::Result<PathBuf>,
+    src_workspace_root: Option<PathBuf>,
     dry_run: bool,
+    stdout: bool,
+    force: bool,
+    register_in: RegisterIn,
+    dst_workspace_root: Option<anyhow::Result<PathBuf>>,
+    replace_in: Option<anyhow::Result<PathBuf>>,
     no_rename: bool,
+    rename_paths: bool,
+    version: Option<BumpLevel>,
+    minimal: bool,
+    exclude: Vec<String>,
+    respect_cargo_config: bool,
+    set_resolver: Option<String>,
+    artifact_dirs: Vec<String>,
+    copy_all: bool,
+    members_per_line: Option<usize>,
+    strip_path_deps_to_versions: bool,
+    keep_path: bool,
+    inline_inherited: bool,
+    prefix_dot_slash: bool,
+    allow_duplicate_names: bool,
+    set_fields: Vec<(String, String)>,
+    name_suffix: Option<String>,
+    scan_path_attrs: bool,
+    fix_path_attrs: bool,
+    smoke_test: bool,
+    rollback_on_failure: bool,
+    offline: bool,
+    verbose: bool,
+    timings: Timings,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -697,6 +2850,39 @@ impl Cp<NoColor<Sink>> {
             dst: ensure_absolute(dst),
             dry_run: false,
             no_rename: false,
+            minimal: false,
+            exclude: vec![],
+            stdout: false,
+            force: false,
+            register_in: RegisterIn::Dst,
+            src_workspace_root: None,
+            respect_cargo_config: false,
+            set_resolver: None,
+            artifact_dirs: DEFAULT_ARTIFACT_DIRS
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect(),
+            copy_all: false,
+            members_per_line: None,
+            strip_path_deps_to_versions: false,
+            keep_path: false,
+            prefix_dot_slash: false,
+            allow_duplicate_names: false,
+            set_fields: vec![],
+            name_suffix: None,
+            scan_path_attrs: false,
+            fix_path_attrs: false,
+            smoke_test: false,
+            rollback_on_failure: false,
+            offline: false,
+            verbose: false,
+            timings: Timings::default(),
+            replace_in: None,
+            inline_inherited: false,
+            rename_paths: false,
+            dst_workspace_root: None,
+            version: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -714,6 +2900,39 @@ impl Cp<NoColor<Sink>> {
             dst: ensure_absolute(dst),
             dry_run: false,
             no_rename: false,
+            minimal: false,
+            exclude: vec![],
+            stdout: false,
+            force: false,
+            register_in: RegisterIn::Dst,
+            src_workspace_root: Some(metadata.workspace_root.clone().into_std_path_buf()),
+            respect_cargo_config: false,
+            set_resolver: None,
+            artifact_dirs: DEFAULT_ARTIFACT_DIRS
+                .iter()
+                .map(|&s| s.to_owned())
+                .collect(),
+            copy_all: false,
+            members_per_line: None,
+            strip_path_deps_to_versions: false,
+            keep_path: false,
+            prefix_dot_slash: false,
+            allow_duplicate_names: false,
+            set_fields: vec![],
+            name_suffix: None,
+            scan_path_attrs: false,
+            fix_path_attrs: false,
+            smoke_test: false,
+            rollback_on_failure: false,
+            offline: false,
+            verbose: false,
+            timings: Timings::default(),
+            replace_in: None,
+            inline_inherited: false,
+            rename_paths: false,
+            dst_workspace_root: None,
+            version: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -728,46 +2947,504 @@ impl<W: WriteColor> Cp<W> {
         Self { no_rename, ..self }
     }
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Cp<W2> {
-        Cp {
-            src: self.src,
-            dst: self.dst,
-            dry_run: self.dry_run,
-            no_rename: self.no_rename,
-            stderr,
+    /// Copies even if the destination is an existing package or member.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    /// Which workspace to add the copy to. Defaults to [`RegisterIn::Dst`].
+    pub fn register_in(self, register_in: RegisterIn) -> Self {
+        Self {
+            register_in,
+            ..self
         }
     }
 
-    pub fn exec(self) -> anyhow::Result<()> {
-        let Self {
-            mut stderr,
-            src,
-            dst,
-            dry_run,
-            no_rename,
-        } = self;
+    /// The root of the workspace [`RegisterIn::Dst`] adds the copy to. Without this, it is the
+    /// workspace that contains the destination, if any.
+    pub fn dst_workspace_root(self, dst_workspace_root: Option<&Path>) -> Self {
+        Self {
+            dst_workspace_root: dst_workspace_root.map(ensure_absolute),
+            ..self
+        }
+    }
 
-        let (src, dst) = (src?, dst?);
+    /// Copies only `Cargo.toml` and the files under `src/`.
+    pub fn minimal(self, minimal: bool) -> Self {
+        Self { minimal, ..self }
+    }
 
-        let dst = if dst.exists() {
-            dst.join(src.file_name().expect("should be absolute"))
-        } else {
-            dst
-        };
+    /// Skips the paths matched by these globs. They are applied after the `minimal` preset.
+    pub fn exclude<Ss: IntoIterator<Item = S>, S: AsRef<str>>(self, exclude: Ss) -> Self {
+        let exclude = exclude.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self { exclude, ..self }
+    }
 
-        ensure!(!dst.exists(), "`{}` exists", dst.display());
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
 
-        let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
-            .with_context(|| format!("`{}` does not seem to be a package", src.display()))?;
-        if let Some(package) = cargo_toml["package"].as_table_mut() {
-            package.remove("workspace");
-            if !no_rename {
-                let file_name = dst.file_name().expect("should exist");
-                let file_name = file_name
-                    .to_str()
-                    .with_context(|| format!("{:?} is not valid UTF-8", file_name))?;
-                package["name"] = toml_edit::value(file_name);
-            }
+    /// Skips `build.target-dir` configured in `.cargo/config.toml` or `$CARGO_TARGET_DIR` if it
+    /// is inside the source package.
+    pub fn respect_cargo_config(self, respect_cargo_config: bool) -> Self {
+        Self {
+            respect_cargo_config,
+            ..self
+        }
+    }
+
+    /// Sets `workspace.resolver` in the workspace manifest.
+    ///
+    /// Without this, a warning is emitted when an edition 2024 package is added to a workspace
+    /// whose resolver is lower than `"3"`.
+    pub fn set_resolver<S: AsRef<str>>(self, set_resolver: Option<S>) -> Self {
+        let set_resolver = set_resolver.map(|s| s.as_ref().to_owned());
+        Self {
+            set_resolver,
+            ..self
+        }
+    }
+
+    /// Directories (relative to the source package) skipped as build artifacts. Defaults to
+    /// [`DEFAULT_ARTIFACT_DIRS`]. VCS directories are always skipped unless `copy_all` is enabled.
+    pub fn artifact_dirs<Ss: IntoIterator<Item = S>, S: AsRef<str>>(
+        self,
+        artifact_dirs: Ss,
+    ) -> Self {
+        let artifact_dirs = artifact_dirs
+            .into_iter()
+            .map(|s| s.as_ref().to_owned())
+            .collect();
+        Self {
+            artifact_dirs,
+            ..self
+        }
+    }
+
+    /// Disables all skipping: artifact directories, VCS directories and ignore files.
+    pub fn copy_all(self, copy_all: bool) -> Self {
+        Self { copy_all, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Sets `version` of each path dependency to the version of the package it points to, and
+    /// removes `path`, so that the package can be published.
+    pub fn strip_path_deps_to_versions(self, strip_path_deps_to_versions: bool) -> Self {
+        Self {
+            strip_path_deps_to_versions,
+            ..self
+        }
+    }
+
+    /// With `strip_path_deps_to_versions`, keeps `path` alongside `version`.
+    pub fn keep_path(self, keep_path: bool) -> Self {
+        Self { keep_path, ..self }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Proceeds even if the workspace the copy is added to already has a package with the same
+    /// name.
+    pub fn allow_duplicate_names(self, allow_duplicate_names: bool) -> Self {
+        Self {
+            allow_duplicate_names,
+            ..self
+        }
+    }
+
+    /// Sets `package.{key}` of the copy to each value, parsed as a TOML fragment. Strings must be
+    /// quoted: `("rust-version", "\"1.70\"")`, `("authors", "[\"Me\"]")`.
+    pub fn set_fields<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<str>>(
+        self,
+        set_fields: I,
+    ) -> Self {
+        let set_fields = set_fields
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+            .collect();
+        Self { set_fields, ..self }
+    }
+
+    /// If the name derived from the destination is taken by another member of the workspace, names
+    /// the copy `{name}{suffix}`, or `{name}{suffix}-{n}` with the smallest `n` starting from `2`
+    /// that is not taken. Has no effect with [`no_rename`](Self::no_rename).
+    pub fn name_suffix<S: AsRef<str>>(self, name_suffix: Option<S>) -> Self {
+        let name_suffix = name_suffix.map(|s| s.as_ref().to_owned());
+        Self {
+            name_suffix,
+            ..self
+        }
+    }
+
+    /// Reports the `#[path = ".."]` attributes in the `.rs` files of the package whose targets are
+    /// outside the package, which may stop resolving from the destination.
+    pub fn scan_path_attrs(self, scan_path_attrs: bool) -> Self {
+        Self {
+            scan_path_attrs,
+            ..self
+        }
+    }
+
+    /// With `scan_path_attrs`, rewrites the reported attributes so that they still resolve from the
+    /// destination.
+    pub fn fix_path_attrs(self, fix_path_attrs: bool) -> Self {
+        Self {
+            fix_path_attrs,
+            ..self
+        }
+    }
+
+    /// Runs `cargo build -p <name>` for the new package afterwards and fails if it does not
+    /// build. Skipped with `dry_run`.
+    pub fn smoke_test(self, smoke_test: bool) -> Self {
+        Self { smoke_test, ..self }
+    }
+
+    /// With `smoke_test`, restores `Cargo.toml` and `Cargo.lock` of the workspace and removes the
+    /// new package if it does not build.
+    pub fn rollback_on_failure(self, rollback_on_failure: bool) -> Self {
+        Self {
+            rollback_on_failure,
+            ..self
+        }
+    }
+
+    /// Runs the smoke test with `--offline`.
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Streams the output of the smoke test instead of printing it only if the build fails.
+    pub fn verbose(self, verbose: bool) -> Self {
+        Self { verbose, ..self }
+    }
+
+    /// Records how long planning, copying the files and writing the manifests take.
+    pub fn timings(self, timings: Timings) -> Self {
+        Self { timings, ..self }
+    }
+
+    /// Points the dependencies of the package at `replace_in` on the source package to the copy,
+    /// setting their `package` to the new name. Fails if it does not depend on the source package.
+    pub fn replace_in(self, replace_in: Option<&Path>) -> Self {
+        Self {
+            replace_in: replace_in.map(ensure_absolute),
+            ..self
+        }
+    }
+
+    /// Inlines the fields the package inherits from its workspace (e.g. `version.workspace = true`)
+    /// if the copy does not belong to the same workspace. Without this, copying such a package out
+    /// of its workspace is an error unless the destination workspace defines the same fields.
+    pub fn inline_inherited(self, inline_inherited: bool) -> Self {
+        Self {
+            inline_inherited,
+            ..self
+        }
+    }
+
+    /// Renames the source files of the `lib` and `bin` targets named after the package (e.g.
+    /// `src/bin/<name>.rs`) along with the package. `src/main.rs` and `src/lib.rs` are left alone.
+    pub fn rename_paths(self, rename_paths: bool) -> Self {
+        Self {
+            rename_paths,
+            ..self
+        }
+    }
+
+    /// Changes `package.version` of the copy. [`BumpLevel::Version`] sets it, and the others bump
+    /// the version of the source package, which must then be a valid version.
+    pub fn version(self, version: Option<BumpLevel>) -> Self {
+        Self { version, ..self }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Cp<W2> {
+        Cp {
+            src: self.src,
+            dst: self.dst,
+            dry_run: self.dry_run,
+            no_rename: self.no_rename,
+            minimal: self.minimal,
+            exclude: self.exclude,
+            stderr,
+            stdout: self.stdout,
+            force: self.force,
+            register_in: self.register_in,
+            src_workspace_root: self.src_workspace_root,
+            respect_cargo_config: self.respect_cargo_config,
+            set_resolver: self.set_resolver,
+            artifact_dirs: self.artifact_dirs,
+            copy_all: self.copy_all,
+            members_per_line: self.members_per_line,
+            strip_path_deps_to_versions: self.strip_path_deps_to_versions,
+            keep_path: self.keep_path,
+            prefix_dot_slash: self.prefix_dot_slash,
+            allow_duplicate_names: self.allow_duplicate_names,
+            set_fields: self.set_fields,
+            name_suffix: self.name_suffix,
+            scan_path_attrs: self.scan_path_attrs,
+            fix_path_attrs: self.fix_path_attrs,
+            smoke_test: self.smoke_test,
+            rollback_on_failure: self.rollback_on_failure,
+            offline: self.offline,
+            verbose: self.verbose,
+            timings: self.timings,
+            replace_in: self.replace_in,
+            inline_inherited: self.inline_inherited,
+            rename_paths: self.rename_paths,
+            dst_workspace_root: self.dst_workspace_root,
+            version: self.version,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Returns the changes `exec` would make without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            mut stderr,
+            src,
+            dst,
+            dry_run,
+            no_rename,
+            minimal,
+            exclude,
+            stdout,
+            force,
+            register_in,
+            src_workspace_root,
+            respect_cargo_config,
+            set_resolver,
+            artifact_dirs,
+            copy_all,
+            members_per_line,
+            strip_path_deps_to_versions,
+            keep_path,
+            prefix_dot_slash,
+            allow_duplicate_names,
+            set_fields,
+            name_suffix,
+            scan_path_attrs,
+            fix_path_attrs,
+            smoke_test,
+            rollback_on_failure,
+            offline,
+            verbose,
+            timings,
+            replace_in,
+            inline_inherited,
+            rename_paths,
+            dst_workspace_root,
+            version,
+            on_top_of,
+        } = self;
+
+        let start = Instant::now();
+
+        let dry_run = dry_run || stdout;
+
+        let (src, dst) = (src?, normalize_path(&dst?));
+        let set_fields = parse_package_fields(&set_fields)?;
+
+        let dst = if dst.exists() {
+            dst.join(src.file_name().expect("should be absolute"))
+        } else {
+            dst
+        };
+
+        if cfg!(windows) {
+            ensure_not_reserved_on_windows(dst.file_name().unwrap_or_default())?;
+        }
+
+        if !force {
+            ensure!(
+                !dst.exists(),
+                "`{}` exists. enable `--force` to copy",
+                dst.display()
+            );
+            if let Some(member) = find_conflicting_member(&dst)? {
+                bail!(
+                    "`{}` conflicts with {}. enable `--force` to copy",
+                    dst.display(),
+                    member,
+                );
+            }
+        }
+
+        let workspace_root = match (register_in, dst_workspace_root.transpose()?) {
+            (RegisterIn::Dst, Some(dst_workspace_root)) => {
+                let dst_workspace_root = normalize_path(&dst_workspace_root);
+                ensure!(
+                    is_workspace_root(&dst_workspace_root)?,
+                    "`{}` is not the root of a workspace",
+                    dst_workspace_root.join("Cargo.toml").display(),
+                );
+                Some(dst_workspace_root)
+            }
+            (RegisterIn::Dst, None) => match &*dst
+                .ancestors()
+                .skip(1)
+                .filter(|d| d.join("Cargo.toml").exists())
+                .collect::<Vec<_>>()
+            {
+                [dst_workspace_root] => Some(dst_workspace_root.to_path_buf()),
+                _ => None,
+            },
+            (RegisterIn::Src, _) => {
+                let src_workspace_root = src_workspace_root.clone().or_else(|| {
+                    src.ancestors()
+                        .skip(1)
+                        .filter(|d| d.join("Cargo.toml").exists())
+                        .last()
+                        .map(ToOwned::to_owned)
+                });
+                Some(src_workspace_root.with_context(|| {
+                    format!("`{}` does not seem to be in a workspace", src.display())
+                })?)
+            }
+            (RegisterIn::None, _) => None,
+        };
+
+        let mut cargo_toml = crate::fs::read_toml_edit(src.join("Cargo.toml"))
+            .with_context(|| format!("`{}` does not seem to be a package", src.display()))?;
+        let old_name = cargo_toml["package"]["name"]
+            .as_str()
+            .map(ToOwned::to_owned);
+
+        let replace_in = replace_in
+            .transpose()?
+            .map(|dir| -> anyhow::Result<_> {
+                let deps = dependencies_on(&dir, &src)?;
+                if deps.is_empty() {
+                    bail!(
+                        "`{}` does not depend on `{}`",
+                        dir.join("Cargo.toml").display(),
+                        src.display(),
+                    );
+                }
+                Ok((dir, deps))
+            })
+            .transpose()?;
+
+        // `(key, old, new)`
+        let mut edits = vec![];
+        if let Some(package) = cargo_toml["package"].as_table_mut() {
+            let old_workspace = package
+                .remove("workspace")
+                .and_then(|w| w.as_str().map(ToOwned::to_owned));
+            let mut new_workspace = None;
+            if let Some(workspace_root) = &workspace_root {
+                if !dst.starts_with(workspace_root) {
+                    let rel = relative_path(&dst, workspace_root);
+                    let rel = rel
+                        .to_str()
+                        .with_context(|| format!("{:?} is not valid UTF-8", rel))?;
+                    package["workspace"] = toml_edit::value(rel);
+                    new_workspace = Some(rel.to_owned());
+                }
+            }
+            if old_workspace != new_workspace {
+                edits.push(("package.workspace", old_workspace, new_workspace));
+            }
+            if !no_rename {
+                let file_name = dst.file_name().expect("should exist");
+                let file_name = file_name
+                    .to_str()
+                    .with_context(|| format!("{:?} is not valid UTF-8", file_name))?;
+                let mut new_name = file_name.to_owned();
+                if let (Some(name_suffix), Some(workspace_root)) = (&name_suffix, &workspace_root) {
+                    let taken = member_names(workspace_root, &[&dst])?
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<_>>();
+                    if taken.contains(&new_name) {
+                        new_name = (1..)
+                            .map(|n| match n {
+                                1 => format!("{}{}", file_name, name_suffix),
+                                n => format!("{}{}-{}", file_name, name_suffix, n),
+                            })
+                            .find(|name| !taken.contains(name))
+                            .expect("should be found");
+                        stderr.note(format!(
+                            "`{}` is taken. naming the copy `{}`",
+                            file_name, new_name,
+                        ))?;
+                    }
+                }
+                let old_name = package["name"].as_str().map(ToOwned::to_owned);
+                if old_name.as_deref() != Some(&new_name) {
+                    edits.push(("package.name", old_name, Some(new_name.clone())));
+                }
+                package["name"] = toml_edit::value(new_name);
+            }
+            if let Some(version) = &version {
+                let src_manifest_path = src.join("Cargo.toml");
+                let old_version = package["version"].as_str().map(ToOwned::to_owned);
+                let new_version = match (version, &old_version) {
+                    (BumpLevel::Version(new_version), _) => new_version.clone(),
+                    (level, Some(old_version)) => {
+                        let old_version = old_version.parse::<Version>().with_context(|| {
+                            format!(
+                                "invalid `package.version` in `{}`",
+                                src_manifest_path.display(),
+                            )
+                        })?;
+                        level.apply(&old_version)
+                    }
+                    (_, None) if package["version"]["workspace"].as_bool() == Some(true) => bail!(
+                        "`package.version` of `{}` is inherited from the workspace. specify the \
+                         version instead",
+                        src_manifest_path.display(),
+                    ),
+                    (_, None) => bail!(
+                        "`package.version` is not found in `{}`",
+                        src_manifest_path.display(),
+                    ),
+                };
+                let new_version = new_version.to_string();
+                if old_version.as_deref() != Some(&new_version) {
+                    edits.push(("package.version", old_version, Some(new_version.clone())));
+                }
+                package["version"] = toml_edit::value(new_version);
+            }
+        }
+
+        if let (Some(workspace_root), Some(name), false) = (
+            &workspace_root,
+            cargo_toml["package"]["name"].as_str(),
+            allow_duplicate_names,
+        ) {
+            ensure_unique_name(workspace_root, name, &[&dst])?;
         }
 
         stderr.status(
@@ -775,65 +3452,364 @@ impl<W: WriteColor> Cp<W> {
             format!("`{}` to `{}`", src.display(), dst.display()),
         )?;
 
+        let dst_manifest_path = dst.join("Cargo.toml");
+        for (key, old, new) in edits {
+            match (old, new) {
+                (Some(old), Some(new)) => stderr.status(
+                    "Changing",
+                    format!(
+                        "`{}` in `{}` from {:?} to {:?}",
+                        key,
+                        dst_manifest_path.display(),
+                        old,
+                        new,
+                    ),
+                )?,
+                (None, Some(new)) => stderr.status(
+                    "Adding",
+                    format!("`{} = {:?}` to `{}`", key, new, dst_manifest_path.display()),
+                )?,
+                (Some(_), None) => stderr.status_with_color(
+                    "Removing",
+                    format!("`{}` from `{}`", key, dst_manifest_path.display()),
+                    termcolor::Color::Red,
+                )?,
+                (None, None) => {}
+            }
+        }
+
+        set_package_fields(
+            &mut cargo_toml,
+            &set_fields,
+            &dst_manifest_path,
+            &mut stderr,
+        )?;
+
+        let new_name = cargo_toml["package"]["name"]
+            .as_str()
+            .map(ToOwned::to_owned);
+        let renames = match (&old_name, &new_name) {
+            (Some(old_name), Some(new_name)) if !no_rename && old_name != new_name => {
+                rename_targets(
+                    &mut cargo_toml,
+                    &src,
+                    old_name,
+                    new_name,
+                    rename_paths,
+                    &dst_manifest_path,
+                    &mut stderr,
+                )?
+            }
+            _ => vec![],
+        };
+
+        let inherited = inherited_fields(&cargo_toml);
+        if !inherited.is_empty() && register_in != RegisterIn::None {
+            let src_workspace_root = match src_workspace_root {
+                Some(src_workspace_root) => src_workspace_root,
+                None => find_workspace_root(&src.join("Cargo.toml"))?,
+            };
+            if workspace_root.as_ref() != Some(&src_workspace_root) {
+                if inline_inherited {
+                    inline_inherited_fields(
+                        &mut cargo_toml,
+                        &src_workspace_root,
+                        &dst,
+                        &mut stderr,
+                    )?;
+                } else {
+                    let dst_root_cargo_toml = workspace_root
+                        .as_ref()
+                        .map(|w| crate::fs::read_toml_edit(w.join("Cargo.toml")))
+                        .transpose()?;
+                    let undefined = inherited
+                        .iter()
+                        .filter(|field| {
+                            let dst_root_cargo_toml = match &dst_root_cargo_toml {
+                                Some(dst_root_cargo_toml) => dst_root_cargo_toml,
+                                None => return true,
+                            };
+                            let workspace = &dst_root_cargo_toml["workspace"];
+                            let item = match field.rsplit_once('.') {
+                                Some(("package", key)) => &workspace["package"][key],
+                                Some((_, key)) => &workspace["dependencies"][key],
+                                None => &workspace[field.as_str()],
+                            };
+                            item.is_none()
+                        })
+                        .map(|field| format!("`{}`", field))
+                        .collect::<Vec<_>>();
+                    if !undefined.is_empty() {
+                        bail!(
+                            "`{}` inherits {} from `{}`, which {}. enable `--inline-inherited` to \
+                             inline them",
+                            src.join("Cargo.toml").display(),
+                            undefined.join(", "),
+                            src_workspace_root.join("Cargo.toml").display(),
+                            match &workspace_root {
+                                Some(workspace_root) => format!(
+                                    "`{}` does not define for the copy",
+                                    workspace_root.join("Cargo.toml").display(),
+                                ),
+                                None => "the copy will not belong to".to_owned(),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        if strip_path_deps_to_versions {
+            strip_path_dependencies(&mut cargo_toml, &src, keep_path, &mut stderr)?;
+        }
+
         let src_root = src;
-        for src in WalkBuilder::new(&src_root).hidden(false).build() {
+
+        let mut overrides = OverrideBuilder::new(&src_root);
+        if minimal {
+            for glob in &["/Cargo.toml", "/src/**"] {
+                overrides.add(glob)?;
+            }
+        }
+        for glob in &exclude {
+            overrides
+                .add(&format!("!{}", glob))
+                .with_context(|| format!("invalid glob: {:?}", glob))?;
+        }
+        if respect_cargo_config {
+            if let Some(target_dir) = cargo_config_target_dir(&src_root)? {
+                if let Ok(rel) = target_dir.strip_prefix(&src_root) {
+                    if let Some(rel) = rel.to_str().filter(|s| !s.is_empty()) {
+                        debug!("skipping the target directory `{}`", target_dir.display());
+                        overrides.add(&format!("!/{}/", glob::Pattern::escape(rel)))?;
+                    }
+                }
+            }
+        }
+        let mut skipped = vec![];
+        if !copy_all {
+            for dir in VCS_DIRS
+                .iter()
+                .copied()
+                .chain(artifact_dirs.iter().map(|s| &**s))
+            {
+                let dir = dir.trim_matches('/');
+                if src_root.join(dir).is_dir() {
+                    overrides.add(&format!("!/{}/", glob::Pattern::escape(dir)))?;
+                    skipped.push(dir);
+                }
+            }
+        }
+        let overrides = overrides.build()?;
+
+        // The directories planned to be created, so that each is created once.
+        let mut planned_dirs = vec![dst.clone()];
+        let mut actions = on_top_of;
+        actions.push(Action::CreateDir { path: dst.clone() });
+
+        // `.gitignore` files are honored even if the package is not in a Git repository.
+        for src in WalkBuilder::new(&src_root)
+            .standard_filters(!copy_all)
+            .require_git(false)
+            .hidden(false)
+            .overrides(overrides)
+            .build()
+        {
             match src {
                 Ok(src) => {
                     let src = src.path();
-                    if !(src.is_dir()
-                        || src == src_root.join("Cargo.toml")
-                        || src.starts_with(src_root.join(".git")))
-                    {
-                        let dst = dst.join(src.strip_prefix(&src_root)?);
+                    if !(src.is_dir() || src == src_root.join("Cargo.toml")) {
+                        let dst = dst.join(renamed(src.strip_prefix(&src_root)?, &renames));
                         if let Some(parent) = dst.parent() {
-                            if !parent.exists() {
-                                crate::fs::create_dir_all(parent, dry_run)?;
+                            if !(parent.exists() || planned_dirs.iter().any(|d| d == parent)) {
+                                actions.push(Action::CreateDir {
+                                    path: parent.to_owned(),
+                                });
+                                planned_dirs.push(parent.to_owned());
                             }
                         }
-                        crate::fs::copy(src, dst, dry_run)?;
+                        actions.push(Action::CopyFile {
+                            src: src.to_owned(),
+                            dst,
+                        });
                     }
                 }
                 Err(err) => stderr.warn(err)?,
             }
         }
 
-        crate::fs::write(dst.join("Cargo.toml"), cargo_toml.to_string(), dry_run)?;
+        Action::plan_write(&mut actions, dst.join("Cargo.toml"), cargo_toml.to_string())?;
 
-        if let [_, dst_workspace_root] = &*dst
-            .ancestors()
-            .filter(|d| d.join("Cargo.toml").exists())
-            .collect::<Vec<_>>()
-        {
+        if !skipped.is_empty() {
+            let bytes = skipped
+                .iter()
+                .map(|d| dir_size(&src_root.join(d)))
+                .sum::<u64>();
+            stderr.status(
+                "Skipped",
+                format!(
+                    "{} bytes in {}",
+                    bytes,
+                    skipped
+                        .iter()
+                        .format_with(", ", |d, f| f(&format_args!("`{}`", d))),
+                ),
+            )?;
+        }
+
+        if scan_path_attrs {
+            rewrite_path_attrs(
+                &src_root,
+                &dst,
+                &renames,
+                fix_path_attrs,
+                &mut actions,
+                &mut stderr,
+            )?;
+        }
+
+        if let Some(workspace_root) = &workspace_root {
             stderr.status_with_color(
                 "Found",
-                format!("workspace at {}", dst_workspace_root.display()),
+                format!("workspace at {}", workspace_root.display()),
                 termcolor::Color::Cyan,
             )?;
 
-            modify_members(
-                dst_workspace_root,
+            plan_members(
+                workspace_root,
                 &[&dst],
                 &[],
                 &[],
                 &[&dst],
-                dry_run,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
                 &mut stderr,
             )?;
+
+            // A package copied within its workspace is already resolved with its resolver.
+            if set_resolver.is_some() || !src_root.starts_with(workspace_root) {
+                check_resolver(
+                    workspace_root,
+                    &dst,
+                    &cargo_toml,
+                    set_resolver.as_deref(),
+                    &mut actions,
+                    &mut stderr,
+                )?;
+            }
         }
 
-        if dry_run {
-            stderr.warn("not copying due to dry run")?;
+        if let Some((dir, deps)) = &replace_in {
+            replace_dependencies(
+                dir,
+                deps,
+                &dst,
+                old_name.as_deref(),
+                cargo_toml["package"]["name"].as_str(),
+                &mut actions,
+                &mut stderr,
+            )?;
+        }
+        timings.add("planning", start);
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![],
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: Some(SmokeTest {
+                dir: dst,
+                workspace_root,
+                verbose,
+                rollback: rollback_on_failure,
+            })
+            .filter(|_| smoke_test),
+            staging: None,
+            dry_run_warning: Some("not copying due to dry run"),
+            timings,
+        })
+    }
+}
+
+/// Returns `(package.name, dir)` of each member of the workspace other than `ignore`.
+fn member_names(workspace_root: &Path, ignore: &[&Path]) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let mut names = vec![];
+    for dir in expand_members(workspace_root, &root_cargo_toml)?.0 {
+        if ignore.contains(&&*dir) {
+            continue;
+        }
+        let cargo_toml = crate::fs::read_toml_edit(dir.join("Cargo.toml"))?;
+        if let Some(name) = cargo_toml["package"]["name"].as_str() {
+            names.push((name.to_owned(), dir));
+        }
+    }
+    Ok(names)
+}
+
+/// Fails if a member of the workspace other than `ignore` is named `name`.
+fn ensure_unique_name(workspace_root: &Path, name: &str, ignore: &[&Path]) -> anyhow::Result<()> {
+    for (other, dir) in member_names(workspace_root, ignore)? {
+        if other == name {
+            bail!(
+                "the workspace at `{}` would have two packages named `{}`: `{}` and `{}`. enable \
+                 `--allow-duplicate-names` to proceed anyway",
+                workspace_root.display(),
+                name,
+                dir.display(),
+                ignore.last().expect("should not be empty").display(),
+            );
         }
-        Ok(())
     }
+    Ok(())
+}
+
+/// How [`Mv`] finds the members that depend on the moved package.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum DependentScan {
+    /// Read the manifest of every member.
+    All,
+    /// Read only the manifests of the packages that depend on the moved one according to the
+    /// resolve graph of `cargo metadata`, which also covers renamed dependencies. Falls back to
+    /// `All` if the metadata has no resolve graph.
+    ResolveGraph,
+}
+
+/// The workspace to add a copied package to.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum RegisterIn {
+    /// The workspace that contains the destination, if any.
+    Dst,
+    /// The workspace of the source package. `package.workspace` is set if the destination is
+    /// outside of it.
+    Src,
+    /// Do not add the copy to any workspace.
+    None,
 }
 
 #[derive(Debug)]
 pub struct Rm<W> {
     workspace_root: anyhow::Result<PathBuf>,
     paths: anyhow::Result<Vec<PathBuf>>,
+    unresolved_specs: Vec<anyhow::Error>,
     force: bool,
     dry_run: bool,
+    stdout: bool,
+    ignore_missing: bool,
+    prune_deps: bool,
+    members_per_line: Option<usize>,
+    on_top_of: Vec<Action>,
     stderr: W,
 }
 
@@ -847,6 +3823,12 @@ impl Rm<NoColor<Sink>> {
             paths: paths.into_iter().map(ensure_absolute).collect(),
             force: false,
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs: vec![],
+            prune_deps: false,
+            members_per_line: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -861,23 +3843,22 @@ impl Rm<NoColor<Sink>> {
         paths: Ps,
         specs: Ss,
     ) -> Self {
+        let (member_dirs, unresolved_specs) = resolve_specs(metadata, specs);
         Self {
             workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
             paths: paths
                 .into_iter()
                 .map(ensure_absolute)
-                .chain(specs.into_iter().map(|spec| {
-                    let member = metadata.query_for_member(Some(spec.as_ref()))?;
-                    Ok(member
-                        .manifest_path
-                        .clone().into_std_path_buf()
-                        .parent()
-                        .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                        .to_owned())
-                }))
+                .chain(member_dirs.into_iter().map(Ok))
                 .collect(),
             force: false,
             dry_run: false,
+            stdout: false,
+            ignore_missing: false,
+            unresolved_specs,
+            prune_deps: false,
+            members_per_line: None,
+            on_top_of: vec![],
             stderr: NoColor::new(io::sink()),
         }
     }
@@ -892,6 +3873,44 @@ impl<W: WriteColor> Rm<W> {
         Self { dry_run, ..self }
     }
 
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Skips package specs that do not match any member and paths that do not exist instead of failing.
+    pub fn ignore_missing(self, ignore_missing: bool) -> Self {
+        Self {
+            ignore_missing,
+            ..self
+        }
+    }
+
+    /// Also removes the entries of `workspace.dependencies` pointing to the removed packages.
+    pub fn prune_deps(self, prune_deps: bool) -> Self {
+        Self { prune_deps, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
     pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Rm<W2> {
         Rm {
             stderr,
@@ -899,182 +3918,5530 @@ impl<W: WriteColor> Rm<W> {
             paths: self.paths,
             force: self.force,
             dry_run: self.dry_run,
+            stdout: self.stdout,
+            ignore_missing: self.ignore_missing,
+            unresolved_specs: self.unresolved_specs,
+            prune_deps: self.prune_deps,
+            members_per_line: self.members_per_line,
+            on_top_of: self.on_top_of,
         }
     }
 
     pub fn exec(self) -> anyhow::Result<()> {
-        let Self {
+        self.plan()?.apply()
+    }
+
+    /// Returns the changes `exec` would make without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
             mut stderr,
             workspace_root,
             paths,
             force,
             dry_run,
+            stdout,
+            ignore_missing,
+            unresolved_specs,
+            prune_deps,
+            members_per_line,
+            on_top_of,
         } = self;
 
+        let dry_run = dry_run || stdout;
+
         let (workspace_root, paths) = (workspace_root?, paths?);
 
-        let modified = paths.iter().try_fold(false, |acc, path| {
-            if !(force || path.join("Cargo.toml").exists()) {
-                return Err(
-                    anyhow!("`{}` does not exist", path.join("Cargo.toml").display()).context(
-                        format!(
-                            "`{}` does not seem to be a package. enable `--force` to remove",
-                            path.display(),
+        for err in unresolved_specs {
+            if !ignore_missing {
+                return Err(err);
+            }
+            stderr.note(format!("skipping: {}", err))?;
+        }
+
+        let manifest_path = workspace_root.join("Cargo.toml");
+        let orig = Action::read_planned(&on_top_of, &manifest_path)?;
+        let mut actions = on_top_of;
+        for path in &paths {
+            if ignore_missing && !path.exists() {
+                stderr.note(format!("skipping: `{}` does not exist", path.display()))?;
+            } else {
+                if !(force || path.join("Cargo.toml").exists()) {
+                    return Err(
+                        anyhow!("`{}` does not exist", path.join("Cargo.toml").display()).context(
+                            format!(
+                                "`{}` does not seem to be a package. enable `--force` to remove",
+                                path.display(),
+                            ),
                         ),
-                    ),
-                );
+                    );
+                }
+                stderr.status_with_color(
+                    "Removing",
+                    format!("directory `{}`", path.display()),
+                    termcolor::Color::Red,
+                )?;
+                actions.push(Action::RemoveDir { path: path.clone() });
             }
-            stderr.status_with_color(
-                "Removing",
-                format!("directory `{}`", path.display()),
-                termcolor::Color::Red,
-            )?;
-            crate::fs::remove_dir_all(path, dry_run)?;
-            modify_members(
+            plan_members(
                 &workspace_root,
                 &[],
                 &[],
                 &[path],
                 &[path],
-                dry_run,
+                MemberLayout {
+                    per_line: members_per_line,
+                    prefix_dot_slash: false,
+                    comment: None,
+                    position: None,
+                },
+                &mut actions,
                 &mut stderr,
-            )
-                .map(|p| acc | p)
-        })?;
+            )?;
+        }
 
-        if !modified {
+        if prune_deps {
+            prune_workspace_dependencies(&workspace_root, &paths, &mut actions, &mut stderr)?;
+        }
+
+        let cargo_toml = Action::read_planned(&actions, &manifest_path)?;
+        if cargo_toml == orig || dry_run && !stdout {
             stderr.warn("`workspace` unchanged")?;
         }
 
-        if dry_run {
-            stderr.warn("not modifying the manifest due to dry run")?;
-        } else if !is_empty_workspace(&workspace_root.join("Cargo.toml"))? {
-            stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
-
-            cargo_metadata(
-                Some(&workspace_root.join("Cargo.toml")),
-                false,
-                false,
-                false,
-                &workspace_root,
-            )?;
+        let update_lockfile = if is_empty_workspace(&cargo_toml)? {
+            None
+        } else {
+            Some(workspace_root)
+        };
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: update_lockfile.into_iter().collect(),
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Mv<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    src: anyhow::Result<PathBuf>,
+    dst: anyhow::Result<PathBuf>,
+    into_manifest: Option<anyhow::Result<PathBuf>>,
+    dry_run: bool,
+    stdout: bool,
+    no_rename: bool,
+    rename_paths: bool,
+    members_per_line: Option<usize>,
+    dependent_scan: DependentScan,
+    resolved_dependents: Option<Vec<PathBuf>>,
+    update_deps: bool,
+    prefix_dot_slash: bool,
+    allow_duplicate_names: bool,
+    set_fields: Vec<(String, String)>,
+    update_readme_links: bool,
+    scan_path_attrs: bool,
+    fix_path_attrs: bool,
+    inline_inherited: bool,
+    smoke_test: bool,
+    offline: bool,
+    verbose: bool,
+    timings: Timings,
+    on_top_of: Vec<Action>,
+    stderr: W,
+}
+
+impl Mv<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, src: &Path, dst: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            src: ensure_absolute(src),
+            dst: ensure_absolute(dst),
+            dry_run: false,
+            no_rename: false,
+            stdout: false,
+            members_per_line: None,
+            dependent_scan: DependentScan::All,
+            resolved_dependents: None,
+            prefix_dot_slash: false,
+            allow_duplicate_names: false,
+            set_fields: vec![],
+            update_readme_links: false,
+            scan_path_attrs: false,
+            fix_path_attrs: false,
+            smoke_test: false,
+            offline: false,
+            verbose: false,
+            timings: Timings::default(),
+            update_deps: true,
+            inline_inherited: false,
+            rename_paths: false,
+            into_manifest: None,
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    /// `src` is a package ID specification of a member, or the name or the path of a package in
+    /// `workspace.exclude`.
+    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
+        let src = metadata
+            .query_for_member(Some(src))
+            .map(|member| {
+                member
+                    .manifest_path
+                    .clone()
+                    .into_std_path_buf()
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned()
+            })
+            .or_else(|err| {
+                find_excluded_package(metadata.workspace_root.as_std_path(), src)?.ok_or(err)
+            });
+        let resolved_dependents = src
+            .as_ref()
+            .ok()
+            .and_then(|src| resolved_dependents(metadata, src));
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
+            src,
+            dst: ensure_absolute(dst),
+            dry_run: false,
+            no_rename: false,
+            stdout: false,
+            members_per_line: None,
+            dependent_scan: DependentScan::ResolveGraph,
+            resolved_dependents,
+            prefix_dot_slash: false,
+            allow_duplicate_names: false,
+            set_fields: vec![],
+            update_readme_links: false,
+            scan_path_attrs: false,
+            fix_path_attrs: false,
+            smoke_test: false,
+            offline: false,
+            verbose: false,
+            timings: Timings::default(),
+            update_deps: true,
+            inline_inherited: false,
+            rename_paths: false,
+            into_manifest: None,
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Mv<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn no_rename(self, no_rename: bool) -> Self {
+        Self { no_rename, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Lays out the modified `workspace.{members, exclude}` arrays with this many entries per
+    /// line, `0` being the same as `1`. Without this, new entries are added on their own lines
+    /// and the rest of the layout is kept.
+    pub fn members_per_line(self, members_per_line: Option<usize>) -> Self {
+        Self {
+            members_per_line,
+            ..self
+        }
+    }
+
+    /// How to find the members whose path dependencies point to the package. Defaults to
+    /// [`DependentScan::ResolveGraph`] for [`Mv::from_metadata`] and [`DependentScan::All`] for
+    /// [`Mv::new`].
+    pub fn dependent_scan(self, dependent_scan: DependentScan) -> Self {
+        Self {
+            dependent_scan,
+            ..self
+        }
+    }
+
+    /// Writes the entries added to `workspace.{members, exclude}` as `./{path}`. This only affects
+    /// how the entries are written, not how they are resolved.
+    pub fn prefix_dot_slash(self, prefix_dot_slash: bool) -> Self {
+        Self {
+            prefix_dot_slash,
+            ..self
+        }
+    }
+
+    /// Proceeds even if another member has the same name as the moved package.
+    pub fn allow_duplicate_names(self, allow_duplicate_names: bool) -> Self {
+        Self {
+            allow_duplicate_names,
+            ..self
+        }
+    }
+
+    /// Sets `package.{key}` of the moved package to each value, parsed as a TOML fragment. Strings must be
+    /// quoted: `("rust-version", "\"1.70\"")`, `("authors", "[\"Me\"]")`.
+    pub fn set_fields<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<str>>(
+        self,
+        set_fields: I,
+    ) -> Self {
+        let set_fields = set_fields
+            .into_iter()
+            .map(|(k, v)| (k.as_ref().to_owned(), v.as_ref().to_owned()))
+            .collect();
+        Self { set_fields, ..self }
+    }
+
+    /// Rewrites the relative links in the Markdown files of the package that point to the other
+    /// members, so that they still resolve from the new location.
+    pub fn update_readme_links(self, update_readme_links: bool) -> Self {
+        Self {
+            update_readme_links,
+            ..self
+        }
+    }
+
+    /// Reports the `#[path = ".."]` attributes in the `.rs` files of the package whose targets are
+    /// outside the package, which may stop resolving from the destination.
+    pub fn scan_path_attrs(self, scan_path_attrs: bool) -> Self {
+        Self {
+            scan_path_attrs,
+            ..self
+        }
+    }
+
+    /// With `scan_path_attrs`, rewrites the reported attributes so that they still resolve from the
+    /// destination.
+    pub fn fix_path_attrs(self, fix_path_attrs: bool) -> Self {
+        Self {
+            fix_path_attrs,
+            ..self
+        }
+    }
+
+    /// Runs `cargo build -p <name>` for the moved package afterwards and fails if it does not
+    /// build. Skipped with `dry_run`.
+    pub fn smoke_test(self, smoke_test: bool) -> Self {
+        Self { smoke_test, ..self }
+    }
+
+    /// Runs the smoke test with `--offline`.
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Streams the output of the smoke test instead of printing it only if the build fails.
+    pub fn verbose(self, verbose: bool) -> Self {
+        Self { verbose, ..self }
+    }
+
+    /// Records how long planning, copying the files, writing the manifests and removing the source take.
+    pub fn timings(self, timings: Timings) -> Self {
+        Self { timings, ..self }
+    }
+
+    /// Rewrites the path dependencies of the other packages that point to the package, so that they
+    /// point to the destination. Enabled by default. When disabled, they are only reported.
+    pub fn update_deps(self, update_deps: bool) -> Self {
+        Self {
+            update_deps,
+            ..self
+        }
+    }
+
+    /// Inlines the fields the package inherits from the workspace if the destination is outside of
+    /// it. See [`Cp::inline_inherited`].
+    pub fn inline_inherited(self, inline_inherited: bool) -> Self {
+        Self {
+            inline_inherited,
+            ..self
+        }
+    }
+
+    /// Renames the source files of the targets named after the package. See
+    /// [`Cp::rename_paths`].
+    pub fn rename_paths(self, rename_paths: bool) -> Self {
+        Self {
+            rename_paths,
+            ..self
+        }
+    }
+
+    /// The root manifest of the workspace to move the package to. The package is removed from
+    /// the source workspace and added to this one, inlining the fields it inherits from the
+    /// source workspace. Without this, the package is added to the workspace that contains the
+    /// destination, if any.
+    pub fn into_manifest(self, into_manifest: Option<&Path>) -> Self {
+        Self {
+            into_manifest: into_manifest.map(ensure_absolute),
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Mv<W2> {
+        Mv {
+            stderr,
+            workspace_root: self.workspace_root,
+            src: self.src,
+            dst: self.dst,
+            dry_run: self.dry_run,
+            no_rename: self.no_rename,
+            stdout: self.stdout,
+            members_per_line: self.members_per_line,
+            dependent_scan: self.dependent_scan,
+            resolved_dependents: self.resolved_dependents,
+            prefix_dot_slash: self.prefix_dot_slash,
+            allow_duplicate_names: self.allow_duplicate_names,
+            set_fields: self.set_fields,
+            update_readme_links: self.update_readme_links,
+            scan_path_attrs: self.scan_path_attrs,
+            fix_path_attrs: self.fix_path_attrs,
+            smoke_test: self.smoke_test,
+            offline: self.offline,
+            verbose: self.verbose,
+            timings: self.timings,
+            update_deps: self.update_deps,
+            inline_inherited: self.inline_inherited,
+            rename_paths: self.rename_paths,
+            into_manifest: self.into_manifest,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Returns the changes `exec` would make without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            mut stderr,
+            workspace_root,
+            src,
+            dst,
+            dry_run,
+            no_rename,
+            stdout,
+            members_per_line,
+            dependent_scan,
+            resolved_dependents,
+            prefix_dot_slash,
+            allow_duplicate_names,
+            set_fields,
+            update_readme_links,
+            scan_path_attrs,
+            fix_path_attrs,
+            smoke_test,
+            offline,
+            verbose,
+            timings,
+            update_deps,
+            inline_inherited,
+            rename_paths,
+            into_manifest,
+            on_top_of,
+        } = self;
+
+        let start = Instant::now();
+
+        let dry_run = dry_run || stdout;
+
+        let (workspace_root, src, dst) = (workspace_root?, src?, dst?);
+        let dst_workspace_root = into_manifest
+            .transpose()?
+            .map(|manifest_path| {
+                let manifest_path = normalize_path(&manifest_path);
+                ensure!(
+                    manifest_path.ends_with("Cargo.toml"),
+                    "`{}` is not a `Cargo.toml`",
+                    manifest_path.display(),
+                );
+                Ok(manifest_path
+                    .parent()
+                    .expect(r#"should end with "Cargo.toml""#)
+                    .to_owned())
+            })
+            .transpose()?;
+
+        // Where `Cp` puts the package.
+        let moved_to = if dst.exists() {
+            dst.join(src.file_name().expect("should be absolute"))
+        } else {
+            normalize_path(&dst)
+        };
+        let new_name = if no_rename {
+            None
+        } else {
+            moved_to.file_name().and_then(OsStr::to_str)
+        };
+        let old_name = crate::fs::read_toml_edit(src.join("Cargo.toml"))?["package"]["name"]
+            .as_str()
+            .map(ToOwned::to_owned);
+        if let (Some(name), false) = (new_name.or(old_name.as_deref()), allow_duplicate_names) {
+            ensure_unique_name(
+                dst_workspace_root.as_ref().unwrap_or(&workspace_root),
+                name,
+                &[&src, &moved_to],
+            )?;
+        }
+        parse_package_fields(&set_fields)?;
+
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+
+        let dependents = match (dependent_scan, resolved_dependents) {
+            (DependentScan::ResolveGraph, Some(dependents)) => dependents,
+            (dependent_scan, _) => {
+                if dependent_scan == DependentScan::ResolveGraph {
+                    stderr.note(
+                        "the resolve graph is not available. reading the manifests of all the \
+                         members",
+                    )?;
+                }
+                expand_members(&workspace_root, &root_cargo_toml)?.0
+            }
+        };
+        if !update_deps {
+            warn_path_dependents(&dependents, &src, &mut stderr)?;
+        }
+        let excluded = !expand_members(&workspace_root, &root_cargo_toml)?
+            .0
+            .contains(&src)
+            && workspace_entries(&root_cargo_toml, "exclude")
+                .iter()
+                .any(|e| normalize_path(&workspace_root.join(e)) == src);
+        // A package in `workspace.exclude` is kept excluded, updating the entry in place instead of
+        // going through `Rm`.
+        let keep_excluded = excluded && dst_workspace_root.is_none();
+        let cp = Cp::new(&src, &dst)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .no_rename(no_rename)
+            .rename_paths(rename_paths)
+            .allow_duplicate_names(true)
+            .set_fields(set_fields)
+            .scan_path_attrs(scan_path_attrs)
+            .fix_path_attrs(fix_path_attrs)
+            .on_top_of(on_top_of);
+        let cp = if keep_excluded {
+            cp.register_in(RegisterIn::None)
+        } else {
+            cp.inline_inherited(
+                inline_inherited
+                    || dst_workspace_root
+                        .as_ref()
+                        .is_some_and(|dst_workspace_root| *dst_workspace_root != workspace_root),
+            )
+            .dst_workspace_root(dst_workspace_root.as_deref())
+            .members_per_line(members_per_line)
+            .prefix_dot_slash(prefix_dot_slash)
+        };
+        let (mut actions, mut update_lockfiles) = cp.stderr(&mut stderr).plan()?.into_actions()?;
+
+        if update_readme_links {
+            let members = expand_members(&workspace_root, &root_cargo_toml)?.0;
+            rewrite_markdown_links(&src, &moved_to, &members, &mut actions, &mut stderr)?;
+        }
+
+        update_workspace_dependencies(
+            &workspace_root,
+            &src,
+            &moved_to,
+            old_name.as_deref(),
+            new_name,
+            &mut actions,
+            &mut stderr,
+        )?;
+        if update_deps {
+            update_path_dependents(
+                &dependents,
+                &src,
+                &moved_to,
+                old_name.as_deref(),
+                new_name,
+                &mut actions,
+                &mut stderr,
+            )?;
+        }
+
+        let dry_run_warning = if keep_excluded {
+            let layout = MemberLayout {
+                prefix_dot_slash,
+                ..MemberLayout::default()
+            };
+            rename_workspace_entry(
+                &workspace_root,
+                "exclude",
+                &src,
+                &layout.entry(&workspace_root, &moved_to)?,
+                &mut actions,
+                &mut stderr,
+            )?;
+            stderr.status_with_color(
+                "Removing",
+                format!("directory `{}`", src.display()),
+                termcolor::Color::Red,
+            )?;
+            actions.push(Action::RemoveDir { path: src });
+            Some("not modifying the manifest due to dry run")
+        } else {
+            let (rm_actions, rm_lockfiles) = Rm::new(&workspace_root, [&src])
+                .members_per_line(members_per_line)
+                .dry_run(dry_run)
+                .stdout(stdout)
+                .on_top_of(actions)
+                .stderr(&mut stderr)
+                .plan()?
+                .into_actions()?;
+            actions = rm_actions;
+            update_lockfiles.extend(rm_lockfiles);
+            update_lockfiles.extend(dst_workspace_root.filter(|d| *d != workspace_root));
+            None
+        };
+        timings.add("planning", start);
+
+        Ok(Plan {
+            actions,
+            update_lockfiles,
+            offline,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: Some(SmokeTest {
+                dir: moved_to,
+                workspace_root: None,
+                verbose,
+                rollback: false,
+            })
+            .filter(|_| smoke_test),
+            staging: None,
+            dry_run_warning,
+            timings,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MergeInto<W> {
+    workspace_root: PathBuf,
+    src: anyhow::Result<(String, PathBuf)>,
+    dst: anyhow::Result<(String, PathBuf)>,
+    members: Vec<PathBuf>,
+    force: bool,
+    dry_run: bool,
+    stdout: bool,
+    on_top_of: Vec<Action>,
+    stderr: W,
+}
+
+impl MergeInto<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &str) -> Self {
+        let query = |spec| {
+            metadata.query_for_member(Some(spec)).map(|member| {
+                let dir = member
+                    .manifest_path
+                    .clone()
+                    .into_std_path_buf()
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned();
+                (member.name.clone(), dir)
+            })
+        };
+
+        Self {
+            workspace_root: metadata.workspace_root.clone().into_std_path_buf(),
+            src: query(src),
+            dst: query(dst),
+            members: metadata
+                .packages
+                .iter()
+                .filter(|Package { id, .. }| metadata.workspace_members.contains(id))
+                .map(|p| p.manifest_path.clone().into_std_path_buf())
+                .collect(),
+            force: false,
+            dry_run: false,
+            stdout: false,
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> MergeInto<W> {
+    /// Merges even if there are conflicting files or dependencies. Conflicting files are
+    /// overwritten and conflicting dependencies of the destination are kept.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> MergeInto<W2> {
+        MergeInto {
+            workspace_root: self.workspace_root,
+            src: self.src,
+            dst: self.dst,
+            members: self.members,
+            force: self.force,
+            dry_run: self.dry_run,
+            stdout: self.stdout,
+            stderr,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let names = self.names();
+        self.plan()?
+            .apply()
+            .map_err(|err| dependency_cycle_context(err, names))
+    }
+
+    /// The names of `src` and `dst`, for [`dependency_cycle_context`].
+    fn names(&self) -> Option<(String, String)> {
+        match (&self.src, &self.dst) {
+            (Ok((src_name, _)), Ok((dst_name, _))) => Some((src_name.clone(), dst_name.clone())),
+            _ => None,
+        }
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        const DEPENDENCY_TABLES: &[&str] =
+            &["dependencies", "dev-dependencies", "build-dependencies"];
+
+        let Self {
+            workspace_root,
+            src,
+            dst,
+            members,
+            force,
+            dry_run,
+            stdout,
+            mut stderr,
+            on_top_of,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let ((src_name, src_dir), (dst_name, dst_dir)) = (src?, dst?);
+        ensure!(
+            src_dir != dst_dir,
+            "cannot merge `{}` into itself",
+            src_name
+        );
+
+        let module = src_name.replace('-', "_");
+        let module_dir = dst_dir.join("src").join(&module);
+        let src_manifest_path = src_dir.join("Cargo.toml");
+        let dst_manifest_path = dst_dir.join("Cargo.toml");
+
+        let mut conflicts = vec![];
+
+        for path in &[
+            dst_dir.join("src").join(format!("{}.rs", module)),
+            module_dir.clone(),
+        ] {
+            if path.exists() {
+                conflicts.push(format!("`{}` exists", path.display()));
+            }
+        }
+        for entry in std::fs::read_dir(&src_dir)
+            .with_context(|| format!("failed to read `{}`", src_dir.display()))?
+        {
+            let file_name = entry?.file_name();
+            if !["Cargo.toml", "Cargo.lock", "src", "target"]
+                .iter()
+                .any(|n| file_name == *n)
+            {
+                conflicts.push(format!(
+                    "`{}` is not merged and will be removed",
+                    src_dir.join(file_name).display(),
+                ));
+            }
+        }
+
+        let src_cargo_toml = Action::read_toml_planned(&on_top_of, &src_manifest_path)?;
+        let mut dst_cargo_toml = Action::read_toml_planned(&on_top_of, &dst_manifest_path)?;
+
+        // `(table, key, item)`
+        let mut deps_to_add = vec![];
+        for &table in DEPENDENCY_TABLES {
+            let (src_table, dst_table) = match src_cargo_toml[table].as_table() {
+                Some(src_table) => (src_table, dst_cargo_toml[table].as_table()),
+                None => continue,
+            };
+            for (key, item) in src_table.iter() {
+                if dependency_name(key, item) == dst_name {
+                    continue;
+                }
+                let mut item = item.clone();
+                rebase_path_dependency(&mut item, &src_dir, &dst_dir)?;
+                match dst_table.map(|t| &t[key]).filter(|i| !i.is_none()) {
+                    None => deps_to_add.push((table, key.to_owned(), item)),
+                    Some(dst_item) => {
+                        if normalize_dependency(&item, &dst_dir)?
+                            != normalize_dependency(dst_item, &dst_dir)?
+                        {
+                            conflicts.push(format!(
+                                "`{}.{}` differs between `{}` and `{}`",
+                                table, key, src_name, dst_name,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !conflicts.is_empty() {
+            for conflict in &conflicts {
+                stderr.warn(conflict)?;
+            }
+            if !force {
+                bail!(
+                    "aborting due to {} conflict(s). enable `--force` to merge anyway",
+                    conflicts.len(),
+                );
+            }
+        }
+
+        stderr.status(
+            "Moving",
+            format!(
+                "`{}` to `{}`",
+                src_dir.join("src").display(),
+                module_dir.display(),
+            ),
+        )?;
+        let src_root = src_dir.join("src");
+        let crate_root = ["lib.rs", "main.rs"]
+            .iter()
+            .map(|n| src_root.join(n))
+            .find(|p| p.exists());
+        let mut actions = on_top_of;
+        let mut planned_dirs = vec![];
+        for entry in WalkBuilder::new(&src_root).hidden(false).build() {
+            match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    if path.is_dir() {
+                        continue;
+                    }
+                    let dst = if Some(path) == crate_root.as_deref() {
+                        module_dir.join("mod.rs")
+                    } else {
+                        module_dir.join(path.strip_prefix(&src_root)?)
+                    };
+                    if let Some(parent) = dst.parent() {
+                        if !(parent.exists() || planned_dirs.iter().any(|d| d == parent)) {
+                            actions.push(Action::CreateDir {
+                                path: parent.to_owned(),
+                            });
+                            planned_dirs.push(parent.to_owned());
+                        }
+                    }
+                    actions.push(Action::CopyFile {
+                        src: path.to_owned(),
+                        dst,
+                    });
+                }
+                Err(err) => stderr.warn(err)?,
+            }
+        }
+
+        for (table, key, item) in deps_to_add {
+            stderr.status(
+                "Adding",
+                format!("`{}.{}` to `{}`", table, key, dst_manifest_path.display()),
+            )?;
+            if dst_cargo_toml[table].is_none() {
+                dst_cargo_toml[table] = toml_edit::table();
+            }
+            dst_cargo_toml[table][&key] = item;
+        }
+        for &table in DEPENDENCY_TABLES {
+            if let Some(dst_table) = dst_cargo_toml[table].as_table_mut() {
+                for key in dependency_keys(dst_table, &src_name) {
+                    stderr.status_with_color(
+                        "Removing",
+                        format!("`{}.{}` from `{}`", table, key, dst_manifest_path.display()),
+                        termcolor::Color::Red,
+                    )?;
+                    dst_table.remove(&key);
+                }
+            }
+        }
+        Action::plan_write(
+            &mut actions,
+            dst_manifest_path.clone(),
+            dst_cargo_toml.to_string(),
+        )?;
+
+        // `package.version` may be omitted.
+        let (src_version, dst_version) = (
+            package_version(&src_dir).ok(),
+            package_version(&dst_dir).ok(),
+        );
+        for manifest_path in &members {
+            if [&src_manifest_path, &dst_manifest_path].contains(&manifest_path) {
+                continue;
+            }
+            let mut cargo_toml = Action::read_toml_planned(&actions, manifest_path)?;
+            let orig = cargo_toml.to_string();
+            let dir = manifest_path
+                .parent()
+                .expect("should end with `Cargo.toml`");
+            for &table in DEPENDENCY_TABLES {
+                if let Some(table_value) = cargo_toml[table].as_table_mut() {
+                    let keys = dependency_keys(table_value, &src_name);
+                    if keys.is_empty() {
+                        continue;
+                    }
+                    let depends_on_dst = !dependency_keys(table_value, &dst_name).is_empty();
+                    for key in keys {
+                        if depends_on_dst {
+                            table_value.remove(&key);
+                            stderr.status_with_color(
+                                "Removing",
+                                format!("`{}.{}` from `{}`", table, key, manifest_path.display()),
+                                termcolor::Color::Red,
+                            )?;
+                            continue;
+                        }
+                        // Edit the entry in place so that `features`, `optional` and so on are
+                        // kept, and so is the key, which the dependent's code refers to.
+                        let item = &mut table_value[&key];
+                        if let Some(version) = item.as_str() {
+                            let value = format!("{{ version = {:?} }}", version)
+                                .parse::<Value>()
+                                .map_err(|e| anyhow!("{}", e))?;
+                            *item = toml_edit::Item::Value(value);
+                        }
+                        let desc = format!("{}.{}", table, key);
+                        let path = relative_path(dir, &dst_dir);
+                        let path = path
+                            .to_str()
+                            .with_context(|| format!("{:?} is not valid UTF-8", path))?;
+                        stderr.status(
+                            "Changing",
+                            format!(
+                                "`{}.path` in `{}` to {:?}",
+                                desc,
+                                manifest_path.display(),
+                                path,
+                            ),
+                        )?;
+                        remove_dependency_field(item, "workspace");
+                        set_dependency_field(item, "path", path);
+                        if let Some(dst_version) = dst_version.as_ref().filter(|&v| {
+                            !item["version"].is_none() && src_version.as_ref() != Some(v)
+                        }) {
+                            stderr.status(
+                                "Changing",
+                                format!("`{}.version` to {:?}", desc, dst_version),
+                            )?;
+                            set_dependency_field(item, "version", dst_version);
+                        }
+                        rename_dependency_package(
+                            item,
+                            &desc,
+                            &key,
+                            Some(&src_name),
+                            Some(&dst_name),
+                            &mut stderr,
+                        )?;
+                    }
+                }
+            }
+            let cargo_toml = cargo_toml.to_string();
+            if cargo_toml != orig {
+                Action::plan_write(&mut actions, manifest_path.clone(), cargo_toml)?;
+            }
+        }
+
+        stderr.note(format!(
+            "add `mod {};` to the crate root of `{}`",
+            module, dst_name,
+        ))?;
+
+        let (actions, mut update_lockfiles) = Rm::new(&workspace_root, &[src_dir])
+            .force(true)
+            .dry_run(dry_run)
+            .stdout(stdout)
+            .on_top_of(actions)
+            .stderr(&mut stderr)
+            .plan()?
+            .into_actions()?;
+        update_lockfiles.push(workspace_root);
+
+        Ok(Plan {
+            actions,
+            update_lockfiles,
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: None,
+            timings: Timings::default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckVersionSync<W> {
+    workspace_root: PathBuf,
+    members: anyhow::Result<Vec<(String, Version, PathBuf)>>,
+    expected: Option<Version>,
+    fix: bool,
+    dry_run: bool,
+    stdout: bool,
+    unreadable: Vec<(PathBuf, anyhow::Error)>,
+    stderr: W,
+}
+
+impl CheckVersionSync<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            workspace_root: metadata.workspace_root.clone().into_std_path_buf(),
+            members: Ok(metadata
+                .packages
+                .iter()
+                .filter(|Package { id, .. }| metadata.workspace_members.contains(id))
+                .map(|p| {
+                    let manifest_path = p.manifest_path.clone().into_std_path_buf();
+                    (p.name.clone(), p.version.clone(), manifest_path)
+                })
+                .collect()),
+            expected: None,
+            fix: false,
+            dry_run: false,
+            stdout: false,
+            unreadable: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    /// Reads the members from their manifests instead of `cargo metadata`, for when it fails.
+    /// Members whose manifests cannot be read are reported and skipped.
+    pub fn from_manifests(workspace_root: &Path) -> Self {
+        let (members, unreadable) = match read_members_without_metadata(workspace_root) {
+            Ok((members, unreadable)) => (Ok(members), unreadable),
+            Err(err) => (Err(err), vec![]),
+        };
+        Self {
+            workspace_root: workspace_root.to_owned(),
+            members,
+            expected: None,
+            fix: false,
+            dry_run: false,
+            stdout: false,
+            unreadable,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> CheckVersionSync<W> {
+    pub fn expected(self, expected: Option<Version>) -> Self {
+        Self { expected, ..self }
+    }
+
+    pub fn fix(self, fix: bool) -> Self {
+        Self { fix, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> CheckVersionSync<W2> {
+        CheckVersionSync {
+            workspace_root: self.workspace_root,
+            members: self.members,
+            expected: self.expected,
+            fix: self.fix,
+            dry_run: self.dry_run,
+            stderr,
+            stdout: self.stdout,
+            unreadable: self.unreadable,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            workspace_root,
+            members,
+            expected,
+            fix,
+            dry_run,
+            mut stderr,
+            stdout,
+            unreadable,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        for (manifest_path, err) in unreadable {
+            stderr.warn(format!("skipping `{}`: {:#}", manifest_path.display(), err))?;
+        }
+
+        let mut targets = vec![];
+        for (name, version, manifest_path) in members? {
+            let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+            if cargo_toml["package"]["version"]["workspace"].as_bool() == Some(true) {
+                stderr.note(format!(
+                    "skipping `{}`: `package.version` is inherited from the workspace",
+                    name,
+                ))?;
+            } else {
+                targets.push((name, version, manifest_path, cargo_toml));
+            }
+        }
+
+        let expected = match expected {
+            Some(expected) => expected,
+            None => match targets
+                .iter()
+                .map(|(_, version, ..)| version)
+                .counts()
+                .into_iter()
+                .max_by(|(v1, n1), (v2, n2)| n1.cmp(n2).then_with(|| v1.cmp(v2)))
+            {
+                Some((version, _)) => version.clone(),
+                None => return Ok(()),
+            },
+        };
+
+        let mut out_of_sync = 0;
+        for (name, version, manifest_path, mut cargo_toml) in targets {
+            if version == expected {
+                continue;
+            }
+            out_of_sync += 1;
+            if fix {
+                stderr.status(
+                    "Changing",
+                    format!(
+                        "`package.version` of {:?} from {:?} to {:?}",
+                        name,
+                        version.to_string(),
+                        expected.to_string(),
+                    ),
+                )?;
+                cargo_toml["package"]["version"] = toml_edit::value(expected.to_string());
+                crate::fs::write_or_print(manifest_path, cargo_toml.to_string(), dry_run, stdout)?;
+            } else {
+                stderr.warn(format!(
+                    "`{}` is at {} (expected {})",
+                    name, version, expected,
+                ))?;
+            }
+        }
+
+        if out_of_sync == 0 {
+            stderr.status("Finished", format!("all the versions are {}", expected))?;
+        } else if !fix {
+            bail!("{} package(s) are out of sync", out_of_sync);
+        } else if dry_run {
+            stderr.warn("not modifying the manifests due to dry run")?;
+        } else {
+            stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
+
+            cargo_metadata(
+                Some(&workspace_root.join("Cargo.toml")),
+                false,
+                false,
+                false,
+                &workspace_root,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Bump<W> {
+    workspace_root: PathBuf,
+    members: anyhow::Result<Vec<(String, Version, PathBuf)>>,
+    level: BumpLevel,
+    workspace_package: bool,
+    dry_run: bool,
+    stdout: bool,
+    stderr: W,
+}
+
+impl Bump<NoColor<Sink>> {
+    /// Bumps the members matching `packages` (all the members if empty) except `exclude`.
+    pub fn from_metadata<
+        Ps: IntoIterator<Item = P>,
+        P: AsRef<str>,
+        Es: IntoIterator<Item = E>,
+        E: AsRef<str>,
+    >(
+        metadata: &Metadata,
+        level: BumpLevel,
+        packages: Ps,
+        exclude: Es,
+    ) -> Self {
+        let members = (|| {
+            let specs = packages.into_iter().collect::<Vec<_>>();
+            let (included, mut errors) = resolve_specs(metadata, &specs);
+            let (excluded, exclude_errors) = resolve_specs(metadata, exclude);
+            errors.extend(exclude_errors);
+            if let Some(err) = errors.into_iter().next() {
+                return Err(err);
+            }
+            Ok(metadata
+                .packages
+                .iter()
+                .filter(|Package { id, .. }| metadata.workspace_members.contains(id))
+                .map(|p| {
+                    let manifest_path = p.manifest_path.clone().into_std_path_buf();
+                    (p.name.clone(), p.version.clone(), manifest_path)
+                })
+                .filter(|(_, _, manifest_path)| {
+                    let dir = manifest_path
+                        .parent()
+                        .expect("should end with `Cargo.toml`");
+                    (specs.is_empty() || included.iter().any(|d| d == dir))
+                        && !excluded.iter().any(|d| d == dir)
+                })
+                .collect())
+        })();
+
+        Self {
+            workspace_root: metadata.workspace_root.clone().into_std_path_buf(),
+            members,
+            level,
+            workspace_package: false,
+            dry_run: false,
+            stdout: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Bump<W> {
+    /// Bumps `workspace.package.version` of the root manifest. Members inheriting it are skipped
+    /// regardless of this.
+    pub fn workspace_package(self, workspace_package: bool) -> Self {
+        Self {
+            workspace_package,
+            ..self
+        }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Bump<W2> {
+        Bump {
+            workspace_root: self.workspace_root,
+            members: self.members,
+            level: self.level,
+            workspace_package: self.workspace_package,
+            dry_run: self.dry_run,
+            stderr,
+            stdout: self.stdout,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            workspace_root,
+            members,
+            level,
+            workspace_package,
+            dry_run,
+            mut stderr,
+            stdout,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let mut actions = vec![];
+
+        if workspace_package {
+            let manifest_path = workspace_root.join("Cargo.toml");
+            let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+            let version = cargo_toml["workspace"]["package"]["version"]
+                .as_str()
+                .with_context(|| {
+                    format!(
+                        "`workspace.package.version` is not found in `{}`",
+                        manifest_path.display(),
+                    )
+                })?
+                .parse::<Version>()
+                .with_context(|| {
+                    format!(
+                        "invalid `workspace.package.version` in `{}`",
+                        manifest_path.display(),
+                    )
+                })?;
+            let new_version = level.apply(&version);
+            stderr.status(
+                "Bumping",
+                format!(
+                    "`workspace.package.version` from {} to {}",
+                    version, new_version
+                ),
+            )?;
+            cargo_toml["workspace"]["package"]["version"] =
+                toml_edit::value(new_version.to_string());
+            Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+        }
+
+        for (name, version, manifest_path) in members? {
+            let mut cargo_toml = crate::fs::parse_toml_edit(
+                &Action::read_planned(&actions, &manifest_path)?,
+                &manifest_path,
+            )?;
+            if cargo_toml["package"]["version"]["workspace"].as_bool() == Some(true) {
+                if !workspace_package {
+                    stderr.note(format!(
+                        "skipping `{}`: `package.version` is inherited from the workspace. \
+                         enable `--workspace-package` to bump it",
+                        name,
+                    ))?;
+                }
+                continue;
+            }
+            let new_version = level.apply(&version);
+            stderr.status(
+                "Bumping",
+                format!("`{}` from {} to {}", name, version, new_version),
+            )?;
+            cargo_toml["package"]["version"] = toml_edit::value(new_version.to_string());
+            Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+        }
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![workspace_root],
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// How [`Bump`] changes the versions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BumpLevel {
+    Major,
+    Minor,
+    Patch,
+    /// Sets the version as-is.
+    Version(Version),
+}
+
+impl BumpLevel {
+    /// Returns the bumped version. The pre-release and the build metadata are cleared.
+    pub fn apply(&self, version: &Version) -> Version {
+        match self {
+            Self::Major => Version::new(version.major + 1, 0, 0),
+            Self::Minor => Version::new(version.major, version.minor + 1, 0),
+            Self::Patch => Version::new(version.major, version.minor, version.patch + 1),
+            Self::Version(version) => version.clone(),
+        }
+    }
+}
+
+impl FromStr for BumpLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "major" => Ok(Self::Major),
+            "minor" => Ok(Self::Minor),
+            "patch" => Ok(Self::Patch),
+            s => s.parse().map(Self::Version).with_context(|| {
+                format!(
+                    "expected `major`, `minor`, `patch` or a version, got {:?}",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Init<W> {
+    dir: anyhow::Result<PathBuf>,
+    members: Vec<String>,
+    include_existing: bool,
+    resolver: Option<String>,
+    workspace_package: bool,
+    workspace_dependencies: bool,
+    force: bool,
+    dry_run: bool,
+    stderr: W,
+}
+
+impl Init<NoColor<Sink>> {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: ensure_absolute(dir),
+            members: vec![],
+            include_existing: false,
+            resolver: None,
+            workspace_package: false,
+            workspace_dependencies: false,
+            force: false,
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Init<W> {
+    /// Entries of `workspace.members` to start with. They may be globs (e.g. `crates/*`).
+    pub fn members<I: IntoIterator<Item = S>, S: AsRef<str>>(self, members: I) -> Self {
+        let members = members.into_iter().map(|s| s.as_ref().to_owned()).collect();
+        Self { members, ..self }
+    }
+
+    /// Also adds the packages under the directory that [`members`](Self::members) does not
+    /// cover to `workspace.members`.
+    pub fn include_existing(self, include_existing: bool) -> Self {
+        Self {
+            include_existing,
+            ..self
+        }
+    }
+
+    /// Sets `workspace.resolver`.
+    pub fn resolver<S: AsRef<str>>(self, resolver: Option<S>) -> Self {
+        let resolver = resolver.map(|s| s.as_ref().to_owned());
+        Self { resolver, ..self }
+    }
+
+    /// Adds an empty `[workspace.package]` table.
+    pub fn workspace_package(self, workspace_package: bool) -> Self {
+        Self {
+            workspace_package,
+            ..self
+        }
+    }
+
+    /// Adds an empty `[workspace.dependencies]` table.
+    pub fn workspace_dependencies(self, workspace_dependencies: bool) -> Self {
+        Self {
+            workspace_dependencies,
+            ..self
+        }
+    }
+
+    /// Replaces `[workspace]` of an existing root manifest instead of failing.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    /// Prints the manifest to stdout instead of writing it.
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Init<W2> {
+        Init {
+            dir: self.dir,
+            members: self.members,
+            include_existing: self.include_existing,
+            resolver: self.resolver,
+            workspace_package: self.workspace_package,
+            workspace_dependencies: self.workspace_dependencies,
+            force: self.force,
+            dry_run: self.dry_run,
+            stderr,
+        }
+    }
+
+    /// Creates `Cargo.toml` with a `[workspace]` in the directory. If the directory already has a
+    /// `Cargo.toml` of a package, `[workspace]` is added to it.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            dir,
+            members,
+            include_existing,
+            resolver,
+            workspace_package,
+            workspace_dependencies,
+            force,
+            dry_run,
+            mut stderr,
+        } = self;
+
+        let dir = dir?;
+        let manifest_path = dir.join("Cargo.toml");
+
+        let orig = if manifest_path.exists() {
+            let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+            if !cargo_toml["workspace"].is_none() {
+                ensure!(
+                    force,
+                    "`{}` already has `[workspace]`. enable `--force` to replace it",
+                    manifest_path.display(),
+                );
+                cargo_toml.as_table_mut().remove("workspace");
+            }
+            Some(cargo_toml.to_string())
+        } else {
+            None
+        };
+
+        let mut entries = members;
+        if include_existing && dir.exists() {
+            let mut cargo_toml = toml_edit::Document::new();
+            cargo_toml["workspace"]["members"] = toml_edit::value(
+                entries
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<toml_edit::Array>(),
+            );
+            let covered = expand_members(&dir, &cargo_toml)?.0;
+            let mut existing = find_orphans(&dir, &covered, &[], &mut stderr)?
+                .iter()
+                .map(|d| member_entry(&dir, d).map(|e| canonicalize_member(&e)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            existing.sort();
+            entries.extend(existing);
+        }
+
+        let mut workspace = "[workspace]\n".to_owned();
+        if entries.is_empty() {
+            workspace += "members = []\n";
+        } else {
+            workspace += "members = [\n";
+            for entry in &entries {
+                workspace += &format!("    {},\n", Value::from(&**entry));
+            }
+            workspace += "]\n";
+        }
+        if let Some(resolver) = &resolver {
+            workspace += &format!("resolver = {}\n", Value::from(&**resolver));
+        }
+        if workspace_package {
+            workspace += "\n[workspace.package]\n";
+        }
+        if workspace_dependencies {
+            workspace += "\n[workspace.dependencies]\n";
+        }
+        let cargo_toml = match &orig {
+            Some(orig) if !orig.trim().is_empty() => format!("{}\n{}", orig.trim_end(), workspace),
+            _ => workspace,
+        };
+        crate::fs::parse_toml_edit(&cargo_toml, &manifest_path)?;
+
+        if orig.is_some() {
+            stderr.status(
+                "Adding",
+                format!("`[workspace]` to `{}`", manifest_path.display()),
+            )?;
+        } else {
+            stderr.status("Creating", format!("`{}`", manifest_path.display()))?;
+        }
+        for entry in &entries {
+            stderr.status("Adding", format!("{:?} to `workspace.members`", entry))?;
+        }
+
+        if dry_run {
+            crate::fs::write_or_print(&manifest_path, cargo_toml, true, true)?;
+            stderr.warn("not writing the manifest due to dry run")?;
+            return Ok(());
+        }
+        if !dir.exists() {
+            crate::fs::create_dir_all(&dir, false)?;
+        }
+        crate::fs::write(&manifest_path, cargo_toml, false)
+    }
+}
+
+#[derive(Debug)]
+pub struct Canonicalize<W> {
+    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    stdout: bool,
+    manifest_file_name: OsString,
+    stderr: W,
+}
+
+impl Canonicalize<NoColor<Sink>> {
+    pub fn new(possibly_empty_workspace_root: &Path) -> Self {
+        Self {
+            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
+            dry_run: false,
+            stdout: false,
+            manifest_file_name: "Cargo.toml".into(),
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Canonicalize<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// File name of the root manifest. Defaults to `Cargo.toml`.
+    pub fn manifest_file_name<S: AsRef<OsStr>>(self, manifest_file_name: S) -> Self {
+        let manifest_file_name = manifest_file_name.as_ref().to_owned();
+        Self {
+            manifest_file_name,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Canonicalize<W2> {
+        Canonicalize {
+            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
+            dry_run: self.dry_run,
+            stderr,
+            stdout: self.stdout,
+            manifest_file_name: self.manifest_file_name,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            possibly_empty_workspace_root,
+            dry_run,
+            mut stderr,
+            stdout,
+            manifest_file_name,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let workspace_root = possibly_empty_workspace_root?;
+        let manifest_path = workspace_root.join(manifest_file_name);
+        let orig = crate::fs::read_to_string(&manifest_path)?;
+        let mut cargo_toml = crate::fs::parse_toml_edit(&orig, &manifest_path)?;
+
+        for field in &["members", "exclude"] {
+            let array = match cargo_toml["workspace"][field].as_array_mut() {
+                Some(array) => array,
+                None => continue,
+            };
+            let mut seen = vec![];
+            for value in array.iter_mut() {
+                let old = match value.as_str() {
+                    Some(old) => old.to_owned(),
+                    None => continue,
+                };
+                let new = canonicalize_member_in(&workspace_root, &old);
+                if new != old {
+                    stderr.status(
+                        "Changing",
+                        format!("{:?} to {:?} in `workspace.{}`", old, new, field),
+                    )?;
+                    let prefix = value.decor().prefix().unwrap_or(" ").to_owned();
+                    let suffix = value.decor().suffix().unwrap_or("").to_owned();
+                    *value = Value::from(&*new).decorated(&prefix, &suffix);
+                }
+                if seen.contains(&new) {
+                    stderr.warn(format!(
+                        "{:?} appears more than once in `workspace.{}`",
+                        new, field,
+                    ))?;
+                }
+                seen.push(new);
+            }
+        }
+
+        let mut actions = vec![];
+        let cargo_toml = cargo_toml.to_string();
+        if cargo_toml == orig {
+            stderr.warn("`workspace` unchanged")?;
+        } else {
+            Action::plan_write(&mut actions, manifest_path, cargo_toml)?;
+        }
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![],
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Sort<W> {
+    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
+    check: bool,
+    dry_run: bool,
+    stdout: bool,
+    manifest_file_name: OsString,
+    on_top_of: Vec<Action>,
+    stderr: W,
+}
+
+impl Sort<NoColor<Sink>> {
+    pub fn new(possibly_empty_workspace_root: &Path) -> Self {
+        Self {
+            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
+            check: false,
+            dry_run: false,
+            stdout: false,
+            manifest_file_name: "Cargo.toml".into(),
+            on_top_of: vec![],
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Sort<W> {
+    /// Fails instead of writing the manifest if `workspace.{members, exclude}` are not sorted or
+    /// contain duplicates.
+    pub fn check(self, check: bool) -> Self {
+        Self { check, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifests to stdout instead of writing them. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// File name of the root manifest. Defaults to `Cargo.toml`.
+    pub fn manifest_file_name<S: AsRef<OsStr>>(self, manifest_file_name: S) -> Self {
+        let manifest_file_name = manifest_file_name.as_ref().to_owned();
+        Self {
+            manifest_file_name,
+            ..self
+        }
+    }
+
+    /// Plans the changes on top of `actions`, reading the files as if they had been performed.
+    /// The plan starts with `actions`, a write of the same file being merged into the earlier
+    /// one.
+    pub fn on_top_of(self, actions: Vec<Action>) -> Self {
+        Self {
+            on_top_of: actions,
+            ..self
+        }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Sort<W2> {
+        Sort {
+            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
+            check: self.check,
+            dry_run: self.dry_run,
+            stdout: self.stdout,
+            manifest_file_name: self.manifest_file_name,
+            stderr,
+            on_top_of: self.on_top_of,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    ///
+    /// The entries of `workspace.{members, exclude}` are sorted alphabetically and exact
+    /// duplicates are removed. Comments stay with the entries they are written next to.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            possibly_empty_workspace_root,
+            check,
+            dry_run,
+            stdout,
+            manifest_file_name,
+            mut stderr,
+            on_top_of,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let workspace_root = possibly_empty_workspace_root?;
+        let manifest_path = workspace_root.join(manifest_file_name);
+        let orig = Action::read_planned(&on_top_of, &manifest_path)?;
+        let mut cargo_toml = crate::fs::parse_toml_edit(&orig, &manifest_path)?;
+
+        let mut unsorted = vec![];
+        for field in &["members", "exclude"] {
+            let array = match cargo_toml["workspace"][field].as_array_mut() {
+                Some(array) => array,
+                None => continue,
+            };
+            let mut seen = vec![];
+            let mut i = 0;
+            while let Some(value) = array.get(i) {
+                if let Some(entry) = value.as_str().map(ToOwned::to_owned) {
+                    if seen.contains(&entry) {
+                        if !check {
+                            stderr.status(
+                                "Removing",
+                                format!("duplicate {:?} from `workspace.{}`", entry, field),
+                            )?;
+                        }
+                        remove_array_entry(array, i);
+                        unsorted.push(field);
+                        continue;
+                    }
+                    seen.push(entry);
+                }
+                i += 1;
+            }
+            if sort_array(array) {
+                if !check {
+                    stderr.status("Sorting", format!("`workspace.{}`", field))?;
+                }
+                unsorted.push(field);
+            }
+        }
+        let unsorted = unsorted.into_iter().unique().collect::<Vec<_>>();
+
+        let mut actions = on_top_of;
+        let cargo_toml = cargo_toml.to_string();
+        if check {
+            ensure!(
+                unsorted.is_empty(),
+                "{} in `{}` {} not sorted. run `cargo member sort`",
+                unsorted
+                    .iter()
+                    .map(|f| format!("`workspace.{}`", f))
+                    .join(" and "),
+                manifest_path.display(),
+                if unsorted.len() == 1 { "is" } else { "are" },
+            );
+        } else if cargo_toml == orig {
+            stderr.warn("`workspace` unchanged")?;
+        } else {
+            verify_sorted(&orig, &cargo_toml, &manifest_path)?;
+            Action::plan_write(&mut actions, manifest_path, cargo_toml)?;
+        }
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![],
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// The packages [`List::only`] keeps.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ListOnly {
+    /// The members.
+    Members,
+    /// The packages in `workspace.exclude`.
+    Exclude,
+    /// The members in `workspace.default-members`, or the ones Cargo selects without it: the root
+    /// package if any, otherwise every member.
+    DefaultMembers,
+}
+
+#[derive(Debug)]
+pub struct List<W> {
+    workspace_root: PathBuf,
+    /// `(package.name, directory, number of the members it depends on)`. The last is `None` if the
+    /// metadata has no resolve graph.
+    members: Vec<(String, PathBuf, Option<usize>)>,
+    with_deps_count: bool,
+    with_loc: bool,
+    only: Option<ListOnly>,
+    print_members_only: bool,
+    names: bool,
+    json_lines: bool,
+    color: termcolor::ColorChoice,
+    stderr: W,
+}
+
+impl List<NoColor<Sink>> {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let deps_count = |id: &PackageId| {
+            let node = metadata
+                .resolve
+                .as_ref()?
+                .nodes
+                .iter()
+                .find(|n| n.id == *id)?;
+            Some(
+                node.deps
+                    .iter()
+                    .filter(|d| metadata.workspace_members.contains(&d.pkg))
+                    .count(),
+            )
+        };
+        Self {
+            workspace_root: metadata.workspace_root.clone().into_std_path_buf(),
+            members: metadata
+                .packages
+                .iter()
+                .filter(|Package { id, .. }| metadata.workspace_members.contains(id))
+                .map(|p| {
+                    let manifest_path = p.manifest_path.clone().into_std_path_buf();
+                    let dir = manifest_path.parent().expect("should have a file name");
+                    (p.name.clone(), dir.to_owned(), deps_count(&p.id))
+                })
+                .collect(),
+            with_deps_count: false,
+            with_loc: false,
+            only: None,
+            print_members_only: false,
+            names: false,
+            json_lines: false,
+            color: termcolor::ColorChoice::Never,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> List<W> {
+    /// Adds the number of the other members each member depends on, including dev- and
+    /// build-dependencies. Requires the resolve graph.
+    pub fn with_deps_count(self, with_deps_count: bool) -> Self {
+        Self {
+            with_deps_count,
+            ..self
+        }
+    }
+
+    /// Adds the lines of Rust code of each member. This is a rough count of the lines of the
+    /// `.rs` files under `src/`, blank lines and comments included.
+    pub fn with_loc(self, with_loc: bool) -> Self {
+        Self { with_loc, ..self }
+    }
+
+    /// Lists only the members, the excluded packages or the default members.
+    pub fn only(self, only: Option<ListOnly>) -> Self {
+        Self { only, ..self }
+    }
+
+    /// Prints only the path of each member to stdout, one per line, with nothing else. The
+    /// packages [`List::only`] selects are printed instead if it is set.
+    pub fn print_members_only(self, print_members_only: bool) -> Self {
+        Self {
+            print_members_only,
+            ..self
+        }
+    }
+
+    /// With [`List::print_members_only`], prints the package names instead of the paths.
+    pub fn names(self, names: bool) -> Self {
+        Self { names, ..self }
+    }
+
+    /// Prints each package to stdout as a JSON object with `path`, `name` and `status`, and
+    /// `deps_count` and `loc` if requested, instead of a table.
+    pub fn json_lines(self, json_lines: bool) -> Self {
+        Self { json_lines, ..self }
+    }
+
+    /// Whether to color the `STATUS` column of the table. Defaults to
+    /// [`ColorChoice::Never`](termcolor::ColorChoice::Never).
+    pub fn color(self, color: termcolor::ColorChoice) -> Self {
+        Self { color, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> List<W2> {
+        List {
+            workspace_root: self.workspace_root,
+            members: self.members,
+            with_deps_count: self.with_deps_count,
+            with_loc: self.with_loc,
+            only: self.only,
+            print_members_only: self.print_members_only,
+            names: self.names,
+            json_lines: self.json_lines,
+            color: self.color,
+            stderr,
+        }
+    }
+
+    /// Prints the packages under the workspace root to stdout, sorted by path, with their status:
+    ///
+    /// - `member`: a member of the workspace
+    /// - `excluded`: in `workspace.exclude`
+    /// - `deactivated`: in neither `workspace.{members, exclude}`, as `deactivate` leaves it
+    /// - `untracked`: in neither, and belonging to another workspace
+    ///
+    /// This never modifies any file.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            workspace_root,
+            members,
+            with_deps_count,
+            with_loc,
+            only,
+            print_members_only,
+            names,
+            json_lines,
+            color,
+            mut stderr,
+        } = self;
+
+        let only = match only {
+            None if print_members_only => Some(ListOnly::Members),
+            only => only,
+        };
+
+        let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+        let excluded = workspace_entries(&root_cargo_toml, "exclude")
+            .iter()
+            .map(|e| normalize_path(&workspace_root.join(e)))
+            .collect::<Vec<_>>();
+
+        // `(package.name, directory, status, number of the members it depends on)`
+        let mut packages = members
+            .into_iter()
+            .map(|(name, dir, deps_count)| (name, dir, "member", deps_count))
+            .collect::<Vec<_>>();
+        for entry in Walk::new(&workspace_root) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    stderr.warn(err)?;
+                    continue;
+                }
+            };
+            if !entry.path().ends_with("Cargo.toml") {
+                continue;
+            }
+            let dir = entry.path().parent().expect("should not empty");
+            if packages.iter().any(|(_, d, _, _)| d == dir) {
+                continue;
+            }
+            let cargo_toml = crate::fs::read_toml_edit(entry.path())?;
+            let name = match cargo_toml["package"]["name"].as_str() {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            let status = if excluded.iter().any(|e| dir.starts_with(e)) {
+                "excluded"
+            } else if cargo_toml["workspace"].is_none()
+                && cargo_toml["package"]["workspace"].is_none()
+            {
+                "deactivated"
+            } else {
+                "untracked"
+            };
+            packages.push((name, dir.to_owned(), status, None));
+        }
+        packages.sort_by(|(_, a, _, _), (_, b, _, _)| a.cmp(b));
+
+        if let Some(only) = only {
+            // Without `default-members`, Cargo selects the root package, or every member if the
+            // root is not a package.
+            let root = glob::Pattern::escape(&workspace_root.to_string_lossy());
+            let default_members = match root_cargo_toml["workspace"]["default-members"] {
+                toml_edit::Item::None if root_cargo_toml["package"].is_none() => None,
+                toml_edit::Item::None => Some(vec![root]),
+                _ => Some(
+                    workspace_entries(&root_cargo_toml, "default-members")
+                        .iter()
+                        .map(|e| {
+                            let pattern = normalize_path(Path::new(&format!("{}/{}", root, e)));
+                            pattern.to_string_lossy().into_owned()
+                        })
+                        .collect(),
+                ),
+            }
+            .map(|patterns| {
+                patterns
+                    .iter()
+                    .map(|p| {
+                        glob::Pattern::new(p).with_context(|| format!("invalid glob: {:?}", p))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .transpose()?;
+            packages.retain(|(_, dir, status, _)| match only {
+                ListOnly::Members => *status == "member",
+                ListOnly::Exclude => *status == "excluded",
+                ListOnly::DefaultMembers => {
+                    *status == "member"
+                        && default_members
+                            .as_ref()
+                            .is_none_or(|ps| ps.iter().any(|p| p.matches_path(dir)))
+                }
+            });
+        }
+
+        let mut rows = vec![];
+        for (name, dir, status, deps_count) in packages {
+            let path = match relative_path(&workspace_root, &dir) {
+                path if path == Path::new("") => ".".to_owned(),
+                path => path.to_string_lossy().into_owned(),
+            };
+            let deps_count = if with_deps_count && status == "member" {
+                Some(deps_count.with_context(|| {
+                    format!(
+                        "the dependencies of `{}` are not resolved. `--with-deps-count` requires \
+                         the resolve graph",
+                        name,
+                    )
+                })?)
+            } else {
+                None
+            };
+            let loc = if with_loc {
+                Some(count_rust_lines(&dir.join("src"), &mut stderr)?)
+            } else {
+                None
+            };
+            rows.push((path, name, status, deps_count, loc));
+        }
+
+        let mut stdout = BufferedStandardStream::stdout(color);
+        if print_members_only {
+            for (path, name, ..) in rows {
+                writeln!(stdout, "{}", if names { name } else { path })?;
+            }
+        } else if json_lines {
+            for (path, name, status, deps_count, loc) in rows {
+                let mut package =
+                    serde_json::json!({ "path": path, "name": name, "status": status });
+                if let Some(deps_count) = deps_count {
+                    package["deps_count"] = deps_count.into();
+                }
+                if let Some(loc) = loc {
+                    package["loc"] = loc.into();
+                }
+                writeln!(stdout, "{}", package)?;
+            }
+        } else {
+            let mut table = vec![vec!["PATH".to_owned(), "NAME".to_owned()]];
+            if with_deps_count {
+                table[0].push("DEPS".to_owned());
+            }
+            if with_loc {
+                table[0].push("LOC".to_owned());
+            }
+            table[0].push("STATUS".to_owned());
+            for (path, name, status, deps_count, loc) in &rows {
+                let mut row = vec![path.clone(), name.clone()];
+                if with_deps_count {
+                    row.push(deps_count.map_or_else(|| "-".to_owned(), |n| n.to_string()));
+                }
+                row.extend(loc.map(|n| n.to_string()));
+                row.push((*status).to_owned());
+                table.push(row);
+            }
+            let widths = (0..table[0].len())
+                .map(|i| table.iter().map(|row| row[i].len()).max().unwrap_or(0))
+                .collect::<Vec<_>>();
+            for (i, row) in table.iter().enumerate() {
+                let (status, cells) = row.split_last().expect("should not be empty");
+                for (cell, width) in cells.iter().zip(&widths) {
+                    write!(stdout, "{:<width$}  ", cell, width = width)?;
+                }
+                let fg = match (i, &**status) {
+                    (0, _) => None,
+                    (_, "member") => Some(termcolor::Color::Green),
+                    (_, "excluded") => Some(termcolor::Color::Cyan),
+                    (_, "deactivated") => Some(termcolor::Color::Yellow),
+                    _ => Some(termcolor::Color::Red),
+                };
+                stdout.set_color(ColorSpec::new().set_fg(fg))?;
+                write!(stdout, "{}", status)?;
+                stdout.reset()?;
+                writeln!(stdout)?;
+            }
+        }
+        stdout.flush().map_err(Into::into)
+    }
+}
+
+/// Counts the lines of the `.rs` files under `dir`.
+fn count_rust_lines(dir: &Path, mut stderr: impl WriteColor) -> anyhow::Result<usize> {
+    let mut loc = 0;
+    if !dir.exists() {
+        return Ok(loc);
+    }
+    for entry in WalkBuilder::new(dir).build() {
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+                if path.is_file() && path.extension() == Some("rs".as_ref()) {
+                    loc += crate::fs::read_to_string(path)?.lines().count();
+                }
+            }
+            Err(err) => stderr.warn(err)?,
+        }
+    }
+    Ok(loc)
+}
+
+#[derive(Debug)]
+pub struct Status<W> {
+    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
+    verbose: bool,
+    check: bool,
+    json_lines: bool,
+    manifest_file_name: OsString,
+    stderr: W,
+}
+
+impl Status<NoColor<Sink>> {
+    pub fn new(possibly_empty_workspace_root: &Path) -> Self {
+        Self {
+            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
+            verbose: false,
+            check: false,
+            manifest_file_name: "Cargo.toml".into(),
+            json_lines: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Status<W> {
+    /// Prints the details of each category in addition to the counts.
+    pub fn verbose(self, verbose: bool) -> Self {
+        Self { verbose, ..self }
+    }
+
+    /// Fails if any category is non-empty.
+    pub fn check(self, check: bool) -> Self {
+        Self { check, ..self }
+    }
+
+    /// File name of the root manifest. Defaults to `Cargo.toml`.
+    pub fn manifest_file_name<S: AsRef<OsStr>>(self, manifest_file_name: S) -> Self {
+        let manifest_file_name = manifest_file_name.as_ref().to_owned();
+        Self {
+            manifest_file_name,
+            ..self
+        }
+    }
+
+    /// Prints each finding to stdout as a JSON object with `category`, `path`, `message` and
+    /// `severity`, followed by a summary object, instead of reporting them to stderr.
+    pub fn json_lines(self, json_lines: bool) -> Self {
+        Self { json_lines, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Status<W2> {
+        Status {
+            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
+            verbose: self.verbose,
+            check: self.check,
+            stderr,
+            manifest_file_name: self.manifest_file_name,
+            json_lines: self.json_lines,
+        }
+    }
+
+    /// Summarizes the drift of the workspace:
+    ///
+    /// - packages under the workspace root that are neither members nor excluded
+    /// - entries in `workspace.members` that do not point to a package
+    /// - members whose `package.version` differs from the most common one
+    /// - path dependencies of the members that do not point to a package
+    ///
+    /// Unlike the other operations, this does not run `cargo metadata`, so it works on a broken
+    /// workspace.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            possibly_empty_workspace_root,
+            verbose,
+            check,
+            mut stderr,
+            manifest_file_name,
+            json_lines,
+        } = self;
+
+        let workspace_root = possibly_empty_workspace_root?;
+        let manifest_path = workspace_root.join(manifest_file_name);
+        let root_cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+
+        let excluded = workspace_entries(&root_cargo_toml, "exclude")
+            .iter()
+            .map(|e| normalize_path(&workspace_root.join(e)))
+            .collect::<Vec<_>>();
+        let (members, dead) = expand_members(&workspace_root, &root_cargo_toml)?;
+        let orphans = find_orphans(&workspace_root, &members, &excluded, &mut stderr)?;
+
+        let mut versions = vec![];
+        let mut dangling = vec![];
+        for dir in &members {
+            let cargo_toml = crate::fs::read_toml_edit(dir.join("Cargo.toml"))?;
+            let name = cargo_toml["package"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned();
+
+            if let Some(version) = cargo_toml["package"]["version"]
+                .as_str()
+                .and_then(|v| v.parse::<Version>().ok())
+            {
+                versions.push((name.clone(), version, dir.join("Cargo.toml")));
+            }
+
+            for (dep, value) in dependencies(&cargo_toml) {
+                if let Some(path) = value["path"].as_str() {
+                    if !dir.join(path).join("Cargo.toml").exists() {
+                        dangling.push((
+                            name.clone(),
+                            dep.to_owned(),
+                            path.to_owned(),
+                            dir.join("Cargo.toml"),
+                        ));
+                    }
+                }
+            }
+        }
+        let expected = versions
+            .iter()
+            .map(|(_, v, _)| v)
+            .counts()
+            .into_iter()
+            .max_by(|(v1, n1), (v2, n2)| n1.cmp(n2).then_with(|| v1.cmp(v2)))
+            .map(|(v, _)| v.clone());
+        let drift = versions
+            .into_iter()
+            .filter(|(_, v, _)| Some(v) != expected.as_ref())
+            .collect::<Vec<_>>();
+
+        if json_lines {
+            let severity = if check { "error" } else { "warning" };
+            let orphans = orphans.iter().map(|orphan| {
+                let message = format!("`{}` is not registered", orphan.display());
+                ("orphans", orphan.join("Cargo.toml"), message)
+            });
+            let dead = dead.iter().map(|entry| {
+                let message = format!("{:?} does not point to a package", entry);
+                ("dead", manifest_path.clone(), message)
+            });
+            let drift = drift.iter().map(|(name, version, path)| {
+                let message = format!("`{}` is at {}", name, version);
+                ("drift", path.clone(), message)
+            });
+            let dangling = dangling.iter().map(|(name, dep, path, manifest_path)| {
+                let message = format!(
+                    "`{}` depends on `{}` at {:?}, which is not a package",
+                    name, dep, path,
+                );
+                ("dangling", manifest_path.clone(), message)
+            });
+            let findings = orphans
+                .chain(dead)
+                .chain(drift)
+                .chain(dangling)
+                .collect::<Vec<_>>();
+            write_json_lines(
+                findings
+                    .iter()
+                    .map(|(category, path, message)| (*category, severity, &**path, &**message)),
+            )?;
+        } else {
+            let color = |n: usize| {
+                if n == 0 {
+                    termcolor::Color::Green
+                } else {
+                    termcolor::Color::Yellow
+                }
+            };
+
+            stderr.status_with_color(
+                "Orphans",
+                format!(
+                    "{} package(s) in neither `workspace.members` nor `workspace.exclude`",
+                    orphans.len(),
+                ),
+                color(orphans.len()),
+            )?;
+            if verbose {
+                for orphan in &orphans {
+                    stderr.note(format!("`{}` is not registered", orphan.display()))?;
+                }
+            }
+
+            stderr.status_with_color(
+                "Dead",
+                format!(
+                    "{} entry(s) in `workspace.members` without a package",
+                    dead.len(),
+                ),
+                color(dead.len()),
+            )?;
+            if verbose {
+                for entry in &dead {
+                    stderr.note(format!("{:?} does not point to a package", entry))?;
+                }
+            }
+
+            stderr.status_with_color(
+                "Drift",
+                match &expected {
+                    Some(expected) => format!("{} package(s) not at {}", drift.len(), expected),
+                    None => format!("{} package(s) out of sync", drift.len()),
+                },
+                color(drift.len()),
+            )?;
+            if verbose {
+                for (name, version, _) in &drift {
+                    stderr.note(format!("`{}` is at {}", name, version))?;
+                }
+            }
+
+            stderr.status_with_color(
+                "Dangling",
+                format!("{} path dependency(s) without a package", dangling.len(),),
+                color(dangling.len()),
+            )?;
+            if verbose {
+                for (name, dep, path, _) in &dangling {
+                    stderr.note(format!(
+                        "`{}` depends on `{}` at {:?}, which is not a package",
+                        name, dep, path,
+                    ))?;
+                }
+            }
+        }
+
+        let issues = orphans.len() + dead.len() + drift.len() + dangling.len();
+        if check && issues > 0 {
+            bail!("{} issue(s) found", issues);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the packages under `dirs` that are neither in `workspace.members` nor under
+/// `workspace.exclude` of `workspace_root`, without descending into `target` directories and
+/// the roots of other workspaces.
+fn find_packages_under(
+    workspace_root: &Path,
+    dirs: &[PathBuf],
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let root_manifest_path = workspace_root.join("Cargo.toml");
+    let (members, excluded) = if root_manifest_path.exists() {
+        let root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+        let excluded = workspace_entries(&root_cargo_toml, "exclude")
+            .iter()
+            .map(|e| normalize_path(&workspace_root.join(e)))
+            .collect::<Vec<_>>();
+        (
+            expand_members(workspace_root, &root_cargo_toml)?.0,
+            excluded,
+        )
+    } else {
+        (vec![], vec![])
+    };
+
+    let mut packages = vec![];
+    for dir in dirs {
+        let mut found = false;
+        let walk = WalkBuilder::new(dir)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .filter_entry(|entry| {
+                !(entry.file_type().is_some_and(|t| t.is_dir())
+                    && (entry.file_name() == "target"
+                        || entry.depth() > 0 && is_workspace_root(entry.path()).unwrap_or(false)))
+            })
+            .build();
+        for entry in walk {
+            match entry {
+                Ok(entry) => {
+                    if !entry.path().ends_with("Cargo.toml") {
+                        continue;
+                    }
+                    let dir = normalize_path(entry.path().parent().expect("should not empty"));
+                    found = true;
+                    if dir == workspace_root
+                        || members.contains(&dir)
+                        || excluded.iter().any(|e| dir.starts_with(e))
+                        || packages.contains(&dir)
+                    {
+                        continue;
+                    }
+                    packages.push(dir);
+                }
+                Err(err) => stderr.warn(err)?,
+            }
+        }
+        if !found {
+            stderr.warn(format!("found no packages under `{}`", dir.display()))?;
+        }
+    }
+    Ok(packages)
+}
+
+/// Returns the packages under `workspace_root` that are neither in `members` nor under `excluded`,
+/// skipping the ones that belong to other workspaces.
+fn find_orphans(
+    workspace_root: &Path,
+    members: &[PathBuf],
+    excluded: &[PathBuf],
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut orphans = vec![];
+    for entry in Walk::new(workspace_root) {
+        match entry {
+            Ok(entry) => {
+                if !entry.path().ends_with("Cargo.toml") {
+                    continue;
+                }
+                let dir = entry.path().parent().expect("should not empty");
+                if dir == workspace_root
+                    || members.iter().any(|m| m == dir)
+                    || excluded.iter().any(|e| dir.starts_with(e))
+                {
+                    continue;
+                }
+                let cargo_toml = crate::fs::read_toml_edit(entry.path())?;
+                if !cargo_toml["package"].is_none()
+                    && cargo_toml["workspace"].is_none()
+                    && cargo_toml["package"]["workspace"].is_none()
+                {
+                    orphans.push(dir.to_owned());
+                }
+            }
+            Err(err) => stderr.warn(err)?,
+        }
+    }
+    Ok(orphans)
+}
+
+/// Prints the findings of [`Check`] and [`Status`] to stdout as JSON lines, one object per
+/// finding followed by a summary object.
+fn write_json_lines<'a>(
+    findings: impl IntoIterator<Item = (&'a str, &'a str, &'a Path, &'a str)>,
+) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    let (mut num_errors, mut num_warnings) = (0, 0);
+    for (category, severity, path, message) in findings {
+        match severity {
+            "error" => num_errors += 1,
+            _ => num_warnings += 1,
+        }
+        let finding = serde_json::json!({
+            "category": category,
+            "path": path,
+            "message": message,
+            "severity": severity,
+        });
+        writeln!(stdout, "{}", finding)?;
+    }
+    let summary = serde_json::json!({
+        "summary": {
+            "errors": num_errors,
+            "warnings": num_warnings,
+        },
+    });
+    writeln!(stdout, "{}", summary)?;
+    stdout.flush()
+}
+
+/// What [`Check`] compares `workspace.{members, exclude}` entries by.
+#[derive(EnumString, EnumVariantNames, IntoStaticStr, Clone, Copy, PartialEq, Eq, Debug)]
+#[strum(serialize_all = "kebab-case")]
+pub enum SortKey {
+    /// The entries themselves.
+    Path,
+    /// The `package.name`s of the manifests the entries point to. Entries without a readable
+    /// manifest (e.g. globs) are compared by themselves.
+    Name,
+}
+
+#[derive(Debug)]
+pub struct Check<W> {
+    possibly_empty_workspace_root: anyhow::Result<PathBuf>,
+    require_sorted: bool,
+    sort_key: SortKey,
+    locked: bool,
+    offline: bool,
+    json: bool,
+    json_lines: bool,
+    manifest_file_name: OsString,
+    stderr: W,
+}
+
+impl Check<NoColor<Sink>> {
+    pub fn new(possibly_empty_workspace_root: &Path) -> Self {
+        Self {
+            possibly_empty_workspace_root: ensure_absolute(possibly_empty_workspace_root),
+            require_sorted: false,
+            sort_key: SortKey::Path,
+            locked: false,
+            offline: false,
+            json: false,
+            manifest_file_name: "Cargo.toml".into(),
+            json_lines: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> Check<W> {
+    /// Also requires `workspace.{members, exclude}` to be sorted.
+    pub fn require_sorted(self, require_sorted: bool) -> Self {
+        Self {
+            require_sorted,
+            ..self
+        }
+    }
+
+    /// Key that `require_sorted` compares the entries by.
+    pub fn sort_key(self, sort_key: SortKey) -> Self {
+        Self { sort_key, ..self }
+    }
+
+    /// Also requires `Cargo.lock` to be up to date, running `cargo metadata --locked`. `cargo`
+    /// only reads a manifest named `Cargo.toml`, so this cannot be used with
+    /// [`Check::manifest_file_name`].
+    pub fn locked(self, locked: bool) -> Self {
+        Self { locked, ..self }
+    }
+
+    pub fn offline(self, offline: bool) -> Self {
+        Self { offline, ..self }
+    }
+
+    /// Prints the violations to stdout as JSON instead of reporting them to stderr.
+    pub fn json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    /// File name of the root manifest. Defaults to `Cargo.toml`.
+    pub fn manifest_file_name<S: AsRef<OsStr>>(self, manifest_file_name: S) -> Self {
+        let manifest_file_name = manifest_file_name.as_ref().to_owned();
+        Self {
+            manifest_file_name,
+            ..self
+        }
+    }
+
+    /// Prints each finding to stdout as a JSON object with `category`, `path`, `message` and
+    /// `severity`, followed by a summary object, instead of reporting them to stderr.
+    pub fn json_lines(self, json_lines: bool) -> Self {
+        Self { json_lines, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Check<W2> {
+        Check {
+            possibly_empty_workspace_root: self.possibly_empty_workspace_root,
+            require_sorted: self.require_sorted,
+            sort_key: self.sort_key,
+            locked: self.locked,
+            offline: self.offline,
+            json: self.json,
+            manifest_file_name: self.manifest_file_name,
+            stderr,
+            json_lines: self.json_lines,
+        }
+    }
+
+    /// Verifies that the membership of the workspace is consistent, failing if any of these is
+    /// found:
+    ///
+    /// - `unregistered`: packages under the workspace root that are neither members nor excluded
+    /// - `dead`: entries in `workspace.members` that do not point to a package
+    /// - `unclean`: entries in `workspace.{members, exclude}` that are not canonical or duplicated
+    /// - `unsorted`: unsorted `workspace.{members, exclude}`, if `require_sorted` is enabled
+    /// - `lockfile`: `Cargo.lock` that needs updating, if `locked` is enabled
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            possibly_empty_workspace_root,
+            require_sorted,
+            sort_key,
+            locked,
+            offline,
+            json,
+            manifest_file_name,
+            mut stderr,
+            json_lines,
+        } = self;
+
+        let workspace_root = possibly_empty_workspace_root?;
+        ensure!(
+            !locked || manifest_file_name == "Cargo.toml",
+            "`--locked` cannot be used with a manifest not named `Cargo.toml`",
+        );
+        let manifest_path = workspace_root.join(manifest_file_name);
+        let root_cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+
+        let excluded = workspace_entries(&root_cargo_toml, "exclude")
+            .iter()
+            .map(|e| normalize_path(&workspace_root.join(e)))
+            .collect::<Vec<_>>();
+        let (members, dead) = expand_members(&workspace_root, &root_cargo_toml)?;
+
+        let mut categories = vec![];
+
+        let unregistered = find_orphans(&workspace_root, &members, &excluded, &mut stderr)?
+            .into_iter()
+            .map(|dir| {
+                let message = format!(
+                    "`{}` is in neither `workspace.members` nor `workspace.exclude`",
+                    dir.display(),
+                );
+                (dir.join("Cargo.toml"), message)
+            })
+            .collect::<Vec<_>>();
+        categories.push(("unregistered", "Unregistered", "package(s)", unregistered));
+
+        let dead = dead
+            .iter()
+            .map(|entry| {
+                let message = format!(
+                    "{:?} in `workspace.members` does not point to a package",
+                    entry,
+                );
+                (manifest_path.clone(), message)
+            })
+            .collect();
+        categories.push(("dead", "Dead", "entry(s)", dead));
+
+        let mut unclean = vec![];
+        let mut unsorted = vec![];
+        let mut package_names = BTreeMap::new();
+        for field in &["members", "exclude"] {
+            let entries = workspace_entries(&root_cargo_toml, field);
+            let mut seen = vec![];
+            for entry in &entries {
+                let canonical = canonicalize_member_in(&workspace_root, entry);
+                if canonical != *entry {
+                    let message = format!(
+                        "{:?} in `workspace.{}` should be {:?}",
+                        entry, field, canonical,
+                    );
+                    unclean.push((manifest_path.clone(), message));
+                }
+                if seen.contains(&canonical) {
+                    let message = format!(
+                        "{:?} appears more than once in `workspace.{}`",
+                        canonical, field,
+                    );
+                    unclean.push((manifest_path.clone(), message));
+                }
+                seen.push(canonical);
+            }
+            let keys = match sort_key {
+                SortKey::Path => entries.clone(),
+                SortKey::Name => entries
+                    .iter()
+                    .map(|entry| {
+                        package_names
+                            .entry(normalize_path(&workspace_root.join(entry)))
+                            .or_insert_with_key(|dir: &PathBuf| {
+                                crate::fs::read_toml_edit(dir.join("Cargo.toml"))
+                                    .ok()
+                                    .and_then(|t| t["package"]["name"].as_str().map(Into::into))
+                                    .unwrap_or_else(|| entry.clone())
+                            })
+                            .clone()
+                    })
+                    .collect(),
+            };
+            if !keys.windows(2).all(|w| w[0] <= w[1]) {
+                let message = match sort_key {
+                    SortKey::Path => format!("`workspace.{}` is not sorted", field),
+                    SortKey::Name => format!("`workspace.{}` is not sorted by name", field),
+                };
+                unsorted.push((manifest_path.clone(), message));
+            }
+        }
+        categories.push(("unclean", "Unclean", "entry(s)", unclean));
+        if require_sorted {
+            categories.push(("unsorted", "Unsorted", "array(s)", unsorted));
+        }
+
+        if locked {
+            let lockfile =
+                match cargo_metadata(Some(&manifest_path), false, true, offline, &workspace_root) {
+                    Ok(_) => vec![],
+                    Err(err) => vec![(workspace_root.join("Cargo.lock"), format!("{:#}", err))],
+                };
+            categories.push(("lockfile", "Lockfile", "error(s)", lockfile));
+        }
+
+        let num_violations = categories
+            .iter()
+            .map(|(_, _, _, violations)| violations.len())
+            .sum::<usize>();
+
+        if json_lines {
+            write_json_lines(categories.iter().flat_map(|(category, _, _, violations)| {
+                violations
+                    .iter()
+                    .map(move |(file, message)| (*category, "error", &**file, &**message))
+            }))?;
+        } else if json {
+            let violations = categories
+                .iter()
+                .flat_map(|(category, _, _, violations)| {
+                    violations.iter().map(move |(file, message)| {
+                        serde_json::json!({
+                            "category": category,
+                            "file": file,
+                            "message": message,
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut stdout = io::stdout();
+            writeln!(
+                stdout,
+                "{}",
+                serde_json::json!({ "violations": violations })
+            )?;
+            stdout.flush()?;
+        } else {
+            for (_, status, unit, violations) in &categories {
+                stderr.status_with_color(
+                    status,
+                    format!("{} {}", violations.len(), unit),
+                    if violations.is_empty() {
+                        termcolor::Color::Green
+                    } else {
+                        termcolor::Color::Red
+                    },
+                )?;
+                for (_, message) in violations {
+                    stderr.note(message)?;
+                }
+            }
+        }
+
+        if num_violations > 0 {
+            bail!("{} violation(s) found", num_violations);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Gc<W> {
+    target_dir: anyhow::Result<PathBuf>,
+    crate_names: Vec<String>,
+    force: bool,
+    dry_run: bool,
+    stderr: W,
+}
+
+impl Gc<NoColor<Sink>> {
+    /// Artifacts of crates other than `crate_names` are removed. `-` and `_` are not distinguished.
+    pub fn new<Ss: IntoIterator<Item = S>, S: AsRef<str>>(
+        target_dir: &Path,
+        crate_names: Ss,
+    ) -> Self {
+        Self {
+            target_dir: ensure_absolute(target_dir),
+            crate_names: crate_names
+                .into_iter()
+                .map(|s| s.as_ref().to_owned())
+                .collect(),
+            force: false,
+            dry_run: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    /// Keeps the artifacts of every package in `metadata`, including the dependencies, and of
+    /// their targets.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let crate_names = metadata
+            .packages
+            .iter()
+            .flat_map(|p| iter::once(&p.name).chain(p.targets.iter().map(|t| &t.name)));
+        Self::new(metadata.target_directory.as_std_path(), crate_names)
+    }
+}
+
+impl<W: WriteColor> Gc<W> {
+    /// Actually removes the artifacts. Without this (and `dry_run`), `exec` fails if there is
+    /// anything to remove.
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Gc<W2> {
+        Gc {
+            target_dir: self.target_dir,
+            crate_names: self.crate_names,
+            force: self.force,
+            dry_run: self.dry_run,
+            stderr,
+        }
+    }
+
+    /// Removes the artifacts in `{deps, .fingerprint, build, incremental, examples}` of each
+    /// profile directory (e.g. `target/debug`, `target/x86_64-unknown-linux-gnu/release`) whose
+    /// crate name is not known.
+    ///
+    /// Only entries named `{name}-{hash}` (with an optional `lib` prefix and extensions) are
+    /// considered. Final artifacts such as `target/debug/{name}` cannot be told apart from
+    /// other files and are left as they are.
+    pub fn exec(self) -> anyhow::Result<()> {
+        let Self {
+            target_dir,
+            crate_names,
+            force,
+            dry_run,
+            mut stderr,
+        } = self;
+
+        let target_dir = target_dir?;
+
+        if !target_dir.exists() {
+            stderr.warn(format!("`{}` does not exist", target_dir.display()))?;
+            return Ok(());
+        }
+
+        let known = |name: &str| crate_names.iter().any(|n| n.replace('-', "_") == name);
+        let stale = stale_artifacts(&target_dir, |name| {
+            let name = name.replace('-', "_");
+            known(&name) || name.strip_prefix("lib").is_some_and(known)
+        })?;
+
+        if !(force || dry_run || stale.is_empty()) {
+            bail!(
+                "found {} stale artifact(s) in `{}`. enable `--force` to remove them, or \
+                 `--dry-run` to list them",
+                stale.len(),
+                target_dir.display(),
+            );
+        }
+
+        let mut bytes = 0;
+        for path in &stale {
+            stderr.status_with_color(
+                "Removing",
+                format!("`{}`", path.display()),
+                termcolor::Color::Red,
+            )?;
+            if path.is_dir() {
+                bytes += dir_size(path);
+                crate::fs::remove_dir_all(path, dry_run)?;
+            } else {
+                bytes += std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+                crate::fs::remove_file(path, dry_run)?;
+            }
+        }
+
+        stderr.status(
+            if dry_run { "Found" } else { "Removed" },
+            format!("{} stale artifact(s), {} bytes", stale.len(), bytes),
+        )?;
+        if dry_run {
+            stderr.warn("not removing the artifacts due to dry run")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the `{name}-{hash}` entries in the profile directories under `target_dir` for which
+/// `known(name)` is `false`, sorted.
+fn stale_artifacts(
+    target_dir: &Path,
+    known: impl Fn(&str) -> bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let read_dir = |dir: &Path| -> anyhow::Result<Vec<PathBuf>> {
+        std::fs::read_dir(dir)
+            .and_then(|entries| entries.map(|e| e.map(|e| e.path())).collect())
+            .with_context(|| format!("failed to read `{}`", dir.display()))
+    };
+
+    // `target/{profile}` and `target/{triple}/{profile}`
+    let mut profile_dirs = vec![];
+    for dir in read_dir(target_dir)?.into_iter().filter(|p| p.is_dir()) {
+        if dir.join(".fingerprint").is_dir() {
+            profile_dirs.push(dir);
+        } else {
+            profile_dirs.extend(
+                read_dir(&dir)?
+                    .into_iter()
+                    .filter(|p| p.join(".fingerprint").is_dir()),
+            );
+        }
+    }
+
+    let mut stale = vec![];
+    for profile_dir in profile_dirs {
+        for subdir in &["deps", ".fingerprint", "build", "incremental", "examples"] {
+            let subdir = profile_dir.join(subdir);
+            if !subdir.is_dir() {
+                continue;
+            }
+            for path in read_dir(&subdir)? {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let stem = file_name.split('.').next().unwrap_or_default();
+                let name = match stem.rsplit_once('-') {
+                    // 16 hex digits, or 13 base-36 digits for `incremental`
+                    Some((name, hash))
+                        if !name.is_empty()
+                            && hash.len() >= 13
+                            && hash
+                                .bytes()
+                                .all(|b| b.is_ascii_digit() || b.is_ascii_lowercase()) =>
+                    {
+                        name
+                    }
+                    _ => continue,
+                };
+                if !known(name) {
+                    stale.push(path);
+                }
+            }
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+#[derive(Debug)]
+pub struct VendorMember<W> {
+    workspace_root: anyhow::Result<PathBuf>,
+    member: anyhow::Result<PathBuf>,
+    dry_run: bool,
+    stdout: bool,
+    strip_path_deps_to_versions: bool,
+    keep_path: bool,
+    stderr: W,
+}
+
+impl VendorMember<NoColor<Sink>> {
+    pub fn new(workspace_root: &Path, member: &Path) -> Self {
+        Self {
+            workspace_root: ensure_absolute(workspace_root),
+            member: ensure_absolute(member),
+            dry_run: false,
+            stdout: false,
+            strip_path_deps_to_versions: false,
+            keep_path: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+
+    pub fn from_metadata(metadata: &Metadata, spec: &str) -> Self {
+        Self {
+            workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
+            member: metadata.query_for_member(Some(spec)).map(|member| {
+                member
+                    .manifest_path
+                    .clone()
+                    .into_std_path_buf()
+                    .parent()
+                    .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+                    .to_owned()
+            }),
+            dry_run: false,
+            stdout: false,
+            strip_path_deps_to_versions: false,
+            keep_path: false,
+            stderr: NoColor::new(io::sink()),
+        }
+    }
+}
+
+impl<W: WriteColor> VendorMember<W> {
+    pub fn dry_run(self, dry_run: bool) -> Self {
+        Self { dry_run, ..self }
+    }
+
+    /// Prints the modified manifest to stdout instead of writing it. Implies `dry_run`.
+    pub fn stdout(self, stdout: bool) -> Self {
+        Self { stdout, ..self }
+    }
+
+    /// Sets `version` of each path dependency to the version of the package it points to, and
+    /// removes `path`, so that the package can be published.
+    pub fn strip_path_deps_to_versions(self, strip_path_deps_to_versions: bool) -> Self {
+        Self {
+            strip_path_deps_to_versions,
+            ..self
+        }
+    }
+
+    /// With `strip_path_deps_to_versions`, keeps `path` alongside `version`.
+    pub fn keep_path(self, keep_path: bool) -> Self {
+        Self { keep_path, ..self }
+    }
+
+    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> VendorMember<W2> {
+        VendorMember {
+            workspace_root: self.workspace_root,
+            member: self.member,
+            dry_run: self.dry_run,
+            stdout: self.stdout,
+            stderr,
+            strip_path_deps_to_versions: self.strip_path_deps_to_versions,
+            keep_path: self.keep_path,
+        }
+    }
+
+    pub fn exec(self) -> anyhow::Result<()> {
+        self.plan()?.apply()
+    }
+
+    /// Plans the changes without performing them.
+    pub fn plan(self) -> anyhow::Result<Plan<W>> {
+        let Self {
+            workspace_root,
+            member,
+            dry_run,
+            stdout,
+            mut stderr,
+            strip_path_deps_to_versions,
+            keep_path,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let (workspace_root, member) = (workspace_root?, member?);
+
+        let manifest_path = member.join("Cargo.toml");
+        let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+        inline_inherited_fields(&mut cargo_toml, &workspace_root, &member, &mut stderr)?;
+
+        if strip_path_deps_to_versions {
+            strip_path_dependencies(&mut cargo_toml, &member, keep_path, &mut stderr)?;
+        }
+
+        let mut actions = vec![];
+        Action::plan_write(&mut actions, manifest_path, cargo_toml.to_string())?;
+
+        Ok(Plan {
+            actions,
+            update_lockfiles: vec![],
+            offline: false,
+            dry_run,
+            stdout,
+            stderr,
+            report_file: None,
+            verify: None,
+            smoke_test: None,
+            staging: None,
+            dry_run_warning: Some("not modifying the manifest due to dry run"),
+            timings: Timings::default(),
+        })
+    }
+}
+
+/// Replaces the fields `cargo_toml` inherits from the workspace at `workspace_root`
+/// (`package.*.workspace = true`, `lints.workspace = true` and `*.workspace = true` in the
+/// dependency tables) with their values. The relative paths in the values are rebased onto
+/// `member_dir`, where the manifest is to be written.
+fn inline_inherited_fields(
+    cargo_toml: &mut toml_edit::Document,
+    workspace_root: &Path,
+    member_dir: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let root_manifest_path = workspace_root.join("Cargo.toml");
+    let root_cargo_toml = crate::fs::read_toml_edit(&root_manifest_path)?;
+
+    let not_found = |field: &str, workspace_field: &str| {
+        anyhow!(
+            "`{}` is inherited but `workspace.{}` is not found in `{}`",
+            field,
+            workspace_field,
+            root_manifest_path.display(),
+        )
+    };
+
+    // `package.*.workspace = true`
+    if let Some(package) = cargo_toml["package"].as_table_mut() {
+        let keys = package
+            .iter()
+            .filter(|(_, item)| item["workspace"].as_bool() == Some(true))
+            .map(|(key, _)| key.to_owned())
+            .collect::<Vec<_>>();
+        for key in keys {
+            let field = format!("package.{}", key);
+            let mut value = root_cargo_toml["workspace"]["package"][&key]
+                .as_value()
+                .ok_or_else(|| not_found(&field, &field))?
+                .clone()
+                .decorated(" ", "");
+            // These are relative to the workspace root.
+            if ["license-file", "readme"].contains(&&*key) {
+                if let Some(path) = value.as_str() {
+                    let path =
+                        relative_path(member_dir, &normalize_path(&workspace_root.join(path)));
+                    let path = path
+                        .to_str()
+                        .with_context(|| format!("{:?} is not valid UTF-8", path))?;
+                    value = Value::from(path).decorated(" ", "");
+                }
+            }
+            stderr.status(
+                "Inlining",
+                format!("`{}` as {}", field, value.to_string().trim()),
+            )?;
+            replace_inherited_value(package, &key, value);
+        }
+    }
+
+    // `lints.workspace = true`
+    if cargo_toml["lints"]["workspace"].as_bool() == Some(true) {
+        let mut lints = root_cargo_toml["workspace"]["lints"]
+            .as_table()
+            .ok_or_else(|| not_found("lints", "lints"))?
+            .clone();
+        if let Some(old) = cargo_toml["lints"].as_table() {
+            *lints.decor_mut() = old.decor().clone();
+            if let Some(position) = old.position() {
+                set_table_positions(&mut lints, position);
+            }
+        }
+        stderr.status("Inlining", "`lints`")?;
+        cargo_toml["lints"] = toml_edit::Item::Table(lints);
+    }
+
+    // `{dependencies, ..}.*.workspace = true`
+    for (prefix, deps) in dependency_tables_mut(cargo_toml) {
+        let keys = deps
+            .iter()
+            .filter(|(_, item)| item["workspace"].as_bool() == Some(true))
+            .map(|(key, _)| key.to_owned())
+            .collect::<Vec<_>>();
+        for key in keys {
+            let field = format!("{}.{}", prefix, key);
+            let workspace_item = &root_cargo_toml["workspace"]["dependencies"][&key];
+            if workspace_item.is_none() {
+                return Err(not_found(&field, &format!("dependencies.{}", key)));
+            }
+            let value = inline_workspace_dependency(
+                &deps[&key],
+                workspace_item,
+                workspace_root,
+                member_dir,
+            )?;
+            stderr.status("Inlining", format!("`{}` as {}", field, value))?;
+            if let Some(table) = deps[&key].as_table_mut().filter(|t| !t.is_dotted()) {
+                table.remove("workspace");
+                for (k, v) in value.as_inline_table().into_iter().flat_map(|t| t.iter()) {
+                    table[k] = toml_edit::Item::Value(v.clone().decorated(" ", ""));
+                }
+                if let Some(version) = value.as_str() {
+                    table["version"] = toml_edit::value(version);
+                }
+            } else {
+                replace_inherited_value(deps, &key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the fields `cargo_toml` inherits from the workspace, e.g. `package.version`, `lints`
+/// and `dependencies.serde`.
+fn inherited_fields(cargo_toml: &toml_edit::Document) -> Vec<String> {
+    let package = cargo_toml["package"]
+        .as_table()
+        .into_iter()
+        .flat_map(|package| package.iter())
+        .filter(|(_, item)| item["workspace"].as_bool() == Some(true))
+        .map(|(key, _)| format!("package.{}", key));
+    let lints = Some("lints".to_owned())
+        .filter(|_| cargo_toml["lints"]["workspace"].as_bool() == Some(true));
+    let mut cargo_toml_mut = cargo_toml.clone();
+    let dependencies = dependency_tables_mut(&mut cargo_toml_mut)
+        .into_iter()
+        .flat_map(|(prefix, deps)| {
+            deps.iter()
+                .filter(|(_, item)| item["workspace"].as_bool() == Some(true))
+                .map(|(key, _)| format!("{}.{}", prefix, key))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    package.chain(lints).chain(dependencies).collect()
+}
+
+/// Merges the member's `key = { workspace = true, .. }` into the workspace's definition of `key`,
+/// as Cargo does. `path` is rebased onto `member_dir`.
+fn inline_workspace_dependency(
+    item: &toml_edit::Item,
+    workspace_item: &toml_edit::Item,
+    workspace_root: &Path,
+    member_dir: &Path,
+) -> anyhow::Result<Value> {
+    let mut workspace_item = workspace_item.clone();
+    rebase_path_dependency(&mut workspace_item, workspace_root, member_dir)?;
+
+    let mut table = toml_edit::InlineTable::default();
+    if let Some(version) = workspace_item.as_str() {
+        table.insert("version", version.into());
+    }
+    for (key, value) in workspace_item
+        .as_table_like()
+        .into_iter()
+        .flat_map(|t| t.iter())
+    {
+        if let Some(value) = value.as_value() {
+            table.insert(key, value.clone());
+        }
+    }
+    for (key, value) in item.as_table_like().into_iter().flat_map(|t| t.iter()) {
+        let value = match value.as_value() {
+            Some(value) if key != "workspace" => value,
+            _ => continue,
+        };
+        match (
+            table.get_mut(key).and_then(Value::as_array_mut),
+            value.as_array(),
+        ) {
+            // `features` are additive.
+            (Some(features), Some(additional)) if key == "features" => {
+                for feature in additional.iter() {
+                    if !features.iter().any(|f| f.as_str() == feature.as_str()) {
+                        features.push_formatted(feature.clone());
+                    }
+                }
+                features.fmt();
+            }
+            _ => {
+                table.insert(key, value.clone());
+            }
+        }
+    }
+
+    if table.len() == 1 {
+        if let Some(version) = table.get("version").and_then(Value::as_str) {
+            return Ok(version.into());
+        }
+    }
+    toml_edit::InlineTable::fmt(&mut table);
+    Ok(Value::InlineTable(table))
+}
+
+/// Sets `version` of each path dependency in `dir/Cargo.toml` to the version of the package it
+/// points to, removing `path` unless `keep_path` is enabled.
+fn strip_path_dependencies(
+    cargo_toml: &mut toml_edit::Document,
+    dir: &Path,
+    keep_path: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for (prefix, deps) in dependency_tables_mut(cargo_toml) {
+        for (key, item) in deps.iter_mut() {
+            let path = match item["path"].as_str() {
+                Some(path) => normalize_path(&dir.join(path)),
+                None => continue,
+            };
+            let version = match item["version"].as_str() {
+                Some(version) => version.to_owned(),
+                None => {
+                    let version = package_version(&path)?;
+                    set_dependency_field(item, "version", &version);
+                    version
+                }
+            };
+            if !keep_path {
+                remove_dependency_field(item, "path");
+                // `{ version = "1" }` → `"1"`
+                if let Some(table) = item.as_inline_table().filter(|t| t.len() == 1) {
+                    if table.contains_key("version") {
+                        let value = redecorated(Value::from(&*version), table.decor(), (" ", ""));
+                        *item = toml_edit::Item::Value(value);
+                    }
+                }
+            }
+            stderr.status("Pinning", format!("`{}.{}` to {:?}", prefix, key, version))?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads `(name, version, manifest_path)` of each member listed in the root manifest at
+/// `workspace_root` without `cargo metadata`, for when it fails. Also returns the manifests that
+/// cannot be read, with the reasons.
+#[allow(clippy::type_complexity)]
+fn read_members_without_metadata(
+    workspace_root: &Path,
+) -> anyhow::Result<(
+    Vec<(String, Version, PathBuf)>,
+    Vec<(PathBuf, anyhow::Error)>,
+)> {
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let (mut members, mut unreadable) = (vec![], vec![]);
+    for dir in expand_members(workspace_root, &root_cargo_toml)?.0 {
+        let manifest_path = dir.join("Cargo.toml");
+        let member = (|| -> anyhow::Result<_> {
+            let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+            let name = cargo_toml["package"]["name"]
+                .as_str()
+                .with_context(|| {
+                    format!(
+                        "`package.name` is not found in `{}`",
+                        manifest_path.display()
+                    )
+                })?
+                .to_owned();
+            // `package.version` defaults to `0.0.0`.
+            let version = if cargo_toml["package"]["version"].is_none() {
+                Version::new(0, 0, 0)
+            } else {
+                let version = package_version(&dir)?;
+                version
+                    .parse()
+                    .with_context(|| format!("invalid version {:?}", version))?
+            };
+            Ok((name, version, manifest_path.clone()))
+        })();
+        match member {
+            Ok(member) => members.push(member),
+            Err(err) => unreadable.push((manifest_path, err)),
+        }
+    }
+    Ok((members, unreadable))
+}
+
+/// Returns `package.version` of `dir/Cargo.toml`, following `version.workspace = true`.
+fn package_version(dir: &Path) -> anyhow::Result<String> {
+    let manifest_path = dir.join("Cargo.toml");
+    let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+    let version = &cargo_toml["package"]["version"];
+    if version["workspace"].as_bool() == Some(true) {
+        for workspace_root in dir.ancestors().skip(1) {
+            if is_workspace_root(workspace_root)? {
+                let root_manifest_path = workspace_root.join("Cargo.toml");
+                return crate::fs::read_toml_edit(&root_manifest_path)?["workspace"]["package"]
+                    ["version"]
+                    .as_str()
+                    .map(ToOwned::to_owned)
+                    .with_context(|| {
+                        format!(
+                            "`workspace.package.version` is not found in `{}`",
+                            root_manifest_path.display(),
+                        )
+                    });
+            }
+        }
+        bail!("`{}` is not in a workspace", dir.display());
+    }
+    version.as_str().map(ToOwned::to_owned).with_context(|| {
+        format!(
+            "`package.version` is not found in `{}`",
+            manifest_path.display()
+        )
+    })
+}
+
+/// Returns `(name, table)` of every `[dependencies]`, `[dev-dependencies]` and
+/// `[build-dependencies]`, including the `[target.'..']` ones.
+fn dependency_tables_mut(
+    cargo_toml: &mut toml_edit::Document,
+) -> Vec<(String, &mut toml_edit::Table)> {
+    const KINDS: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+    let mut tables = vec![];
+    for (key, item) in cargo_toml.as_table_mut().iter_mut() {
+        if KINDS.contains(&key) {
+            tables.extend(item.as_table_mut().map(|t| (key.to_owned(), t)));
+        } else if key == "target" {
+            for (target, item) in item.as_table_mut().into_iter().flat_map(|t| t.iter_mut()) {
+                for (kind, item) in item.as_table_mut().into_iter().flat_map(|t| t.iter_mut()) {
+                    if KINDS.contains(&kind) {
+                        let name = format!("target.{}.{}", target, kind);
+                        tables.extend(item.as_table_mut().map(|t| (name, t)));
+                    }
+                }
+            }
+        }
+    }
+    tables
+}
+
+/// Replaces `key = { workspace = true }` or `key.workspace = true` in `table` with `key = value`,
+/// keeping the decor.
+fn replace_inherited_value(table: &mut toml_edit::Table, key: &str, value: Value) {
+    let item = &mut table[key];
+    let dotted = item.as_inline_table().is_some_and(|t| t.is_dotted())
+        || item.as_table().is_some_and(|t| t.is_dotted());
+    let (prefix, suffix) = match item.as_value() {
+        Some(old) if !dotted => (
+            old.decor().prefix().unwrap_or(" ").to_owned(),
+            old.decor().suffix().unwrap_or("").to_owned(),
+        ),
+        _ => (" ".to_owned(), "".to_owned()),
+    };
+    *item = toml_edit::Item::Value(value.decorated(&prefix, &suffix));
+    if dotted {
+        // `key.workspace` has no space before the dot.
+        if let Some(decor) = table.key_decor_mut(key) {
+            let prefix = decor.prefix().unwrap_or("").to_owned();
+            *decor = toml_edit::Decor::new(prefix, " ");
+        }
+    }
+}
+
+/// Sets the position of `table` and its subtables, so that the document puts them at `position`.
+fn set_table_positions(table: &mut toml_edit::Table, position: usize) {
+    table.set_position(position);
+    for (_, item) in table.iter_mut() {
+        if let Some(table) = item.as_table_mut() {
+            set_table_positions(table, position);
+        }
+    }
+}
+
+/// Returns the keys of `workspace.dependencies` whose `path` points to any of `paths`.
+fn workspace_dependencies_at(
+    root_cargo_toml: &toml_edit::Document,
+    workspace_root: &Path,
+    paths: &[PathBuf],
+) -> Vec<String> {
+    root_cargo_toml["workspace"]["dependencies"]
+        .as_table_like()
+        .into_iter()
+        .flat_map(|deps| deps.iter())
+        .filter(|(_, value)| {
+            value["path"]
+                .as_str()
+                .is_some_and(|path| paths.contains(&normalize_path(&workspace_root.join(path))))
+        })
+        .map(|(key, _)| key.to_owned())
+        .collect()
+}
+
+/// Removes the entries of `workspace.dependencies` pointing to `paths`, and warns about the members
+/// that still inherit them with `workspace = true`.
+fn prune_workspace_dependencies(
+    workspace_root: &Path,
+    paths: &[PathBuf],
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+
+    let keys = workspace_dependencies_at(&cargo_toml, workspace_root, paths);
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let deps = cargo_toml["workspace"]["dependencies"]
+        .as_table_like_mut()
+        .expect("should be a table");
+    for key in &keys {
+        deps.remove(key);
+        stderr.status_with_color(
+            "Removing",
+            format!("`workspace.dependencies.{}`", key),
+            termcolor::Color::Red,
+        )?;
+    }
+    Action::plan_write(actions, manifest_path, cargo_toml.to_string())?;
+
+    let (members, _) = expand_members(workspace_root, &cargo_toml)?;
+    for member in members.iter().filter(|m| !paths.contains(m)) {
+        let member_cargo_toml = Action::read_toml_planned(actions, &member.join("Cargo.toml"))?;
+        for (key, value) in dependencies(&member_cargo_toml) {
+            if keys.iter().any(|k| k == key) && value["workspace"].as_bool() == Some(true) {
+                stderr.warn(format!(
+                    "`{}` still depends on the removed `workspace.dependencies.{}`",
+                    member.display(),
+                    key,
+                ))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Points the entries of `workspace.dependencies` for `src` to `dst`, and sets their `package` to
+/// `new_name` if the package is renamed.
+#[allow(clippy::too_many_arguments)]
+fn update_workspace_dependencies(
+    workspace_root: &Path,
+    src: &Path,
+    dst: &Path,
+    old_name: Option<&str>,
+    new_name: Option<&str>,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+
+    let keys = workspace_dependencies_at(&cargo_toml, workspace_root, &[src.to_owned()]);
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let new_path = member_entry(workspace_root, dst)?;
+    for key in &keys {
+        let dep_item = &mut cargo_toml["workspace"]["dependencies"][key];
+        let old_path = dep_item["path"].as_str().unwrap_or_default().to_owned();
+        stderr.status(
+            "Changing",
+            format!(
+                "`workspace.dependencies.{}.path` from {:?} to {:?}",
+                key, old_path, new_path,
+            ),
+        )?;
+        set_dependency_field(dep_item, "path", &new_path);
+        rename_dependency_package(
+            dep_item,
+            &format!("workspace.dependencies.{}", key),
+            key,
+            old_name,
+            new_name,
+            &mut stderr,
+        )?;
+    }
+    Action::plan_write(actions, manifest_path, cargo_toml.to_string())
+}
+
+/// Sets `package` of the dependency `item` at `key` to `new_name` if the package is renamed,
+/// removing it instead if `key` is the new name.
+fn rename_dependency_package(
+    item: &mut toml_edit::Item,
+    desc: &str,
+    key: &str,
+    old_name: Option<&str>,
+    new_name: Option<&str>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    if let Some(new_name) = new_name.filter(|&n| Some(n) != old_name) {
+        if key == new_name {
+            if remove_dependency_field(item, "package") {
+                stderr.status_with_color(
+                    "Removing",
+                    format!("`{}.package`", desc),
+                    termcolor::Color::Red,
+                )?;
+            }
+        } else {
+            stderr.status("Changing", format!("`{}.package` to {:?}", desc, new_name))?;
+            set_dependency_field(item, "package", new_name);
+        }
+    }
+    Ok(())
+}
+
+/// Returns `(table, key)` of each dependency in `dir/Cargo.toml` on the package at `src`, either
+/// with `path` or inherited from an entry of `workspace.dependencies` with `path`.
+fn dependencies_on(dir: &Path, src: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+    let workspace_root = find_workspace_root(&manifest_path)?;
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    let inherited = workspace_dependencies_at(&root_cargo_toml, &workspace_root, &[src.to_owned()]);
+
+    let mut deps = vec![];
+    for (prefix, table) in dependency_tables_mut(&mut cargo_toml) {
+        for (key, item) in table.iter() {
+            let on_src = match item["path"].as_str() {
+                Some(path) => normalize_path(&dir.join(path)) == src,
+                None => {
+                    item["workspace"].as_bool() == Some(true) && inherited.contains(&key.to_owned())
+                }
+            };
+            if on_src {
+                deps.push((prefix.clone(), key.to_owned()));
+            }
+        }
+    }
+    Ok(deps)
+}
+
+/// Points the dependencies `deps` (`(table, key)`) in `dir/Cargo.toml` to the package at `dst`,
+/// replacing `workspace = true` with `path`, and sets their `package` to `new_name` if it differs
+/// from `old_name`.
+#[allow(clippy::too_many_arguments)]
+fn replace_dependencies(
+    dir: &Path,
+    deps: &[(String, String)],
+    dst: &Path,
+    old_name: Option<&str>,
+    new_name: Option<&str>,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+    let new_path = relative_path(dir, dst);
+    let new_path = new_path
+        .to_str()
+        .with_context(|| format!("{:?} is not valid UTF-8", new_path))?;
+    for (prefix, table) in dependency_tables_mut(&mut cargo_toml) {
+        for (key, item) in table.iter_mut() {
+            if !deps.contains(&(prefix.clone(), key.to_owned())) {
+                continue;
+            }
+            let old = match item["path"].as_str() {
+                Some(path) => format!("{:?}", path),
+                None => "`workspace = true`".to_owned(),
+            };
+            stderr.status(
+                "Changing",
+                format!(
+                    "`{}.{}.path` in `{}` from {} to {:?}",
+                    prefix,
+                    key,
+                    manifest_path.display(),
+                    old,
+                    new_path,
+                ),
+            )?;
+            remove_dependency_field(item, "workspace");
+            set_dependency_field(item, "path", new_path);
+            rename_dependency_package(
+                item,
+                &format!("{}.{}", prefix, key),
+                key,
+                old_name,
+                new_name,
+                &mut stderr,
+            )?;
+        }
+    }
+    Action::plan_write(actions, manifest_path, cargo_toml.to_string())
+}
+
+/// Points each path dependency in the manifests in `dirs` that points to `src` to `dst`, and sets
+/// its `package` to `new_name` if the package is renamed.
+#[allow(clippy::too_many_arguments)]
+fn update_path_dependents(
+    dirs: &[PathBuf],
+    src: &Path,
+    dst: &Path,
+    old_name: Option<&str>,
+    new_name: Option<&str>,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for dir in dirs {
+        let manifest_path = dir.join("Cargo.toml");
+        if dir.starts_with(src) || !manifest_path.exists() {
+            continue;
+        }
+        let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+        let mut modified = false;
+        for (prefix, deps) in dependency_tables_mut(&mut cargo_toml) {
+            for (key, item) in deps.iter_mut() {
+                let old_path = match item["path"].as_str() {
+                    Some(path) if normalize_path(&dir.join(path)) == src => path.to_owned(),
+                    _ => continue,
+                };
+                let new_path = relative_path(dir, dst);
+                let new_path = new_path
+                    .to_str()
+                    .with_context(|| format!("{:?} is not valid UTF-8", new_path))?;
+                stderr.status(
+                    "Changing",
+                    format!(
+                        "`{}.{}.path` in `{}` from {:?} to {:?}",
+                        prefix,
+                        key,
+                        manifest_path.display(),
+                        old_path,
+                        new_path,
+                    ),
+                )?;
+                set_dependency_field(item, "path", new_path);
+                rename_dependency_package(
+                    item,
+                    &format!("{}.{}", prefix, key),
+                    key,
+                    old_name,
+                    new_name,
+                    &mut stderr,
+                )?;
+                modified = true;
+            }
+        }
+        if modified {
+            Action::plan_write(actions, manifest_path, cargo_toml.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the entry of `workspace.{field}` pointing to `from` with `new`, keeping its position.
+fn rename_workspace_entry(
+    workspace_root: &Path,
+    field: &str,
+    from: &Path,
+    new: &str,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = workspace_root.join("Cargo.toml");
+    let mut cargo_toml = Action::read_toml_planned(actions, &manifest_path)?;
+
+    let array = match cargo_toml["workspace"][field].as_array_mut() {
+        Some(array) => array,
+        None => return Ok(()),
+    };
+    let i = array.iter().position(|v| {
+        v.as_str()
+            .is_some_and(|s| normalize_path(&workspace_root.join(s)) == from)
+    });
+    let i = match i {
+        Some(i) => i,
+        None => return Ok(()),
+    };
+    let old = array.get(i).expect("should exist");
+    stderr.status(
+        "Changing",
+        format!(
+            "{:?} to {:?} in `workspace.{}`",
+            old.as_str().unwrap_or_default(),
+            new,
+            field,
+        ),
+    )?;
+    let new = redecorated(Value::from(new), old.decor(), (" ", ""));
+    array.replace_formatted(i, new);
+    Action::plan_write(actions, manifest_path, cargo_toml.to_string())
+}
+
+/// Finds the package in `workspace.exclude` whose name or path is `spec`.
+fn find_excluded_package(workspace_root: &Path, spec: &str) -> anyhow::Result<Option<PathBuf>> {
+    let root_cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    for entry in workspace_entries(&root_cargo_toml, "exclude") {
+        let dir = normalize_path(&workspace_root.join(&entry));
+        let manifest_path = dir.join("Cargo.toml");
+        if !manifest_path.exists() {
+            continue;
+        }
+        let name = crate::fs::read_toml_edit(&manifest_path)?["package"]["name"]
+            .as_str()
+            .map(ToOwned::to_owned);
+        if canonicalize_member_in(workspace_root, &entry)
+            == canonicalize_member_in(workspace_root, spec)
+            || name.as_deref() == Some(spec)
+        {
+            return Ok(Some(dir));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the string entries of `workspace.{field}`.
+fn workspace_entries(cargo_toml: &toml_edit::Document, field: &str) -> Vec<String> {
+    cargo_toml["workspace"][field]
+        .as_array()
+        .into_iter()
+        .flat_map(|a| a.iter())
+        .flat_map(|v| v.as_str())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Expands `workspace.members` (and the root package) into the directories of the members without
+/// running `cargo metadata`. Entries that do not point to a package are returned separately.
+fn expand_members(
+    workspace_root: &Path,
+    root_cargo_toml: &toml_edit::Document,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<String>)> {
+    let excluded = workspace_entries(root_cargo_toml, "exclude")
+        .iter()
+        .map(|e| normalize_path(&workspace_root.join(e)))
+        .collect::<Vec<_>>();
+
+    let mut members = vec![];
+    if !root_cargo_toml["package"].is_none() {
+        members.push(workspace_root.to_owned());
+    }
+    let mut dead = vec![];
+    for entry in workspace_entries(root_cargo_toml, "members") {
+        if entry.contains(&['*', '?', '['][..]) {
+            let pattern = workspace_root.join(&entry);
+            let pattern = pattern
+                .to_str()
+                .with_context(|| format!("{:?} is not valid UTF-8", pattern))?;
+            for dir in glob::glob(pattern).with_context(|| format!("invalid glob: {:?}", entry))? {
+                let dir = dir?;
+                if !dir.is_dir() || excluded.contains(&dir) {
+                    continue;
+                }
+                if dir.join("Cargo.toml").exists() {
+                    members.push(dir);
+                } else {
+                    dead.push(relative_path(workspace_root, &dir).display().to_string());
+                }
+            }
+        } else {
+            let dir = normalize_path(&workspace_root.join(&entry));
+            if dir.join("Cargo.toml").exists() {
+                members.push(dir);
+            } else {
+                dead.push(entry);
+            }
+        }
+    }
+    Ok((members, dead))
+}
+
+/// Returns `(key, value)` of every entry in `[dependencies]`, `[dev-dependencies]` and
+/// `[build-dependencies]`, including the `[target.'..']` ones.
+fn dependencies(cargo_toml: &toml_edit::Document) -> Vec<(&str, &toml_edit::Item)> {
+    let targets = cargo_toml["target"]
+        .as_table()
+        .into_iter()
+        .flat_map(|t| t.iter())
+        .map(|(_, t)| t);
+    iter::once(&cargo_toml.root)
+        .chain(targets)
+        .flat_map(|table| {
+            ["dependencies", "dev-dependencies", "build-dependencies"]
+                .iter()
+                .flat_map(move |kind| table[kind].as_table_like())
+                .flat_map(|deps| deps.iter())
+        })
+        .collect()
+}
+
+/// [`canonicalize_member`], also turning an absolute entry under `workspace_root` into a relative
+/// one so that it compares equal to the relative entries pointing to the same directory.
+fn canonicalize_member_in(workspace_root: &Path, entry: &str) -> String {
+    let canonical = canonicalize_member(entry);
+    if !Path::new(&canonical).is_absolute() || canonical.contains(&['*', '?', '['][..]) {
+        return canonical;
+    }
+    match normalize_path(Path::new(&canonical)).strip_prefix(normalize_path(workspace_root)) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_owned(),
+        Ok(relative) => relative.iter().map(|c| c.to_string_lossy()).join("/"),
+        Err(_) => canonical,
+    }
+}
+
+/// Removes `.` components, empty components and trailing slashes from a `workspace.members` or
+/// `workspace.exclude` entry, and uses `/` as the separator. Globs and `..` are kept as-is.
+fn canonicalize_member(entry: &str) -> String {
+    if entry.contains(&['*', '?', '['][..]) {
+        return entry.to_owned();
+    }
+    let is_absolute = entry.starts_with('/');
+    let components = entry
+        .split(&['/', '\\'][..])
+        .filter(|c| !["", "."].contains(c))
+        .collect::<Vec<_>>();
+    match (is_absolute, &*components) {
+        (true, components) => format!("/{}", components.join("/")),
+        (false, []) => ".".to_owned(),
+        (false, components) => components.join("/"),
+    }
+}
+
+/// How long each phase of an operation took, for `--time`. Clones share the same record.
+///
+/// The default value records nothing.
+#[derive(Clone, Default, Debug)]
+pub struct Timings(Option<Rc<RefCell<Phases>>>);
+
+/// The phases recorded in [`Timings`] and their total durations.
+type Phases = Vec<(&'static str, Duration)>;
+
+impl Timings {
+    pub fn new() -> Self {
+        Self(Some(Rc::default()))
+    }
+
+    /// Adds the time elapsed since `start` to `phase`.
+    pub fn add(&self, phase: &'static str, start: Instant) {
+        if let Some(phases) = &self.0 {
+            let elapsed = start.elapsed();
+            let mut phases = phases.borrow_mut();
+            match phases.iter_mut().find(|(p, _)| *p == phase) {
+                Some((_, duration)) => *duration += elapsed,
+                None => phases.push((phase, elapsed)),
+            }
+        }
+    }
+
+    /// Prints the time of each phase in the order they first occurred, and the total.
+    pub fn report(&self, mut stderr: impl WriteColor) -> io::Result<()> {
+        let phases = match &self.0 {
+            Some(phases) => phases.borrow(),
+            None => return Ok(()),
+        };
+        let total = phases.iter().map(|&(_, d)| d).sum::<Duration>();
+        let width = phases.iter().map(|(p, _)| p.len()).max().unwrap_or(0);
+        for (phase, duration) in phases.iter().chain(iter::once(&("total", total))) {
+            stderr.status(
+                "Timing",
+                format_args!(
+                    "{:<width$} {:.3}s",
+                    phase,
+                    duration.as_secs_f64(),
+                    width = width.max("total".len()),
+                ),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// File system changes planned by an operation. Created by `plan` methods and performed by
+/// [`Plan::apply`].
+#[derive(Debug)]
+pub struct Plan<W> {
+    actions: Vec<Action>,
+    update_lockfiles: Vec<PathBuf>,
+    offline: bool,
+    dry_run: bool,
+    stdout: bool,
+    stderr: W,
+    report_file: Option<PathBuf>,
+    verify: Option<Verify>,
+    smoke_test: Option<SmokeTest>,
+    staging: Option<StagingDir>,
+    dry_run_warning: Option<&'static str>,
+    timings: Timings,
+}
+
+impl<W: WriteColor> Plan<W> {
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut Vec<Action> {
+        &mut self.actions
+    }
+
+    /// The workspace roots whose `Cargo.lock` are updated after the actions.
+    pub fn update_lockfiles(&self) -> &[PathBuf] {
+        &self.update_lockfiles
+    }
+
+    /// Appends a record of each performed action to this file as a JSON line, after the actions
+    /// are performed. Nothing is recorded if `dry_run` or `stdout` was enabled.
+    ///
+    /// Each record is an [`Action`] serialized as JSON with an additional `timestamp` field, the
+    /// number of seconds since the Unix epoch at which the records were written:
+    ///
+    /// ```json
+    /// {"timestamp":1700000000.0,"action":"write-file","path":"/ws/Cargo.toml","before":"..","after":".."}
+    /// {"timestamp":1700000000.0,"action":"create-dir","path":"/ws/a"}
+    /// {"timestamp":1700000000.0,"action":"copy-file","src":"/ws/a/x","dst":"/ws/b/x"}
+    /// {"timestamp":1700000000.0,"action":"remove-dir","path":"/ws/a"}
+    /// {"timestamp":1700000000.0,"action":"remove-file","path":"/ws/a/x"}
+    /// ```
+    ///
+    /// The records of one operation are appended with a single write, so that concurrent runs do
+    /// not interleave their records.
+    pub fn report_file<P: AsRef<Path>>(self, report_file: Option<P>) -> Self {
+        let report_file = report_file.map(|p| p.as_ref().to_owned());
+        Self {
+            report_file,
+            ..self
+        }
+    }
+
+    /// Performs the actions, updates the lockfiles and runs the smoke test if any. Nothing is
+    /// written if `dry_run` or `stdout` was enabled.
+    ///
+    /// If an action fails, the files the previous actions wrote, copied over or removed are
+    /// restored and the directories they created are removed. The same is done when the
+    /// verification or the smoke test fails if it was asked to roll back.
+    pub fn apply(self) -> anyhow::Result<()> {
+        self.apply_into(&mut Rollback::default())
+    }
+
+    /// Performs the actions like [`Plan::apply`], also returning what undoes them. The removed
+    /// directories are kept until the [`Rollback`] is dropped. If this fails, the changes that
+    /// `apply` would leave in place are undone by the `Rollback` as well.
+    fn apply_undoable(self) -> (anyhow::Result<()>, Rollback) {
+        let mut rollback = Rollback {
+            keep_removed_dirs: true,
+            ..Rollback::default()
+        };
+        let result = self.apply_into(&mut rollback);
+        (result, rollback)
+    }
+
+    fn apply_into(self, rollback: &mut Rollback) -> anyhow::Result<()> {
+        let Self {
+            actions,
+            update_lockfiles,
+            offline,
+            dry_run,
+            stdout,
+            mut stderr,
+            report_file,
+            verify,
+            smoke_test,
+            staging,
+            dry_run_warning,
+            timings,
+        } = self;
+
+        let dry_run = dry_run || stdout;
+
+        let report = match &report_file {
+            Some(_) if !dry_run => Some(actions.clone()),
+            _ => None,
+        };
+
+        // The outermost directories the actions create.
+        let created = if dry_run {
+            vec![]
+        } else {
+            actions
+                .iter()
+                .flat_map(|action| match action {
+                    Action::CreateDir { path } => {
+                        path.ancestors().take_while(|d| !d.exists()).last()
+                    }
+                    _ => None,
+                })
+                .unique()
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        };
+
+        let update_lockfiles = update_lockfiles.into_iter().unique().collect::<Vec<_>>();
+
+        rollback.created.extend(created);
+        if !dry_run {
+            for action in &actions {
+                let path = match action {
+                    Action::WriteFile { path, .. }
+                    | Action::CopyFile { dst: path, .. }
+                    | Action::RemoveFile { path } => path,
+                    Action::CreateDir { .. } | Action::RemoveDir { .. } => continue,
+                };
+                if !rollback.created.iter().any(|d| path.starts_with(d)) {
+                    rollback.save_file(path)?;
+                }
+            }
+            for workspace_root in update_lockfiles
+                .iter()
+                .chain(smoke_test.iter().flat_map(|s| &s.workspace_root))
+            {
+                rollback.save_file(&workspace_root.join("Cargo.lock"))?;
+            }
+        }
+        let roll_back_checks = verify.is_some() && !update_lockfiles.is_empty()
+            || smoke_test.as_ref().is_some_and(|s| s.rollback);
+
+        for action in actions {
+            let start = Instant::now();
+            let phase = action.phase();
+            let result = match action {
+                Action::RemoveDir { path } if !dry_run && rollback.keep_removed_dirs => {
+                    rollback.remove_dir(&path)
+                }
+                action => action.apply(dry_run, stdout),
+            };
+            if let Err(err) = result {
+                rollback.restore(&mut stderr)?;
+                return Err(err);
+            }
+            timings.add(phase, start);
+        }
+        drop(staging);
+
+        if let (Some(report_file), Some(report)) = (report_file, report) {
+            append_report(&report_file, &report)?;
+        }
+
+        if dry_run {
+            if let Some(dry_run_warning) = dry_run_warning {
+                stderr.warn(dry_run_warning)?;
+            }
+            return Ok(());
+        }
+
+        let result = (|| -> anyhow::Result<()> {
+            for workspace_root in &update_lockfiles {
+                stderr.status("Updating", workspace_root.join("Cargo.lock").display())?;
+
+                cargo_metadata(
+                    Some(&workspace_root.join("Cargo.toml")),
+                    false,
+                    false,
+                    offline,
+                    workspace_root,
+                )?;
+            }
+            if let (Some(verify), Some(workspace_root)) = (verify, update_lockfiles.first()) {
+                verify.run(workspace_root, offline, &mut stderr)?;
+            }
+            if let Some(SmokeTest { dir, verbose, .. }) = smoke_test {
+                let start = Instant::now();
+                self::smoke_test(&dir, offline, verbose, &mut stderr)?;
+                timings.add("smoke test", start);
+            }
+            Ok(())
+        })();
+        if result.is_err() && roll_back_checks {
+            rollback.restore(&mut stderr)?;
+        }
+        result
+    }
+
+    /// Returns the actions without performing them, for the `plan` of another operation to
+    /// continue from. Warns as [`Plan::apply`] does on dry run.
+    ///
+    /// Also returns the workspace roots whose `Cargo.lock` should be updated. The verification
+    /// and the smoke test are not carried over.
+    fn into_actions(self) -> anyhow::Result<(Vec<Action>, Vec<PathBuf>)> {
+        let Self {
+            actions,
+            update_lockfiles,
+            dry_run,
+            stdout,
+            mut stderr,
+            dry_run_warning,
+            ..
+        } = self;
+
+        if let (true, Some(dry_run_warning)) = (dry_run || stdout, dry_run_warning) {
+            stderr.warn(dry_run_warning)?;
+        }
+        Ok((actions, update_lockfiles))
+    }
+}
+
+/// `cargo build` run by [`Plan::apply`] after the actions. See [`New::smoke_test`].
+#[derive(Debug)]
+struct SmokeTest {
+    /// The package to build.
+    dir: PathBuf,
+    /// The workspace the package is built in, whose `Cargo.lock` is restored on failure.
+    workspace_root: Option<PathBuf>,
+    verbose: bool,
+    rollback: bool,
+}
+
+/// A temporary directory the actions of a [`Plan`] copy files from. Removed when dropped.
+#[derive(Debug)]
+struct StagingDir(PathBuf);
+
+impl StagingDir {
+    fn new(prefix: &str) -> anyhow::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        let path = env::temp_dir().join(format!("{}-{}-{}", prefix, process::id(), nanos));
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create `{}`", path.display()))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = remove_dir_all::remove_dir_all(&self.0);
+    }
+}
+
+/// `cargo check` run by [`Plan::apply`] after the actions. See [`Include::verify`].
+#[derive(Debug)]
+struct Verify {
+    packages: Vec<String>,
+    stderr_redirection: Stdio,
+}
+
+impl Verify {
+    fn run(
+        self,
+        workspace_root: &Path,
+        offline: bool,
+        mut stderr: impl WriteColor,
+    ) -> anyhow::Result<()> {
+        let Self {
+            packages,
+            stderr_redirection,
+        } = self;
+
+        if packages.is_empty() {
+            return Ok(());
+        }
+        stderr.status(
+            "Verifying",
+            packages
+                .iter()
+                .format_with(", ", |p, f| f(&format_args!("`{}`", p))),
+        )?;
+
+        let cargo_exe = cargo_exe();
+        let manifest_path = workspace_root.join("Cargo.toml");
+
+        let mut args = Args::new()
+            .arg("check")
+            .option(Some(&manifest_path), "--manifest-path");
+        for package in &packages {
+            args = args.option(Some(package), "-p");
+        }
+        let args = args.flag(offline, "--offline");
+
+        let output = Command::new(&cargo_exe)
+            .args(&args)
+            .current_dir(workspace_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(stderr_redirection)
+            .output()
+            .with_context(|| format!("failed to execute `{}`", cargo_exe.to_string_lossy()))?;
+
+        if output.status.success() {
+            return Ok(());
+        }
+        let err = anyhow!(
+            "`{}{}` failed ({})",
+            shell_escape::escape(cargo_exe.to_string_lossy()),
+            args.0.iter().format_with("", |s, f| f(&format_args!(
+                " {}",
+                shell_escape::escape(s.to_string_lossy()),
+            ))),
+            output.status,
+        );
+        match str::from_utf8(&output.stderr).map(str::trim_end) {
+            Ok(cargo_stderr) if !cargo_stderr.is_empty() => {
+                Err(anyhow!("{}", cargo_stderr).context(err.to_string()))
+            }
+            _ => Err(err),
+        }
+    }
+}
+
+/// A file system change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum Action {
+    /// Writes `after` to `path`. `before` is the previous content, if the file existed.
+    WriteFile {
+        path: PathBuf,
+        before: Option<String>,
+        after: String,
+    },
+    CreateDir {
+        path: PathBuf,
+    },
+    CopyFile {
+        src: PathBuf,
+        dst: PathBuf,
+    },
+    RemoveDir {
+        path: PathBuf,
+    },
+    RemoveFile {
+        path: PathBuf,
+    },
+}
+
+impl Action {
+    /// The phase of [`Timings`] the action belongs to.
+    fn phase(&self) -> &'static str {
+        match self {
+            Self::WriteFile { .. } => "manifest write",
+            Self::CreateDir { .. } | Self::CopyFile { .. } => "file copy",
+            Self::RemoveDir { .. } | Self::RemoveFile { .. } => "removal",
+        }
+    }
+
+    fn apply(self, dry_run: bool, stdout: bool) -> anyhow::Result<()> {
+        match self {
+            Self::WriteFile { path, after, .. } => {
+                crate::fs::write_or_print(path, after, dry_run, stdout)
+            }
+            Self::CreateDir { path } => crate::fs::create_dir_all(path, dry_run),
+            Self::CopyFile { src, dst } => crate::fs::copy(src, dst, dry_run),
+            Self::RemoveDir { path } => crate::fs::remove_dir_all(path, dry_run),
+            Self::RemoveFile { path } => crate::fs::remove_file(path, dry_run),
+        }
+    }
+
+    /// Reads `path` as if `actions` had been performed. A copied file is read from its source.
+    fn read_planned(actions: &[Action], path: &Path) -> anyhow::Result<String> {
+        for action in actions.iter().rev() {
+            match action {
+                Self::WriteFile { path: p, after, .. } if p == path => return Ok(after.clone()),
+                Self::CopyFile { src, dst } if dst == path => {
+                    return crate::fs::read_to_string(src);
+                }
+                _ => {}
+            }
+        }
+        crate::fs::read_to_string(path)
+    }
+
+    /// Parses the TOML file at `path` as if `actions` had been performed.
+    fn read_toml_planned(actions: &[Action], path: &Path) -> anyhow::Result<toml_edit::Document> {
+        crate::fs::parse_toml_edit(&Self::read_planned(actions, path)?, path)
+    }
+
+    /// Adds a write of `path`, merging it into the previous write of the same file if any.
+    fn plan_write(
+        actions: &mut Vec<Action>,
+        path: PathBuf,
+        contents: String,
+    ) -> anyhow::Result<()> {
+        for action in actions.iter_mut().rev() {
+            if let Self::WriteFile { path: p, after, .. } = action {
+                if *p == path {
+                    *after = contents;
+                    return Ok(());
+                }
+            }
+        }
+        let copied = actions
+            .iter()
+            .any(|action| matches!(action, Self::CopyFile { dst, .. } if *dst == path));
+        let before = if copied || path.exists() {
+            Some(Self::read_planned(actions, &path)?)
+        } else {
+            None
+        };
+        actions.push(Self::WriteFile {
+            path,
+            before,
+            after: contents,
+        });
+        Ok(())
+    }
+
+    /// Describes the action as a JSON object with `kind` and `path`. For a write of a TOML file,
+    /// `edits` lists the changed keys with their values `before` and `after` the write. Arrays
+    /// are compared as a whole.
+    ///
+    /// ```
+    /// # use std::path::PathBuf;
+    /// let action = cargo_member::Action::WriteFile {
+    ///     path: PathBuf::from("Cargo.toml"),
+    ///     before: Some("[workspace]\nmembers = [\"a\"]\n".to_owned()),
+    ///     after: "[workspace]\nmembers = [\"a\", \"b\"]\n".to_owned(),
+    /// };
+    /// assert_eq!(
+    ///     action.to_json(),
+    ///     serde_json::json!({
+    ///         "kind": "write-file",
+    ///         "path": "Cargo.toml",
+    ///         "edits": [
+    ///             { "key": "workspace.members", "before": ["a"], "after": ["a", "b"] },
+    ///         ],
+    ///     }),
+    /// );
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::WriteFile {
+                path,
+                before,
+                after,
+            } => {
+                let parse = |s: &str| s.parse::<toml::Value>().ok();
+                let before = match before {
+                    Some(before) => parse(before),
+                    None => Some(toml::Value::Table(toml::value::Table::new())),
+                };
+                let edits = match (before, parse(after)) {
+                    (Some(before), Some(after)) => {
+                        let mut edits = vec![];
+                        diff_toml("", Some(&before), Some(&after), &mut edits);
+                        edits
+                    }
+                    _ => vec![],
+                };
+                serde_json::json!({ "kind": "write-file", "path": path, "edits": edits })
+            }
+            Self::CreateDir { path } => serde_json::json!({ "kind": "create-dir", "path": path }),
+            Self::CopyFile { src, dst } => {
+                serde_json::json!({ "kind": "copy-file", "path": dst, "src": src })
+            }
+            Self::RemoveDir { path } => serde_json::json!({ "kind": "remove-dir", "path": path }),
+            Self::RemoveFile { path } => serde_json::json!({ "kind": "remove-file", "path": path }),
+        }
+    }
+}
+
+/// Pushes the differences between `before` and `after` under `key` to `edits`. Tables are
+/// compared recursively and any other values as a whole.
+fn diff_toml(
+    key: &str,
+    before: Option<&toml::Value>,
+    after: Option<&toml::Value>,
+    edits: &mut Vec<serde_json::Value>,
+) {
+    if let (Some(toml::Value::Table(before)), Some(toml::Value::Table(after))) = (before, after) {
+        for k in before.keys().chain(after.keys().filter(|k| !before.contains_key(*k))) {
+            let key = if key.is_empty() {
+                k.clone()
+            } else {
+                format!("{}.{}", key, k)
+            };
+            diff_toml(&key, before.get(k), after.get(k), edits);
+        }
+    } else if before != after {
+        edits.push(serde_json::json!({ "key": key, "before": before, "after": after }));
+    }
+}
+
+/// Appends `actions` to `report_file` as JSON lines. See [`Plan::report_file`].
+fn append_report(report_file: &Path, actions: &[Action]) -> anyhow::Result<()> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut records = vec![];
+    for action in actions {
+        let mut record = serde_json::json!({ "timestamp": timestamp });
+        if let (Some(record), serde_json::Value::Object(action)) =
+            (record.as_object_mut(), serde_json::to_value(action)?)
+        {
+            record.extend(action);
+        }
+        serde_json::to_writer(&mut records, &record)?;
+        records.push(b'\n');
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(report_file)
+        .and_then(|mut file| file.write_all(&records))
+        .with_context(|| format!("failed to append to `{}`", report_file.display()))
+}
+
+fn ensure_absolute(path: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+    let path = path.as_ref();
+    ensure!(path.is_absolute(), "must be absolute: {}", path.display());
+    Ok(path.to_owned())
+}
+
+/// Fails if `name` is a device name such as `CON` or `aux.rs`, or ends with `.` or ` `, which
+/// Windows does not allow for files.
+fn ensure_not_reserved_on_windows(name: &OsStr) -> anyhow::Result<()> {
+    static RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let name = name.to_string_lossy();
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    ensure!(
+        !RESERVED.iter().any(|r| r.eq_ignore_ascii_case(stem)),
+        "`{}` is a reserved name on Windows",
+        name,
+    );
+    ensure!(
+        !name.ends_with(&['.', ' '][..]),
+        "`{}` is not a valid file name on Windows",
+        name,
+    );
+    Ok(())
+}
+
+fn is_workspace_root(dir: &Path) -> anyhow::Result<bool> {
+    let manifest_path = dir.join("Cargo.toml");
+    if !manifest_path.exists() {
+        return Ok(false);
+    }
+    let cargo_toml = crate::fs::read_toml_edit(manifest_path)?;
+    Ok(cargo_toml.as_table().contains_key("workspace"))
+}
+
+/// Returns the root of the workspace `manifest_path` belongs to, the way Cargo finds it.
+///
+/// A manifest with `[workspace]` is the root itself and `package.workspace` points to the root.
+/// Otherwise the root is the nearest ancestor with `[workspace]` that does not exclude the package,
+/// skipping any package manifests in between. Without such an ancestor, the package is a root on
+/// its own.
+fn find_workspace_root(manifest_path: &Path) -> anyhow::Result<PathBuf> {
+    let package_dir = manifest_path.parent().expect("should have a file name");
+    let cargo_toml = crate::fs::read_toml_edit(manifest_path)?;
+    if cargo_toml.as_table().contains_key("workspace") {
+        return Ok(package_dir.to_owned());
+    }
+    if let Some(root) = cargo_toml["package"]["workspace"].as_str() {
+        return Ok(normalize_path(&package_dir.join(root)));
+    }
+    for ancestor in package_dir.ancestors().skip(1) {
+        if !is_workspace_root(ancestor)? {
+            continue;
+        }
+        let root_toml = crate::fs::read_toml_edit(ancestor.join("Cargo.toml"))?;
+        let starts_with =
+            |entry: &String| package_dir.starts_with(normalize_path(&ancestor.join(entry)));
+        let excluded = workspace_entries(&root_toml, "exclude")
+            .iter()
+            .any(starts_with)
+            && !workspace_entries(&root_toml, "members")
+                .iter()
+                .any(starts_with);
+        if !excluded {
+            return Ok(ancestor.to_owned());
+        }
+        debug!(
+            "`{}` is excluded from `{}`",
+            package_dir.display(),
+            ancestor.join("Cargo.toml").display(),
+        );
+    }
+    Ok(package_dir.to_owned())
+}
+
+/// Removes the `[workspace]` table of `dir/Cargo.toml` and returns its `members` and `exclude` as
+/// paths, and whether `dir` is still a package. `dir/Cargo.toml` is removed if it is not.
+fn flatten_nested_workspace(
+    dir: &Path,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<(Vec<PathBuf>, Vec<PathBuf>, bool)> {
+    let manifest_path = dir.join("Cargo.toml");
+    let mut cargo_toml = crate::fs::parse_toml_edit(
+        &Action::read_planned(actions, &manifest_path)?,
+        &manifest_path,
+    )?;
+    let workspace = cargo_toml
+        .as_table_mut()
+        .remove("workspace")
+        .expect("should be checked");
+
+    let paths = |field: &str| -> anyhow::Result<Vec<PathBuf>> {
+        match &workspace[field] {
+            toml_edit::Item::None => Ok(vec![]),
+            item => item
+                .as_array()
+                .with_context(|| format!("`workspace.{}` must be an array", field))?
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(|s| dir.join(s))
+                        .with_context(|| format!("`workspace.{}` must be strings", field))
+                })
+                .collect(),
+        }
+    };
+    let (members, exclude) = (paths("members")?, paths("exclude")?);
+
+    if let Some(workspace) = workspace.as_table() {
+        for (key, _) in workspace.iter() {
+            if !["members", "exclude", "resolver"].contains(&key) {
+                stderr.warn(format!(
+                    "`workspace.{}` in `{}` is not merged",
+                    key,
+                    manifest_path.display(),
+                ))?;
+            }
+        }
+    }
+
+    stderr.status_with_color(
+        "Removing",
+        format!("`workspace` from `{}`", manifest_path.display()),
+        termcolor::Color::Red,
+    )?;
+    let is_package = cargo_toml.as_table().contains_key("package");
+    if is_package {
+        Action::plan_write(actions, manifest_path, cargo_toml.to_string())?;
+    } else {
+        for (key, _) in cargo_toml.iter() {
+            stderr.warn(format!(
+                "`{}` in `{}` is not merged",
+                key,
+                manifest_path.display(),
+            ))?;
+        }
+        stderr.status_with_color(
+            "Removing",
+            format!("`{}`", manifest_path.display()),
+            termcolor::Color::Red,
+        )?;
+        actions.push(Action::RemoveFile {
+            path: manifest_path,
+        });
+    }
+    Ok((members, exclude, is_package))
+}
+
+fn is_empty_workspace(cargo_toml: &str) -> anyhow::Result<bool> {
+    return {
+        let CargoToml { workspace, package } = toml::from_str(cargo_toml)?;
+        Ok(!workspace.members.is_empty() || package.is_some())
+    };
+
+    #[derive(Deserialize)]
+    struct CargoToml {
+        #[serde(default)]
+        workspace: CargoTomlWorkspace,
+        package: Option<toml::Value>,
+    }
+
+    #[derive(Deserialize, Default)]
+    struct CargoTomlWorkspace {
+        members: Vec<String>,
+    }
+}
+
+/// Finds a package that `path` would clobber or be nested in, or a `workspace.members` entry
+/// that already points to `path`.
+fn find_conflicting_member(path: &Path) -> anyhow::Result<Option<String>> {
+    let manifest_dirs = path
+        .ancestors()
+        .filter(|d| d.join("Cargo.toml").exists())
+        .collect::<Vec<_>>();
+    let (workspace_root, manifest_dirs) = match manifest_dirs.split_last() {
+        Some((workspace_root, manifest_dirs)) => (*workspace_root, manifest_dirs),
+        None => return Ok(None),
+    };
+
+    for dir in manifest_dirs {
+        let cargo_toml = crate::fs::read_toml_edit(dir.join("Cargo.toml"))?;
+        if let Some(name) = cargo_toml["package"]["name"].as_str() {
+            return Ok(Some(format!(
+                "the package `{}` at `{}`",
+                name,
+                dir.display()
+            )));
+        }
+    }
+
+    let cargo_toml = crate::fs::read_toml_edit(workspace_root.join("Cargo.toml"))?;
+    if let Some(members) = cargo_toml["workspace"]["members"].as_array() {
+        for member in members.iter().flat_map(toml_edit::Value::as_str) {
+            if workspace_root.join(member) == path {
+                return Ok(Some(format!(
+                    "{:?} in `workspace.members` of `{}`",
+                    member,
+                    workspace_root.join("Cargo.toml").display(),
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Rewrites the relative links in the Markdown files of `src` that point into the other
+/// `members`, so that they resolve from `moved_to`. The files are read from `src` and written
+/// under `moved_to`. URLs, absolute paths and links within the package are left alone.
+fn rewrite_markdown_links(
+    src: &Path,
+    moved_to: &Path,
+    members: &[PathBuf],
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    // Inline links and images (`[text](target)`) and reference definitions (`[id]: target`).
+    let link = regex::Regex::new(r"(?m)(\]\(\s*<?|^ {0,3}\[[^\]]+\]:\s*<?)([^\s)>]+)")
+        .expect("should be valid");
+
+    let relink = |target: &str, old_dir: &Path, new_dir: &Path| -> Option<String> {
+        let (path, fragment) = target.split_at(target.find('#').unwrap_or(target.len()));
+        if path.is_empty() || path.starts_with('/') || target.contains(':') {
+            return None;
+        }
+        let resolved = normalize_path(&old_dir.join(path));
+        if resolved.starts_with(src) || !members.iter().any(|m| m != src && resolved.starts_with(m))
+        {
+            return None;
+        }
+        let relinked = relative_path(new_dir, &resolved)
+            .iter()
+            .map(|s| s.to_string_lossy())
+            .join("/")
+            + fragment;
+        Some(relinked).filter(|relinked| relinked != target)
+    };
+
+    // Ignores the same files as `Cp` so that no ignored file is written under `moved_to`.
+    for entry in WalkBuilder::new(src)
+        .require_git(false)
+        .hidden(false)
+        .build()
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                stderr.warn(err)?;
+                continue;
+            }
+        };
+        let path = entry.path();
+        if path.extension() != Some("md".as_ref()) || !path.is_file() {
+            continue;
+        }
+        let moved_path = moved_to.join(path.strip_prefix(src)?);
+        let (old_dir, new_dir) = (
+            path.parent().expect("should be under `src`"),
+            moved_path.parent().expect("should be under `moved_to`"),
+        );
+
+        let content = crate::fs::read_to_string(path)?;
+        let mut relinked = vec![];
+        let updated = link.replace_all(&content, |caps: &regex::Captures<'_>| {
+            match relink(&caps[2], old_dir, new_dir) {
+                Some(target) => {
+                    relinked.push((caps[2].to_owned(), target.clone()));
+                    format!("{}{}", &caps[1], target)
+                }
+                None => caps[0].to_owned(),
+            }
+        });
+        for (old, new) in &relinked {
+            stderr.status(
+                "Updating",
+                format!("link {:?} to {:?} in `{}`", old, new, moved_path.display()),
+            )?;
+        }
+        if !relinked.is_empty() {
+            Action::plan_write(actions, moved_path, updated.into_owned())?;
+        }
+    }
+    Ok(())
+}
+
+/// Finds the `#[path = ".."]` attributes in the `.rs` files under `src` that point outside `src`,
+/// and reports (or, with `fix`, rewrites) the ones that no longer resolve once the files are at
+/// `moved_to`.
+///
+/// This is best-effort: the paths are resolved relative to the directory of the file, which is
+/// wrong for attributes on modules nested in inline `mod` blocks.
+fn rewrite_path_attrs(
+    src: &Path,
+    moved_to: &Path,
+    renames: &[(PathBuf, PathBuf)],
+    fix: bool,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let path_attr =
+        regex::Regex::new(r#"(#\[\s*path\s*=\s*")([^"]+)(")"#).expect("should be valid");
+
+    for entry in WalkBuilder::new(src)
+        .require_git(false)
+        .hidden(false)
+        .build()
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                stderr.warn(err)?;
+                continue;
+            }
+        };
+        let path = entry.path();
+        if path.extension() != Some("rs".as_ref()) || !path.is_file() {
+            continue;
+        }
+        let moved_path = moved_to.join(renamed(path.strip_prefix(src)?, renames));
+        let (old_dir, new_dir) = (
+            path.parent().expect("should be under `src`"),
+            moved_path.parent().expect("should be under `moved_to`"),
+        );
+
+        let content = crate::fs::read_to_string(path)?;
+        let mut findings = vec![];
+        let updated = path_attr.replace_all(&content, |caps: &regex::Captures<'_>| {
+            let target = &caps[2];
+            let resolved = normalize_path(&old_dir.join(target));
+            if Path::new(target).is_absolute() || resolved.starts_with(src) {
+                return caps[0].to_owned();
+            }
+            let line = content[..caps.get(0).expect("should exist").start()]
+                .matches('\n')
+                .count()
+                + 1;
+            let new_target = relative_path(new_dir, &resolved)
+                .iter()
+                .map(|s| s.to_string_lossy())
+                .join("/");
+            findings.push((line, target.to_owned(), new_target.clone()));
+            if fix {
+                format!("{}{}{}", &caps[1], new_target, &caps[3])
+            } else {
+                caps[0].to_owned()
+            }
+        });
+
+        let mut modified = false;
+        for (line, old, new) in findings {
+            let location = format!("{}:{}", moved_path.display(), line);
+            if old == new {
+                stderr.note(format!(
+                    "`#[path = {:?}]` in `{}` points outside the package",
+                    old, location,
+                ))?;
+            } else if fix {
+                stderr.status(
+                    "Updating",
+                    format!("`#[path = {:?}]` to {:?} in `{}`", old, new, location),
+                )?;
+                modified = true;
+            } else {
+                stderr.warn(format!(
+                    "`#[path = {:?}]` in `{}` no longer resolves. enable `--fix-path-attrs` to \
+                     rewrite it to {:?}",
+                    old, location, new,
+                ))?;
+            }
+        }
+        if modified {
+            Action::plan_write(actions, moved_path, updated.into_owned())?;
+        }
+    }
+    Ok(())
+}
+
+/// Renames the `lib` and `bin` targets of `cargo_toml` named after the package from `old_name` to
+/// `new_name`. A `bin` target whose source file is inferred from its name gets an explicit `path`.
+///
+/// With `rename_paths`, the source files named after the package are renamed instead, including
+/// the ones of the automatically discovered `bin` targets. The renames are returned as
+/// `(from, to)` relative to `package_dir`. `src/main.rs` and `src/lib.rs` are left alone.
+fn rename_targets(
+    cargo_toml: &mut toml_edit::Document,
+    package_dir: &Path,
+    old_name: &str,
+    new_name: &str,
+    rename_paths: bool,
+    manifest_path: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<Vec<(PathBuf, PathBuf)>> {
+    // `src/bin/<old>.rs` to `src/bin/<new>.rs`, and `src/bin/<old>/main.rs` to
+    // `src/bin/<new>/main.rs`. The second is the renamed directory.
+    let rename_file = |path: &str, old: &str, new: &str| -> Option<(String, PathBuf, PathBuf)> {
+        let (dir, file_name) = path.rsplit_once('/').unwrap_or(("", path));
+        if file_name == format!("{}.rs", old) {
+            let new_path = format!("{}/{}.rs", dir, new)
+                .trim_start_matches('/')
+                .to_owned();
+            return Some((
+                new_path.clone(),
+                PathBuf::from(path),
+                PathBuf::from(new_path),
+            ));
+        }
+        let (parent, dir_name) = dir.rsplit_once('/').unwrap_or(("", dir));
+        if file_name == "main.rs" && dir_name == old {
+            let new_dir = format!("{}/{}", parent, new)
+                .trim_start_matches('/')
+                .to_owned();
+            let new_path = format!("{}/main.rs", new_dir);
+            return Some((new_path, PathBuf::from(dir), PathBuf::from(new_dir)));
+        }
+        None
+    };
+    let mut renames = vec![];
+    let mut rename =
+        |from: PathBuf, to: PathBuf, mut stderr: &mut dyn WriteColor| -> io::Result<()> {
+            stderr.status(
+                "Renaming",
+                format!(
+                    "`{}` to `{}`",
+                    package_dir.join(&from).display(),
+                    manifest_path.with_file_name(&to).display(),
+                ),
+            )?;
+            renames.push((from, to));
+            Ok(())
+        };
+
+    let (old_lib_name, new_lib_name) = (old_name.replace('-', "_"), new_name.replace('-', "_"));
+    if let Some(lib) = cargo_toml["lib"].as_table_mut() {
+        if let Some(name) = lib["name"].as_str().map(ToOwned::to_owned) {
+            if name == old_name || name == old_lib_name {
+                stderr.status(
+                    "Changing",
+                    format!(
+                        "`lib.name` in `{}` from {:?} to {:?}",
+                        manifest_path.display(),
+                        name,
+                        new_lib_name,
+                    ),
+                )?;
+                lib["name"] = toml_edit::value(&*new_lib_name);
+            }
+        }
+        if let (true, Some(path)) = (rename_paths, lib["path"].as_str().map(ToOwned::to_owned)) {
+            let renamed = rename_file(&path, old_name, new_name)
+                .or_else(|| rename_file(&path, &old_lib_name, &new_lib_name));
+            if let Some((new_path, from, to)) = renamed {
+                lib["path"] = toml_edit::value(new_path);
+                rename(from, to, &mut stderr)?;
+            }
+        }
+    }
+
+    let mut explicit_paths = vec![];
+    for bin in cargo_toml["bin"]
+        .as_array_of_tables_mut()
+        .into_iter()
+        .flat_map(|bins| bins.iter_mut())
+    {
+        let path = bin["path"].as_str().map(ToOwned::to_owned);
+        if bin["name"].as_str() == Some(old_name) {
+            stderr.status(
+                "Changing",
+                format!(
+                    "`bin.name` in `{}` from {:?} to {:?}",
+                    manifest_path.display(),
+                    old_name,
+                    new_name,
+                ),
+            )?;
+            bin["name"] = toml_edit::value(new_name);
+            if path.is_none() {
+                let inferred = [
+                    format!("src/bin/{}.rs", old_name),
+                    format!("src/bin/{}/main.rs", old_name),
+                ]
+                .iter()
+                .find(|p| package_dir.join(p).exists())
+                .cloned();
+                if let Some(inferred) = inferred {
+                    if rename_paths {
+                        let (_, from, to) = rename_file(&inferred, old_name, new_name)
+                            .expect("should be named after the package");
+                        rename(from, to, &mut stderr)?;
+                    } else {
+                        stderr.status(
+                            "Adding",
+                            format!(
+                                "`bin.path = {:?}` to `{}`",
+                                inferred,
+                                manifest_path.display(),
+                            ),
+                        )?;
+                        bin["path"] = toml_edit::value(&*inferred);
+                    }
+                    explicit_paths.push(inferred);
+                }
+            }
+        }
+        if let Some(path) = path {
+            if rename_paths {
+                if let Some((new_path, from, to)) = rename_file(&path, old_name, new_name) {
+                    bin["path"] = toml_edit::value(new_path);
+                    rename(from, to, &mut stderr)?;
+                }
+            }
+            explicit_paths.push(path);
+        }
+    }
+
+    if rename_paths && cargo_toml["package"]["autobins"].as_bool() != Some(false) {
+        for inferred in &[
+            format!("src/bin/{}.rs", old_name),
+            format!("src/bin/{}/main.rs", old_name),
+        ] {
+            if package_dir.join(inferred).exists() && !explicit_paths.contains(inferred) {
+                let (_, from, to) = rename_file(inferred, old_name, new_name)
+                    .expect("should be named after the package");
+                rename(from, to, &mut stderr)?;
+            }
+        }
+    }
+    Ok(renames)
+}
+
+/// Applies the first of `renames` that `path` is under.
+fn renamed(path: &Path, renames: &[(PathBuf, PathBuf)]) -> PathBuf {
+    renames
+        .iter()
+        .find_map(|(from, to)| match path.strip_prefix(from).ok()? {
+            rest if rest == Path::new("") => Some(to.clone()),
+            rest => Some(to.join(rest)),
+        })
+        .unwrap_or_else(|| path.to_owned())
+}
+
+/// Returns a relative path from `from` to `to`. Both must be absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(c1, c2)| c1 == c2)
+        .count();
+    from.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(to.components().skip(common))
+        .collect()
+}
+
+/// Returns the name of the package that the dependency `key = item` refers to.
+fn dependency_name<'a>(key: &'a str, item: &'a toml_edit::Item) -> &'a str {
+    item["package"].as_str().unwrap_or(key)
+}
+
+/// Returns the keys of the dependencies on `name` in `table`.
+fn dependency_keys(table: &toml_edit::Table, name: &str) -> Vec<String> {
+    table
+        .iter()
+        .filter(|&(key, item)| dependency_name(key, item) == name)
+        .map(|(key, _)| key.to_owned())
+        .collect()
+}
+
+/// Rewrites `path` of a dependency written in `from/Cargo.toml` so that it can be written in
+/// `to/Cargo.toml`.
+fn rebase_path_dependency(
+    item: &mut toml_edit::Item,
+    from: &Path,
+    to: &Path,
+) -> anyhow::Result<()> {
+    let path = match item["path"].as_str() {
+        Some(path) => relative_path(to, &normalize_path(&from.join(path))),
+        None => return Ok(()),
+    };
+    let path = path
+        .to_str()
+        .with_context(|| format!("{:?} is not valid UTF-8", path))?;
+    set_dependency_field(item, "path", path);
+    Ok(())
+}
+
+/// Sets `key` of the dependency `item` to `value`.
+///
+/// An existing entry is replaced in place, keeping its position and decor. A new entry is
+/// appended. The other entries are left as they are.
+fn set_dependency_field(item: &mut toml_edit::Item, key: &str, value: &str) {
+    if let Some(table) = item.as_table_mut() {
+        match table[key].as_value_mut() {
+            Some(old) => *old = redecorated(Value::from(value), old.decor(), (" ", "")),
+            None => table[key] = toml_edit::value(value),
+        }
+    } else if let Some(table) = item.as_inline_table_mut() {
+        if let Some(old) = table.get_mut(key) {
+            *old = redecorated(Value::from(value), old.decor(), (" ", " "));
+            return;
+        }
+        // `{ a = 1 }` → `{ a = 1, b = 2 }`: the trailing decor moves to the new last entry.
+        let mut suffix = " ".to_owned();
+        if let Some(last) = table.iter_mut().last().map(|(_, last)| last) {
+            suffix = last.decor().suffix().unwrap_or(" ").to_owned();
+            let prefix = last.decor().prefix().unwrap_or(" ").to_owned();
+            *last = last.clone().decorated(&prefix, "");
+        }
+        table.insert(key, Value::from(value).decorated(" ", &suffix));
+    }
+}
+
+/// Removes `key` from the dependency `item`, keeping the position and the decor of the other
+/// entries.
+fn remove_dependency_field(item: &mut toml_edit::Item, key: &str) -> bool {
+    if let Some(table) = item.as_table_mut() {
+        return table.remove(key).is_some();
+    }
+    let table = match item.as_inline_table_mut() {
+        Some(table) => table,
+        None => return false,
+    };
+    let was_last = table.iter().last().map(|(k, _)| k == key) == Some(true);
+    let removed = match table.remove(key) {
+        Some(removed) => removed,
+        None => return false,
+    };
+    if was_last {
+        if let Some(last) = table.iter_mut().last().map(|(_, last)| last) {
+            let prefix = last.decor().prefix().unwrap_or(" ").to_owned();
+            let suffix = removed.decor().suffix().unwrap_or(" ");
+            *last = last.clone().decorated(&prefix, suffix);
         }
-        Ok(())
     }
+    true
 }
 
-#[derive(Debug)]
-pub struct Mv<W> {
-    workspace_root: anyhow::Result<PathBuf>,
-    src: anyhow::Result<PathBuf>,
-    dst: anyhow::Result<PathBuf>,
-    dry_run: bool,
-    no_rename: bool,
-    stderr: W,
+/// Returns `value` decorated with `decor`, or with `default` if `decor` is unset.
+fn redecorated(value: Value, decor: &toml_edit::Decor, default: (&str, &str)) -> Value {
+    let prefix = decor.prefix().unwrap_or(default.0).to_owned();
+    let suffix = decor.suffix().unwrap_or(default.1).to_owned();
+    value.decorated(&prefix, &suffix)
 }
 
-impl Mv<NoColor<Sink>> {
-    pub fn new(workspace_root: &Path, src: &Path, dst: &Path) -> Self {
-        Self {
-            workspace_root: ensure_absolute(workspace_root),
-            src: ensure_absolute(src),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
-            stderr: NoColor::new(io::sink()),
+/// Converts a dependency written in `dir/Cargo.toml` into a comparable form.
+fn normalize_dependency(
+    item: &toml_edit::Item,
+    dir: &Path,
+) -> anyhow::Result<BTreeMap<String, toml::Value>> {
+    let parse = |value: &Value| -> anyhow::Result<toml::Value> {
+        let mut table = toml::from_str::<toml::value::Table>(&format!("v = {}", value))?;
+        Ok(table.remove("v").expect("should be present"))
+    };
+
+    let mut ret = BTreeMap::new();
+    if let Some(version) = item.as_str() {
+        ret.insert("version".to_owned(), version.into());
+    } else if let Some(table) = item.as_table() {
+        for (key, item) in table.iter() {
+            if let Some(value) = item.as_value() {
+                ret.insert(key.to_owned(), parse(value)?);
+            }
+        }
+    } else if let Some(table) = item.as_inline_table() {
+        for (key, value) in table.iter() {
+            ret.insert(key.to_owned(), parse(value)?);
         }
     }
+    if let Some(toml::Value::String(path)) = ret.get_mut("path") {
+        *path = normalize_path(&dir.join(&*path))
+            .to_str()
+            .with_context(|| format!("{:?} is not valid UTF-8", path))?
+            .to_owned();
+    }
+    Ok(ret)
+}
 
-    pub fn from_metadata(metadata: &Metadata, src: &str, dst: &Path) -> Self {
-        Self {
-            workspace_root: Ok(metadata.workspace_root.clone().into_std_path_buf()),
-            src: metadata.query_for_member(Some(src)).map(|member| {
+/// Resolves `.` and `..` in `path` without accessing the file system.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut ret = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                ret.pop();
+            }
+            component => ret.push(component),
+        }
+    }
+    ret
+}
+
+/// Resolves `specs` to the directories of the members. Specs that cannot be resolved are returned
+/// as the second element.
+fn resolve_specs<Ss: IntoIterator<Item = S>, S: AsRef<str>>(
+    metadata: &Metadata,
+    specs: Ss,
+) -> (Vec<PathBuf>, Vec<anyhow::Error>) {
+    let (mut member_dirs, mut unresolved_specs) = (vec![], vec![]);
+    for spec in specs {
+        match metadata.query_for_member(Some(spec.as_ref())) {
+            Ok(member) => member_dirs.push(
                 member
                     .manifest_path
-                    .clone().into_std_path_buf()
+                    .clone()
+                    .into_std_path_buf()
                     .parent()
                     .expect(r#"`manifest_path` should end with "Cargo.toml""#)
-                    .to_owned()
-            }),
-            dst: ensure_absolute(dst),
-            dry_run: false,
-            no_rename: false,
-            stderr: NoColor::new(io::sink()),
+                    .to_owned(),
+            ),
+            Err(err) => unresolved_specs.push(err),
         }
     }
+    (member_dirs, unresolved_specs)
 }
 
-impl<W: WriteColor> Mv<W> {
-    pub fn dry_run(self, dry_run: bool) -> Self {
-        Self { dry_run, ..self }
-    }
+/// Returns the name, directory and dependency directories of each workspace member, using the
+/// resolve graph. Only dependencies on other members are listed.
+fn workspace_dependencies(metadata: &Metadata) -> Vec<(String, PathBuf, Vec<PathBuf>)> {
+    let dir = |package: &Package| -> PathBuf {
+        package
+            .manifest_path
+            .clone()
+            .into_std_path_buf()
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+            .to_owned()
+    };
 
-    pub fn no_rename(self, no_rename: bool) -> Self {
-        Self { no_rename, ..self }
-    }
+    let resolve = match &metadata.resolve {
+        Some(resolve) => resolve,
+        None => return vec![],
+    };
+    resolve
+        .nodes
+        .iter()
+        .filter(|node| metadata.workspace_members.contains(&node.id))
+        .map(|node| {
+            let deps = node
+                .deps
+                .iter()
+                .filter(|dep| metadata.workspace_members.contains(&dep.pkg))
+                .map(|dep| dir(&metadata[&dep.pkg]))
+                .collect();
+            let package = &metadata[&node.id];
+            (package.name.clone(), dir(package), deps)
+        })
+        .collect()
+}
 
-    pub fn stderr<W2: WriteColor>(self, stderr: W2) -> Mv<W2> {
-        Mv {
-            stderr,
-            workspace_root: self.workspace_root,
-            src: self.src,
-            dst: self.dst,
-            dry_run: self.dry_run,
-            no_rename: self.no_rename,
+/// Returns the directories of the packages that depend on the package at `dir` according to the
+/// resolve graph, or `None` if `metadata` does not have one.
+fn resolved_dependents(metadata: &Metadata, dir: &Path) -> Option<Vec<PathBuf>> {
+    let resolve = metadata.resolve.as_ref()?;
+    let package_dir = |package: &Package| -> PathBuf {
+        package
+            .manifest_path
+            .clone()
+            .into_std_path_buf()
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+            .to_owned()
+    };
+    let id = match metadata.packages.iter().find(|p| package_dir(p) == dir) {
+        Some(package) => &package.id,
+        None => return Some(vec![]),
+    };
+    let dependents = resolve
+        .nodes
+        .iter()
+        .filter(|node| node.deps.iter().any(|dep| dep.pkg == *id))
+        .map(|node| package_dir(&metadata[&node.id]))
+        .collect();
+    Some(dependents)
+}
+
+/// Returns the directories of the workspace members the package at `dir` transitively depends
+/// on, or the ones that transitively depend on it if `reverse`. `None` if `metadata` does not have
+/// the resolve graph.
+fn resolved_member_closure(metadata: &Metadata, dir: &Path, reverse: bool) -> Option<Vec<PathBuf>> {
+    let resolve = metadata.resolve.as_ref()?;
+    let package_dir = |package: &Package| -> PathBuf {
+        package
+            .manifest_path
+            .clone()
+            .into_std_path_buf()
+            .parent()
+            .expect(r#"`manifest_path` should end with "Cargo.toml""#)
+            .to_owned()
+    };
+    let id = match metadata.packages.iter().find(|p| package_dir(p) == dir) {
+        Some(package) => &package.id,
+        None => return Some(vec![]),
+    };
+    let mut visited = vec![id];
+    let mut stack = vec![id];
+    while let Some(id) = stack.pop() {
+        let next = if reverse {
+            resolve
+                .nodes
+                .iter()
+                .filter(|node| node.deps.iter().any(|dep| dep.pkg == *id))
+                .map(|node| &node.id)
+                .collect::<Vec<_>>()
+        } else {
+            resolve
+                .nodes
+                .iter()
+                .filter(|node| node.id == *id)
+                .flat_map(|node| node.deps.iter().map(|dep| &dep.pkg))
+                .collect()
+        };
+        for next in next {
+            if metadata.workspace_members.contains(next) && !visited.contains(&next) {
+                visited.push(next);
+                stack.push(next);
+            }
         }
     }
+    Some(
+        visited[1..]
+            .iter()
+            .map(|&id| package_dir(&metadata[id]))
+            .collect(),
+    )
+}
 
-    pub fn exec(self) -> anyhow::Result<()> {
-        let Self {
-            mut stderr,
-            workspace_root,
-            src,
-            dst,
-            dry_run,
-            no_rename,
-        } = self;
-
-        let (workspace_root, src, dst) = (workspace_root?, src?, dst?);
-
-        Cp::new(&src, &dst)
-            .dry_run(dry_run)
-            .no_rename(no_rename)
-            .stderr(&mut stderr)
-            .exec()?;
-
-        Rm::new(&workspace_root, &[src])
-            .dry_run(dry_run)
-            .stderr(stderr)
-            .exec()
+/// Warns about each path dependency in the manifests in `dirs` that points to `src`.
+fn warn_path_dependents(
+    dirs: &[PathBuf],
+    src: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for dir in dirs {
+        let manifest_path = dir.join("Cargo.toml");
+        if dir == src || !manifest_path.exists() {
+            continue;
+        }
+        let cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
+        for (key, value) in dependencies(&cargo_toml) {
+            match value["path"].as_str() {
+                Some(path) if normalize_path(&dir.join(path)) == src => {
+                    stderr.warn(format!(
+                        "`{}` depends on `{}` at {:?}, which will not point to the package \
+                         after the move",
+                        manifest_path.display(),
+                        key,
+                        path,
+                    ))?;
+                }
+                _ => {}
+            }
+        }
     }
+    Ok(())
 }
 
-fn ensure_absolute(path: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
-    let path = path.as_ref();
-    ensure!(path.is_absolute(), "must be absolute: {}", path.display());
-    Ok(path.to_owned())
+/// Warns, or fails if `strict && !force`, when any of `paths` is a dependency of a member that is
+/// not in `paths`.
+fn warn_dependents(
+    workspace_dependencies: &[(String, PathBuf, Vec<PathBuf>)],
+    paths: &[PathBuf],
+    strict: bool,
+    force: bool,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    for path in paths {
+        let dependents = workspace_dependencies
+            .iter()
+            .filter(|(_, dir, deps)| !paths.contains(dir) && deps.contains(path))
+            .map(|(name, _, _)| format!("`{}`", name))
+            .collect::<Vec<_>>();
+        if dependents.is_empty() {
+            continue;
+        }
+        let msg = format!(
+            "`{}` is a dependency of {}, which will remain in the workspace",
+            path.display(),
+            dependents.join(", "),
+        );
+        if strict && !force {
+            bail!("{}. enable `--force` to proceed anyway", msg);
+        }
+        stderr.warn(msg)?;
+    }
+    Ok(())
 }
 
-fn is_empty_workspace(manifest_path: &Path) -> anyhow::Result<bool> {
-    return {
-        let CargoToml { workspace, package } = crate::fs::read_toml(manifest_path)?;
-        Ok(!workspace.members.is_empty() || package.is_some())
-    };
-
-    #[derive(Deserialize)]
-    struct CargoToml {
-        #[serde(default)]
-        workspace: CargoTomlWorkspace,
-        package: Option<toml::Value>,
+/// Returns `build.target-dir` that cargo would use in `dir`, looking at `$CARGO_TARGET_DIR`,
+/// `$CARGO_BUILD_TARGET_DIR`, `.cargo/config{,.toml}` in `dir` and its ancestors, and
+/// `$CARGO_HOME/config{,.toml}`.
+fn cargo_config_target_dir(dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    for var in &["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"] {
+        if let Some(target_dir) = env::var_os(var) {
+            debug!("`build.target-dir` from `${}`: {:?}", var, target_dir);
+            return Ok(Some(env::current_dir()?.join(target_dir)));
+        }
     }
 
-    #[derive(Deserialize, Default)]
-    struct CargoTomlWorkspace {
-        members: Vec<String>,
+    let config_dirs = dir
+        .ancestors()
+        .map(|d| d.join(".cargo"))
+        .chain(env::var_os("CARGO_HOME").map(PathBuf::from));
+    for config_dir in config_dirs {
+        // cargo prefers `config` if both exist
+        for file_name in &["config", "config.toml"] {
+            let config_path = config_dir.join(file_name);
+            if !config_path.is_file() {
+                continue;
+            }
+            let config = crate::fs::read_to_string(&config_path)?
+                .parse::<toml::Value>()
+                .with_context(|| {
+                    format!("failed to parse the TOML file at {}", config_path.display())
+                })?;
+            if let Some(target_dir) = config
+                .get("build")
+                .and_then(|b| b.get("target-dir"))
+                .and_then(toml::Value::as_str)
+            {
+                debug!(
+                    "`build.target-dir` from `{}`: {:?}",
+                    config_path.display(),
+                    target_dir,
+                );
+                // relative to the parent of `.cargo`
+                let base = config_dir.parent().unwrap_or(&config_dir);
+                return Ok(Some(base.join(target_dir)));
+            }
+            break;
+        }
     }
+    Ok(None)
+}
+
+/// Returns `$CARGO`, or `cargo` (looked up in `$PATH`) if it is not set.
+pub(crate) fn cargo_exe() -> OsString {
+    env::var_os("CARGO").unwrap_or_else(|| {
+        debug!("`$CARGO` is not present. using `cargo` in `$PATH`");
+        "cargo".into()
+    })
 }
 
-fn cargo_metadata(
+/// Runs `cargo metadata` in `cwd`, with `--frozen`, `--locked` and `--offline` as specified.
+///
+/// The error message is `cargo`'s stderr if `cargo metadata` fails.
+pub fn cargo_metadata(
     manifest_path: Option<&Path>,
     frozen: bool,
     locked: bool,
@@ -1102,15 +9469,40 @@ fn cargo_metadata(
     Ok(metadata)
 }
 
-fn modify_members<'a>(
+fn cargo_metadata_no_deps(
+    manifest_path: Option<&Path>,
+    offline: bool,
+    cwd: &Path,
+) -> anyhow::Result<Metadata> {
+    let mut cmd = MetadataCommand::new();
+    if let Some(manifest_path) = manifest_path {
+        cmd.manifest_path(manifest_path);
+    }
+    if offline {
+        cmd.other_options(vec!["--offline".to_owned()]);
+    }
+    cmd.no_deps()
+        .current_dir(cwd)
+        .exec()
+        .map_err(|err| match err {
+            cargo_metadata::Error::CargoMetadata { stderr } => anyhow!("{}", stderr.trim_end()),
+            err => err.into(),
+        })
+}
+
+/// Plans the edit of `workspace.members` and `workspace.exclude`, on top of the edits already in
+/// `actions`. The added entries and the modified arrays are written as specified by `layout`.
+#[allow(clippy::too_many_arguments)]
+fn plan_members<'a>(
     possibly_empty_workspace_root: &Path,
     add_to_workspace_members: &[&'a Path],
     add_to_workspace_exclude: &[&'a Path],
     rm_from_workspace_members: &[&'a Path],
     rm_from_workspace_exclude: &[&'a Path],
-    dry_run: bool,
+    layout: MemberLayout<'_>,
+    actions: &mut Vec<Action>,
     mut stderr: impl WriteColor,
-) -> anyhow::Result<bool> {
+) -> anyhow::Result<()> {
     if [
         add_to_workspace_members,
         add_to_workspace_exclude,
@@ -1129,8 +9521,8 @@ fn modify_members<'a>(
     }
 
     let manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
-    let mut cargo_toml = crate::fs::read_toml_edit(&manifest_path)?;
-    let orig = cargo_toml.to_string();
+    let orig = Action::read_planned(actions, &manifest_path)?;
+    let mut cargo_toml = crate::fs::parse_toml_edit(&orig, &manifest_path)?;
 
     for (field, add, rm) in &[
         (
@@ -1144,17 +9536,13 @@ fn modify_members<'a>(
             rm_from_workspace_exclude,
         ),
     ] {
-        let relative_to_root = |path: &'a Path| -> _ {
-            let path = path
-                .strip_prefix(possibly_empty_workspace_root)
-                .unwrap_or(path);
-            path.to_str()
-                .with_context(|| format!("{:?} is not valid UTF-8 path", path))
-        };
+        let relative_to_root = |path: &'a Path| member_entry(possibly_empty_workspace_root, path);
 
+        // Entries may be absolute or unnormalized (e.g. `./a/`).
         let same_paths = |value: &toml_edit::Value, target: &str| -> _ {
-            value.as_str().map_or(false, |s| {
-                possibly_empty_workspace_root.join(s) == possibly_empty_workspace_root.join(target)
+            value.as_str().is_some_and(|s| {
+                normalize_path(&possibly_empty_workspace_root.join(s))
+                    == normalize_path(&possibly_empty_workspace_root.join(target))
             })
         };
 
@@ -1163,36 +9551,127 @@ fn modify_members<'a>(
             .or_insert(toml_edit::value(toml_edit::Array::default()))
             .as_array_mut()
             .with_context(|| format!("`workspace.{}` must be an array", field))?;
+        let mut modified = false;
+        // Where to insert the entries added to `workspace.members`. `None` means appending.
+        let mut index = match layout
+            .position
+            .filter(|_| *field == "members" && !add.is_empty())
+        {
+            None | Some(MemberPosition::Last) => None,
+            Some(MemberPosition::First) => Some(0),
+            Some(MemberPosition::Before(entry)) | Some(MemberPosition::After(entry)) => {
+                let i = array
+                    .iter()
+                    .position(|m| same_paths(m, entry))
+                    .with_context(|| format!("{:?} is not in `workspace.members`", entry))?;
+                match layout.position {
+                    Some(MemberPosition::After(_)) => Some(i + 1),
+                    _ => Some(i),
+                }
+            }
+        };
+        let comment = layout.comment.filter(|_| *field == "members");
         for add in *add {
-            let add = relative_to_root(add)?;
-            if array.iter().all(|m| !same_paths(m, add)) {
-                if !dry_run {
-                    // push every new workspace on a new line and add a comma at the end of the name.
-                    // example: workspace = [
-                    //  "b",
-                    //  "c",
-                    // ]
-                    // this reduce the chance of git merge conflict
-                    // should we use 4 spaces to format?
-                    array
-                        .push_formatted(Value::from(add).decorated("\n    ", ""));
-                    // we push to the end, so these two option only affect the element we pushed
-                    array.set_trailing_comma(true);
-                    array.set_trailing("\n");
-                }
-                stderr.status("Adding", format!("{:?} to `workspace.{}`", add, field))?;
+            let add = layout.entry(possibly_empty_workspace_root, add)?;
+            if array.iter().all(|m| !same_paths(m, &add)) {
+                // a comment after an element is written after its comma, that is, in the prefix of
+                // the next element or in the trailing of the array.
+                let comment_suffix = comment.map(|c| format!(" # {}", c)).unwrap_or_default();
+                match index.filter(|&i| i < array.len()) {
+                    Some(i) => {
+                        // the new element takes over the prefix of the element it is inserted
+                        // before, which may hold the comment after the previous element.
+                        let next = array.get(i).expect("should exist").clone();
+                        let prefix = next
+                            .decor()
+                            .prefix()
+                            .filter(|p| p.contains('\n'))
+                            .unwrap_or("\n    ")
+                            .to_owned();
+                        let suffix = next.decor().suffix().unwrap_or("").to_owned();
+                        array.replace_formatted(
+                            i,
+                            next.decorated(&format!("{}\n    ", comment_suffix), &suffix),
+                        );
+                        array.insert_formatted(i, Value::from(&*add).decorated(&prefix, ""));
+                        array.set_trailing_comma(true);
+                        if !array.trailing().contains('\n') {
+                            array.set_trailing("\n");
+                        }
+                    }
+                    None => {
+                        // push every new workspace on a new line and add a comma at the end of the name.
+                        // example: workspace = [
+                        //  "b",
+                        //  "c",
+                        // ]
+                        // this reduce the chance of git merge conflict
+                        // should we use 4 spaces to format?
+                        // a comment after the last element is kept after it, in front of the new one.
+                        let carried = match array.trailing().trim_end_matches('\n') {
+                            trailing if trailing.contains('#') => trailing.to_owned(),
+                            _ => "".to_owned(),
+                        };
+                        array.push_formatted(
+                            Value::from(&*add).decorated(&format!("{}\n    ", carried), ""),
+                        );
+                        // we push to the end, so these two option only affect the element we pushed
+                        array.set_trailing_comma(true);
+                        array.set_trailing(&format!("{}\n", comment_suffix));
+                    }
+                }
+                index = index.map(|i| i + 1);
+                match comment {
+                    Some(comment) => stderr.status(
+                        "Adding",
+                        format!("{:?} to `workspace.{}` with `# {}`", add, field, comment),
+                    )?,
+                    None => {
+                        stderr.status("Adding", format!("{:?} to `workspace.{}`", add, field))?
+                    }
+                }
+                modified = true;
             }
         }
         for rm in *rm {
             let rm = relative_to_root(rm)?;
-            let i = array.iter().position(|m| same_paths(m, rm));
+            let i = array.iter().position(|m| same_paths(m, &rm));
             if let Some(i) = i {
-                if !dry_run {
-                    array.remove(i);
-                }
+                let removed = remove_array_entry(array, i);
+                modified = true;
+                stderr.status_with_color(
+                    "Removing",
+                    format!(
+                        "{:?} from `workspace.{}`",
+                        removed.as_str().unwrap_or(&rm),
+                        field,
+                    ),
+                    termcolor::Color::Red,
+                )?;
+            }
+        }
+        if let Some(per_line) = layout.per_line.filter(|_| modified) {
+            wrap_array(array, per_line);
+        }
+    }
+
+    // `workspace.default-members` must be a subset of `workspace.members`. It is never created
+    // here.
+    if let Some(array) = cargo_toml["workspace"]["default-members"].as_array_mut() {
+        for rm in rm_from_workspace_members {
+            let rm = normalize_path(&possibly_empty_workspace_root.join(rm));
+            let i = array.iter().position(|m| {
+                m.as_str()
+                    .is_some_and(|s| normalize_path(&possibly_empty_workspace_root.join(s)) == rm)
+            });
+            if let Some(i) = i {
+                let removed = remove_array_entry(array, i);
                 stderr.status_with_color(
                     "Removing",
-                    format!("{:?} from `workspace.{}`", rm, field),
+                    format!(
+                        "{:?} from `workspace.default-members`",
+                        removed.as_str().unwrap_or_default(),
+                    ),
                     termcolor::Color::Red,
                 )?;
             }
@@ -1200,11 +9679,277 @@ fn modify_members<'a>(
     }
 
     let cargo_toml = cargo_toml.to_string();
-    let modified = cargo_toml != orig;
-    if modified {
-        crate::fs::write(manifest_path, cargo_toml, dry_run)?;
+    if cargo_toml != orig {
+        Action::plan_write(actions, manifest_path, cargo_toml)?;
+    }
+    Ok(())
+}
+
+/// Appends `add` to `workspace.default-members`. Each of them must be in `workspace.members`.
+fn plan_default_members(
+    possibly_empty_workspace_root: &Path,
+    add: &[&Path],
+    layout: MemberLayout<'_>,
+    actions: &mut Vec<Action>,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let manifest_path = possibly_empty_workspace_root.join("Cargo.toml");
+    let orig = Action::read_planned(actions, &manifest_path)?;
+    let mut cargo_toml = crate::fs::parse_toml_edit(&orig, &manifest_path)?;
+
+    let same_paths = |value: &toml_edit::Value, target: &str| -> _ {
+        value.as_str().is_some_and(|s| {
+            possibly_empty_workspace_root.join(s) == possibly_empty_workspace_root.join(target)
+        })
+    };
+
+    let members = cargo_toml["workspace"]["members"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let array = cargo_toml["workspace"]["default-members"]
+        .or_insert(toml_edit::value(toml_edit::Array::default()))
+        .as_array_mut()
+        .context("`workspace.default-members` must be an array")?;
+    let mut modified = false;
+    for &path in add {
+        let add = member_entry(possibly_empty_workspace_root, path)?;
+        let in_members = members.iter().any(|m| {
+            same_paths(m, &add)
+                || m.as_str()
+                    .is_some_and(|m| glob::Pattern::new(m).is_ok_and(|p| p.matches(&add)))
+        });
+        ensure!(
+            in_members,
+            "{:?} is not in `workspace.members`. `workspace.default-members` must be a subset of it",
+            add,
+        );
+        let add = layout.entry(possibly_empty_workspace_root, path)?;
+        if array.iter().all(|m| !same_paths(m, &add)) {
+            array.push_formatted(Value::from(&*add).decorated("\n    ", ""));
+            array.set_trailing_comma(true);
+            array.set_trailing("\n");
+            modified = true;
+            stderr.status(
+                "Adding",
+                format!("{:?} to `workspace.default-members`", add),
+            )?;
+        }
+    }
+    if let Some(per_line) = layout.per_line.filter(|_| modified) {
+        wrap_array(array, per_line);
+    }
+
+    let cargo_toml = cargo_toml.to_string();
+    if cargo_toml != orig {
+        Action::plan_write(actions, manifest_path, cargo_toml)?;
+    }
+    Ok(())
+}
+
+/// Removes the `i`th entry of `array`, keeping the comment after the previous entry.
+///
+/// A comment after an entry is written after its comma, that is, in the prefix of the next entry
+/// or in the trailing of the array. It is moved to the entry or the trailing that follows the
+/// removed one, replacing the comment after the removed entry.
+fn remove_array_entry(array: &mut toml_edit::Array, i: usize) -> Value {
+    let removed = array.remove(i);
+    // Only for entries on their own lines.
+    let carried = match removed.decor().prefix().and_then(|p| p.split_once('\n')) {
+        Some((line, _)) => line,
+        None => return removed,
+    };
+    // Replaces the first line of `after`, keeping a newline after the carried comment.
+    let replace = |after: &str| -> Option<String> {
+        match after.find('\n') {
+            Some(n) => Some(carried.to_owned() + &after[n..]),
+            None if carried.contains('#') => Some(format!("{}\n{}", carried, after.trim_start())),
+            None => None,
+        }
+    };
+    match array.get(i) {
+        Some(next) => {
+            if let Some(prefix) = replace(next.decor().prefix().unwrap_or("")) {
+                let suffix = next.decor().suffix().unwrap_or("").to_owned();
+                let next = next.clone().decorated(&prefix, &suffix);
+                array.replace_formatted(i, next);
+            }
+        }
+        None => {
+            if let Some(trailing) = replace(array.trailing()) {
+                array.set_trailing(&trailing);
+            }
+        }
+    }
+    removed
+}
+
+/// Parses the manifest sorted by [`Sort`] again, failing unless `workspace.{members, exclude}` have
+/// the same entries as in `orig` apart from the removed exact duplicates.
+///
+/// Sorting moves the comments and the whitespace around the entries, where a mistake could comment
+/// out or merge entries.
+fn verify_sorted(orig: &str, sorted: &str, manifest_path: &Path) -> anyhow::Result<()> {
+    let orig = crate::fs::parse_toml_edit(orig, manifest_path)?;
+    let sorted = crate::fs::parse_toml_edit(sorted, manifest_path)
+        .with_context(|| "sorting broke the manifest. nothing is written")?;
+    for field in &["members", "exclude"] {
+        let len = |cargo_toml: &toml_edit::Document| {
+            cargo_toml["workspace"][field]
+                .as_array()
+                .map_or(0, toml_edit::Array::len)
+        };
+        let (orig_entries, sorted_entries) = (
+            workspace_entries(&orig, field),
+            workspace_entries(&sorted, field),
+        );
+        let unique = orig_entries.iter().unique().count();
+        ensure!(
+            sorted_entries.iter().unique().count() == sorted_entries.len()
+                && sorted_entries.len() == unique
+                && len(&sorted) == len(&orig) - (orig_entries.len() - unique)
+                && sorted_entries.iter().all(|e| orig_entries.contains(e)),
+            "sorting changed the entries of `workspace.{}` in `{}`. nothing is written",
+            field,
+            manifest_path.display(),
+        );
+    }
+    Ok(())
+}
+
+/// Sorts `array` if every entry is a string. Returns `false` if it is already sorted.
+///
+/// A comment at the end of the line of an entry, which is the first line of the prefix of the
+/// next entry, moves with the entry, as well as the comment lines above it. Entries on the same
+/// line keep the spacing of their positions.
+fn sort_array(array: &mut toml_edit::Array) -> bool {
+    let entries = match array
+        .iter()
+        .map(|v| v.as_str().map(ToOwned::to_owned))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(entries) => entries,
+        None => return false,
+    };
+    let mut order = (0..entries.len()).collect::<Vec<_>>();
+    order.sort_by(|&i, &j| entries[i].cmp(&entries[j]));
+    if order.iter().enumerate().all(|(j, &i)| i == j) {
+        return false;
+    }
+
+    let split = |s: &str| match s.find('\n') {
+        Some(n) => (s[..n].to_owned(), s[n..].to_owned()),
+        None => (s.to_owned(), "".to_owned()),
+    };
+    let values = array.iter().cloned().collect::<Vec<_>>();
+    let (firsts, rests): (Vec<_>, Vec<_>) = values
+        .iter()
+        .map(|v| split(v.decor().prefix().unwrap_or("")))
+        .unzip();
+    let suffixes = values
+        .iter()
+        .map(|v| v.decor().suffix().unwrap_or("").to_owned())
+        .collect::<Vec<_>>();
+    let (trailing_first, trailing_rest) = split(array.trailing());
+    let comments = (0..values.len())
+        .map(|i| Some(firsts.get(i + 1).unwrap_or(&trailing_first)).filter(|c| c.contains('#')))
+        .collect::<Vec<_>>();
+    // The first line of the prefix of position `j`, or of the trailing if `j` is the end.
+    let first_line = |j: usize| -> String {
+        let first = firsts.get(j).unwrap_or(&trailing_first);
+        match comments[order[j - 1]] {
+            Some(comment) => comment.clone(),
+            None if first.contains('#') => "".to_owned(),
+            None => first.clone(),
+        }
+    };
+
+    for (j, &i) in order.iter().enumerate() {
+        let first = if j == 0 {
+            firsts[0].clone()
+        } else {
+            first_line(j)
+        };
+        let value = values[i]
+            .clone()
+            .decorated(&(first + &rests[i]), &suffixes[j]);
+        array.replace_formatted(j, value);
+    }
+    let trailing = first_line(values.len()) + &trailing_rest;
+    array.set_trailing(&trailing);
+    true
+}
+
+/// Lays out `array` with `per_line` entries per line, `0` being the same as `1`. If every entry
+/// fits in one line, the array is written as `["a", "b"]`. Comments inside the array are dropped.
+fn wrap_array(array: &mut toml_edit::Array, per_line: usize) {
+    let per_line = per_line.max(1);
+    let single_line = array.len() <= per_line;
+    for (i, value) in array.iter_mut().enumerate() {
+        let prefix = match (single_line, i % per_line == 0) {
+            (true, _) if i == 0 => "",
+            (false, true) => "\n    ",
+            _ => " ",
+        };
+        *value = value.clone().decorated(prefix, "");
+    }
+    array.set_trailing_comma(!single_line);
+    array.set_trailing(if single_line { "" } else { "\n" });
+}
+
+/// How [`plan_members`] writes the entries it adds.
+#[derive(Clone, Copy, Default, Debug)]
+struct MemberLayout<'a> {
+    /// Lays out the modified arrays with [`wrap_array`].
+    per_line: Option<usize>,
+    /// Writes the added entries as `./{path}`.
+    prefix_dot_slash: bool,
+    /// Writes `# {comment}` after the entries added to `workspace.members`.
+    comment: Option<&'a str>,
+    /// Where to insert the entries added to `workspace.members`. Defaults to appending them.
+    position: Option<&'a MemberPosition>,
+}
+
+impl MemberLayout<'_> {
+    fn entry(self, possibly_empty_workspace_root: &Path, path: &Path) -> anyhow::Result<String> {
+        let entry = member_entry(possibly_empty_workspace_root, path)?;
+        if !self.prefix_dot_slash
+            || entry == ".."
+            || entry.starts_with("../")
+            || Path::new(&entry).is_absolute()
+        {
+            return Ok(entry);
+        }
+        Ok(format!("./{}", entry))
     }
-    Ok(modified)
+}
+
+/// Notes `workspace.members` of `manifest_path` as planned in `actions`, for dry runs.
+fn note_planned_members(
+    actions: &[Action],
+    manifest_path: &Path,
+    mut stderr: impl WriteColor,
+) -> anyhow::Result<()> {
+    let planned = crate::fs::parse_toml_edit(
+        &Action::read_planned(actions, manifest_path)?,
+        manifest_path,
+    )?;
+    stderr.note(format!(
+        "`workspace.members` would be {:?}",
+        workspace_entries(&planned, "members"),
+    ))?;
+    Ok(())
+}
+
+/// Returns `path` as an entry of `workspace.{members, exclude}`.
+fn member_entry(possibly_empty_workspace_root: &Path, path: &Path) -> anyhow::Result<String> {
+    let path = match path.strip_prefix(possibly_empty_workspace_root) {
+        Ok(path) => path.to_owned(),
+        Err(_) => relative_path(possibly_empty_workspace_root, path),
+    };
+    path.to_str()
+        .map(ToOwned::to_owned)
+        .with_context(|| format!("{:?} is not valid UTF-8 path", path))
 }
 
 trait WriteColorExt: WriteColor {
@@ -1221,6 +9966,19 @@ trait WriteColorExt: WriteColor {
         self.flush()
     }
 
+    fn note(&mut self, message: impl Display) -> io::Result<()> {
+        self.set_color(
+            ColorSpec::new()
+                .set_fg(Some(termcolor::Color::Cyan))
+                .set_bold(true)
+                .set_reset(false),
+        )?;
+        self.write_all(b"note:")?;
+        self.reset()?;
+        writeln!(self, " {}", message)?;
+        self.flush()
+    }
+
     fn status(&mut self, status: impl Display, message: impl Display) -> io::Result<()> {
         self.status_with_color(status, message, termcolor::Color::Green)
     }
@@ -1249,7 +10007,7 @@ impl<W: WriteColor> WriteColorExt for W {}
 #[ext(MetadataExt)]
 impl Metadata {
     fn query_for_member<'a>(&'a self, spec: Option<&str>) -> anyhow::Result<&'a Package> {
-        let cargo_exe = env::var_os("CARGO").with_context(|| "`$CARGO` should be present")?;
+        let cargo_exe = cargo_exe();
 
         let manifest_path = self
             .resolve
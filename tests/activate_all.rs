@@ -0,0 +1,137 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn activate_all() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-activate-all")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir(tempdir.path().join("target"))?;
+
+    let err = cargo_member::ActivateAll::new(tempdir.path())
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "this rewrites `workspace.{{members, exclude}}` of `{}`. enable `--force` to proceed, \
+             or `--dry-run` to see the result",
+            tempdir.path().join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+
+    let mut stderr = vec![];
+
+    cargo_member::ActivateAll::new(tempdir.path())
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(&stderr, EXPECTED_STDERR_DRY_RUN)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::ActivateAll::new(tempdir.path())
+        .force(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+    assert_eq!(3, metadata.workspace_members.len());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = ["target", "b"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "b",
+    "c",
+]
+exclude = ["target"]
+"#;
+
+    static EXPECTED_STDERR_DRY_RUN: &str = r#"      Adding "b" to `workspace.members`
+      Adding "c" to `workspace.members`
+    Removing "b" from `workspace.exclude`
+note: `workspace.members` would be ["a", "b", "c"]
+warning: not modifying the manifest due to dry run
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "b" to `workspace.members`
+      Adding "c" to `workspace.members`
+    Removing "b" from `workspace.exclude`
+    Updating {}
+"#;
+}
+
+#[test]
+fn unchanged() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-activate-all-unchanged")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "activate-all")
+        .dir(tempdir.path())
+        .stderr_capture()
+        .run()?;
+
+    assert_eq!(
+        "warning: `workspace` unchanged\n",
+        str::from_utf8(&output.stderr)?,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path) -> cargo_metadata::Result<Metadata> {
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+}
@@ -0,0 +1,265 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::MetadataCommand;
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+
+#[test]
+fn batch() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-batch")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::write(tempdir.path().join("batch.json"), BATCH)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "batch",
+        "--dry-run",
+        "batch.json",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let actions = str::from_utf8(&output.stdout)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(1, actions.len());
+    assert_eq!(
+        serde_json::json!({
+            "kind": "write-file",
+            "path": tempdir.path().join("Cargo.toml"),
+            "edits": [
+                { "key": "workspace.members", "before": ["a"], "after": ["b", "c"] },
+                { "key": "workspace.exclude", "before": null, "after": ["a"] },
+            ],
+        }),
+        actions[0],
+    );
+
+    cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "batch", "-",)
+        .dir(tempdir.path())
+        .stdin_bytes(BATCH)
+        .stderr_null()
+        .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static BATCH: &str = r#"[
+  { "command": "include", "args": ["b", "c"] },
+  { "command": "exclude", "args": ["a"] }
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = [
+    "b",
+    "c",
+]
+exclude = [
+    "a",
+]
+"#;
+}
+
+#[test]
+fn failure() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-batch-failure")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let lockfile = fs::read_to_string(tempdir.path().join("Cargo.lock"))?;
+
+    for (batch, expected_stderr) in &[
+        (
+            r#"[{ "command": "include", "args": ["--bogus", "b"] }]"#,
+            "error: invalid step 1 of 1 (`cargo member include --bogus b`)\n\nCaused by:\n  \
+             Found argument '--bogus' which wasn't expected, or isn't valid in this context\n",
+        ),
+        (
+            r#"[{ "command": "include", "args": ["b"] }, { "command": "open", "args": ["a"] }]"#,
+            "error: `open` cannot be used in a batch: step 2 of 2 (`cargo member open a`)\n",
+        ),
+        (
+            r#"[{ "command": "include", "args": ["b"], "dry_run": true }]"#,
+            "error: failed to parse stdin\n\nCaused by:\n  unknown field `dry_run`, expected \
+             `command` or `args` at line 1 column 49\n",
+        ),
+    ] {
+        let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "batch", "-",)
+            .dir(tempdir.path())
+            .stdin_bytes(*batch)
+            .stderr_capture()
+            .unchecked()
+            .run()?;
+
+        assert!(!output.status.success());
+        assert_stderr(&output.stderr, expected_stderr)?;
+        assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    }
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "batch", "-",)
+        .dir(tempdir.path())
+        .stdin_bytes(BATCH)
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+
+    assert!(!output.status.success());
+    let stderr = str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains(&format!(
+        "   Restoring {}\n",
+        tempdir.path().join("Cargo.toml").display(),
+    )));
+    assert!(stderr.contains(
+        "error: step 2 of 3 (`cargo member rm c`) failed. the remaining 1 step(s) were not run \
+         and the changes of the batch were undone\n",
+    ));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_manifest(&tempdir.path().join("Cargo.lock"), &lockfile)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static BATCH: &str = r#"[
+  { "command": "include", "args": ["b"] },
+  { "command": "rm", "args": ["c"] },
+  { "command": "exclude", "args": ["a"] }
+]
+"#;
+}
+
+#[test]
+fn failure_undoes_steps_in_reverse() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-batch-failure-undoes-steps-in-reverse")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let lockfile = fs::read_to_string(tempdir.path().join("Cargo.lock"))?;
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "batch", "-")
+        .dir(tempdir.path())
+        .stdin_bytes(BATCH)
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?
+        .contains("error: step 3 of 3 (`cargo member rm d`) failed."));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_manifest(&tempdir.path().join("Cargo.lock"), &lockfile)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static BATCH: &str = r#"[
+  { "command": "include", "args": ["b"] },
+  { "command": "include", "args": ["c"] },
+  { "command": "rm", "args": ["d"] }
+]
+"#;
+}
+
+#[test]
+fn failure_restores_dirs() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-batch-failure-restores-dirs")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let main_rs = fs::read_to_string(tempdir.path().join("b").join("src").join("main.rs"))?;
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "batch", "-")
+        .dir(tempdir.path())
+        .stdin_bytes(BATCH)
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?
+        .contains("error: step 3 of 3 (`cargo member rm d`) failed."));
+    assert!(!tempdir.path().join("c").exists());
+    assert_manifest(
+        &tempdir.path().join("b").join("src").join("main.rs"),
+        &main_rs,
+    )?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b"]
+"#;
+
+    static BATCH: &str = r#"[
+  { "command": "cp", "args": ["a", "c"] },
+  { "command": "rm", "args": ["b"] },
+  { "command": "rm", "args": ["d"] }
+]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<()> {
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(|&s| s.to_owned()).collect::<Vec<_>>())
+        .exec()
+        .map(drop)
+}
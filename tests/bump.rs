@@ -0,0 +1,154 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn bump() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-bump")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for (name, version) in &[
+        ("a", r#""0.1.0""#),
+        ("b", r#""1.2.3-alpha""#),
+        ("c", "{ workspace = true }"),
+    ] {
+        fs::create_dir_all(tempdir.path().join(name).join("src"))?;
+        fs::write(
+            tempdir.path().join(name).join("Cargo.toml"),
+            format!(
+                "[package]\nname = {:?}\nversion = {}\nedition = \"2018\"\n",
+                name, version,
+            ),
+        )?;
+        fs::write(tempdir.path().join(name).join("src").join("lib.rs"), "")?;
+    }
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Bump::from_metadata(&metadata, "minor".parse()?, None::<&str>, Some("b"))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "[package]\nname = \"a\"\nversion = \"0.2.0\"\nedition = \"2018\"\n",
+    )?;
+    assert_manifest(
+        &tempdir.path().join("b").join("Cargo.toml"),
+        "[package]\nname = \"b\"\nversion = \"1.2.3-alpha\"\nedition = \"2018\"\n",
+    )?;
+    assert_stderr(
+        &stderr,
+        &format!(
+            "     Bumping `a` from 0.1.0 to 0.2.0\n\
+             note: skipping `c`: `package.version` is inherited from the workspace. enable \
+             `--workspace-package` to bump it\n    Updating {}\n",
+            tempdir.path().join("Cargo.lock").display(),
+        ),
+    )?;
+
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Bump::from_metadata(&metadata, "major".parse()?, Some("b"), None::<&str>)
+        .workspace_package(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(
+        &stderr,
+        "     Bumping `workspace.package.version` from 0.1.0 to 1.0.0\n     Bumping `b` from \
+         1.2.3-alpha to 2.0.0\nwarning: not modifying the manifest due to dry run\n",
+    )?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "3"
+
+[workspace.package]
+version = "0.1.0"
+"#;
+}
+
+#[test]
+fn failures() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-bump-failures")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir_all(tempdir.path().join("a").join("src"))?;
+    fs::write(tempdir.path().join("a").join("Cargo.toml"), A_MANIFEST)?;
+    fs::write(tempdir.path().join("a").join("src").join("lib.rs"), "")?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    for spec in &["1.x", "minor+1", ""] {
+        assert_eq!(
+            format!(
+                "expected `major`, `minor`, `patch` or a version, got {:?}",
+                spec,
+            ),
+            spec.parse::<cargo_member::BumpLevel>()
+                .unwrap_err()
+                .to_string(),
+        );
+    }
+
+    let err =
+        cargo_member::Bump::from_metadata(&metadata, "minor".parse()?, Some("nope"), None::<&str>)
+            .exec()
+            .unwrap_err();
+    assert_eq!(
+        "package ID specification `nope` did not match any packages",
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), A_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+resolver = "3"
+"#;
+
+    static A_MANIFEST: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2018"
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
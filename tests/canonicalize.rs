@@ -0,0 +1,131 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use std::{
+    fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn canonicalize() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-canonicalize")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Canonicalize::new(tempdir.path())
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(
+        &stderr,
+        &format!(
+            "{}warning: not modifying the manifest due to dry run\n",
+            EXPECTED_STDERR
+        ),
+    )?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Canonicalize::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["./crates/a", "crates/b/", "crates/./c", "crates/*", # comment
+    "crates/a",
+]
+exclude = [".\\d"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["crates/a", "crates/b", "crates/c", "crates/*", # comment
+    "crates/a",
+]
+exclude = ["d"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Changing "./crates/a" to "crates/a" in `workspace.members`
+    Changing "crates/b/" to "crates/b" in `workspace.members`
+    Changing "crates/./c" to "crates/c" in `workspace.members`
+warning: "crates/a" appears more than once in `workspace.members`
+    Changing ".\\d" to "d" in `workspace.exclude`
+"#;
+}
+
+#[test]
+fn absolute_entry() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-canonicalize-absolute-entry")?;
+
+    let root = tempdir.path().to_string_lossy().replace('\\', "/");
+    fs::write(
+        tempdir.path().join("Cargo.toml"),
+        ORIGINAL.replace("{{root}}", &root),
+    )?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Canonicalize::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, &EXPECTED_STDERR.replace("{{root}}", &root))?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["{{root}}/a", "a", "/elsewhere/b"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "a", "/elsewhere/b"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Changing "{{root}}/a" to "a" in `workspace.members`
+warning: "a" appears more than once in `workspace.members`
+"#;
+}
+
+#[test]
+fn manifest_file_name() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-canonicalize-manifest-file-name")?;
+
+    fs::write(tempdir.path().join("Workspace.toml"), ORIGINAL)?;
+
+    cargo_member::Canonicalize::new(tempdir.path())
+        .manifest_file_name("Workspace.toml")
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Workspace.toml"), EXPECTED_MANIFEST)?;
+    assert!(!tempdir.path().join("Cargo.toml").exists());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["./a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
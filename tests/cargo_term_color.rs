@@ -0,0 +1,70 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::{fs, str};
+use tempdir::TempDir;
+
+#[test]
+fn cargo_term_color() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cargo-term-color")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let stderr = |args: &[&str]| -> anyhow::Result<String> {
+        let output = cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member", "include", "--dry-run", "./a"].iter().chain(args),
+        )
+        .dir(tempdir.path())
+        .env("CARGO_TERM_COLOR", "always")
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+        assert!(!output.status.success());
+        Ok(str::from_utf8(&output.stderr)?.to_owned())
+    };
+
+    assert!(stderr(&[])?.contains('\x1b'));
+    assert!(!stderr(&["--color", "never"])?.contains('\x1b'));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+}
+
+#[test]
+fn no_color() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-no-color")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let stderr = |envs: &[(&str, &str)], args: &[&str]| -> anyhow::Result<String> {
+        let mut cmd = cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member", "include", "--dry-run", "./a"].iter().chain(args),
+        )
+        .dir(tempdir.path())
+        .env_remove("CARGO_TERM_COLOR")
+        .env_remove("NO_COLOR");
+        for (key, val) in envs {
+            cmd = cmd.env(key, val);
+        }
+        let output = cmd.stderr_capture().unchecked().run()?;
+        assert!(!output.status.success());
+        Ok(str::from_utf8(&output.stderr)?.to_owned())
+    };
+
+    assert!(stderr(&[], &["--color", "always"])?.contains('\x1b'));
+    assert!(stderr(&[("NO_COLOR", "1")], &["--color", "always"])?.contains('\x1b'));
+    assert!(!stderr(&[("NO_COLOR", "1")], &[])?.contains('\x1b'));
+    assert!(!stderr(&[], &["--color", "always", "--no-color"])?.contains('\x1b'));
+    assert!(!stderr(&[("CARGO_TERM_COLOR", "always")], &["--no-color"])?.contains('\x1b'));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+}
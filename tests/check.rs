@@ -0,0 +1,298 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    fs,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn check() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    for name in &["a", "b", "c", "x"] {
+        write_package(&tempdir.path().join(name), name)?;
+    }
+
+    let mut stderr = vec![];
+
+    let err = cargo_member::Check::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!("4 violation(s) found", err.to_string());
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+
+    let err = cargo_member::Check::new(tempdir.path())
+        .require_sorted(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!("5 violation(s) found", err.to_string());
+
+    fs::write(tempdir.path().join("Cargo.toml"), CLEAN_ROOT)?;
+    cargo_member::Check::new(tempdir.path())
+        .require_sorted(true)
+        .exec()?;
+
+    cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "check")
+        .dir(tempdir.path())
+        .stderr_null()
+        .run()?;
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["b", "a", "gone"]
+exclude = ["./x", "x"]
+"#;
+
+    static CLEAN_ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = ["x"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"Unregistered 1 package(s)
+note: `{{root}}/c` is in neither `workspace.members` nor `workspace.exclude`
+        Dead 1 entry(s)
+note: "gone" in `workspace.members` does not point to a package
+     Unclean 2 entry(s)
+note: "./x" in `workspace.exclude` should be "x"
+note: "x" appears more than once in `workspace.exclude`
+"#;
+}
+
+#[test]
+fn json() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-json")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    write_package(&tempdir.path().join("a"), "a")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check",
+        "--require-sorted",
+        "--json",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    let json = serde_json::from_slice::<serde_json::Value>(&output.stdout)?;
+    assert_eq!(
+        serde_json::json!({
+            "violations": [
+                {
+                    "category": "unsorted",
+                    "file": tempdir.path().canonicalize()?.join("Cargo.toml"),
+                    "message": "`workspace.members` is not sorted",
+                },
+            ],
+        }),
+        json,
+    );
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "."]
+
+[package]
+name = "root"
+version = "0.0.0"
+edition = "2021"
+"#;
+}
+
+#[test]
+fn message_format_json() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-message-format-json")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    write_package(&tempdir.path().join("a"), "a")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check",
+        "--require-sorted",
+        "--message-format",
+        "json",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    let lines = str::from_utf8(&output.stdout)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(
+        [
+            serde_json::json!({
+                "category": "unsorted",
+                "path": tempdir.path().canonicalize()?.join("Cargo.toml"),
+                "message": "`workspace.members` is not sorted",
+                "severity": "error",
+            }),
+            serde_json::json!({ "summary": { "errors": 1, "warnings": 0 } }),
+        ],
+        *lines,
+    );
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "."]
+
+[package]
+name = "root"
+version = "0.0.0"
+edition = "2021"
+"#;
+}
+
+#[test]
+fn sort_key() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-sort-key")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    write_package(&tempdir.path().join("x"), "b")?;
+    write_package(&tempdir.path().join("y"), "a")?;
+
+    cargo_member::Check::new(tempdir.path())
+        .require_sorted(true)
+        .exec()?;
+
+    let mut stderr = vec![];
+    let err = cargo_member::Check::new(tempdir.path())
+        .require_sorted(true)
+        .sort_key(cargo_member::SortKey::Name)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+    assert_eq!("1 violation(s) found", err.to_string());
+    assert_stderr(
+        &stderr,
+        "Unregistered 0 package(s)
+        Dead 0 entry(s)
+     Unclean 0 entry(s)
+    Unsorted 1 array(s)
+note: `workspace.members` is not sorted by name
+",
+    )?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), SORTED_BY_NAME)?;
+    cargo_member::Check::new(tempdir.path())
+        .require_sorted(true)
+        .sort_key(cargo_member::SortKey::Name)
+        .exec()?;
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["x", "y"]
+"#;
+
+    static SORTED_BY_NAME: &str = r#"[workspace]
+members = ["y", "x"]
+"#;
+}
+
+#[test]
+fn manifest_file_name() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-manifest-file-name")?;
+
+    fs::write(tempdir.path().join("Workspace.toml"), ROOT)?;
+    for name in &["a", "b"] {
+        write_package(&tempdir.path().join(name), name)?;
+    }
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check",
+        "--manifest-path",
+        "Workspace.toml",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check",
+        "--manifest-path",
+        "Workspace.toml",
+        "--locked",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert_stderr(
+        &output.stderr,
+        "error: `--locked` cannot be used with a manifest not named `Cargo.toml`\n",
+    )?;
+
+    // The other commands do not honor the file name, and reject it.
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--manifest-path",
+        "Workspace.toml",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert_stderr(
+        &output.stderr,
+        "error: `--manifest-path` must point to a `Cargo.toml` for this command, not \
+         `Workspace.toml`. only `status`, `check`, `canonicalize` and `sort` accept a manifest \
+         with another name\n",
+    )?;
+    assert_eq!(
+        ROOT,
+        fs::read_to_string(tempdir.path().join("Workspace.toml"))?,
+    );
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+}
+
+fn write_package(dir: &Path, name: &str) -> anyhow::Result<()> {
+    fs::create_dir(dir)?;
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2021\"\n",
+            name,
+        ),
+    )?;
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
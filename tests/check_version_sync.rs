@@ -0,0 +1,203 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn fix() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-version-sync-fix")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    cargo_new(&tempdir.path().join("c"))?;
+    set_version(&tempdir.path().join("c"), "0.2.0")?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    let err = cargo_member::CheckVersionSync::from_metadata(&metadata)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+    assert_eq!("1 package(s) are out of sync", err.to_string());
+    assert_stderr(&stderr, EXPECTED_STDERR_CHECK)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::CheckVersionSync::from_metadata(&metadata)
+        .fix(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("c").join("Cargo.toml"), EXPECTED_C)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR_FIX.replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+"#;
+
+    static EXPECTED_C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+"#;
+
+    static EXPECTED_STDERR_CHECK: &str = r#"warning: `c` is at 0.2.0 (expected 0.1.0)
+"#;
+
+    static EXPECTED_STDERR_FIX: &str = r#"    Changing `package.version` of "c" from "0.2.0" to "0.1.0"
+    Updating {}
+"#;
+}
+
+#[test]
+fn inherited() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-version-sync-inherited")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    set_version(&tempdir.path().join("b"), "0.2.0")?;
+    fs::write(tempdir.path().join("Cargo.toml"), MODIFIED)?;
+    let manifest_path = tempdir.path().join("a").join("Cargo.toml");
+    fs::write(
+        &manifest_path,
+        fs::read_to_string(&manifest_path)?
+            .replace(r#"version = "0.1.0""#, "version.workspace = true"),
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::CheckVersionSync::from_metadata(&metadata)
+        .fix(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static MODIFIED: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+
+[workspace.package]
+version = "0.3.0"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"note: skipping `a`: `package.version` is inherited from the workspace
+    Finished all the versions are 0.2.0
+"#;
+}
+
+#[test]
+fn broken_member() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-check-version-sync-broken-member")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    fs::write(tempdir.path().join("c").join("Cargo.toml"), "[package\n")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check-version-sync",
+        "--color",
+        "never",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    let stderr = str::from_utf8(&output.stderr)?;
+    assert!(stderr
+        .starts_with("warning: `cargo metadata` failed. reading the manifests directly instead\n"));
+    assert!(stderr.contains(&format!(
+        "warning: skipping `{}`: failed to parse the TOML file at {}",
+        tempdir.path().join("c").join("Cargo.toml").display(),
+        tempdir.path().join("c").join("Cargo.toml").display(),
+    )));
+    assert!(stderr.ends_with("    Finished all the versions are 0.1.0\n"));
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "check-version-sync",
+        "--fix",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .unchecked()
+    .run()
+    .map(|output| assert!(!output.status.success()))?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn set_version(path: &Path, version: &str) -> io::Result<()> {
+    let manifest_path = path.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)?
+        .replace(r#"version = "0.1.0""#, &format!("version = {:?}", version));
+    fs::write(manifest_path, manifest)
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
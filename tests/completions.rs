@@ -0,0 +1,55 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::str;
+use tempdir::TempDir;
+
+#[test]
+fn out_dir() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-completions-out-dir")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "completions",
+        "--all",
+        "--out-dir",
+        "out",
+        "--color",
+        "never",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .run()?;
+
+    assert!(output.stdout.is_empty());
+    let stderr = str::from_utf8(&output.stderr)?;
+    for file_name in &[
+        "cargo-member.bash",
+        "_cargo-member",
+        "cargo-member.fish",
+        "_cargo-member.ps1",
+        "cargo-member.elv",
+    ] {
+        let path = tempdir.path().join("out").join(file_name);
+        assert!(path.metadata()?.len() > 0);
+        assert!(stderr.contains(&format!("Writing {}\n", path.display())));
+    }
+    Ok(())
+}
+
+#[test]
+fn stdout() -> anyhow::Result<()> {
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "completions",
+        "bash",
+    )
+    .stdout_capture()
+    .run()?;
+
+    assert!(str::from_utf8(&output.stdout)?.contains("cargo-member"));
+    Ok(())
+}
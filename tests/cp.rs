@@ -51,6 +51,1121 @@ exclude = []
 "#;
 }
 
+#[test]
+fn minimal() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-minimal")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    fs::write(tempdir.path().join("ws").join("a").join("README.md"), "")?;
+    fs::write(
+        tempdir
+            .path()
+            .join("ws")
+            .join("a")
+            .join("src")
+            .join("sub.rs"),
+        "",
+    )?;
+    fs::write(
+        tempdir
+            .path()
+            .join("ws")
+            .join("a")
+            .join("src")
+            .join("skip.rs"),
+        "",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b"))
+        .dry_run(false)
+        .minimal(true)
+        .exclude(["src/skip.rs"])
+        .exec()?;
+
+    assert!(tempdir.path().join("b").join("Cargo.toml").exists());
+    assert!(tempdir
+        .path()
+        .join("b")
+        .join("src")
+        .join("main.rs")
+        .exists());
+    assert!(tempdir.path().join("b").join("src").join("sub.rs").exists());
+    assert!(!tempdir
+        .path()
+        .join("b")
+        .join("src")
+        .join("skip.rs")
+        .exists());
+    assert!(!tempdir.path().join("b").join("README.md").exists());
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+#[test]
+fn conflict() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-conflict")?;
+
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let err = cargo_member::Cp::new(&tempdir.path().join("a"), &tempdir.path().join("b"))
+        .dry_run(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` conflicts with the package `b` at `{}`. enable `--force` to copy",
+            tempdir.path().join("b").join("a").display(),
+            tempdir.path().join("b").display(),
+        ),
+        err.to_string(),
+    );
+
+    let err = cargo_member::Cp::new(&tempdir.path().join("a"), &tempdir.path().join("c"))
+        .dry_run(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` conflicts with \"c\" in `workspace.members` of `{}`. enable `--force` to copy",
+            tempdir.path().join("c").display(),
+            tempdir.path().join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+
+    cargo_member::Cp::new(&tempdir.path().join("a"), &tempdir.path().join("c"))
+        .dry_run(true)
+        .force(true)
+        .exec()?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+"#;
+}
+
+#[test]
+fn duplicate_names() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-duplicate-names")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    let dst = tempdir.path().join("ws").join("b");
+
+    let err = cargo_member::Cp::from_metadata(&metadata, "a", &dst)
+        .no_rename(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "the workspace at `{}` would have two packages named `a`: `{}` and `{}`. enable \
+             `--allow-duplicate-names` to proceed anyway",
+            tempdir.path().join("ws").display(),
+            tempdir.path().join("ws").join("a").display(),
+            dst.display(),
+        ),
+        err.to_string(),
+    );
+    assert!(!dst.exists());
+    assert_manifest(&tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &dst)
+        .no_rename(true)
+        .allow_duplicate_names(true)
+        .dry_run(true)
+        .exec()?;
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("ws").join("c")).exec()?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn set_fields() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-set-fields")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    for (set_fields, expected_err) in &[
+        (
+            &[("name", "\"c\"")][..],
+            "`package.name` cannot be set directly",
+        ),
+        (&[("a.b", "1")][..], "invalid key: \"a.b\""),
+        (
+            &[("keywords", "\"x\"")][..],
+            "`package.keywords` must be an array",
+        ),
+        (
+            &[("description", "x\ny = 1")][..],
+            "invalid value for `package.description`: \"x\\ny = 1\". note that values are \
+             parsed as TOML (strings must be quoted)",
+        ),
+    ] {
+        let err = cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b"))
+            .set_fields(set_fields.iter().copied())
+            .exec()
+            .unwrap_err();
+        assert_eq!(*expected_err, err.to_string());
+        assert!(!tempdir.path().join("b").exists());
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b"))
+        .set_fields([("description", r#""copied""#), ("publish", "false")])
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    let manifest = fs::read_to_string(tempdir.path().join("b").join("Cargo.toml"))?;
+    assert!(manifest.contains("\ndescription = \"copied\"\npublish = false\n"));
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "     Setting `package.publish = false` in `{}`\n",
+        tempdir.path().join("b").join("Cargo.toml").display(),
+    )));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn version() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-version")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "a",
+        "b",
+        "--bump",
+        "minor",
+        "--dry-run",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+    assert!(!tempdir.path().join("b").exists());
+    assert!(str::from_utf8(&output.stderr)?.contains(&format!(
+        "    Changing `package.version` in `{}` from \"0.1.0\" to \"0.2.0\"\n",
+        tempdir.path().join("b").join("Cargo.toml").display(),
+    )));
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "a",
+        "b",
+        "--bump",
+        "minor",
+        "--set-version",
+        "1.0.0",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert!(!tempdir.path().join("b").exists());
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "a",
+        "b",
+        "--set-version",
+        "1.0.0-alpha.1",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+    let manifest = fs::read_to_string(tempdir.path().join("b").join("Cargo.toml"))?;
+    assert!(manifest.contains("\nversion = \"1.0.0-alpha.1\"\n"));
+
+    fs::write(
+        tempdir.path().join("a").join("Cargo.toml"),
+        "[package]\nname = \"a\"\nversion = \"one\"\nedition = \"2021\"\n",
+    )?;
+    let err = cargo_member::Cp::new(&tempdir.path().join("a"), &tempdir.path().join("c"))
+        .version(Some(cargo_member::BumpLevel::Patch))
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "invalid `package.version` in `{}`",
+            tempdir.path().join("a").join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+    assert!(!tempdir.path().join("c").exists());
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+}
+
+#[test]
+fn name_suffix() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-name-suffix")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+    cargo_member::Cp::from_metadata(
+        &metadata,
+        "a",
+        &tempdir.path().join("ws").join("x").join("a"),
+    )
+    .dry_run(true)
+    .exec()
+    .unwrap_err();
+
+    for (dir, expected_name) in &[("x", "a-copy"), ("y", "a-copy-2")] {
+        let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+        let dst = tempdir.path().join("ws").join(dir).join("a");
+
+        let mut stderr = vec![];
+
+        cargo_member::Cp::from_metadata(&metadata, "a", &dst)
+            .name_suffix(Some("-copy"))
+            .stderr(NoColor::new(&mut stderr))
+            .exec()?;
+
+        assert!(str::from_utf8(&stderr)?.starts_with(&format!(
+            "note: `a` is taken. naming the copy `{}`\n",
+            expected_name,
+        )));
+        let manifest = fs::read_to_string(dst.join("Cargo.toml"))?;
+        assert!(manifest.contains(&format!("\nname = {:?}\n", expected_name)));
+    }
+    assert_manifest(
+        &tempdir.path().join("ws").join("Cargo.toml"),
+        EXPECTED_MANIFEST,
+    )?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "x/a",
+    "y/a",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn register_in_src() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-register-in-src")?;
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace(
+            "{{src}}",
+            &tempdir.path().join("ws1").join("a").to_string_lossy(),
+        )
+        .replace(
+            "{{dst}}",
+            &tempdir.path().join("ws2").join("b").to_string_lossy(),
+        )
+        .replace("{{ws1}}", &tempdir.path().join("ws1").to_string_lossy());
+
+    fs::create_dir(tempdir.path().join("ws1"))?;
+    fs::create_dir(tempdir.path().join("ws2"))?;
+    cargo_new(&tempdir.path().join("ws1").join("a"))?;
+    cargo_new(&tempdir.path().join("ws2").join("x"))?;
+    fs::write(tempdir.path().join("ws1").join("Cargo.toml"), MANIFEST_1)?;
+    fs::write(tempdir.path().join("ws2").join("Cargo.toml"), MANIFEST_2)?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws1").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("ws2").join("b"))
+        .register_in(cargo_member::RegisterIn::Src)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("ws1").join("Cargo.toml"),
+        EXPECTED_MANIFEST_1,
+    )?;
+    assert_manifest(&tempdir.path().join("ws2").join("Cargo.toml"), MANIFEST_2)?;
+    assert_manifest(
+        &tempdir.path().join("ws2").join("b").join("Cargo.toml"),
+        EXPECTED_MANIFEST_B,
+    )?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    cargo_metadata(&tempdir.path().join("ws1").join("Cargo.toml"), &[])?;
+    cargo_metadata(&tempdir.path().join("ws2").join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static MANIFEST_1: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+
+    static MANIFEST_2: &str = r#"[workspace]
+members = ["x"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST_1: &str = r#"[workspace]
+members = ["a",
+    "../ws2/b",
+]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST_B: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2024"
+workspace = "../../ws1"
+
+[dependencies]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Copying `{{src}}` to `{{dst}}`
+      Adding `package.workspace = "../../ws1"` to `{{dst}}/Cargo.toml`
+    Changing `package.name` in `{{dst}}/Cargo.toml` from "a" to "b"
+       Found workspace at {{ws1}}
+      Adding "../ws2/b" to `workspace.members`
+"#;
+}
+
+#[cfg(windows)]
+#[test]
+fn windows() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-windows")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("COM1"))
+        .exec()
+        .unwrap_err();
+    assert_eq!("`COM1` is a reserved name on Windows", err.to_string());
+
+    let dst = tempdir
+        .path()
+        .join("x".repeat(100))
+        .join("y".repeat(100))
+        .join("z".repeat(100))
+        .join("b");
+    cargo_member::Cp::from_metadata(&metadata, "a", &dst)
+        .register_in(cargo_member::RegisterIn::None)
+        .exec()?;
+    let manifest = fs::read_to_string(format!(r"\\?\{}", dst.join("Cargo.toml").display()))?;
+    assert!(manifest.contains(r#"name = "b""#));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[cfg(unix)]
+#[test]
+fn rollback() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-rollback")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    std::os::unix::fs::symlink(
+        tempdir.path().join("nonexisting"),
+        tempdir
+            .path()
+            .join("ws")
+            .join("a")
+            .join("src")
+            .join("dangling"),
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    let dst = tempdir.path().join("new").join("b");
+    cargo_member::Cp::from_metadata(&metadata, "a", &dst)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert!(!tempdir.path().join("new").exists());
+    assert!(str::from_utf8(&stderr)?.ends_with(&format!(
+        "    Removing directory `{}`\n",
+        tempdir.path().join("new").display(),
+    )));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn respect_cargo_config() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-respect-cargo-config")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    fs::create_dir(tempdir.path().join("ws").join("a").join(".cargo"))?;
+    fs::write(
+        tempdir
+            .path()
+            .join("ws")
+            .join("a")
+            .join(".cargo")
+            .join("config.toml"),
+        "[build]\ntarget-dir = \"out\"\n",
+    )?;
+    fs::create_dir(tempdir.path().join("ws").join("a").join("out"))?;
+    fs::write(
+        tempdir.path().join("ws").join("a").join("out").join("x"),
+        "",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    for (respect_cargo_config, name) in &[(false, "b"), (true, "c")] {
+        cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join(name))
+            .respect_cargo_config(*respect_cargo_config)
+            .exec()?;
+
+        assert!(tempdir
+            .path()
+            .join(name)
+            .join("src")
+            .join("main.rs")
+            .exists());
+        assert_eq!(
+            !respect_cargo_config,
+            tempdir.path().join(name).join("out").exists(),
+        );
+    }
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn artifact_dirs() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-artifact-dirs")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    for dir in &["target", "dist", ".git"] {
+        fs::create_dir(tempdir.path().join("ws").join("a").join(dir))?;
+        fs::write(
+            tempdir.path().join("ws").join("a").join(dir).join("x"),
+            "xyz",
+        )?;
+    }
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b"))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+    assert!(str::from_utf8(&stderr)?.contains("Skipped 6 bytes in `.git`, `target`\n"));
+    assert!(!tempdir.path().join("b").join("target").exists());
+    assert!(!tempdir.path().join("b").join(".git").exists());
+    assert!(tempdir.path().join("b").join("dist").exists());
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("c"))
+        .artifact_dirs(["target", "dist"])
+        .exec()?;
+    assert!(!tempdir.path().join("c").join("target").exists());
+    assert!(!tempdir.path().join("c").join("dist").exists());
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("d"))
+        .copy_all(true)
+        .exec()?;
+    for dir in &["target", "dist", ".git"] {
+        assert!(tempdir.path().join("d").join(dir).join("x").exists());
+    }
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn ignore_files() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-ignore-files")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    let a = tempdir.path().join("ws").join("a");
+    cargo_new(&a)?;
+    fs::write(a.join(".gitignore"), "/.env\n/local/\n")?;
+    fs::write(a.join(".env"), "TOKEN=xxx\n")?;
+    fs::create_dir(a.join("local"))?;
+    fs::write(a.join("local").join("notes.txt"), "")?;
+    fs::create_dir(a.join("src").join("fixtures"))?;
+    fs::write(a.join("src").join("fixtures").join(".gitignore"), "*.key\n")?;
+    fs::write(a.join("src").join("fixtures").join("id.key"), "")?;
+    fs::write(a.join("src").join("fixtures").join("data.json"), "{}")?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    // Not in a Git repository.
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b")).exec()?;
+    let b = tempdir.path().join("b");
+    assert!(b.join(".gitignore").exists());
+    assert!(!b.join(".env").exists());
+    assert!(!b.join("local").exists());
+    assert!(b.join("src").join("fixtures").join("data.json").exists());
+    assert!(!b.join("src").join("fixtures").join("id.key").exists());
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("c"))
+        .copy_all(true)
+        .exec()?;
+    let c = tempdir.path().join("c");
+    assert!(c.join(".env").exists());
+    assert!(c.join("local").join("notes.txt").exists());
+    assert!(c.join("src").join("fixtures").join("id.key").exists());
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn inline_inherited() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-inline-inherited")?;
+
+    let ws = tempdir.path().join("ws");
+    fs::create_dir(&ws)?;
+    fs::write(ws.join("Cargo.toml"), WS_MANIFEST)?;
+    cargo_new_lib(&ws.join("a"))?;
+    cargo_new_lib(&ws.join("b"))?;
+    fs::write(ws.join("Cargo.toml"), WS_MANIFEST)?;
+    fs::write(ws.join("a").join("Cargo.toml"), A_MANIFEST)?;
+    let other = tempdir.path().join("other");
+    fs::create_dir(&other)?;
+    fs::write(other.join("Cargo.toml"), OTHER_MANIFEST)?;
+    let metadata = cargo_metadata(&ws.join("Cargo.toml"), &[])?;
+
+    // Stays in the same workspace.
+    cargo_member::Cp::from_metadata(&metadata, "a", &ws.join("c")).exec()?;
+    assert_manifest(
+        &ws.join("c").join("Cargo.toml"),
+        &A_MANIFEST.replace("\"a\"", "\"c\""),
+    )?;
+
+    let err = cargo_member::Cp::from_metadata(&metadata, "a", &other.join("a"))
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` inherits `package.edition`, `dependencies.b` from `{}`, which `{}` does not \
+             define for the copy. enable `--inline-inherited` to inline them",
+            ws.join("a").join("Cargo.toml").display(),
+            ws.join("Cargo.toml").display(),
+            other.join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+    assert!(!other.join("a").exists());
+
+    let mut stderr = vec![];
+    cargo_member::Cp::from_metadata(&metadata, "a", &other.join("a"))
+        .inline_inherited(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+    assert_manifest(&other.join("a").join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(str::from_utf8(&stderr)?.contains("Inlining `package.version` as \"1.2.3\"\n"));
+    cargo_metadata(&other.join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static WS_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b"]
+
+[workspace.package]
+version = "1.2.3"
+edition = "2021"
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static OTHER_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = []
+
+[workspace.package]
+version = "0.1.0"
+"#;
+
+    static A_MANIFEST: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition.workspace = true
+
+[dependencies]
+b.workspace = true
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[package]
+name = "a"
+version = "1.2.3"
+edition = "2021"
+
+[dependencies]
+b = { path = "../../ws/b" }
+"#;
+}
+
+#[test]
+fn rename_targets() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-rename-targets")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), WS_MANIFEST)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), WS_MANIFEST)?;
+    fs::write(tempdir.path().join("a").join("Cargo.toml"), A_MANIFEST)?;
+    fs::create_dir(tempdir.path().join("a").join("src").join("bin"))?;
+    fs::write(
+        tempdir
+            .path()
+            .join("a")
+            .join("src")
+            .join("bin")
+            .join("a.rs"),
+        "fn main() {}\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("b")).exec()?;
+    assert_manifest(
+        &tempdir.path().join("b").join("Cargo.toml"),
+        EXPECTED_MANIFEST_B,
+    )?;
+    assert!(tempdir
+        .path()
+        .join("b")
+        .join("src")
+        .join("bin")
+        .join("a.rs")
+        .exists());
+
+    let mut stderr = vec![];
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("c"))
+        .rename_paths(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+    assert_manifest(
+        &tempdir.path().join("c").join("Cargo.toml"),
+        EXPECTED_MANIFEST_C,
+    )?;
+    assert!(tempdir
+        .path()
+        .join("c")
+        .join("src")
+        .join("bin")
+        .join("c.rs")
+        .exists());
+    assert!(!tempdir
+        .path()
+        .join("c")
+        .join("src")
+        .join("bin")
+        .join("a.rs")
+        .exists());
+    assert!(tempdir.path().join("c").join("src").join("lib.rs").exists());
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "Renaming `{}` to `{}`\n",
+        tempdir.path().join("a").join("src/bin/a.rs").display(),
+        tempdir.path().join("c").join("src/bin/c.rs").display(),
+    )));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static WS_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static A_MANIFEST: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+name = "a"
+
+[[bin]]
+name = "a"
+"#;
+
+    static EXPECTED_MANIFEST_B: &str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+name = "b"
+
+[[bin]]
+name = "b"
+path = "src/bin/a.rs"
+"#;
+
+    static EXPECTED_MANIFEST_C: &str = r#"[package]
+name = "c"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+name = "c"
+
+[[bin]]
+name = "c"
+"#;
+}
+
+#[test]
+fn smoke_test() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-smoke-test")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    let a_manifest_path = tempdir.path().join("a").join("Cargo.toml");
+    fs::write(
+        &a_manifest_path,
+        fs::read_to_string(&a_manifest_path)? + A_DEPS,
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let lockfile = fs::read_to_string(tempdir.path().join("Cargo.lock"))?;
+
+    let mut stderr = vec![];
+
+    // `../b` does not resolve from `nested/a2`.
+    let err =
+        cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("nested").join("a2"))
+            .smoke_test(true)
+            .rollback_on_failure(true)
+            .offline(true)
+            .stderr(NoColor::new(&mut stderr))
+            .exec()
+            .unwrap_err();
+
+    assert!(
+        err.to_string().contains(" build --manifest-path "),
+        "{}",
+        err
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    assert_manifest(&tempdir.path().join("Cargo.lock"), &lockfile)?;
+    assert!(!tempdir.path().join("nested").exists());
+    let stderr = str::from_utf8(&stderr)?;
+    assert!(
+        stderr.contains("    Building `a2` as a smoke test\n"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with(&format!(
+            "   Restoring {}\n    Removing directory `{}`\n",
+            tempdir.path().join("Cargo.toml").display(),
+            tempdir.path().join("nested").display(),
+        )),
+        "{}",
+        stderr
+    );
+
+    let mut stderr = vec![];
+
+    cargo_member::Cp::from_metadata(&metadata, "a", &tempdir.path().join("a3"))
+        .smoke_test(true)
+        .rollback_on_failure(true)
+        .offline(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert!(str::from_utf8(&stderr)?
+        .ends_with("    Building `a3` as a smoke test\n    Finished the smoke test of `a3`\n"));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static A_DEPS: &str = r#"b = { path = "../b" }
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "b",
+    "a3",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn time() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-time")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "cp", "a", "b")
+        .dir(tempdir.path())
+        .stderr_capture()
+        .run()?;
+    assert!(!str::from_utf8(&output.stderr)?.contains("Timing"));
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "--time",
+        "a",
+        "c",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    let phases = str::from_utf8(&output.stderr)?
+        .lines()
+        .flat_map(|line| line.strip_prefix("      Timing "))
+        .map(|line| line.split_whitespace().next().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["metadata", "planning", "file", "manifest", "total"],
+        phases,
+    );
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+#[test]
+fn replace_in() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-replace-in")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b", "c"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    let a_manifest_path = tempdir.path().join("a").join("Cargo.toml");
+    fs::write(
+        &a_manifest_path,
+        fs::read_to_string(&a_manifest_path)? + A_DEPS,
+    )?;
+    let original_a_manifest = fs::read_to_string(&a_manifest_path)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "b",
+        "forks/b2",
+        "--replace-in",
+        "c",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_eq!(
+        format!(
+            "error: `{}` does not depend on `{}`\n",
+            tempdir.path().join("c").join("Cargo.toml").display(),
+            tempdir.path().join("b").display(),
+        ),
+        str::from_utf8(&output.stderr)?,
+    );
+    assert!(!tempdir.path().join("forks").exists());
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "--dry-run",
+        "b",
+        "forks/b2",
+        "--replace-in",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    assert!(str::from_utf8(&output.stderr)?.contains(&format!(
+        "    Changing `dependencies.b.path` in `{}` from \"../b\" to \"../forks/b2\"\n    \
+         Changing `dependencies.b.package` to \"b2\"\n    Changing `dev-dependencies.b.path` in \
+         `{}` from `workspace = true` to \"../forks/b2\"\n    Changing \
+         `dev-dependencies.b.package` to \"b2\"\n",
+        a_manifest_path.display(),
+        a_manifest_path.display(),
+    )));
+    assert_manifest(&a_manifest_path, &original_a_manifest)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "cp",
+        "b",
+        "forks/b2",
+        "--replace-in",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(
+        &a_manifest_path,
+        &original_a_manifest.replace(A_DEPS, EXPECTED_A_DEPS),
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let a = metadata.packages.iter().find(|p| p.name == "a").unwrap();
+    assert!(a.dependencies.iter().all(|d| d.name == "b2"));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "3"
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static A_DEPS: &str = r#"b = { path = "../b" }
+
+[dev-dependencies]
+b = { workspace = true }
+"#;
+
+    static EXPECTED_A_DEPS: &str = r#"b = { path = "../forks/b2", package = "b2" }
+
+[dev-dependencies]
+b = { path = "../forks/b2", package = "b2" }
+"#;
+}
+
+#[test]
+fn plan() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-cp-plan")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+
+    let plan = cargo_member::Cp::new(&tempdir.path().join("a"), &tempdir.path().join("b"))
+        .stderr(NoColor::new(vec![]))
+        .plan()?;
+
+    let actions = plan
+        .actions()
+        .iter()
+        .map(|action| match action {
+            cargo_member::Action::WriteFile { path, .. } => ("write-file", path.clone()),
+            cargo_member::Action::CreateDir { path } => ("create-dir", path.clone()),
+            cargo_member::Action::CopyFile { dst, .. } => ("copy-file", dst.clone()),
+            cargo_member::Action::RemoveDir { path } => ("remove-dir", path.clone()),
+            cargo_member::Action::RemoveFile { path } => ("remove-file", path.clone()),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            ("create-dir", tempdir.path().join("b")),
+            ("create-dir", tempdir.path().join("b").join("src")),
+            (
+                "copy-file",
+                tempdir.path().join("b").join("src").join("main.rs")
+            ),
+            ("write-file", tempdir.path().join("b").join("Cargo.toml")),
+            ("write-file", tempdir.path().join("Cargo.toml")),
+        ],
+        actions,
+    );
+    assert!(!tempdir.path().join("b").exists());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    plan.apply()?;
+
+    assert!(tempdir
+        .path()
+        .join("b")
+        .join("src")
+        .join("main.rs")
+        .exists());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "b",
+]
+exclude = []
+"#;
+}
+
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
@@ -0,0 +1,209 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::MetadataCommand;
+use duct::cmd;
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tempdir::TempDir;
+
+/// Every mutating command leaves the file system untouched with `--dry-run`.
+#[test]
+fn no_writes() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-dry-run-no-writes")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b", "c"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    append(&tempdir.path().join("b").join("Cargo.toml"), B_DEPS)?;
+    fs::create_dir_all(tempdir.path().join("a").join("target").join("debug"))?;
+    fs::write(
+        tempdir
+            .path()
+            .join("a")
+            .join("target")
+            .join("debug")
+            .join("artifact"),
+        "",
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+
+    let before = snapshot(tempdir.path())?;
+
+    for args in COMMANDS {
+        let output = cmd(env!("CARGO_BIN_EXE_cargo-member"), args.iter())
+            .dir(tempdir.path())
+            .stdout_null()
+            .stderr_capture()
+            .unchecked()
+            .run()?;
+        assert!(
+            output.status.success(),
+            "`{}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr),
+        );
+        assert_eq!(
+            before,
+            snapshot(tempdir.path())?,
+            "`{}` modified the file system",
+            args.join(" "),
+        );
+    }
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = ["c"]
+resolver = "2"
+"#;
+
+    static B_DEPS: &str = r#"a = { path = "../a" }
+"#;
+
+    static COMMANDS: &[&[&str]] = &[
+        &["member", "init", "--dry-run", "new-workspace"],
+        &["member", "include", "--dry-run", "c"],
+        &["member", "exclude", "--dry-run", "b"],
+        &[
+            "member",
+            "exclude",
+            "--dry-run",
+            "--report-file",
+            "report.jsonl",
+            "b",
+        ],
+        &["member", "deactivate", "--dry-run", "b"],
+        &["member", "focus", "--dry-run", "a"],
+        &["member", "activate-all", "--dry-run"],
+        &["member", "new", "--dry-run", "nested/d"],
+        &[
+            "member",
+            "new",
+            "--dry-run",
+            "--position",
+            "before:b",
+            "nested/d",
+        ],
+        &["member", "cp", "--dry-run", "a", "nested/a2"],
+        &["member", "rm", "--dry-run", "b"],
+        &["member", "mv", "--dry-run", "b", "crates/b"],
+        &["member", "mv", "--dry-run", "c", "crates/c"],
+        &["member", "mv", "--dry-run", "--merge-into", "a", "b"],
+        &[
+            "member",
+            "check-version-sync",
+            "--dry-run",
+            "--fix",
+            "--expected",
+            "0.2.0",
+        ],
+        &["member", "canonicalize", "--dry-run"],
+        &["member", "bump", "--dry-run", "minor"],
+        &[
+            "member",
+            "vendor-member",
+            "--dry-run",
+            "--strip-path-deps-to-versions",
+            "b",
+        ],
+        &["member", "gc", "--dry-run"],
+    ];
+}
+
+/// A command failing under `--dry-run` leaves the file system untouched too.
+#[test]
+fn failures() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-dry-run-failures")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    fs::create_dir(tempdir.path().join("c"))?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+
+    let before = snapshot(tempdir.path())?;
+
+    for args in COMMANDS {
+        let output = cmd(env!("CARGO_BIN_EXE_cargo-member"), args.iter())
+            .dir(tempdir.path())
+            .stdout_null()
+            .stderr_capture()
+            .unchecked()
+            .run()?;
+        assert!(
+            !output.status.success(),
+            "`{}` succeeded: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr),
+        );
+        assert_eq!(
+            before,
+            snapshot(tempdir.path())?,
+            "`{}` modified the file system",
+            args.join(" "),
+        );
+    }
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+resolver = "2"
+"#;
+
+    static COMMANDS: &[&[&str]] = &[
+        &["member", "include", "--dry-run", "c"],
+        &["member", "focus", "--dry-run", "c"],
+        &["member", "cp", "--dry-run", "a", "b"],
+        &["member", "mv", "--dry-run", "a", "b"],
+        &["member", "bump", "--dry-run", "bogus"],
+        &["member", "bump", "--dry-run", "-p", "c", "minor"],
+    ];
+}
+
+/// The contents (`None` for directories) and the modification time of an entry.
+type Entry = (Option<Vec<u8>>, SystemTime);
+
+/// The contents and the modification times of every file and directory under `dir`.
+fn snapshot(dir: &Path) -> io::Result<BTreeMap<PathBuf, Entry>> {
+    let mut snapshot = BTreeMap::new();
+    let mut stack = vec![dir.to_owned()];
+    while let Some(path) = stack.pop() {
+        let metadata = fs::metadata(&path)?;
+        let contents = if metadata.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                stack.push(entry?.path());
+            }
+            None
+        } else {
+            Some(fs::read(&path)?)
+        };
+        snapshot.insert(path, (contents, metadata.modified()?));
+    }
+    Ok(snapshot)
+}
+
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
+
+fn append(path: &Path, contents: &str) -> io::Result<()> {
+    let orig = fs::read_to_string(path)?;
+    fs::write(path, orig + contents)
+}
+
+fn cargo_metadata(manifest_path: &Path) -> cargo_metadata::Result<()> {
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .map(drop)
+}
@@ -23,7 +23,7 @@ fn normal() -> anyhow::Result<()> {
 
     let mut stderr = vec![];
 
-    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], &["c"])
+    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], ["c"])
         .dry_run(false)
         .stderr(NoColor::new(&mut stderr))
         .exec()?;
@@ -65,7 +65,7 @@ fn dry_run() -> anyhow::Result<()> {
 
     let mut stderr = vec![];
 
-    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], &["c"])
+    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], ["c"])
         .dry_run(true)
         .stderr(NoColor::new(&mut stderr))
         .exec()?;
@@ -89,12 +89,446 @@ warning: not modifying the manifest due to dry run
 "#;
 }
 
+#[test]
+fn plan() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-plan")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    let plan =
+        cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], None::<&str>)
+            .dry_run(false)
+            .stderr(NoColor::new(&mut stderr))
+            .plan()?;
+
+    assert_eq!(
+        [cargo_member::Action::WriteFile {
+            path: tempdir.path().join("Cargo.toml"),
+            before: Some(ORIGINAL.to_owned()),
+            after: EXPECTED_MANIFEST.to_owned(),
+        }],
+        plan.actions(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    plan.apply()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = [
+    "b",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "b" from `workspace.members`
+      Adding "b" to `workspace.exclude`
+"#;
+}
+
+#[test]
+fn dependents() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-dependents")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new_lib(&tempdir.path().join("b"))?;
+    let mut manifest = fs::read_to_string(tempdir.path().join("a").join("Cargo.toml"))?;
+    manifest += "b = { path = \"../b\" }\n";
+    fs::write(tempdir.path().join("a").join("Cargo.toml"), manifest)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::Exclude::from_metadata(&metadata, None::<&Path>, ["b"])
+        .strict(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` is a dependency of `a`, which will remain in the workspace. enable `--force` to \
+             proceed anyway",
+            tempdir.path().join("b").display(),
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Exclude::from_metadata(&metadata, None::<&Path>, ["b"])
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(
+        &stderr,
+        &format!(
+            "warning: `{}` is a dependency of `a`, which will remain in the workspace\n{}",
+            tempdir.path().join("b").display(),
+            EXPECTED_STDERR,
+        ),
+    )?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+resolver = "3"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "b" from `workspace.members`
+      Adding "b" to `workspace.exclude`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+}
+
+#[test]
+fn matching() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-matching")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "a-tests", "b-tests", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--matching",
+        "-tests$",
+        "--exclude-matching",
+        "^b",
+        "-p",
+        "c",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_1)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--matching",
+        "^b",
+        "--match-field",
+        "path",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_2)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "a-tests", "b-tests", "c"]
+exclude = []
+resolver = "3"
+"#;
+
+    static EXPECTED_MANIFEST_1: &str = r#"[workspace]
+members = ["a", "b-tests"]
+exclude = [
+    "a-tests",
+    "c",
+]
+resolver = "3"
+"#;
+
+    static EXPECTED_MANIFEST_2: &str = r#"[workspace]
+members = ["a"]
+exclude = [
+    "a-tests",
+    "c",
+    "b-tests",
+]
+resolver = "3"
+"#;
+}
+
+#[test]
+fn recursive() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-recursive")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["crates/a", "crates/b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::create_dir(tempdir.path().join("empty"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--recursive",
+        "--dry-run",
+        "crates",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(
+        &output.stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--recursive",
+        "crates",
+        "empty",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--recursive",
+        "--keep-going",
+        "crates",
+        "empty",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["c", "crates/a", "crates/b"]
+exclude = []
+resolver = "3"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"note: `{{root}}/crates` expands to 2 member(s)
+    Removing "crates/a" from `workspace.members`
+      Adding "crates/a" to `workspace.exclude`
+    Removing "crates/b" from `workspace.members`
+      Adding "crates/b" to `workspace.exclude`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["c"]
+exclude = [
+    "crates/a",
+    "crates/b",
+]
+resolver = "3"
+"#;
+}
+
+#[test]
+fn absolute_entry() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-absolute-entry")?;
+
+    let manifest = ORIGINAL.replace("{{a}}", &tempdir.path().join("a").to_string_lossy());
+    fs::write(tempdir.path().join("Cargo.toml"), &manifest)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), &manifest)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join(".").join("a")])
+        .dry_run(true)
+        .exec()?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), &manifest)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("a")], None::<&str>)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{a}}", &tempdir.path().join("a").to_string_lossy()),
+    )?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["b", "{{a}}"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["b"]
+exclude = [
+    "a",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "{{a}}" from `workspace.members`
+      Adding "a" to `workspace.exclude`
+"#;
+}
+
+#[test]
+fn report_file() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-report-file")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let report_file = tempdir.path().join("report.jsonl");
+    fs::write(&report_file, "{}\n")?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--report-file",
+        "report.jsonl",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    let records = fs::read_to_string(&report_file)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(2, records.len());
+    let mut record = records[1].clone();
+    assert!(record["timestamp"].is_f64());
+    record.as_object_mut().unwrap().remove("timestamp");
+    assert_eq!(
+        serde_json::json!({
+            "action": "write-file",
+            "path": tempdir.path().join("Cargo.toml"),
+            "before": ORIGINAL,
+            "after": EXPECTED_MANIFEST,
+        }),
+        record,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exclude",
+        "--dry-run",
+        "--report-file",
+        "report.jsonl",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+    assert_eq!(2, fs::read_to_string(&report_file)?.lines().count());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = [
+    "b",
+]
+"#;
+}
+
+#[test]
+fn default_members() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exclude-default-members")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("b")], ["c"])
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+default-members = ["a", "./b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+default-members = ["a"]
+exclude = [
+    "b",
+    "c",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing "b" from `workspace.members`
+      Adding "b" to `workspace.exclude`
+    Removing "./b" from `workspace.default-members`
+    Removing "c" from `workspace.members`
+      Adding "c" to `workspace.exclude`
+"#;
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
     Ok(())
 }
 
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
+
 fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
     let modified = fs::read_to_string(manifest_path)?;
     assert_diff!(expected, &modified, "\n", 0);
@@ -0,0 +1,115 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::{env, fs, io, path::Path};
+use tempdir::TempDir;
+
+#[test]
+fn jobs() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exec-jobs")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exec",
+        "--jobs",
+        "2",
+        env::var("CARGO")?,
+        "locate-project",
+        "--message-format",
+        "plain",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .read()?;
+    let mut lines = stdout.lines().collect::<Vec<_>>();
+    lines.sort_unstable();
+    assert_eq!(
+        ["a", "b", "c"]
+            .iter()
+            .map(|name| tempdir.path().join(name).join("Cargo.toml"))
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>(),
+        lines,
+    );
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+"#;
+}
+
+#[cfg(unix)]
+#[test]
+fn keep_going() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-exec-keep-going")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let script = r#"echo "$(basename "$PWD")"; test "$(basename "$PWD")" != b"#;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exec",
+        "sh",
+        "-c",
+        script,
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert_eq!("a\nb\n", std::str::from_utf8(&output.stdout)?);
+    assert!(std::str::from_utf8(&output.stderr)?.contains(
+        "note: skipped 1 member(s). enable `--keep-going` to run the command in every member"
+    ));
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "exec",
+        "--keep-going",
+        "--jobs",
+        "3",
+        "--no-buffer",
+        "sh",
+        "-c",
+        script,
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    let mut lines = std::str::from_utf8(&output.stdout)?
+        .lines()
+        .collect::<Vec<_>>();
+    lines.sort_unstable();
+    assert_eq!(["[a] a", "[b] b", "[c] c"], *lines);
+    assert!(std::str::from_utf8(&output.stderr)?.contains("failed in 1 of 3 member(s)"));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b", "c"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
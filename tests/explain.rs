@@ -0,0 +1,122 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::{fs, path::Path, str};
+use tempdir::TempDir;
+
+#[test]
+fn explain() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-explain")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for name in &["a", "b"] {
+        write_package(&tempdir.path().join(name), name)?;
+    }
+    cmd!(env!("CARGO"), "generate-lockfile", "--offline")
+        .dir(tempdir.path())
+        .stderr_null()
+        .run()?;
+    let manifest_path = tempdir.path().canonicalize()?.join("Cargo.toml");
+
+    let stderr = |args: &[&str]| -> anyhow::Result<String> {
+        let output = cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member"]
+                .iter()
+                .chain(args)
+                .chain(&["--explain", "--dry-run"]),
+        )
+        .dir(tempdir.path())
+        .env("CARGO_TERM_COLOR", "never")
+        .stderr_capture()
+        .run()?;
+        Ok(str::from_utf8(&output.stderr)?.to_owned())
+    };
+
+    assert!(stderr(&["exclude", "a"])?.starts_with(&format!(
+        "     Explain `exclude` moves the package(s) from `workspace.members` to \
+         `workspace.exclude` in `{}`, so that cargo ignores them while they stay under the \
+         workspace root\n",
+        manifest_path.display(),
+    )));
+    assert!(stderr(&["focus", "a", "--exclude"])?.starts_with(&format!(
+        "     Explain `focus` includes `{}` and excludes every other member, editing \
+         `workspace.members` and `workspace.exclude` in `{}`\n",
+        tempdir.path().canonicalize()?.join("a").display(),
+        manifest_path.display(),
+    )));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+resolver = "2"
+"#;
+}
+
+#[test]
+fn failure() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-explain-failure")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    write_package(&tempdir.path().join("a"), "a")?;
+    fs::create_dir(tempdir.path().join("b"))?;
+    cmd!(env!("CARGO"), "generate-lockfile", "--offline")
+        .dir(tempdir.path())
+        .stderr_null()
+        .run()?;
+    let manifest_path = tempdir.path().canonicalize()?.join("Cargo.toml");
+
+    // The explanation is printed before the command fails.
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "b",
+        "--explain",
+    )
+    .dir(tempdir.path())
+    .env("CARGO_TERM_COLOR", "never")
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert_eq!(
+        format!(
+            "     Explain `include` adds the package(s) to `workspace.members` and removes them \
+             from `workspace.exclude` in `{}`, then updates `Cargo.lock`\n\
+             error: `{}` does not seem to be a package. enable `--force` to add\n\
+             \n\
+             Caused by:\n  \
+             `{}` does not exist\n",
+            manifest_path.display(),
+            tempdir.path().canonicalize()?.join("b").display(),
+            tempdir
+                .path()
+                .canonicalize()?
+                .join("b")
+                .join("Cargo.toml")
+                .display(),
+        ),
+        str::from_utf8(&output.stderr)?,
+    );
+    assert_eq!(MANIFEST, fs::read_to_string(&manifest_path)?);
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+resolver = "2"
+"#;
+}
+
+fn write_package(dir: &Path, name: &str) -> anyhow::Result<()> {
+    fs::create_dir_all(dir.join("src"))?;
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2021\"\n",
+            name,
+        ),
+    )?;
+    fs::write(dir.join("src").join("lib.rs"), "")?;
+    Ok(())
+}
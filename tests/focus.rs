@@ -31,7 +31,9 @@ fn focus() -> anyhow::Result<()> {
     assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
     assert_stderr(
         &stderr,
-        &EXPECTED_STDERR.replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
+        &EXPECTED_STDERR
+            .replace("{{root}}", &tempdir.path().to_string_lossy())
+            .replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
     )?;
     cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
     return Ok(());
@@ -47,16 +49,536 @@ exclude = []
 "#;
 
     static EXPECTED_STDERR: &str = r#"    Removing "b" from `workspace.members`
+      Saving `workspace.{members, exclude}` to `{{root}}/.cargo-member-focus.json`
     Updating {}
 "#;
 }
 
+#[test]
+fn restore() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-restore")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    cargo_new(&tempdir.path().join("c"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let snapshot_path = tempdir.path().join(".cargo-member-focus.json");
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--restore",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert_stderr(
+        &output.stderr,
+        &format!(
+            "error: `{}` does not exist. there is no focus to restore\n",
+            snapshot_path.display(),
+        ),
+    )?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--exclude",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), FOCUSED)?;
+    assert!(snapshot_path.exists());
+
+    // Focusing again keeps the first snapshot.
+    cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "focus", "b")
+        .dir(tempdir.path())
+        .stderr_null()
+        .run()?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--restore",
+        "--dry-run",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+    assert!(snapshot_path.exists());
+
+    let mut stderr = vec![];
+    cargo_member::RestoreFocus::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert!(!snapshot_path.exists());
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = [
+    "a",
+    "b", # b
+    "c",
+]
+"#;
+
+    static FOCUSED: &str = r#"[workspace]
+resolver = "3"
+members = [
+    "a",
+]
+exclude = [
+    "b",
+    "c",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"   Restoring `workspace.members` in `{{root}}/Cargo.toml`
+   Restoring `workspace.exclude` in `{{root}}/Cargo.toml`
+    Removing {{root}}/.cargo-member-focus.json
+    Updating {{root}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn workspace_root() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-workspace-root")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+
+    let err = cargo_member::Focus::new(tempdir.path(), tempdir.path())
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`{}` is the workspace root. enable `--force` to focus",
+            tempdir.path().display(),
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    cargo_member::Focus::new(tempdir.path(), tempdir.path())
+        .force(true)
+        .dry_run(true)
+        .exec()?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+#[test]
+fn non_package() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-non-package")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+
+    let err = cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("c"))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`{}` does not seem to be a package. enable `--force` to focus",
+            tempdir.path().join("c").display(),
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+}
+
+#[test]
+fn non_member() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-non-member")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let err = cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("b"))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "`{}` is not a member of the workspace at `{}`. enable `--force` to focus",
+            tempdir.path().join("b").display(),
+            tempdir.path().display(),
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("b"))
+        .force(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn write_failure() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-write-failure")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    cargo_new(&tempdir.path().join("c"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    // makes the write of the temporary file fail, after the snapshot is written
+    fs::create_dir(tempdir.path().join("Cargo.toml.cargo-member.tmp"))?;
+
+    let mut stderr = vec![];
+    let err = cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("a"))
+        .exclude(true)
+        .offline(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "failed to write {}",
+            tempdir.path().join("Cargo.toml").display()
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let snapshot_path = tempdir.path().join(cargo_member::FOCUS_SNAPSHOT_FILE_NAME);
+    assert!(!snapshot_path.exists());
+    assert!(
+        str::from_utf8(&stderr)?.ends_with(&format!("    Removing {}\n", snapshot_path.display(),))
+    );
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+"#;
+}
+
+#[test]
+fn json_plan() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-json-plan")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--exclude",
+        "--dry-run",
+        "--message-format",
+        "json",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_null()
+    .run()?;
+
+    assert_eq!(
+        serde_json::json!({
+            "focus": "a",
+            "deactivate": ["b", "c"],
+            "exclude": ["b", "c"],
+            "default_members": ["a"],
+        }),
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)?,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+default-members = ["a"]
+exclude = []
+"#;
+}
+
+#[test]
+fn with_dependents() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-with-dependents")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c", "d"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("b").join("Cargo.toml"),
+        "a = { path = \"../a\" }\n",
+    )?;
+    append(
+        &tempdir.path().join("c").join("Cargo.toml"),
+        "b = { path = \"../b\" }\n",
+    )?;
+    append(
+        &tempdir.path().join("d").join("Cargo.toml"),
+        "\n[dev-dependencies]\nc = { path = \"../c\" }\n",
+    )?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(tempdir.path().join("Cargo.toml"))
+        .exec()?;
+
+    for (name, focus) in [
+        (
+            "with_deps",
+            cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("b")).with_deps(true),
+        ),
+        (
+            "with_dependents",
+            cargo_member::Focus::new(tempdir.path(), &tempdir.path().join("b"))
+                .with_dependents(true),
+        ),
+    ] {
+        let err = focus.dry_run(true).exec().unwrap_err();
+        assert_eq!(
+            format!(
+                "`{}` requires the resolve graph. use `Focus::from_metadata` with the \
+                 dependencies resolved",
+                name,
+            ),
+            err.to_string(),
+        );
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Focus::from_metadata(&metadata, &tempdir.path().join("b"))
+        .with_deps(true)
+        .with_dependents(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    let stderr = str::from_utf8(&stderr)?;
+    for (dir, relation) in &[("a", "dependency"), ("c", "dependent"), ("d", "dependent")] {
+        assert!(stderr.contains(&format!(
+            "     Keeping `{}` active as a {} of `{}`\n",
+            tempdir.path().join(dir).display(),
+            relation,
+            tempdir.path().join("b").display(),
+        )));
+    }
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::Focus::from_metadata(&metadata, &tempdir.path().join("b"))
+        .with_dependents(true)
+        .allow_broken(true)
+        .offline(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["b", "c", "d", "a"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["b", "c", "d"]
+exclude = []
+"#;
+}
+
+#[test]
+fn package() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-package")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for path in &["crates/a", "crates/b"] {
+        cargo_new(&tempdir.path().join(path))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--dry-run",
+        "--message-format",
+        "json",
+        "-p",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_null()
+    .run()?;
+
+    assert_eq!(
+        serde_json::json!({
+            "focus": "crates/b",
+            "deactivate": ["crates/a"],
+            "exclude": [],
+            "default_members": [],
+        }),
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)?,
+    );
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--dry-run",
+        "-p",
+        "nonexistent",
+    )
+    .dir(tempdir.path())
+    .stdout_null()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?.contains("nonexistent"));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["crates/a", "crates/b"]
+"#;
+}
+
+#[test]
+fn allow_broken() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-focus-allow-broken")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("b").join("Cargo.toml"),
+        "a = { path = \"../a\" }\n",
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--offline",
+        "b"
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(str::from_utf8(&output.stderr)?.starts_with(&format!(
+        "     Keeping `{}` active as a dependency of `{}`\n",
+        tempdir.path().join("a").display(),
+        tempdir.path().join("b").display(),
+    )));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "focus",
+        "--offline",
+        "--allow-broken",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(
+        &tempdir.path().join("Cargo.toml"),
+        EXPECTED_MANIFEST_ALLOW_BROKEN,
+    )?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST_ALLOW_BROKEN: &str = r#"[workspace]
+members = [ "b"]
+exclude = []
+"#;
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
     Ok(())
 }
 
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
+
+fn append(path: &Path, contents: &str) -> io::Result<()> {
+    let orig = fs::read_to_string(path)?;
+    fs::write(path, orig + contents)
+}
+
 fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
     let modified = fs::read_to_string(manifest_path)?;
     assert_diff!(expected, &modified, "\n", 0);
@@ -0,0 +1,128 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use std::{
+    fs,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn gc() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-gc")?;
+    let target_dir = tempdir.path().join("target");
+
+    for path in &[
+        "debug/a",
+        "debug/deps/a-0123456789abcdef",
+        "debug/deps/a-0123456789abcdef.d",
+        "debug/deps/libb_c-0123456789abcdef.rlib",
+        "debug/deps/libgone-0123456789abcdef.rlib",
+        "debug/deps/gone-0123456789abcdef.d",
+        "debug/gone",
+        "debug/libgone.rlib",
+        "debug/.fingerprint/b-c-0123456789abcdef/lib-b_c",
+        "debug/.fingerprint/gone-0123456789abcdef/lib-gone",
+        "debug/incremental/gone-0jn6w0mhzlh9a/s-0",
+        "debug/incremental/not-a-hash/s-0",
+        "x86_64-unknown-linux-gnu/release/.fingerprint/gone-fedcba9876543210/lib-gone",
+        "x86_64-unknown-linux-gnu/release/deps/libgone-fedcba9876543210.rlib",
+    ] {
+        let path = target_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, "")?;
+    }
+
+    let err = cargo_member::Gc::new(&target_dir, ["a", "b-c"])
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "found 6 stale artifact(s) in `{}`. enable `--force` to remove them, or `--dry-run` \
+             to list them",
+            target_dir.display(),
+        ),
+        err.to_string(),
+    );
+
+    let mut stderr = vec![];
+
+    cargo_member::Gc::new(&target_dir, ["a", "b-c"])
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{target}}", &target_dir.to_string_lossy()),
+    )?;
+    assert!(target_dir
+        .join("debug")
+        .join("deps")
+        .join("gone-0123456789abcdef.d")
+        .exists());
+
+    cargo_member::Gc::new(&target_dir, ["a", "b-c"])
+        .force(true)
+        .exec()?;
+
+    for path in &[
+        "debug/a",
+        "debug/deps/a-0123456789abcdef",
+        "debug/deps/libb_c-0123456789abcdef.rlib",
+        "debug/.fingerprint/b-c-0123456789abcdef",
+        "debug/gone",
+        "debug/libgone.rlib",
+        "debug/incremental/not-a-hash",
+    ] {
+        assert!(target_dir.join(path).exists(), "{} should remain", path);
+    }
+    for path in &[
+        "debug/deps/libgone-0123456789abcdef.rlib",
+        "debug/.fingerprint/gone-0123456789abcdef",
+        "debug/incremental/gone-0jn6w0mhzlh9a",
+        "x86_64-unknown-linux-gnu/release/deps/libgone-fedcba9876543210.rlib",
+    ] {
+        assert!(
+            !target_dir.join(path).exists(),
+            "{} should be removed",
+            path
+        );
+    }
+    return Ok(());
+
+    static EXPECTED_STDERR: &str = r#"    Removing `{{target}}/debug/.fingerprint/gone-0123456789abcdef`
+    Removing `{{target}}/debug/deps/gone-0123456789abcdef.d`
+    Removing `{{target}}/debug/deps/libgone-0123456789abcdef.rlib`
+    Removing `{{target}}/debug/incremental/gone-0jn6w0mhzlh9a`
+    Removing `{{target}}/x86_64-unknown-linux-gnu/release/.fingerprint/gone-fedcba9876543210`
+    Removing `{{target}}/x86_64-unknown-linux-gnu/release/deps/libgone-fedcba9876543210.rlib`
+       Found 6 stale artifact(s), 0 bytes
+warning: not removing the artifacts due to dry run
+"#;
+}
+
+#[test]
+fn missing_target_dir() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-gc-missing-target-dir")?;
+    let target_dir = tempdir.path().join("target");
+
+    let mut stderr = vec![];
+
+    cargo_member::Gc::new(&target_dir, ["a"])
+        .force(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_stderr(
+        &stderr,
+        &format!("warning: `{}` does not exist\n", target_dir.display()),
+    )?;
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
@@ -6,6 +6,7 @@ use duct::cmd;
 use std::{
     env, fs, io,
     path::Path,
+    process::Stdio,
     str::{self, Utf8Error},
 };
 use tempdir::TempDir;
@@ -122,6 +123,1227 @@ warning: not modifying the manifest due to dry run
 "#;
 }
 
+#[test]
+fn cli_run() -> anyhow::Result<()> {
+    use cargo_member::cli::{Cargo, Context};
+    use structopt::StructOpt as _;
+
+    let tempdir = TempDir::new("cargo-member-test-include-cli-run")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let Cargo::Member(opt) = Cargo::from_iter_safe(&["cargo", "member", "include", "b"])?;
+    let mut stderr = vec![];
+    let ctx = Context::new(NoColor::new(&mut stderr))?.cwd(tempdir.path());
+    cargo_member::cli::run(opt, ctx)?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(str::from_utf8(&stderr)?.contains(r#"Adding "b" to `workspace.members`"#));
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a",
+    "b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn stdout() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-stdout")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--stdout",
+        "./b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .read()?;
+
+    assert_diff!(EXPECTED_STDOUT.trim_end(), &stdout, "\n", 0);
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = ["b"]
+"#;
+
+    static EXPECTED_STDOUT: &str = r#"[workspace]
+members = ["a",
+    "b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn glob_from_subdirectory() -> anyhow::Result<()> {
+    // Globs are relative to the CWD like the other paths.
+    for (cwd, path, glob) in &[
+        ("", "crates/a", "crates/b*"),
+        ("crates", "a", "b*"),
+        ("crates/a/src", "..", "../../b*"),
+    ] {
+        let tempdir = TempDir::new("cargo-member-test-include-glob-from-subdirectory")?;
+
+        cargo_new(&tempdir.path().join("crates").join("a"))?;
+        cargo_new(&tempdir.path().join("crates").join("b"))?;
+        fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+        cmd!(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            "member",
+            "include",
+            path,
+            glob,
+        )
+        .dir(tempdir.path().join(cwd))
+        .stderr_null()
+        .run()?;
+
+        assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "crates/a",
+    "crates/b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn relative_to_root() -> anyhow::Result<()> {
+    for cwd in &["", "crates"] {
+        let tempdir = TempDir::new("cargo-member-test-include-relative-to-root")?;
+
+        cargo_new(&tempdir.path().join("crates").join("a"))?;
+        cargo_new(&tempdir.path().join("crates").join("b"))?;
+        fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+        cmd!(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            "member",
+            "include",
+            "--relative-to-root",
+            "crates/a",
+            "crates/b*",
+        )
+        .dir(tempdir.path().join(cwd))
+        .stderr_null()
+        .run()?;
+
+        assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "crates/a",
+    "crates/b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn glob() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-glob")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("crates").join("a"))?;
+    cargo_new(&tempdir.path().join("crates").join("b"))?;
+    cargo_new(&tempdir.path().join("vendor").join("x").join("y"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir(tempdir.path().join("crates").join("notes"))?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "docs/*",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_eq!(
+        "error: \"docs/*\" did not match any package\n",
+        str::from_utf8(&output.stderr)?,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "crates/*",
+        "vendor/**/",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "crates/a",
+    "crates/b",
+    "vendor/x/y",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn recursive() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-recursive")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for path in &[
+        "crates/a",
+        "crates/b",
+        "crates/group/c",
+        "crates/old/d",
+        "crates/target/e",
+        "crates/other/f",
+    ] {
+        cargo_new(&tempdir.path().join(path))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::write(
+        tempdir
+            .path()
+            .join("crates")
+            .join("other")
+            .join("Cargo.toml"),
+        "[workspace]\nmembers = [\"f\"]\n",
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--recursive",
+        "--dry-run",
+        "crates",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(&output.stderr, EXPECTED_STDERR)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--recursive",
+        "crates",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["crates/a"]
+exclude = ["crates/old"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "crates/b" to `workspace.members`
+      Adding "crates/group/c" to `workspace.members`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["crates/a",
+    "crates/b",
+    "crates/group/c",
+]
+exclude = ["crates/old"]
+"#;
+}
+
+#[test]
+fn comments() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-comments")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("c")]).exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("Cargo.toml"),
+        EXPECTED_MANIFEST_INCLUDED,
+    )?;
+
+    let metadata = MetadataCommand::new()
+        .manifest_path(tempdir.path().join("Cargo.toml"))
+        .exec()?;
+    cargo_member::Exclude::from_metadata(&metadata, &[tempdir.path().join("a")], None::<&str>)
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("Cargo.toml"),
+        EXPECTED_MANIFEST_EXCLUDED,
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"# The workspace
+[workspace]
+# Keep sorted
+members = [
+    "a", # the first one
+    # `b` is experimental
+    "b",
+]
+exclude = [] # vendored packages
+
+# Shared settings
+[workspace.package]
+edition = "2021"  # the edition
+"#;
+
+    static EXPECTED_MANIFEST_INCLUDED: &str = r#"# The workspace
+[workspace]
+# Keep sorted
+members = [
+    "a", # the first one
+    # `b` is experimental
+    "b",
+    "c",
+]
+exclude = [] # vendored packages
+
+# Shared settings
+[workspace.package]
+edition = "2021"  # the edition
+"#;
+
+    static EXPECTED_MANIFEST_EXCLUDED: &str = r#"# The workspace
+[workspace]
+# Keep sorted
+members = [
+    # `b` is experimental
+    "b",
+    "c",
+]
+exclude = [
+    "a",
+] # vendored packages
+
+# Shared settings
+[workspace.package]
+edition = "2021"  # the edition
+"#;
+}
+
+#[test]
+fn flatten_nested() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-flatten-nested")?;
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{sub}}", &tempdir.path().join("sub").to_string_lossy())
+        .replace(
+            "{{lock}}",
+            &tempdir.path().join("Cargo.lock").to_string_lossy(),
+        );
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir(tempdir.path().join("sub"))?;
+    fs::write(tempdir.path().join("sub").join("Cargo.toml"), NESTED)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("sub").join("x"))?;
+    cargo_new(&tempdir.path().join("sub").join("y"))?;
+
+    let err = cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("sub")])
+        .dry_run(false)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` is the root of another workspace. exclude it from this workspace, or enable \
+             `--flatten-nested` to merge its members into this workspace",
+            tempdir.path().join("sub").display(),
+        ),
+        err.to_string(),
+    );
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("sub")])
+        .flatten_nested(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(!tempdir.path().join("sub").join("Cargo.toml").exists());
+    assert_stderr(&stderr, &expected_stderr)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = ["sub"]
+"#;
+
+    static NESTED: &str = r#"[workspace]
+members = ["x", "y"]
+exclude = ["z"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "sub/x",
+    "sub/y",
+]
+exclude = [
+    "sub/z",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing `workspace` from `{{sub}}/Cargo.toml`
+    Removing `{{sub}}/Cargo.toml`
+      Adding "sub/x" to `workspace.members`
+      Adding "sub/y" to `workspace.members`
+      Adding "sub/z" to `workspace.exclude`
+    Removing "sub" from `workspace.exclude`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn default_members() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-default-members")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .default_members(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .default_members(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+default-members = ["a"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "b",
+]
+default-members = ["a",
+    "b",
+]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "b" to `workspace.members`
+      Adding "b" to `workspace.default-members`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+}
+
+#[test]
+fn members_per_line() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-members-per-line")?;
+
+    for (members_per_line, expected) in &[
+        (
+            2,
+            r#"[workspace]
+members = [
+    "a", "b",
+    "c", "d",
+    "e",
+]
+exclude = []
+"#,
+        ),
+        (
+            0,
+            r#"[workspace]
+members = [
+    "a",
+    "b",
+    "c",
+    "d",
+    "e",
+]
+exclude = []
+"#,
+        ),
+        (
+            10,
+            r#"[workspace]
+members = ["a", "b", "c", "d", "e"]
+exclude = []
+"#,
+        ),
+    ] {
+        fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+        cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("e")])
+            .force(true)
+            .members_per_line(Some(*members_per_line))
+            .exec()?;
+
+        assert_manifest(&tempdir.path().join("Cargo.toml"), expected)?;
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c", "d"]
+exclude = []
+"#;
+}
+
+#[test]
+fn prefix_dot_slash() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-prefix-dot-slash")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .prefix_dot_slash(true)
+        .default_members(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(tempdir.path().join("Cargo.toml"))
+        .other_options(vec!["--locked".to_owned()])
+        .exec()?;
+    assert_eq!(2, metadata.workspace_members.len());
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .prefix_dot_slash(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(str::from_utf8(&stderr)?.starts_with("warning: `workspace` unchanged\n"));
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["./a"]
+default-members = ["./a"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["./a",
+    "./b",
+]
+default-members = ["./a",
+    "./b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn from_stdin_json() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-from-stdin-json")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--from-stdin-json",
+    )
+    .dir(tempdir.path())
+    .stdin_bytes(r#"[{"path": "a", "package": "b"}, {"path": "b"}]"#)
+    .stdout_capture()
+    .stderr_null()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_eq!(
+        serde_json::json!([
+            {
+                "path": tempdir.path().join("a"),
+                "package": "b",
+                "result": "invalid",
+                "error": format!(
+                    "expected `b`, found `a` at `{}`",
+                    tempdir.path().join("a").join("Cargo.toml").display(),
+                ),
+            },
+            {
+                "path": tempdir.path().join("b"),
+                "package": null,
+                "result": "skipped",
+                "error": null,
+            },
+        ]),
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)?,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--from-stdin-json",
+    )
+    .dir(tempdir.path())
+    .stdin_bytes(r#"[{"path": "a", "package": "a"}, {"path": "b"}]"#)
+    .stderr_null()
+    .read()?;
+
+    assert_eq!(
+        serde_json::json!([
+            {
+                "path": tempdir.path().join("a"),
+                "package": "a",
+                "result": "included",
+                "error": null,
+            },
+            {
+                "path": tempdir.path().join("b"),
+                "package": null,
+                "result": "included",
+                "error": null,
+            },
+        ]),
+        serde_json::from_str::<serde_json::Value>(&stdout)?,
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+resolver = "3"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "a",
+    "b",
+]
+resolver = "3"
+exclude = []
+"#;
+}
+
+#[test]
+fn from_file() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-from-file")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir(tempdir.path().join("lists"))?;
+    fs::write(
+        tempdir.path().join("lists").join("members"),
+        "# Members\n../a\n\n../c # missing\n",
+    )?;
+    fs::write(tempdir.path().join("lists").join("root"), "b\n")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--from-file",
+        Path::new("lists").join("members"),
+        "--color",
+        "never",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    let stderr = str::from_utf8(&output.stderr)?;
+    assert!(stderr.contains(&format!(
+        "warning: {}:4: `{}` does not exist\n",
+        tempdir.path().join("lists").join("members").display(),
+        tempdir.path().join("c").join("Cargo.toml").display(),
+    )));
+    assert!(stderr.contains("1 of 2 entries in"));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--from-file",
+        Path::new("lists").join("members"),
+        "--keep-going",
+        "--color",
+        "never",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?.contains(&format!(
+        "1 of 2 entries in `{}` were skipped",
+        tempdir.path().join("lists").join("members").display(),
+    )));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_A)?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--from-file",
+        Path::new("lists").join("root"),
+        "--from-file-base",
+        "root",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_AB)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST_A: &str = r#"[workspace]
+members = [
+    "a",
+]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST_AB: &str = r#"[workspace]
+members = [
+    "a",
+    "b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn private() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-private")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for (name, publish) in &[("a", ""), ("b", ""), ("c", "publish = true\n")] {
+        fs::create_dir_all(tempdir.path().join(name).join("src"))?;
+        fs::write(
+            tempdir.path().join(name).join("Cargo.toml"),
+            format!(
+                "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = \"2021\"\n{}\n[dependencies]\n",
+                name, publish,
+            ),
+        )?;
+        fs::write(tempdir.path().join(name).join("src").join("lib.rs"), "")?;
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(
+        tempdir.path(),
+        ["a", "b", "c"].map(|p| tempdir.path().join(p)),
+    )
+    .private(true)
+    .stderr(NoColor::new(&mut stderr))
+    .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), EXPECTED_A)?;
+    assert_manifest(&tempdir.path().join("b").join("Cargo.toml"), EXPECTED_B)?;
+    assert_manifest(&tempdir.path().join("c").join("Cargo.toml"), EXPECTED_C)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "b",
+    "c",
+]
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2021"
+
+[dependencies]
+"#;
+
+    static EXPECTED_B: &str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2021"
+publish = false
+
+[dependencies]
+"#;
+
+    static EXPECTED_C: &str = r#"[package]
+name = "c"
+version = "0.0.0"
+edition = "2021"
+publish = true
+
+[dependencies]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding `package.publish = false` to `{{root}}/b/Cargo.toml`
+      Adding "b" to `workspace.members`
+      Adding "c" to `workspace.members`
+    Updating {{root}}/Cargo.lock
+"#;
+}
+
+#[test]
+fn inherit_package() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-inherit-package")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for (name, edition) in &[("a", "2021"), ("b", "2018")] {
+        fs::create_dir_all(tempdir.path().join(name).join("src"))?;
+        fs::write(
+            tempdir.path().join(name).join("Cargo.toml"),
+            format!(
+                "[package]\nname = {:?}\nversion = \"0.0.0\"\nedition = {:?}\n\n[dependencies]\n",
+                name, edition,
+            ),
+        )?;
+        fs::write(tempdir.path().join(name).join("src").join("lib.rs"), "")?;
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir.path(), ["a", "b"].map(|p| tempdir.path().join(p)))
+        .inherit_package(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("b").join("Cargo.toml"), ORIGINAL_B)?;
+    assert!(str::from_utf8(&stderr)?
+        .starts_with(&EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy())));
+
+    cargo_member::Include::new(tempdir.path(), ["a", "b"].map(|p| tempdir.path().join(p)))
+        .inherit_package(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), ORIGINAL_A)?;
+    assert_manifest(&tempdir.path().join("b").join("Cargo.toml"), EXPECTED_B)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+resolver = "2"
+
+[workspace.package]
+version = "0.0.0"
+edition = "2021"
+license = "MIT"
+"#;
+
+    static ORIGINAL_A: &str = r#"[package]
+name = "a"
+version = "0.0.0"
+edition = "2021"
+
+[dependencies]
+"#;
+
+    static ORIGINAL_B: &str = r#"[package]
+name = "b"
+version = "0.0.0"
+edition = "2018"
+
+[dependencies]
+"#;
+
+    static EXPECTED_B: &str = r#"[package]
+name = "b"
+version.workspace = true
+edition = "2018"
+license.workspace = true
+
+[dependencies]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"  Inheriting `package.version` from the workspace in `{{root}}/b/Cargo.toml`
+note: keeping `package.edition` in `{{root}}/b/Cargo.toml`: it differs from `workspace.package.edition`
+  Inheriting `package.license` from the workspace in `{{root}}/b/Cargo.toml`
+"#;
+}
+
+#[test]
+fn comment() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-comment")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c", "d"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(
+        tempdir.path(),
+        &[tempdir.path().join("b"), tempdir.path().join("c")],
+    )
+    .comment(Some("vendored fork"))
+    .stderr(NoColor::new(&mut stderr))
+    .exec()?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("d")])
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--stdout",
+        "--comment",
+        "unused",
+        "--members-per-line",
+        "1",
+        "a",
+    )
+    .dir(tempdir.path())
+    .stdout_null()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+    "a", # the original
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "a", # the original
+    "b", # vendored fork
+    "c", # vendored fork
+    "d",
+]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "b" to `workspace.members` with `# vendored fork`
+      Adding "c" to `workspace.members` with `# vendored fork`
+    Updating {}
+      Adding "d" to `workspace.members`
+    Updating {}
+"#;
+}
+
+#[test]
+fn position() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-position")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c", "d", "e", "f"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .position("first".parse()?)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(&stderr, EXPECTED_STDERR_DRY_RUN)?;
+
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .position("first".parse()?)
+        .exec()?;
+    cargo_member::Include::new(
+        tempdir.path(),
+        &[tempdir.path().join("d"), tempdir.path().join("e")],
+    )
+    .position("before:c".parse()?)
+    .exec()?;
+    cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("f")])
+        .position("after:a".parse()?)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let err = cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .position("after:nonexisting".parse()?)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        "\"nonexisting\" is not in `workspace.members`",
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let err = "middle"
+        .parse::<cargo_member::MemberPosition>()
+        .unwrap_err();
+    assert_eq!(
+        "expected `first`, `last`, `before:<PATH>` or `after:<PATH>`, got \"middle\"",
+        err.to_string(),
+    );
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+    "a",
+    "c",
+]
+"#;
+
+    static EXPECTED_STDERR_DRY_RUN: &str = r#"      Adding "b" to `workspace.members`
+note: `workspace.members` would be ["b", "a", "c"]
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "b",
+    "a",
+    "f",
+    "d",
+    "e",
+    "c",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn verify() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-verify")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::write(
+        tempdir.path().join("b").join("src").join("main.rs"),
+        "compile_error!(\"broken\");\n",
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    let lockfile = fs::read_to_string(tempdir.path().join("Cargo.lock"))?;
+
+    let mut stderr = vec![];
+    let err = cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .verify(true)
+        .verify_stderr_redirection(Stdio::piped())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert!(format!("{:#}", err).contains("broken"));
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_eq!(
+        lockfile,
+        fs::read_to_string(tempdir.path().join("Cargo.lock"))?,
+    );
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "Restoring {}\n",
+        tempdir.path().join("Cargo.toml").display(),
+    )));
+
+    fs::write(
+        tempdir.path().join("b").join("src").join("main.rs"),
+        "fn main() {}\n",
+    )?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--verify",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a",
+    "b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn write_failure() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-include-write-failure")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let package_manifest = fs::read_to_string(tempdir.path().join("b").join("Cargo.toml"))?;
+    // makes the second write, the one of the root manifest, fail
+    fs::create_dir(tempdir.path().join("Cargo.toml.cargo-member.tmp"))?;
+
+    let mut stderr = vec![];
+    let err = cargo_member::Include::new(tempdir.path(), &[tempdir.path().join("b")])
+        .private(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()
+        .unwrap_err();
+
+    assert_eq!(
+        format!(
+            "failed to write {}",
+            tempdir.path().join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_manifest(
+        &tempdir.path().join("b").join("Cargo.toml"),
+        &package_manifest,
+    )?;
+    assert!(str::from_utf8(&stderr)?.ends_with(&format!(
+        "   Restoring {}\n",
+        tempdir.path().join("b").join("Cargo.toml").display(),
+    )));
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
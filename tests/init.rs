@@ -0,0 +1,127 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn init() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-init")?;
+
+    for path in &["crates/a", "crates/b", "tools/x"] {
+        cargo_new(&tempdir.path().join(path))?;
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Init::new(tempdir.path())
+        .members(["crates/*"])
+        .include_existing(true)
+        .resolver(Some("2"))
+        .workspace_package(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+    assert_eq!(3, metadata.workspace_members.len());
+
+    let err = cargo_member::Init::new(tempdir.path()).exec().unwrap_err();
+    assert_eq!(
+        format!(
+            "`{}` already has `[workspace]`. enable `--force` to replace it",
+            tempdir.path().join("Cargo.toml").display(),
+        ),
+        err.to_string(),
+    );
+
+    cargo_member::Init::new(tempdir.path())
+        .members(["crates/a"])
+        .force(true)
+        .exec()?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_FORCE)?;
+    return Ok(());
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "crates/*",
+    "tools/x",
+]
+resolver = "2"
+
+[workspace.package]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Creating `{{root}}/Cargo.toml`
+      Adding "crates/*" to `workspace.members`
+      Adding "tools/x" to `workspace.members`
+"#;
+
+    static EXPECTED_MANIFEST_FORCE: &str = r#"[workspace]
+members = [
+    "crates/a",
+]
+"#;
+}
+
+#[test]
+fn dry_run() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-init-dry-run")?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "init",
+        "--dry-run",
+        "--workspace-dependencies",
+        "new-workspace",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .read()?;
+
+    assert_eq!(EXPECTED_MANIFEST.trim_end(), stdout);
+    assert!(!tempdir.path().join("new-workspace").exists());
+    return Ok(());
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = []
+
+[workspace.dependencies]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let actual = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &actual, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path) -> cargo_metadata::Result<Metadata> {
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+}
@@ -0,0 +1,362 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use duct::cmd;
+use std::{env, fs, io, path::Path};
+use tempdir::TempDir;
+
+#[test]
+fn list() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-list")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("crates").join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    fs::write(
+        tempdir.path().join("a").join("src").join("lib.rs"),
+        A_LIB_RS,
+    )?;
+    fs::write(
+        tempdir.path().join("a").join("src").join("foo.rs"),
+        A_FOO_RS,
+    )?;
+    fs::write(
+        tempdir
+            .path()
+            .join("crates")
+            .join("b")
+            .join("src")
+            .join("main.rs"),
+        B_MAIN_RS,
+    )?;
+    let b_manifest_path = tempdir.path().join("crates").join("b").join("Cargo.toml");
+    fs::write(
+        &b_manifest_path,
+        fs::read_to_string(&b_manifest_path)? + B_DEPS,
+    )?;
+    for path in &["c", "d", "vendor/e"] {
+        cargo_new_lib(&tempdir.path().join(path))?;
+        fs::write(
+            tempdir.path().join(path).join("src").join("lib.rs"),
+            A_LIB_RS,
+        )?;
+    }
+    let d_manifest_path = tempdir.path().join("d").join("Cargo.toml");
+    fs::write(
+        &d_manifest_path,
+        fs::read_to_string(&d_manifest_path)? + "\n[workspace]\n",
+    )?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cmd!(env!("CARGO"), "metadata", "--format-version", "1")
+        .dir(tempdir.path())
+        .stdout_null()
+        .run()?;
+    let lockfile = fs::read_to_string(tempdir.path().join("Cargo.lock"))?;
+
+    let stdout = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "list")
+        .dir(tempdir.path())
+        .read()?;
+    assert_diff!(EXPECTED.trim_end(), &stdout, "\n", 0);
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "list",
+        "--with-deps-count",
+        "--with-loc",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert_diff!(EXPECTED_WITH_COUNTS.trim_end(), &stdout, "\n", 0);
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "list",
+        "--color",
+        "always",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert!(stdout.contains("\x1b[32mmember"), "{:?}", stdout);
+    assert!(stdout.contains("\x1b[33mdeactivated"), "{:?}", stdout);
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "list",
+        "--with-loc",
+        "--message-format",
+        "json",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert_eq!(
+        vec![
+            serde_json::json!({ "path": "a", "name": "a", "status": "member", "loc": 3 }),
+            serde_json::json!({ "path": "c", "name": "c", "status": "deactivated", "loc": 1 }),
+            serde_json::json!({ "path": "crates/b", "name": "b", "status": "member", "loc": 5 }),
+            serde_json::json!({ "path": "d", "name": "d", "status": "untracked", "loc": 1 }),
+            serde_json::json!({ "path": "vendor/e", "name": "e", "status": "excluded", "loc": 1 }),
+        ],
+        stdout
+            .lines()
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<serde_json::Value>, _>>()?,
+    );
+
+    assert_diff!(
+        MANIFEST,
+        &fs::read_to_string(tempdir.path().join("Cargo.toml"))?,
+        "\n",
+        0
+    );
+    assert_diff!(
+        &lockfile,
+        &fs::read_to_string(tempdir.path().join("Cargo.lock"))?,
+        "\n",
+        0
+    );
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["crates/b", "a"]
+exclude = ["vendor"]
+resolver = "3"
+"#;
+
+    static A_LIB_RS: &str = r#"pub fn a() {}
+"#;
+
+    static A_FOO_RS: &str = r#"// not a module of the crate, but counted anyway
+pub fn foo() {}
+"#;
+
+    static B_MAIN_RS: &str = r#"fn main() {
+    a::a();
+
+    println!("Hello, world!");
+}
+"#;
+
+    static B_DEPS: &str = r#"a = { path = "../../a" }
+"#;
+
+    static EXPECTED: &str = r#"PATH      NAME  STATUS
+a         a     member
+c         c     deactivated
+crates/b  b     member
+d         d     untracked
+vendor/e  e     excluded
+"#;
+
+    static EXPECTED_WITH_COUNTS: &str = r#"PATH      NAME  DEPS  LOC  STATUS
+a         a     0     3    member
+c         c     -     1    deactivated
+crates/b  b     1     5    member
+d         d     -     1    untracked
+vendor/e  e     -     1    excluded
+"#;
+}
+
+#[test]
+fn only() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-list-only")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for path in &["a", "crates/b", "vendor/e"] {
+        cargo_new_lib(&tempdir.path().join(path))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let list = |only: &str| -> anyhow::Result<Vec<serde_json::Value>> {
+        let stdout = cmd!(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            "member",
+            "list",
+            "--only",
+            only,
+            "--message-format",
+            "json",
+        )
+        .dir(tempdir.path())
+        .read()?;
+        stdout
+            .lines()
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    };
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "list",
+        "--only",
+        "exclude",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert_diff!(EXPECTED_EXCLUDE.trim_end(), &stdout, "\n", 0);
+
+    assert_eq!(
+        vec![
+            serde_json::json!({ "path": "a", "name": "a", "status": "member" }),
+            serde_json::json!({ "path": "crates/b", "name": "b", "status": "member" }),
+        ],
+        list("members")?,
+    );
+    assert_eq!(
+        vec![serde_json::json!({ "path": "a", "name": "a", "status": "member" })],
+        list("default-members")?,
+    );
+
+    // Without `default-members`, every member is a default member of a virtual workspace.
+    fs::write(
+        tempdir.path().join("Cargo.toml"),
+        MANIFEST.replace("default-members = [\"a\"]\n", ""),
+    )?;
+    assert_eq!(list("members")?, list("default-members")?);
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "list",
+        "--only",
+        "bogus",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)?
+        .starts_with("error: 'bogus' isn't a valid value for '--only <KIND>'"));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["crates/b", "a"]
+default-members = ["a"]
+exclude = ["vendor"]
+resolver = "3"
+"#;
+
+    static EXPECTED_EXCLUDE: &str = r#"PATH      NAME  STATUS
+vendor/e  e     excluded
+"#;
+}
+
+#[test]
+fn print_members_only() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-list-print-members-only")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    for path in &["a", "crates/b", "vendor/e"] {
+        cargo_new_lib(&tempdir.path().join(path))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let list = |args: &[&str]| {
+        duct::cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member", "list", "--print-members-only"]
+                .iter()
+                .chain(args),
+        )
+        .dir(tempdir.path())
+        .read()
+    };
+
+    assert_eq!("a\ncrates/b", list(&[])?);
+    assert_eq!("a\nb", list(&["--names"])?);
+    assert_eq!("vendor/e", list(&["--only", "exclude"])?);
+    assert_eq!("a", list(&["--only", "default-members"])?);
+
+    for (args, expected_stderr) in &[
+        (
+            &["--names"][..],
+            "error: The following required arguments were not provided:\n    \
+             --print-members-only",
+        ),
+        (
+            &["--print-members-only", "--message-format", "json"],
+            "error: `--print-members-only` cannot be used with `--message-format json`\n",
+        ),
+    ] {
+        let output = duct::cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member", "list"].iter().chain(*args),
+        )
+        .dir(tempdir.path())
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8(output.stderr)?;
+        assert!(stderr.starts_with(expected_stderr), "{:?}", stderr);
+    }
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["crates/b", "a"]
+default-members = ["a"]
+exclude = ["vendor"]
+resolver = "3"
+"#;
+}
+
+#[test]
+fn failures() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-list-failures")?;
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), MANIFEST)?;
+    cargo_new_lib(&tempdir.path().join("ws").join("a"))?;
+
+    let list = |dir: &Path, args: &[&str]| -> anyhow::Result<String> {
+        let output = duct::cmd(
+            env!("CARGO_BIN_EXE_cargo-member"),
+            ["member", "list"].iter().chain(args),
+        )
+        .dir(dir)
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        Ok(String::from_utf8(output.stderr)?)
+    };
+
+    assert!(list(tempdir.path(), &[])?.contains(&format!(
+        "could not find `Cargo.toml` in `{}` or any parent directory\n",
+        tempdir.path().display(),
+    )));
+
+    // `--with-deps-count` never creates `Cargo.lock`.
+    assert!(list(&tempdir.path().join("ws"), &["--with-deps-count"])?
+        .contains("because --locked was passed to prevent this"));
+    assert!(!tempdir.path().join("ws").join("Cargo.lock").exists());
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+resolver = "3"
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
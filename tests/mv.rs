@@ -61,6 +61,927 @@ exclude = []
 "#;
 }
 
+#[test]
+fn dry_run() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-dry-run")?;
+
+    let expected_stderr =
+        EXPECTED_STDERR.replace("{{ws}}", &tempdir.path().join("ws").to_string_lossy());
+
+    fs::create_dir(tempdir.path().join("ws"))?;
+    fs::write(tempdir.path().join("ws").join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("ws").join("a"))?;
+    cargo_new(&tempdir.path().join("ws").join("b"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("ws").join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("ws").join("c"))
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("ws").join("Cargo.toml"), ORIGINAL)?;
+    assert!(tempdir.path().join("ws").join("b").exists());
+    assert!(!tempdir.path().join("ws").join("c").exists());
+    assert_stderr(&stderr, &expected_stderr)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Copying `{{ws}}/b` to `{{ws}}/c`
+    Changing `package.name` in `{{ws}}/c/Cargo.toml` from "b" to "c"
+       Found workspace at {{ws}}
+      Adding "c" to `workspace.members`
+warning: not copying due to dry run
+    Removing directory `{{ws}}/b`
+    Removing "b" from `workspace.members`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+}
+
+#[test]
+fn message_format_json() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-message-format-json")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "b = { path = \"../b\" }\n",
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "mv",
+        "b",
+        "c",
+        "--message-format",
+        "json",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?.contains("requires `--dry-run`"));
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "mv",
+        "b",
+        "c",
+        "--dry-run",
+        "--message-format",
+        "json",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .read()?;
+
+    let lines = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<serde_json::Result<Vec<serde_json::Value>>>()?;
+    let write = |path: &Path| {
+        lines
+            .iter()
+            .filter(|line| line["kind"] == "write-file" && line["path"] == path.to_str().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        write(&tempdir.path().join("Cargo.toml")),
+        [&serde_json::json!({
+            "kind": "write-file",
+            "path": tempdir.path().join("Cargo.toml"),
+            "edits": [
+                { "key": "workspace.members", "before": ["a", "b"], "after": ["a", "c"] },
+            ],
+        })],
+    );
+    assert_eq!(
+        write(&tempdir.path().join("a").join("Cargo.toml")),
+        [&serde_json::json!({
+            "kind": "write-file",
+            "path": tempdir.path().join("a").join("Cargo.toml"),
+            "edits": [
+                { "key": "dependencies.b.path", "before": "../b", "after": "../c" },
+                { "key": "dependencies.b.package", "before": null, "after": "c" },
+            ],
+        })],
+    );
+    assert!(lines.contains(&serde_json::json!({
+        "kind": "remove-dir",
+        "path": tempdir.path().join("b"),
+    })));
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert!(tempdir.path().join("b").exists());
+    assert!(!tempdir.path().join("c").exists());
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+resolver = "3"
+"#;
+}
+
+#[test]
+fn merge_into() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-merge-into")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c", "d"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    append(&tempdir.path().join("b").join("Cargo.toml"), B_DEPS)?;
+    append(&tempdir.path().join("c").join("Cargo.toml"), C_DEPS)?;
+    let b_manifest_path = tempdir.path().join("b").join("Cargo.toml");
+    let b_manifest = fs::read_to_string(&b_manifest_path)?
+        .replace(r#"version = "0.1.0""#, r#"version = "0.2.0""#);
+    fs::write(b_manifest_path, b_manifest)?;
+    fs::write(tempdir.path().join("b").join("README.md"), "")?;
+    fs::write(tempdir.path().join("b").join("src").join("sub.rs"), "")?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let err = cargo_member::MergeInto::from_metadata(&metadata, "b", "a")
+        .dry_run(false)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        "aborting due to 1 conflict(s). enable `--force` to merge anyway",
+        err.to_string(),
+    );
+    assert!(tempdir.path().join("b").exists());
+
+    cargo_member::MergeInto::from_metadata(&metadata, "b", "a")
+        .force(true)
+        .dry_run(false)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), EXPECTED_A)?;
+    assert_manifest(&tempdir.path().join("c").join("Cargo.toml"), EXPECTED_C)?;
+    assert!(!tempdir.path().join("b").exists());
+    assert!(tempdir
+        .path()
+        .join("a")
+        .join("src")
+        .join("b")
+        .join("mod.rs")
+        .exists());
+    assert!(tempdir
+        .path()
+        .join("a")
+        .join("src")
+        .join("b")
+        .join("sub.rs")
+        .exists());
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b", "c", "d"]
+exclude = []
+"#;
+
+    static B_DEPS: &str = r#"a = { path = "../a" }
+d = { path = "../d" }
+"#;
+
+    static C_DEPS: &str = r#"b = { path = "../b", version = "0.2.0", default-features = false, optional = true }
+
+[dev-dependencies]
+b-alias = { path = "../b", package = "b" }
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "c", "d"]
+exclude = []
+"#;
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+d = { path = "../d" }
+"#;
+
+    static EXPECTED_C: &str = r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+b = { path = "../a", version = "0.1.0", default-features = false, optional = true, package = "a" }
+
+[dev-dependencies]
+b-alias = { path = "../a", package = "a" }
+"#;
+}
+
+#[test]
+fn merge_into_cycle() -> anyhow::Result<()> {
+    for (b_deps, cyclic) in &[(B_DEPS, true), (B_DEV_DEPS, false)] {
+        let tempdir = TempDir::new("cargo-member-test-mv-merge-into-cycle")?;
+
+        fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+        for name in &["a", "b", "c"] {
+            cargo_new_lib(&tempdir.path().join(name))?;
+        }
+        append(&tempdir.path().join("b").join("Cargo.toml"), b_deps)?;
+        append(&tempdir.path().join("c").join("Cargo.toml"), C_DEPS)?;
+        let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+        let result = cargo_member::MergeInto::from_metadata(&metadata, "a", "b")
+            .dry_run(false)
+            .exec();
+
+        if *cyclic {
+            assert_eq!(
+                "merging `a` into `b` introduced a dependency cycle",
+                result.unwrap_err().to_string(),
+            );
+        } else {
+            result?;
+        }
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b", "c"]
+"#;
+
+    static B_DEPS: &str = r#"c = { path = "../c" }
+"#;
+
+    static B_DEV_DEPS: &str = r#"
+[dev-dependencies]
+c = { path = "../c" }
+"#;
+
+    static C_DEPS: &str = r#"a = { path = "../a" }
+"#;
+}
+
+#[test]
+fn multiple() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-multiple")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "mv",
+        "a",
+        "b",
+        "crates",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert!(str::from_utf8(&output.stderr)?.contains("is not a directory"));
+
+    fs::create_dir(tempdir.path().join("crates"))?;
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "mv",
+        "a",
+        "b",
+        "crates",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(!tempdir.path().join("a").exists());
+    assert!(!tempdir.path().join("b").exists());
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b", "c"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = [ "c",
+    "crates/a",
+    "crates/b",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn workspace_dependencies() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-workspace-dependencies")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    cargo_new_lib(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "b.workspace = true\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("crates").join("c"))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(str::from_utf8(&stderr)?.contains(
+        r#"    Changing `workspace.dependencies.b.path` from "b" to "crates/c"
+    Changing `workspace.dependencies.b.package` to "c"
+"#,
+    ));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+resolver = "3"
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "crates/c",
+]
+exclude = []
+resolver = "3"
+
+[workspace.dependencies]
+b = { path = "crates/c", package = "c" }
+"#;
+}
+
+#[test]
+fn workspace_dependencies_preserve_order() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-workspace-dependencies-preserve-order")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    cargo_new_lib(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "b.workspace = true\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("crates").join("c"))
+        .stderr(NoColor::new(vec![]))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+resolver = "3"
+
+[workspace.dependencies]
+anyhow = "1" # unrelated
+b = {version="0.1.0",   path = "b" , default-features = false} # keep
+libc = { version = "0.2" }
+
+[workspace.dependencies.b2]
+# renamed
+package = "b"
+path = "b" # path
+version = "0.1.0"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a",
+    "crates/c",
+]
+exclude = []
+resolver = "3"
+
+[workspace.dependencies]
+anyhow = "1" # unrelated
+b = {version="0.1.0",   path = "crates/c" , default-features = false, package = "c"} # keep
+libc = { version = "0.2" }
+
+[workspace.dependencies.b2]
+# renamed
+package = "c"
+path = "crates/c" # path
+version = "0.1.0"
+"#;
+}
+
+#[test]
+fn excluded() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-excluded")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new_lib(&tempdir.path().join("a"))?;
+    cargo_new_lib(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("crates").join("c"))
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(!tempdir.path().join("b").exists());
+    assert!(tempdir
+        .path()
+        .join("crates")
+        .join("c")
+        .join("Cargo.toml")
+        .exists());
+    assert!(str::from_utf8(&stderr)?.contains(
+        r#"    Changing "b" to "crates/c" in `workspace.exclude`
+"#,
+    ));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = ["b", "d"]
+resolver = "3"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = ["crates/c", "d"]
+resolver = "3"
+"#;
+}
+
+#[test]
+fn dependents() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-dependents")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for name in &["a", "b", "c"] {
+        cargo_new_lib(&tempdir.path().join(name))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "renamed = { path = \"../b\", package = \"b\" }\n",
+    )?;
+    append(
+        &tempdir.path().join("c").join("Cargo.toml"),
+        "\n[dev-dependencies]\nb = { path = \"../b\" }\n",
+    )?;
+
+    let expected_stderr = EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy());
+
+    for (opts, dependent_scan, note) in &[
+        (&[][..], None, ""),
+        (&["--no-deps"][..], None, NOTE),
+        (&[][..], Some(cargo_member::DependentScan::All), ""),
+    ] {
+        let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), opts)?;
+
+        let mut stderr = vec![];
+
+        let mv = cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("crates"));
+        let mv = match dependent_scan {
+            Some(dependent_scan) => mv.dependent_scan(*dependent_scan),
+            None => mv,
+        };
+        mv.update_deps(false)
+            .dry_run(true)
+            .stderr(NoColor::new(&mut stderr))
+            .exec()?;
+
+        assert!(str::from_utf8(&stderr)?.starts_with(&format!("{}{}", note, expected_stderr)));
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "3"
+"#;
+
+    static NOTE: &str =
+        "note: the resolve graph is not available. reading the manifests of all the \
+                         members\n";
+
+    static EXPECTED_STDERR: &str = r#"warning: `{{root}}/a/Cargo.toml` depends on `renamed` at "../b", which will not point to the package after the move
+warning: `{{root}}/c/Cargo.toml` depends on `b` at "../b", which will not point to the package after the move
+     Copying"#;
+}
+
+#[test]
+fn update_deps() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-update-deps")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    for path in &["a", "b", "crates/c"] {
+        cargo_new_lib(&tempdir.path().join(path))?;
+    }
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        "b = { path = \"../b\" }\n",
+    )?;
+    append(
+        &tempdir.path().join("crates").join("c").join("Cargo.toml"),
+        "\n[dev-dependencies]\nrenamed = { path = \"../../b\", package = \"b\" }\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let expected_stderr =
+        EXPECTED_STDERR_DRY_RUN.replace("{{root}}", &tempdir.path().to_string_lossy());
+
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(
+        &metadata,
+        "b",
+        &tempdir.path().join("nested").join("deep").join("b"),
+    )
+    .dry_run(true)
+    .stderr(NoColor::new(&mut stderr))
+    .exec()?;
+
+    assert!(str::from_utf8(&stderr)?.contains(&expected_stderr));
+    assert!(tempdir.path().join("b").exists());
+    assert_manifest(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        &a_manifest("../b", ""),
+    )?;
+
+    cargo_member::Mv::from_metadata(
+        &metadata,
+        "b",
+        &tempdir.path().join("nested").join("deep").join("b"),
+    )
+    .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        &a_manifest("../nested/deep/b", ""),
+    )?;
+    assert_manifest(
+        &tempdir.path().join("crates").join("c").join("Cargo.toml"),
+        &c_manifest("../../nested/deep/b", "b"),
+    )?;
+
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    cargo_member::Mv::from_metadata(&metadata, "b", &tempdir.path().join("crates").join("d"))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("a").join("Cargo.toml"),
+        &a_manifest("../crates/d", ", package = \"d\""),
+    )?;
+    assert_manifest(
+        &tempdir.path().join("crates").join("c").join("Cargo.toml"),
+        &c_manifest("../d", "d"),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    fn a_manifest(path: &str, package: &str) -> String {
+        format!(
+            r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+b = {{ path = "{}"{} }}
+"#,
+            path, package,
+        )
+    }
+
+    fn c_manifest(path: &str, package: &str) -> String {
+        format!(
+            r#"[package]
+name = "c"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+
+[dev-dependencies]
+renamed = {{ path = "{}", package = "{}" }}
+"#,
+            path, package,
+        )
+    }
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "crates/c"]
+resolver = "3"
+"#;
+
+    static EXPECTED_STDERR_DRY_RUN: &str = r#"    Changing `dependencies.b.path` in `{{root}}/a/Cargo.toml` from "../b" to "../nested/deep/b"
+    Changing `dev-dependencies.renamed.path` in `{{root}}/crates/c/Cargo.toml` from "../../b" to "../../nested/deep/b"
+"#;
+}
+
+#[test]
+fn duplicate_names() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-duplicate-names")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let dst = tempdir.path().join("crates").join("a");
+
+    let err = cargo_member::Mv::from_metadata(&metadata, "b", &dst)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        format!(
+            "the workspace at `{}` would have two packages named `a`: `{}` and `{}`. enable \
+             `--allow-duplicate-names` to proceed anyway",
+            tempdir.path().display(),
+            tempdir.path().join("a").display(),
+            dst.display(),
+        ),
+        err.to_string(),
+    );
+    assert!(tempdir.path().join("b").exists());
+    assert!(!dst.exists());
+
+    cargo_member::Mv::from_metadata(&metadata, "b", &dst)
+        .no_rename(true)
+        .dry_run(true)
+        .exec()?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+}
+
+#[test]
+fn update_readme_links() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-update-readme-links")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("a").join("README.md"), README)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let dst = tempdir.path().join("crates").join("a");
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "a", &dst)
+        .update_readme_links(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_eq!(EXPECTED_README, fs::read_to_string(dst.join("README.md"))?);
+    let stderr = str::from_utf8(&stderr)?
+        .lines()
+        .filter(|l| l.starts_with("    Updating link"))
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    assert_stderr(
+        stderr.as_bytes(),
+        &EXPECTED_STDERR.replace("{{readme}}", &dst.join("README.md").to_string_lossy()),
+    )?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+    static README: &str = r#"# a
+
+See [b](../b/README.md) and ![logo](<../b/logo.png>).
+[Usage][usage], [the source](./src/main.rs), [docs](https://docs.rs/b) and [top](#a).
+
+[usage]: ../b#usage
+"#;
+
+    static EXPECTED_README: &str = r#"# a
+
+See [b](../../b/README.md) and ![logo](<../../b/logo.png>).
+[Usage][usage], [the source](./src/main.rs), [docs](https://docs.rs/b) and [top](#a).
+
+[usage]: ../../b#usage
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Updating link "../b/README.md" to "../../b/README.md" in `{{readme}}`
+    Updating link "../b/logo.png" to "../../b/logo.png" in `{{readme}}`
+    Updating link "../b#usage" to "../../b#usage" in `{{readme}}`
+"#;
+}
+
+#[test]
+fn path_attrs() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-path-attrs")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("a").join("src").join("main.rs"), MAIN)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let copy = tempdir.path().join("copies").join("a");
+    let mut stderr = vec![];
+
+    cargo_member::Cp::new(&tempdir.path().join("a"), &copy)
+        .register_in(cargo_member::RegisterIn::None)
+        .scan_path_attrs(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_eq!(MAIN, fs::read_to_string(copy.join("src").join("main.rs"))?);
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "warning: `#[path = \"../../shared/util.rs\"]` in `{}:1` no longer resolves. enable \
+         `--fix-path-attrs` to rewrite it to \"../../../shared/util.rs\"",
+        copy.join("src").join("main.rs").display(),
+    )));
+
+    let dst = tempdir.path().join("crates").join("a");
+    let mut stderr = vec![];
+
+    cargo_member::Mv::from_metadata(&metadata, "a", &dst)
+        .scan_path_attrs(true)
+        .fix_path_attrs(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_eq!(
+        EXPECTED_MAIN,
+        fs::read_to_string(dst.join("src").join("main.rs"))?,
+    );
+    let stderr = str::from_utf8(&stderr)?
+        .lines()
+        .filter(|l| l.starts_with("    Updating `#[path"))
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    assert_stderr(
+        stderr.as_bytes(),
+        &EXPECTED_STDERR.replace(
+            "{{main}}",
+            &dst.join("src").join("main.rs").to_string_lossy(),
+        ),
+    )?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+
+    static MAIN: &str = r#"#[path = "../../shared/util.rs"] mod util;
+#[path = "inner.rs"]
+mod inner;
+
+fn main() {}
+"#;
+
+    static EXPECTED_MAIN: &str = r#"#[path = "../../../shared/util.rs"] mod util;
+#[path = "inner.rs"]
+mod inner;
+
+fn main() {}
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Updating `#[path = "../../shared/util.rs"]` to "../../../shared/util.rs" in `{{main}}:1`
+"#;
+}
+
+#[test]
+fn into_manifest() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-mv-into-manifest")?;
+
+    let ws1 = tempdir.path().join("ws1");
+    let ws2 = tempdir.path().join("ws2");
+    fs::create_dir(&ws1)?;
+    fs::create_dir(&ws2)?;
+    fs::write(ws1.join("Cargo.toml"), WS1_ORIGINAL)?;
+    fs::write(ws2.join("Cargo.toml"), WS2_ORIGINAL)?;
+    cargo_new_lib(&ws1.join("a"))?;
+    cargo_new_lib(&ws1.join("b"))?;
+    cargo_new_lib(&ws2.join("c"))?;
+    fs::write(ws1.join("Cargo.toml"), WS1_ORIGINAL)?;
+    fs::write(ws2.join("Cargo.toml"), WS2_ORIGINAL)?;
+    fs::write(ws1.join("a").join("Cargo.toml"), A_ORIGINAL)?;
+    cargo_metadata(&ws1.join("Cargo.toml"), &[])?;
+    cargo_metadata(&ws2.join("Cargo.toml"), &[])?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "mv",
+        "a",
+        "../ws2/crates/a",
+        "--into-manifest",
+        "../ws2/Cargo.toml",
+    )
+    .dir(&ws1)
+    .stderr_null()
+    .run()?;
+
+    assert!(!ws1.join("a").exists());
+    assert_manifest(&ws1.join("Cargo.toml"), WS1_EXPECTED)?;
+    assert_manifest(&ws2.join("Cargo.toml"), WS2_EXPECTED)?;
+    assert_manifest(&ws2.join("crates").join("a").join("Cargo.toml"), A_EXPECTED)?;
+    cargo_metadata(&ws1.join("Cargo.toml"), &["--locked"])?;
+    cargo_metadata(&ws2.join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static WS1_ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b"]
+
+[workspace.package]
+version = "1.2.3"
+"#;
+
+    static WS2_ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["c"]
+"#;
+
+    static A_ORIGINAL: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition = "2021"
+"#;
+
+    static WS1_EXPECTED: &str = r#"[workspace]
+resolver = "3"
+members = [ "b"]
+exclude = []
+
+[workspace.package]
+version = "1.2.3"
+"#;
+
+    static WS2_EXPECTED: &str = r#"[workspace]
+resolver = "3"
+members = ["c",
+    "crates/a",
+]
+exclude = []
+"#;
+
+    static A_EXPECTED: &str = r#"[package]
+name = "a"
+version = "1.2.3"
+edition = "2021"
+"#;
+}
+
+fn cargo_new_lib(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", "--lib", path).run()?;
+    Ok(())
+}
+
+fn append(path: &Path, contents: &str) -> io::Result<()> {
+    let orig = fs::read_to_string(path)?;
+    fs::write(path, orig + contents)
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
@@ -34,7 +34,15 @@ fn new() -> anyhow::Result<()> {
     assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
     assert_stderr(
         &stderr,
-        &EXPECTED_STDERR.replace("{}", &tempdir.path().join("Cargo.lock").to_string_lossy()),
+        &EXPECTED_STDERR
+            .replace(
+                "{{manifest}}",
+                &tempdir.path().join("Cargo.toml").to_string_lossy(),
+            )
+            .replace(
+                "{{lock}}",
+                &tempdir.path().join("Cargo.lock").to_string_lossy(),
+            ),
     )?;
     cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
     return Ok(());
@@ -42,6 +50,7 @@ fn new() -> anyhow::Result<()> {
     static ORIGINAL: &str = r#"[workspace]
 members = []
 exclude = []
+resolver = "3"
 "#;
 
     static EXPECTED_MANIFEST: &str = r#"[workspace]
@@ -49,10 +58,327 @@ members = [
     "a",
 ]
 exclude = []
+resolver = "3"
 "#;
 
     static EXPECTED_STDERR: &str = r#"      Adding "a" to `workspace.members`
-    Updating {}
+note: `workspace.lints` is not defined in `{{manifest}}`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn inherit_lints() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-inherit-lints")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .cargo_new_vcs(Some("none"))
+        .offline(true)
+        .dry_run(false)
+        .exec()?;
+
+    let manifest = fs::read_to_string(tempdir.path().join("a").join("Cargo.toml"))?;
+    assert!(manifest.ends_with("\n[lints]\nworkspace = true\n"));
+    assert_eq!(1, manifest.matches("[lints]").count());
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+
+[workspace.lints.rust]
+unsafe_code = "forbid"
+"#;
+}
+
+#[test]
+fn private() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-private")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .cargo_new_vcs(Some("none"))
+        .private(true)
+        .offline(true)
+        .exec()?;
+
+    let manifest = fs::read_to_string(tempdir.path().join("a").join("Cargo.toml"))?;
+    assert_eq!(1, manifest.matches("\npublish = false\n").count());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "a",
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn set_fields() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-set-fields")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let err = cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .cargo_new_vcs(Some("none"))
+        .set_fields([("rust-version", "1.70")])
+        .offline(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!(
+        r#"`package.rust-version` must be a string. note that values are parsed as TOML: `rust-version='"1.70"'`"#,
+        err.to_string(),
+    );
+    assert!(!tempdir.path().join("a").exists());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .cargo_new_vcs(Some("none"))
+        .set_fields([("authors", r#"["Me"]"#), ("rust-version", r#""1.85""#)])
+        .offline(true)
+        .exec()?;
+
+    let manifest = fs::read_to_string(tempdir.path().join("a").join("Cargo.toml"))?;
+    assert!(manifest.contains("\nauthors = [\"Me\"]\n"));
+    assert!(manifest.contains("\nrust-version = \"1.85\"\n"));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+"#;
+}
+
+#[test]
+fn inherit_lints_without_workspace_lints() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-inherit-lints-without-workspace-lints")?;
+
+    for (name, inherit_lints) in [("a", Some(true)), ("b", None)] {
+        fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+        let mut stderr = vec![];
+
+        cargo_member::New::new(tempdir.path(), &tempdir.path().join(name))
+            .cargo_new_vcs(Some("none"))
+            .inherit_lints(inherit_lints)
+            .offline(true)
+            .dry_run(false)
+            .stderr(NoColor::new(&mut stderr))
+            .exec()?;
+
+        assert!(
+            !fs::read_to_string(tempdir.path().join(name).join("Cargo.toml"))?.contains("[lints]")
+        );
+        assert_stderr(
+            &stderr,
+            &EXPECTED_STDERR
+                .replace("{{name}}", name)
+                .replace(
+                    "{{manifest}}",
+                    &tempdir.path().join("Cargo.toml").to_string_lossy(),
+                )
+                .replace(
+                    "{{lock}}",
+                    &tempdir.path().join("Cargo.lock").to_string_lossy(),
+                ),
+        )?;
+    }
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+resolver = "3"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "{{name}}" to `workspace.members`
+note: `workspace.lints` is not defined in `{{manifest}}`
+    Updating {{lock}}
+"#;
+}
+
+#[test]
+fn resolver() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-resolver")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .cargo_new_vcs(Some("none"))
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_A)?;
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "warning: `{}` uses edition 2024 but the workspace at `{}` uses resolver \"2\".",
+        tempdir.path().join("a").display(),
+        tempdir.path().display(),
+    )));
+
+    let mut stderr = vec![];
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("b"))
+        .cargo_new_vcs(Some("none"))
+        .set_resolver(Some("3"))
+        .offline(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST_B)?;
+    assert!(!str::from_utf8(&stderr)?.contains("warning:"));
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "Changing `workspace.resolver` in `{}` from \"2\" to \"3\"",
+        tempdir.path().join("Cargo.toml").display(),
+    )));
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_MANIFEST_A: &str = r#"[workspace]
+members = [
+    "a",
+]
+exclude = []
+resolver = "2"
+"#;
+
+    static EXPECTED_MANIFEST_B: &str = r#"[workspace]
+members = [
+    "a",
+    "b",
+]
+exclude = []
+resolver = "3"
+"#;
+}
+
+#[cfg(windows)]
+#[test]
+fn reserved_name_on_windows() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-reserved-name-on-windows")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let err = cargo_member::New::new(tempdir.path(), &tempdir.path().join("aux"))
+        .cargo_new_stderr_redirection(Stdio::null())
+        .offline(true)
+        .exec()
+        .unwrap_err();
+
+    assert_eq!("`aux` is a reserved name on Windows", err.to_string());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+"#;
+}
+
+#[test]
+fn lib_and_bin() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-new-lib-and-bin")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("foo-bar"))
+        .cargo_new_vcs(Some("none"))
+        .cargo_new_lib(true)
+        .cargo_new_bin(true)
+        .offline(true)
+        .dry_run(false)
+        .exec()?;
+
+    let main = fs::read_to_string(tempdir.path().join("foo-bar").join("src").join("main.rs"))?;
+    assert_eq!("fn main() {\n    foo_bar::run();\n}\n", main);
+    let lib = fs::read_to_string(tempdir.path().join("foo-bar").join("src").join("lib.rs"))?;
+    assert!(lib.starts_with("pub fn run() {\n"));
+    let metadata = MetadataCommand::new()
+        .manifest_path(tempdir.path().join("Cargo.toml"))
+        .exec()?;
+    let mut kinds = metadata.packages[0]
+        .targets
+        .iter()
+        .flat_map(|t| t.kind.clone())
+        .collect::<Vec<_>>();
+    kinds.sort();
+    assert_eq!(["bin", "lib"], &*kinds);
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+resolver = "3"
+"#;
+}
+
+#[test]
+fn smoke_test() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-new-smoke-test")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::New::new(tempdir.path(), &tempdir.path().join("a"))
+        .smoke_test(true)
+        .rollback_on_failure(true)
+        .offline(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR
+            .replace(
+                "{{manifest}}",
+                &tempdir.path().join("Cargo.toml").to_string_lossy(),
+            )
+            .replace(
+                "{{lock}}",
+                &tempdir.path().join("Cargo.lock").to_string_lossy(),
+            ),
+    )?;
+    assert!(tempdir
+        .path()
+        .join("target")
+        .join("debug")
+        .join("a")
+        .exists());
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = []
+resolver = "3"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"      Adding "a" to `workspace.members`
+note: `workspace.lints` is not defined in `{{manifest}}`
+    Updating {{lock}}
+    Building `a` as a smoke test
+    Finished the smoke test of `a`
 "#;
 }
 
@@ -0,0 +1,83 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+#[test]
+fn no_cargo_env() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-no-cargo-env")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+
+    env::remove_var("CARGO");
+
+    cargo_member::Exclude::from_metadata(&metadata, None::<PathBuf>, ["b"])
+        .dry_run(false)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = [
+    "b",
+]
+"#;
+}
+
+#[test]
+fn no_cargo() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-no-cargo-env-no-cargo")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    // Neither `$CARGO` nor `cargo` in `$PATH`.
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "exclude", "a",)
+        .dir(tempdir.path())
+        .env_remove("CARGO")
+        .env("PATH", "")
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("`cargo metadata`"), "{:?}", stderr);
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path) -> cargo_metadata::Result<Metadata> {
+    MetadataCommand::new().manifest_path(manifest_path).exec()
+}
@@ -0,0 +1,110 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::{env, fs, io, path::Path};
+use tempdir::TempDir;
+
+#[test]
+fn print() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-open-print")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("crates").join("a"))?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "open",
+        "--print",
+        "a",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert_eq!(
+        tempdir
+            .path()
+            .join("crates")
+            .join("a")
+            .display()
+            .to_string(),
+        stdout,
+    );
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "open",
+        "--print",
+        "--manifest",
+        "a",
+    )
+    .dir(tempdir.path())
+    .read()?;
+    assert_eq!(
+        tempdir
+            .path()
+            .join("crates")
+            .join("a")
+            .join("Cargo.toml")
+            .display()
+            .to_string(),
+        stdout,
+    );
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "open",
+        "--print",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["crates/a"]
+"#;
+}
+
+#[cfg(unix)]
+#[test]
+fn editor() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-open-editor")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+
+    let stdout = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "open", "a")
+        .dir(tempdir.path())
+        .env_remove("VISUAL")
+        .env("EDITOR", "echo -n")
+        .read()?;
+    assert_eq!(tempdir.path().join("a").display().to_string(), stdout);
+
+    let output = cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "open", "a")
+        .dir(tempdir.path())
+        .env_remove("VISUAL")
+        .env_remove("EDITOR")
+        .stderr_capture()
+        .unchecked()
+        .run()?;
+    assert!(!output.status.success());
+    assert!(std::str::from_utf8(&output.stderr)?.contains(
+        "neither `$VISUAL` nor `$EDITOR` is set. use `--print` to print the path instead"
+    ));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
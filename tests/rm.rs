@@ -27,7 +27,7 @@ fn rm() -> anyhow::Result<()> {
 
     let mut stderr = vec![];
 
-    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("b")], &["c"])
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("b")], ["c"])
         .force(false)
         .dry_run(false)
         .stderr(NoColor::new(&mut stderr))
@@ -55,6 +55,251 @@ exclude = []
 "#;
 }
 
+#[test]
+fn glob() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-glob")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("crates").join("b"))?;
+    cargo_new(&tempdir.path().join("crates").join("c"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    fs::create_dir(tempdir.path().join("crates").join("notes"))?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "rm",
+        "crates/*"
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert!(!tempdir.path().join("crates").join("b").exists());
+    assert!(!tempdir.path().join("crates").join("c").exists());
+    assert!(tempdir.path().join("crates").join("notes").exists());
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "crates/b", "crates/c"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+#[test]
+fn default_members() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-default-members")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("b")], None::<&str>).exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+default-members = [
+    "b",
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+default-members = [
+]
+exclude = []
+"#;
+}
+
+#[test]
+fn ignore_missing() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-ignore-missing")?;
+
+    let expected_stderr = EXPECTED_STDERR
+        .replace("{{b}}", &tempdir.path().join("b").to_string_lossy())
+        .replace("{{c}}", &tempdir.path().join("c").to_string_lossy());
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("c")], ["b", "zzzzzzzz"])
+        .dry_run(false)
+        .exec()
+        .unwrap_err();
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("c")], ["b", "zzzzzzzz"])
+        .ignore_missing(true)
+        .dry_run(false)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, &expected_stderr)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+resolver = "3"
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+resolver = "3"
+members = ["a"]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"note: skipping: package ID specification `zzzzzzzz` did not match any packages
+note: skipping: `{{c}}` does not exist
+    Removing directory `{{b}}`
+    Removing "b" from `workspace.members`
+"#;
+}
+
+#[test]
+fn prune_deps() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-prune-deps")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    cargo_new(&tempdir.path().join("c"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    append(
+        &tempdir.path().join("c").join("Cargo.toml"),
+        "b.workspace = true\n",
+    )?;
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("b")], None::<&str>)
+        .prune_deps(true)
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert!(str::from_utf8(&stderr)?.contains(&format!(
+        "    Removing `workspace.dependencies.b`\nwarning: `{}` still depends on the removed \
+         `workspace.dependencies.b`\n",
+        tempdir.path().join("c").display(),
+    )));
+
+    cargo_member::Rm::from_metadata(&metadata, &[tempdir.path().join("b")], ["c"])
+        .prune_deps(true)
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &["--locked"])?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b", "c"]
+exclude = []
+
+[workspace.dependencies]
+b = { path = "b" }
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+
+[workspace.dependencies]
+"#;
+}
+
+#[test]
+fn report_file() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-rm-report-file")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let report_file = tempdir.path().join("report.jsonl");
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "rm",
+        "--report-file",
+        "report.jsonl",
+        "b",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    let records = fs::read_to_string(&report_file)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let records = records
+        .into_iter()
+        .map(|mut record| {
+            record.as_object_mut().unwrap().remove("timestamp");
+            record
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            serde_json::json!({
+                "action": "remove-dir",
+                "path": tempdir.path().join("b"),
+            }),
+            serde_json::json!({
+                "action": "write-file",
+                "path": tempdir.path().join("Cargo.toml"),
+                "before": ORIGINAL,
+                "after": EXPECTED_MANIFEST,
+            }),
+        ],
+        records,
+    );
+    assert!(!tempdir.path().join("b").exists());
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["a", "b"]
+exclude = []
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = []
+"#;
+}
+
+fn append(path: &Path, contents: &str) -> io::Result<()> {
+    let orig = fs::read_to_string(path)?;
+    fs::write(path, orig + contents)
+}
+
 fn cargo_new(path: &Path) -> io::Result<()> {
     let cargo_exe = env::var("CARGO").unwrap();
     cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
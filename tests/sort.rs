@@ -0,0 +1,184 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn sort() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir.path())
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    assert_stderr(
+        &stderr,
+        &format!(
+            "{}warning: not modifying the manifest due to dry run\n",
+            EXPECTED_STDERR
+        ),
+    )?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, "warning: `workspace` unchanged\n")?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = [
+    "crates/c",
+    # vendored
+    "vendor/x", # pinned
+    "crates/a",
+    "crates/b", # b
+    "crates/a",
+]
+exclude = ["z", "y"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "crates/a",
+    "crates/b", # b
+    "crates/c",
+    # vendored
+    "vendor/x", # pinned
+]
+exclude = ["y", "z"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Removing duplicate "crates/a" from `workspace.members`
+     Sorting `workspace.members`
+     Sorting `workspace.exclude`
+"#;
+}
+
+#[test]
+fn entries_unchanged() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort-entries-unchanged")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let mut stderr = vec![];
+
+    cargo_member::Sort::new(tempdir.path())
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["d", 'c', "b" # b
+]
+exclude = [ # head
+  "z", "y", # two
+  'x',
+  "x",
+]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["b", 'c', "d" # b
+]
+exclude = [ # head
+  'x', "y", # two
+  "z",
+]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Sorting `workspace.members`
+    Removing duplicate "x" from `workspace.exclude`
+     Sorting `workspace.exclude`
+"#;
+}
+
+#[test]
+fn check() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-sort-check")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "sort",
+        "--check",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_stderr(
+        &output.stderr,
+        &EXPECTED_STDERR.replace("{{root}}", &tempdir.path().to_string_lossy()),
+    )?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+
+    cmd!(env!("CARGO_BIN_EXE_cargo-member"), "member", "sort")
+        .dir(tempdir.path())
+        .stderr_null()
+        .run()?;
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "sort",
+        "--check",
+    )
+    .dir(tempdir.path())
+    .stderr_capture()
+    .run()?;
+
+    assert_stderr(&output.stderr, "")?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["b", "a"]
+exclude = ["c", "c"]
+"#;
+
+    static EXPECTED_STDERR: &str = "error: `workspace.members` and `workspace.exclude` in \
+                                    `{{root}}/Cargo.toml` are not sorted. run `cargo member \
+                                    sort`\n";
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
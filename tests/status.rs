@@ -0,0 +1,132 @@
+#![warn(rust_2018_idioms)]
+
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    fs,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn status() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-status")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    for (name, version, dependencies) in &[
+        ("a", "0.1.0", r#"missing = { path = "../missing" }"#),
+        ("b", "0.1.0", r#"a = { path = "../a" }"#),
+        ("c", "0.1.0", ""),
+        ("d", "0.2.0", ""),
+        ("x", "0.3.0", ""),
+    ] {
+        write_package(&tempdir.path().join(name), name, version, dependencies)?;
+    }
+
+    let mut stderr = vec![];
+
+    cargo_member::Status::new(tempdir.path())
+        .verbose(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_stderr(
+        &stderr,
+        &EXPECTED_STDERR.replace("{{c}}", &tempdir.path().join("c").to_string_lossy()),
+    )?;
+
+    let err = cargo_member::Status::new(tempdir.path())
+        .check(true)
+        .exec()
+        .unwrap_err();
+    assert_eq!("4 issue(s) found", err.to_string());
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "d", "gone"]
+exclude = ["x"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"     Orphans 1 package(s) in neither `workspace.members` nor `workspace.exclude`
+note: `{{c}}` is not registered
+        Dead 1 entry(s) in `workspace.members` without a package
+note: "gone" does not point to a package
+       Drift 1 package(s) not at 0.1.0
+note: `d` is at 0.2.0
+    Dangling 1 path dependency(s) without a package
+note: `a` depends on `missing` at "../missing", which is not a package
+"#;
+}
+
+#[test]
+fn message_format_json() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-status-message-format-json")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ROOT)?;
+    write_package(&tempdir.path().join("a"), "a", "0.1.0", "")?;
+    write_package(&tempdir.path().join("b"), "b", "0.1.0", "")?;
+    write_package(&tempdir.path().join("c"), "c", "0.2.0", "")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "status",
+        "--check",
+        "--message-format",
+        "json",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    let root = tempdir.path().canonicalize()?;
+    let lines = str::from_utf8(&output.stdout)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(
+        [
+            serde_json::json!({
+                "category": "dead",
+                "path": root.join("Cargo.toml"),
+                "message": "\"gone\" does not point to a package",
+                "severity": "error",
+            }),
+            serde_json::json!({
+                "category": "drift",
+                "path": root.join("c").join("Cargo.toml"),
+                "message": "`c` is at 0.2.0",
+                "severity": "error",
+            }),
+            serde_json::json!({ "summary": { "errors": 2, "warnings": 0 } }),
+        ],
+        *lines,
+    );
+    return Ok(());
+
+    static ROOT: &str = r#"[workspace]
+members = ["a", "b", "c", "gone"]
+"#;
+}
+
+fn write_package(dir: &Path, name: &str, version: &str, dependencies: &str) -> anyhow::Result<()> {
+    fs::create_dir(dir)?;
+    fs::write(
+        dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = {:?}\nversion = {:?}\nedition = \"2021\"\n\n[dependencies]\n{}\n",
+            name, version, dependencies,
+        ),
+    )?;
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
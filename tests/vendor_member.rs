@@ -0,0 +1,245 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::{Metadata, MetadataCommand};
+use difference::assert_diff;
+use std::{
+    fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+use termcolor::NoColor;
+
+#[test]
+fn vendor_member() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-vendor-member")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL_ROOT)?;
+    fs::write(tempdir.path().join("README.md"), "")?;
+    for (name, manifest) in &[
+        ("a", ORIGINAL_A),
+        ("b", "[package]\nname = \"b\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[features]\nx = []\ny = []\n"),
+        ("c", "[package]\nname = \"c\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+    ] {
+        fs::create_dir_all(tempdir.path().join("crates").join(name).join("src"))?;
+        fs::write(tempdir.path().join("crates").join(name).join("Cargo.toml"), manifest)?;
+        fs::write(tempdir.path().join("crates").join(name).join("src").join("lib.rs"), "")?;
+    }
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::VendorMember::from_metadata(&metadata, "a")
+        .dry_run(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("crates").join("a").join("Cargo.toml"),
+        ORIGINAL_A,
+    )?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    cargo_member::VendorMember::from_metadata(&metadata, "a")
+        .stderr(NoColor::new(vec![]))
+        .exec()?;
+
+    assert_manifest(
+        &tempdir.path().join("crates").join("a").join("Cargo.toml"),
+        EXPECTED_A,
+    )?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), ORIGINAL_ROOT)?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+    return Ok(());
+
+    static ORIGINAL_ROOT: &str = r#"[workspace]
+members = ["crates/*"]
+resolver = "3"
+
+[workspace.package]
+version = "0.1.0"
+edition = "2021"
+license = "MIT"
+readme = "README.md"
+
+[workspace.dependencies]
+b = { path = "crates/b", features = ["x"] }
+c = { version = "0.1.0", path = "crates/c" }
+
+[workspace.lints.rust]
+unsafe_code = "forbid"
+
+[workspace.lints.clippy]
+pedantic = "warn"
+"#;
+
+    static ORIGINAL_A: &str = r#"[package]
+name = "a"
+version.workspace = true
+edition = { workspace = true } # edition
+license.workspace = true
+readme.workspace = true
+
+# Dependencies
+[dependencies]
+b = { workspace = true, features = ["y"] }
+c.workspace = true
+
+[dev-dependencies.c]
+workspace = true
+
+[lints]
+workspace = true
+
+[features]
+default = []
+"#;
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021" # edition
+license = "MIT"
+readme = "../../README.md"
+
+# Dependencies
+[dependencies]
+b = { path = "../b", features = ["x", "y"] }
+c = { version = "0.1.0", path = "../c" }
+
+[dev-dependencies.c]
+version = "0.1.0"
+path = "../c"
+
+[lints.rust]
+unsafe_code = "forbid"
+
+[lints.clippy]
+pedantic = "warn"
+
+[features]
+default = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Inlining `package.version` as "0.1.0"
+    Inlining `package.edition` as "2021"
+    Inlining `package.license` as "MIT"
+    Inlining `package.readme` as "../../README.md"
+    Inlining `lints`
+    Inlining `dependencies.b` as { path = "../b", features = ["x", "y"] }
+    Inlining `dependencies.c` as { version = "0.1.0", path = "../c" }
+    Inlining `dev-dependencies.c` as { version = "0.1.0", path = "../c" }
+warning: not modifying the manifest due to dry run
+"#;
+}
+#[test]
+fn strip_path_deps_to_versions() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-vendor-member-strip-path-deps-to-versions")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL_ROOT)?;
+    for (name, manifest) in &[
+        ("a", ORIGINAL_A),
+        (
+            "b",
+            "[package]\nname = \"b\"\nversion = \"0.2.0\"\nedition = \"2021\"\n",
+        ),
+        (
+            "c",
+            "[package]\nname = \"c\"\nversion.workspace = true\nedition = \"2021\"\n",
+        ),
+    ] {
+        fs::create_dir_all(tempdir.path().join(name).join("src"))?;
+        fs::write(tempdir.path().join(name).join("Cargo.toml"), manifest)?;
+        fs::write(tempdir.path().join(name).join("src").join("lib.rs"), "")?;
+    }
+    let metadata = cargo_metadata(&tempdir.path().join("Cargo.toml"), &[])?;
+
+    let mut stderr = vec![];
+
+    cargo_member::VendorMember::from_metadata(&metadata, "a")
+        .strip_path_deps_to_versions(true)
+        .keep_path(true)
+        .stdout(true)
+        .stderr(NoColor::new(&mut stderr))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), ORIGINAL_A)?;
+    assert_stderr(&stderr, EXPECTED_STDERR)?;
+
+    cargo_member::VendorMember::from_metadata(&metadata, "a")
+        .strip_path_deps_to_versions(true)
+        .stderr(NoColor::new(vec![]))
+        .exec()?;
+
+    assert_manifest(&tempdir.path().join("a").join("Cargo.toml"), EXPECTED_A)?;
+    return Ok(());
+
+    static ORIGINAL_ROOT: &str = r#"[workspace]
+members = ["a", "b", "c"]
+resolver = "3"
+
+[workspace.package]
+version = "0.3.0"
+
+[workspace.dependencies]
+c = { path = "c" }
+"#;
+
+    static ORIGINAL_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = { path = "../b" } # b
+c.workspace = true
+
+[dev-dependencies.b]
+path = "../b"
+version = "0.2"
+"#;
+
+    static EXPECTED_A: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+b = "0.2.0" # b
+c = "0.3.0"
+
+[dev-dependencies.b]
+version = "0.2"
+"#;
+
+    static EXPECTED_STDERR: &str = r#"    Inlining `dependencies.c` as { path = "../c" }
+     Pinning `dependencies.b` to "0.2.0"
+     Pinning `dependencies.c` to "0.3.0"
+     Pinning `dev-dependencies.b` to "0.2"
+warning: not modifying the manifest due to dry run
+"#;
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path, opts: &[&str]) -> cargo_metadata::Result<Metadata> {
+    let opts = opts
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .other_options(opts.iter().map(ToOwned::to_owned).collect::<Vec<_>>())
+        .exec()
+}
@@ -0,0 +1,117 @@
+#![warn(rust_2018_idioms)]
+
+use duct::cmd;
+use std::{env, fs, io, path::Path};
+use tempdir::TempDir;
+
+#[test]
+fn which_workspace() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-which-workspace")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("crates").join("a"))?;
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "which-workspace"
+    )
+    .dir(tempdir.path().join("crates").join("a").join("src"))
+    .read()?;
+    assert_eq!(
+        format!("{} (root)", tempdir.path().join("Cargo.toml").display()),
+        stdout,
+    );
+
+    let stdout = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "which-workspace",
+        "--json",
+    )
+    .dir(tempdir.path().join("crates"))
+    .read()?;
+    assert_eq!(
+        serde_json::json!({
+            "manifest_path": tempdir.path().join("Cargo.toml"),
+            "root_package": "root",
+        }),
+        serde_json::from_str::<serde_json::Value>(&stdout)?,
+    );
+    return Ok(());
+
+    static MANIFEST: &str = r#"[package]
+name = "root"
+version = "0.0.0"
+edition = "2018"
+
+[lib]
+path = "lib.rs"
+
+[workspace]
+members = ["crates/a"]
+"#;
+}
+
+#[test]
+fn no_workspace() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-which-workspace-no-workspace")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "which-workspace"
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)?.starts_with(&format!(
+        "error: could not find `Cargo.toml` in `{}` or any parent directory\n",
+        tempdir.path().display(),
+    )));
+    Ok(())
+}
+
+#[test]
+fn cargo_metadata_failure() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-which-workspace-cargo-metadata-failure")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    fs::write(tempdir.path().join("a").join("Cargo.toml"), "[package\n")?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "which-workspace",
+        "--json",
+    )
+    .dir(tempdir.path())
+    .stdout_capture()
+    .stderr_capture()
+    .run()?;
+    assert_eq!(
+        serde_json::json!({
+            "manifest_path": tempdir.path().join("Cargo.toml"),
+            "root_package": null,
+        }),
+        serde_json::from_slice::<serde_json::Value>(&output.stdout)?,
+    );
+    assert!(String::from_utf8(output.stderr)?
+        .starts_with("warning: `cargo metadata` failed. reading the manifests directly instead\n"));
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
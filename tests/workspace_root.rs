@@ -0,0 +1,217 @@
+#![warn(rust_2018_idioms)]
+
+use cargo_metadata::MetadataCommand;
+use difference::assert_diff;
+use duct::cmd;
+use std::{
+    env, fs, io,
+    path::Path,
+    str::{self, Utf8Error},
+};
+use tempdir::TempDir;
+
+#[test]
+fn workspace_root() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-workspace-root")?;
+
+    for ws in &["ws1", "ws2"] {
+        fs::create_dir(tempdir.path().join(ws))?;
+        fs::write(tempdir.path().join(ws).join("Cargo.toml"), ORIGINAL)?;
+        cargo_new(&tempdir.path().join(ws).join("a"))?;
+        fs::write(tempdir.path().join(ws).join("Cargo.toml"), ORIGINAL)?;
+    }
+
+    cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--workspace-root",
+        "ws2",
+        "./ws2/a",
+    )
+    .dir(tempdir.path())
+    .stderr_null()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("ws1").join("Cargo.toml"), ORIGINAL)?;
+    assert_manifest(
+        &tempdir.path().join("ws2").join("Cargo.toml"),
+        EXPECTED_MANIFEST,
+    )?;
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "which-workspace",
+        "--workspace-root",
+        "ws1/a",
+    )
+    .dir(tempdir.path())
+    .stdout_null()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+
+    assert!(!output.status.success());
+    assert_eq!(
+        format!(
+            "error: `{}` is not a workspace manifest\n",
+            tempdir
+                .path()
+                .join("ws1")
+                .join("a")
+                .join("Cargo.toml")
+                .display(),
+        ),
+        String::from_utf8(output.stderr)?,
+    );
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = []
+resolver = "3"
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = [
+    "a",
+]
+resolver = "3"
+exclude = []
+"#;
+}
+
+/// A package manifest between the CWD and the workspace root does not hide the workspace.
+#[test]
+fn package_between() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-workspace-root-package-between")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    cargo_new(&tempdir.path().join("crates").join("a"))?;
+    cargo_new(&tempdir.path().join("crates").join("b"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), ORIGINAL)?;
+    let a_manifest =
+        fs::read_to_string(tempdir.path().join("crates").join("a").join("Cargo.toml"))?;
+    let cwd = tempdir.path().join("crates").join("a").join("src");
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "include",
+        "--verbose",
+        "../../b",
+    )
+    .dir(&cwd)
+    .stderr_capture()
+    .run()?;
+
+    assert_manifest(&tempdir.path().join("Cargo.toml"), EXPECTED_MANIFEST)?;
+    assert_manifest(
+        &tempdir.path().join("crates").join("a").join("Cargo.toml"),
+        &a_manifest,
+    )?;
+    assert_stderr(
+        &output.stderr,
+        &EXPECTED_STDERR
+            .replacen(
+                "{}",
+                &tempdir.path().join("Cargo.toml").to_string_lossy(),
+                1,
+            )
+            .replacen(
+                "{}",
+                &tempdir.path().join("Cargo.lock").to_string_lossy(),
+                1,
+            ),
+    )?;
+    cargo_metadata(&tempdir.path().join("Cargo.toml"))?;
+    return Ok(());
+
+    static ORIGINAL: &str = r#"[workspace]
+members = ["crates/a"]
+"#;
+
+    static EXPECTED_MANIFEST: &str = r#"[workspace]
+members = ["crates/a",
+    "crates/b",
+]
+exclude = []
+"#;
+
+    static EXPECTED_STDERR: &str = r#"note: using the workspace at `{}`
+      Adding "crates/b" to `workspace.members`
+    Updating {}
+"#;
+}
+
+/// A package excluded from the workspace above it is a root on its own, as with Cargo.
+#[test]
+fn excluded() -> anyhow::Result<()> {
+    let tempdir = TempDir::new("cargo-member-test-workspace-root-excluded")?;
+
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    cargo_new(&tempdir.path().join("a"))?;
+    cargo_new(&tempdir.path().join("standalone"))?;
+    fs::write(tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    let cwd = tempdir.path().join("standalone").join("src");
+
+    let output = cmd!(
+        env!("CARGO_BIN_EXE_cargo-member"),
+        "member",
+        "activate-all",
+        "--verbose",
+        "--dry-run",
+    )
+    .dir(&cwd)
+    .stderr_capture()
+    .run()?;
+
+    assert_stderr(
+        &output.stderr,
+        &EXPECTED_STDERR.replace(
+            "{}",
+            &tempdir
+                .path()
+                .join("standalone")
+                .join("Cargo.toml")
+                .to_string_lossy(),
+        ),
+    )?;
+    assert_manifest(&tempdir.path().join("Cargo.toml"), MANIFEST)?;
+    return Ok(());
+
+    static MANIFEST: &str = r#"[workspace]
+members = ["a"]
+exclude = ["standalone"]
+"#;
+
+    static EXPECTED_STDERR: &str = r#"note: using the workspace at `{}`
+warning: `workspace` unchanged
+warning: not modifying the manifest due to dry run
+"#;
+}
+
+fn cargo_new(path: &Path) -> io::Result<()> {
+    let cargo_exe = env::var("CARGO").unwrap();
+    cmd!(cargo_exe, "new", "-q", "--vcs", "none", path).run()?;
+    Ok(())
+}
+
+fn assert_manifest(manifest_path: &Path, expected: &str) -> io::Result<()> {
+    let modified = fs::read_to_string(manifest_path)?;
+    assert_diff!(expected, &modified, "\n", 0);
+    Ok(())
+}
+
+fn assert_stderr(stderr: &[u8], expected: &str) -> std::result::Result<(), Utf8Error> {
+    assert_diff!(expected, str::from_utf8(stderr)?, "\n", 0);
+    Ok(())
+}
+
+fn cargo_metadata(manifest_path: &Path) -> cargo_metadata::Result<()> {
+    MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .map(drop)
+}